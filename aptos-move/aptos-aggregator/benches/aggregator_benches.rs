@@ -0,0 +1,119 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+// Run this bencher via `cargo bench --features testing`.
+use aptos_aggregator::{
+    aggregator_extension::AggregatorData, aggregator_id_for_test, AggregatorStore,
+};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+const LIMIT: u128 = u128::MAX / 2;
+const NUM_AGGREGATORS: [u128; 3] = [100, 1_000, 10_000];
+const NUM_OPERATIONS: usize = 1_000;
+
+/// Returns aggregator data with `n` aggregators storing deltas.
+fn aggregators_with_deltas(n: u128) -> AggregatorData {
+    let mut aggregator_data = AggregatorData::default();
+    for key in 0..n {
+        aggregator_data
+            .get_aggregator(aggregator_id_for_test(key), LIMIT)
+            .unwrap()
+            .add(key)
+            .unwrap();
+    }
+    aggregator_data
+}
+
+fn get_aggregator_benches(c: &mut Criterion) {
+    let mut group = c.benchmark_group("get_aggregator");
+    for n in NUM_AGGREGATORS {
+        group.throughput(Throughput::Elements(n as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(n), &n, |b, n| {
+            b.iter_with_setup(AggregatorData::default, |mut aggregator_data| {
+                for key in 0..*n {
+                    aggregator_data
+                        .get_aggregator(aggregator_id_for_test(key), LIMIT)
+                        .unwrap();
+                }
+                aggregator_data
+            })
+        });
+    }
+    group.finish();
+}
+
+fn history_benches(c: &mut Criterion) {
+    let id = aggregator_id_for_test(0);
+
+    c.bench_function("add_sub_with_history", |b| {
+        b.iter_with_setup(AggregatorData::default, |mut aggregator_data| {
+            let aggregator = aggregator_data.get_aggregator(id.clone(), LIMIT).unwrap();
+            for i in 0..NUM_OPERATIONS as u128 {
+                aggregator.add(2 * i).unwrap();
+                aggregator.sub(i).unwrap();
+            }
+            aggregator_data
+        })
+    });
+
+    let mut aggregator_data = AggregatorData::default();
+    let aggregator = aggregator_data.get_aggregator(id.clone(), LIMIT).unwrap();
+    for i in 0..NUM_OPERATIONS as u128 {
+        aggregator.add(2 * i).unwrap();
+        aggregator.sub(i).unwrap();
+    }
+    c.bench_function("validate_against_base", |b| {
+        b.iter(|| aggregator.validate_against_base(LIMIT / 4).unwrap())
+    });
+}
+
+fn materialization_benches(c: &mut Criterion) {
+    let mut group = c.benchmark_group("read_and_materialize");
+    for n in NUM_AGGREGATORS {
+        let resolver = AggregatorStore::builder()
+            .with_values((0..n).map(|key| (aggregator_id_for_test(key), key)))
+            .build();
+        group.throughput(Throughput::Elements(n as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(n), &n, |b, n| {
+            b.iter_with_setup(
+                || aggregators_with_deltas(*n),
+                |mut aggregator_data| {
+                    for key in 0..*n {
+                        let id = aggregator_id_for_test(key);
+                        aggregator_data
+                            .get_aggregator(id.clone(), LIMIT)
+                            .unwrap()
+                            .read_and_materialize(&resolver, &id)
+                            .unwrap();
+                    }
+                    aggregator_data
+                },
+            )
+        });
+    }
+    group.finish();
+}
+
+fn change_set_benches(c: &mut Criterion) {
+    let mut group = c.benchmark_group("into_change_set");
+    for n in NUM_AGGREGATORS {
+        group.throughput(Throughput::Elements(n as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(n), &n, |b, n| {
+            b.iter_with_setup(
+                || aggregators_with_deltas(*n),
+                |aggregator_data| aggregator_data.into_change_set(),
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    get_aggregator_benches,
+    history_benches,
+    materialization_benches,
+    change_set_benches
+);
+
+criterion_main!(benches);
@@ -0,0 +1,96 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+//! Move abort codes raised by aggregator natives, and translation of errors
+//! returned by aggregator extensions into them. Codes include the error
+//! category of the Move `std::error` module, e.g. `0x02` for out of range.
+
+use aptos_types::vm_status::StatusCode;
+use move_binary_format::errors::PartialVMError;
+
+/// When `Addition` operation overflows the `limit`. Equal to
+/// `error::out_of_range(EAGGREGATOR_OVERFLOW)` in the aggregator module.
+pub const EADD_OVERFLOW: u64 = 0x02_0001;
+
+/// When `Subtraction` operation goes below zero. Equal to
+/// `error::out_of_range(EAGGREGATOR_UNDERFLOW)` in the aggregator module.
+pub const ESUB_UNDERFLOW: u64 = 0x02_0002;
+
+/// When an aggregator feature is not enabled on chain. Equal to
+/// `error::not_implemented(ENOT_SUPPORTED)` in the aggregator module.
+pub const ENOT_SUPPORTED: u64 = 0x0C_0003;
+
+/// The generic type supplied to aggregator snapshots is not supported. Equal
+/// to `error::invalid_state(EUNSUPPORTED_AGGREGATOR_SNAPSHOT_TYPE)` in the
+/// aggregator V2 module.
+pub const EUNSUPPORTED_AGGREGATOR_SNAPSHOT_TYPE: u64 = 0x03_0005;
+
+/// The aggregator snapshots feature is not enabled. Equal to
+/// `error::invalid_state(EAGGREGATOR_SNAPSHOTS_NOT_ENABLED)` in the
+/// aggregator V2 module.
+pub const EAGGREGATOR_SNAPSHOTS_NOT_ENABLED: u64 = 0x03_0006;
+
+/// Translates an error returned by an aggregator extension into the abort
+/// code of the Move module. Returns the error back if it is not caused by
+/// user input (e.g. extension errors or invariant violations), and so must
+/// not be turned into an abort.
+///
+/// Note that natives of aggregator V1 propagate extension errors as they are:
+/// aborting from a native charges native gas, so using this translation there
+/// changes the gas used by transactions and must be gated by a feature flag.
+pub fn into_abort_code(error: PartialVMError) -> Result<u64, PartialVMError> {
+    match error.major_status() {
+        StatusCode::ABORTED | StatusCode::FEATURE_UNDER_GATING => {
+            let (major_status, sub_status, message, ..) = error.all_data();
+            sub_status.ok_or_else(|| {
+                let error = PartialVMError::new(major_status);
+                match message {
+                    Some(message) => error.with_message(message),
+                    None => error,
+                }
+            })
+        },
+        _ => Err(error),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        aggregator_extension::{extension_error, AggregatorData},
+        aggregator_id_for_test,
+        aggregator_v2_extension::AggregatorV2Data,
+        types::{AggregatorWidth, SnapshotValue},
+    };
+    use claims::{assert_err, assert_ok_eq};
+
+    #[test]
+    fn test_into_abort_code() {
+        let mut aggregator_data = AggregatorData::default();
+        let id = aggregator_id_for_test(100);
+        aggregator_data.create_new_aggregator(id.clone(), 100);
+        let aggregator = aggregator_data.get_aggregator(id, 100).unwrap();
+
+        let error = aggregator.add(101).unwrap_err();
+        assert_ok_eq!(into_abort_code(error), EADD_OVERFLOW);
+        let error = aggregator.sub(1).unwrap_err();
+        assert_ok_eq!(into_abort_code(error), ESUB_UNDERFLOW);
+        let error = aggregator.is_at_least(1).unwrap_err();
+        assert_ok_eq!(into_abort_code(error), ENOT_SUPPORTED);
+
+        let mut data = AggregatorV2Data::default();
+        let error = data
+            .create_new_aggregator(100, AggregatorWidth::U64)
+            .unwrap_err();
+        assert_ok_eq!(into_abort_code(error), EAGGREGATOR_SNAPSHOTS_NOT_ENABLED);
+        let error = data.insert_snapshot(SnapshotValue::Data(5)).unwrap_err();
+        assert_ok_eq!(into_abort_code(error), EAGGREGATOR_SNAPSHOTS_NOT_ENABLED);
+
+        // Errors which are not caused by users are not aborts.
+        let error = assert_err!(into_abort_code(extension_error("error")));
+        assert_eq!(error.major_status(), StatusCode::VM_EXTENSION_ERROR);
+        let error = assert_err!(into_abort_code(PartialVMError::new(StatusCode::ABORTED)));
+        assert_eq!(error.major_status(), StatusCode::ABORTED);
+    }
+}
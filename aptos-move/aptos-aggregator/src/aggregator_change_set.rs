@@ -0,0 +1,278 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    aggregator_extension::AggregatorID,
+    aggregator_v2_extension::EphemeralID,
+    delta_change_set::{delta_error_into_vm_status, DeltaOp},
+    observed_value::ObservedDelta,
+};
+use aptos_types::vm_status::{StatusCode, VMStatus};
+use std::collections::{btree_map, BTreeMap, BTreeSet};
+
+/// Represents a single aggregator change.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AggregatorChange {
+    // A value should be written to storage.
+    Write(u128),
+    // A delta should be merged with the value from storage.
+    Merge(DeltaOp),
+    // Observed values should be joined with the value from storage.
+    Observe(ObservedDelta),
+    // A value should be deleted from the storage.
+    Delete,
+}
+
+/// Represents changes made by all aggregators during this context. This change
+/// set can be converted into appropriate `WriteSet` and `DeltaChangeSet` by the
+/// user, e.g. VM session. Changes are ordered by aggregator identifier, so the
+/// conversion is deterministic.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AggregatorChangeSet {
+    pub changes: BTreeMap<AggregatorID, AggregatorChange>,
+    /// Final values of aggregators removed with
+    /// `AggregatorData::remove_and_read`.
+    pub removed_values: BTreeMap<AggregatorID, u128>,
+    /// Aggregators created during this context, which did not exist in
+    /// storage before.
+    pub new_aggregators: BTreeSet<AggregatorID>,
+}
+
+impl AggregatorChangeSet {
+    pub fn squash(&mut self, other: Self) -> Result<(), VMStatus> {
+        self.removed_values.extend(other.removed_values);
+        self.new_aggregators.extend(other.new_aggregators);
+        for (other_id, other_change) in other.changes {
+            match self.changes.entry(other_id) {
+                // If something was changed only in `other` session, add it.
+                btree_map::Entry::Vacant(entry) => {
+                    entry.insert(other_change);
+                },
+                // Otherwise, we might need to aggregate deltas.
+                btree_map::Entry::Occupied(mut entry) => {
+                    use AggregatorChange::*;
+
+                    let entry_mut = entry.get_mut();
+                    match (*entry_mut, other_change) {
+                        (Write(_) | Merge(_) | Observe(_), Write(data)) => *entry_mut = Write(data),
+                        (Write(_) | Merge(_) | Observe(_), Delete) => *entry_mut = Delete,
+                        (Write(data), Merge(delta)) => {
+                            let new_data =
+                                delta.apply_to(data).map_err(delta_error_into_vm_status)?;
+                            *entry_mut = Write(new_data);
+                        },
+                        (Merge(delta1), Merge(mut delta2)) => {
+                            // `delta1` occurred before `delta2`, therefore we must ensure we merge the latter
+                            // one to the initial delta.
+                            delta2
+                                .merge_with_previous_delta(delta1)
+                                .map_err(delta_error_into_vm_status)?;
+                            *entry_mut = Merge(delta2)
+                        },
+                        (Write(data), Observe(delta)) => *entry_mut = Write(delta.apply_to(data)),
+                        (Observe(delta1), Observe(mut delta2)) => {
+                            delta2.merge_with_previous(delta1);
+                            *entry_mut = Observe(delta2)
+                        },
+                        // Regular and observed-value aggregators cannot share
+                        // an identifier.
+                        (Merge(_), Observe(_)) | (Observe(_), Merge(_)) => {
+                            return Err(VMStatus::error(
+                                StatusCode::UNKNOWN_INVARIANT_VIOLATION_ERROR,
+                                Some(
+                                    "Cannot squash deltas of different aggregator kinds"
+                                        .to_string(),
+                                ),
+                            ));
+                        },
+                        // Hashing properties guarantee that aggregator keys should
+                        // not collide, making this case impossible.
+                        (Delete, _) => unreachable!("resource cannot be accessed after deletion"),
+                    }
+                },
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes changes which have no effect on storage: deltas which do not
+    /// change the value (see `DeltaOp::is_noop`), observations of nothing,
+    /// and deletions of aggregators created during this context. Deltas of
+    /// created aggregators are applied to their initial zero value, so that
+    /// they are written instead.
+    ///
+    /// Dropping no-op deltas changes the write set produced by a transaction,
+    /// so this must not be used when executing transactions unless gated by
+    /// an on-chain feature flag.
+    pub fn compact(&mut self) -> Result<(), VMStatus> {
+        use AggregatorChange::*;
+
+        let mut changes = BTreeMap::new();
+        for (id, change) in std::mem::take(&mut self.changes) {
+            let created = self.new_aggregators.contains(&id);
+            let compacted = match change {
+                Delete if created => None,
+                Merge(delta) if created => Some(Write(
+                    delta.apply_to(0).map_err(delta_error_into_vm_status)?,
+                )),
+                Merge(delta) if delta.is_noop() => None,
+                Observe(delta) if delta.observed().is_none() => None,
+                change => Some(change),
+            };
+            if let Some(change) = compacted {
+                changes.insert(id, change);
+            }
+        }
+        self.new_aggregators.retain(|id| changes.contains_key(id));
+        self.changes = changes;
+        Ok(())
+    }
+}
+
+/// Represents changes made by aggregators V2 during this context, see
+/// `AggregatorV2Data::into_change_set`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AggregatorV2ChangeSet {
+    pub changes: BTreeMap<AggregatorID, AggregatorChange>,
+    /// Storage keys assigned to aggregators created in this context.
+    pub translations: BTreeMap<EphemeralID, AggregatorID>,
+}
+
+impl AggregatorV2ChangeSet {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty() && self.translations.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        aggregator_id_for_test,
+        delta_change_set::{delta_add, DeltaUpdate},
+        observed_value::{ObservedDelta, ObservedKind},
+    };
+    use claims::{assert_err, assert_ok};
+
+    #[test]
+    fn test_squash() {
+        let delta = |update, max_positive, min_negative| {
+            AggregatorChange::Merge(DeltaOp::new(update, 1000, max_positive, min_negative))
+        };
+
+        let mut change_set = AggregatorChangeSet {
+            changes: BTreeMap::from([
+                (aggregator_id_for_test(100), AggregatorChange::Write(10)),
+                (
+                    aggregator_id_for_test(200),
+                    delta(DeltaUpdate::Plus(10), 10, 0),
+                ),
+                (
+                    aggregator_id_for_test(300),
+                    delta(DeltaUpdate::Plus(10), 10, 0),
+                ),
+            ]),
+            ..Default::default()
+        };
+        let other = AggregatorChangeSet {
+            changes: BTreeMap::from([
+                (
+                    aggregator_id_for_test(100),
+                    delta(DeltaUpdate::Plus(5), 5, 0),
+                ),
+                (
+                    aggregator_id_for_test(200),
+                    delta(DeltaUpdate::Minus(20), 0, 20),
+                ),
+                (aggregator_id_for_test(300), AggregatorChange::Delete),
+                (aggregator_id_for_test(400), AggregatorChange::Write(40)),
+            ]),
+            ..Default::default()
+        };
+        assert_ok!(change_set.squash(other));
+        assert_eq!(
+            change_set.changes,
+            BTreeMap::from([
+                (aggregator_id_for_test(100), AggregatorChange::Write(15)),
+                (
+                    aggregator_id_for_test(200),
+                    delta(DeltaUpdate::Minus(10), 10, 10)
+                ),
+                (aggregator_id_for_test(300), AggregatorChange::Delete),
+                (aggregator_id_for_test(400), AggregatorChange::Write(40)),
+            ])
+        );
+
+        // Deltas which cannot be applied to written values fail.
+        let other = AggregatorChangeSet {
+            changes: BTreeMap::from([(
+                aggregator_id_for_test(400),
+                delta(DeltaUpdate::Minus(50), 0, 50),
+            )]),
+            ..Default::default()
+        };
+        assert_err!(change_set.squash(other));
+    }
+
+    #[test]
+    fn test_compact() {
+        let noop = AggregatorChange::Merge(DeltaOp::new(DeltaUpdate::Plus(0), 1000, 0, 0));
+        let mut change_set = AggregatorChangeSet {
+            changes: BTreeMap::from([
+                (aggregator_id_for_test(100), noop),
+                (
+                    aggregator_id_for_test(200),
+                    AggregatorChange::Merge(delta_add(10, 1000)),
+                ),
+                (
+                    aggregator_id_for_test(300),
+                    AggregatorChange::Merge(delta_add(30, 1000)),
+                ),
+                (aggregator_id_for_test(400), AggregatorChange::Delete),
+                (aggregator_id_for_test(500), AggregatorChange::Delete),
+                (
+                    aggregator_id_for_test(600),
+                    AggregatorChange::Observe(ObservedDelta::new(ObservedKind::Max)),
+                ),
+                (aggregator_id_for_test(700), noop),
+            ]),
+            new_aggregators: BTreeSet::from([
+                aggregator_id_for_test(300),
+                aggregator_id_for_test(400),
+                aggregator_id_for_test(700),
+            ]),
+            ..Default::default()
+        };
+        assert_ok!(change_set.compact());
+        assert_eq!(
+            change_set.changes,
+            BTreeMap::from([
+                (
+                    aggregator_id_for_test(200),
+                    AggregatorChange::Merge(delta_add(10, 1000))
+                ),
+                (aggregator_id_for_test(300), AggregatorChange::Write(30)),
+                (aggregator_id_for_test(500), AggregatorChange::Delete),
+                (aggregator_id_for_test(700), AggregatorChange::Write(0)),
+            ])
+        );
+        assert_eq!(
+            change_set.new_aggregators,
+            BTreeSet::from([aggregator_id_for_test(300), aggregator_id_for_test(700)])
+        );
+
+        // Deltas which failed during execution are not trivial.
+        let failed =
+            DeltaOp::new(DeltaUpdate::Plus(0), 1000, 0, 0).with_failure_bounds(Some(900), None);
+        assert!(!failed.is_noop());
+        let mut change_set = AggregatorChangeSet {
+            changes: BTreeMap::from([(
+                aggregator_id_for_test(100),
+                AggregatorChange::Merge(failed),
+            )]),
+            ..Default::default()
+        };
+        assert_ok!(change_set.compact());
+        assert_eq!(change_set.changes.len(), 1);
+    }
+}
@@ -2,19 +2,35 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::{
-    delta_change_set::{addition, subtraction},
-    resolver::{AggregatorReadMode, AggregatorResolver},
+    aggregator_change_set::{AggregatorChange, AggregatorChangeSet},
+    bounded_math::{BoundViolation, BoundedMath, SignedValue},
+    counters::{self, Operation},
+    delta_change_set::{abort_error, addition, subtraction, DeltaOp, DeltaUpdate},
+    features::AggregatorFeatures,
+    gas_hook::{AggregatorOperation, GasMeterHook, NoOpGasMeterHook},
+    observed_value::{ObservedKind, ObservedValueAggregator},
+    resolver::{
+        get_aggregator_value, get_aggregator_value_bounds, AggregatorReadMode, AggregatorResolver,
+    },
+    types::{AggregatorKind, AggregatorWidth, SnapshotValue},
+    versioned::Versioned,
 };
+use anyhow::bail;
+use aptos_crypto::hash::DefaultHasher;
 use aptos_types::{
     state_store::{state_key::StateKey, table::TableHandle},
     vm_status::StatusCode,
 };
 use move_binary_format::errors::{PartialVMError, PartialVMResult};
-use move_core_types::account_address::AccountAddress;
-use std::collections::{BTreeMap, BTreeSet};
+use move_core_types::{account_address::AccountAddress, language_storage::StructTag};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+};
 
 /// Describes the state of each aggregator instance.
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum AggregatorState {
     // If aggregator stores a known value.
     Data,
@@ -24,25 +40,92 @@ pub enum AggregatorState {
     NegativeDelta,
 }
 
+impl std::fmt::Display for AggregatorState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AggregatorState::Data => write!(f, "data"),
+            AggregatorState::PositiveDelta => write!(f, "positive delta"),
+            AggregatorState::NegativeDelta => write!(f, "negative delta"),
+        }
+    }
+}
+
+/// Limit of unbounded aggregators, see `AggregatorKind::Unbounded`.
+pub const UNBOUNDED_LIMIT: u128 = u128::MAX;
+
 #[derive(Debug, Clone, Copy, Hash, Eq, PartialEq, Ord, PartialOrd)]
 pub struct AggregatorHandle(pub AccountAddress);
 
-/// Uniquely identifies each aggregator instance in storage.
-#[derive(Debug, Clone, Hash, Eq, PartialEq, Ord, PartialOrd)]
-pub struct AggregatorID(StateKey);
+impl AggregatorHandle {
+    /// Derives the key of an aggregator created by `aggregator_factory::new_aggregator`
+    /// in the transaction with `txn_hash`, the same way table handles are
+    /// derived: by hashing the transaction hash and `counter`, the number of
+    /// aggregators used in the transaction before (see
+    /// `AggregatorData::num_aggregators`). Keys are namespaced by the table
+    /// handle of the factory, so they can coincide with handles of tables
+    /// created in the same transaction.
+    pub fn derive(txn_hash: &[u8; 32], counter: u32) -> Self {
+        let mut hasher = DefaultHasher::new(&[0_u8; 0]);
+        hasher.update(txn_hash);
+        hasher.update(&counter.to_be_bytes());
+        AggregatorHandle(AccountAddress::new(*hasher.finish()))
+    }
+}
+
+/// Uniquely identifies each aggregator instance in storage. Aggregators are
+/// stored either as table items, or as resources which are members of a
+/// resource group. In the latter case, the value is stored in the group blob
+/// under the tag of the resource, see `resolver::get_aggregator_value`.
+#[derive(Debug, Clone, Hash, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize)]
+pub struct AggregatorID {
+    state_key: StateKey,
+    group_tag: Option<StructTag>,
+}
 
 impl AggregatorID {
     pub fn new(handle: TableHandle, key: AggregatorHandle) -> Self {
         let state_key = StateKey::table_item(handle, key.0.to_vec());
-        AggregatorID(state_key)
+        AggregatorID {
+            state_key,
+            group_tag: None,
+        }
+    }
+
+    /// Derives the identifier of an aggregator created by the factory with
+    /// table `handle`, see `AggregatorHandle::derive`.
+    pub fn derive(handle: TableHandle, txn_hash: &[u8; 32], counter: u32) -> Self {
+        Self::new(handle, AggregatorHandle::derive(txn_hash, counter))
+    }
+
+    /// Creates an identifier of an aggregator stored as resource `tag`,
+    /// which is a member of the resource group at `group_key`.
+    pub fn new_in_resource_group(group_key: StateKey, tag: StructTag) -> Self {
+        AggregatorID {
+            state_key: group_key,
+            group_tag: Some(tag),
+        }
     }
 
+    /// Returns the tag of the resource storing the aggregator, if it is a
+    /// member of a resource group.
+    pub fn resource_group_tag(&self) -> Option<&StructTag> {
+        self.group_tag.as_ref()
+    }
+
+    pub fn is_in_resource_group(&self) -> bool {
+        self.group_tag.is_some()
+    }
+
+    /// Returns the state key of the table item storing the aggregator, or of
+    /// the resource group containing it.
     pub fn as_state_key(&self) -> &StateKey {
-        &self.0
+        &self.state_key
     }
 
+    /// Same as `as_state_key`, but consumes the identifier. Note that for
+    /// aggregators in resource groups, the tag of the member is dropped.
     pub fn into_state_key(self) -> StateKey {
-        self.0
+        self.state_key
     }
 }
 
@@ -74,12 +157,23 @@ impl AggregatorID {
 ///   2. check -A did not drop below zero
 /// Checking +X is irrelevant since +A >= +X.
 ///
-/// TODO: while we support tracking of the history, it is not yet fully used on
-/// executor side because we don't know how to throw errors.
-#[derive(Debug)]
+/// Achieved deltas only describe operations which succeeded. If execution
+/// observed an operation failing (e.g. `try_add` returning false), the
+/// failure must be reproduced as well: otherwise the transaction would have
+/// taken a different branch. For that, the history also stores the smallest
+/// positive delta which overflowed and the largest negative delta which
+/// dropped below zero. Validation then checks that:
+///   3. the smallest overflowing delta still overflows
+///   4. the largest underflowing delta still drops below zero
+///
+/// Executors can use the history to re-validate deltas against updated base
+/// values instead of re-executing transactions, see `DeltaResolution`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct History {
-    pub max_positive: u128,
-    pub min_negative: u128,
+    max_positive: u128,
+    min_negative: u128,
+    min_overflow_positive: Option<u128>,
+    max_underflow_negative: Option<u128>,
 }
 
 impl History {
@@ -87,9 +181,56 @@ impl History {
         History {
             max_positive: 0,
             min_negative: 0,
+            min_overflow_positive: None,
+            max_underflow_negative: None,
+        }
+    }
+
+    /// Returns the history with the given largest positive and smallest
+    /// negative deltas, e.g. of a `DeltaOp`.
+    pub fn from_bounds(max_positive: u128, min_negative: u128) -> Self {
+        History {
+            max_positive,
+            min_negative,
+            min_overflow_positive: None,
+            max_underflow_negative: None,
         }
     }
 
+    /// Sets the smallest positive delta which overflowed and the absolute
+    /// value of the largest negative delta which dropped below zero.
+    pub fn with_failure_bounds(
+        mut self,
+        min_overflow_positive: Option<u128>,
+        max_underflow_negative: Option<u128>,
+    ) -> Self {
+        self.min_overflow_positive = min_overflow_positive;
+        self.max_underflow_negative = max_underflow_negative;
+        self
+    }
+
+    /// Returns the largest positive delta seen, i.e. +A on the graph above.
+    pub fn max_positive(&self) -> u128 {
+        self.max_positive
+    }
+
+    /// Returns the absolute value of the smallest negative delta seen, i.e.
+    /// B for -B on the graph above.
+    pub fn min_negative(&self) -> u128 {
+        self.min_negative
+    }
+
+    /// Returns the smallest positive delta which overflowed, if any.
+    pub fn min_overflow_positive(&self) -> Option<u128> {
+        self.min_overflow_positive
+    }
+
+    /// Returns the absolute value of the largest negative delta which
+    /// dropped below zero, if any.
+    pub fn max_underflow_negative(&self) -> Option<u128> {
+        self.max_underflow_negative
+    }
+
     fn record_positive(&mut self, value: u128) {
         self.max_positive = u128::max(self.max_positive, value);
     }
@@ -97,10 +238,135 @@ impl History {
     fn record_negative(&mut self, value: u128) {
         self.min_negative = u128::max(self.min_negative, value);
     }
+
+    fn record_overflow(&mut self, value: u128) {
+        self.min_overflow_positive = Some(
+            self.min_overflow_positive
+                .map_or(value, |overflow| u128::min(overflow, value)),
+        );
+    }
+
+    fn record_underflow(&mut self, value: u128) {
+        self.max_underflow_negative = Some(
+            self.max_underflow_negative
+                .map_or(value, |underflow| u128::min(underflow, value)),
+        );
+    }
+
+    /// Validates that none of the deltas seen overflow `limit` or drop below
+    /// zero when applied to `base`, and that the deltas which failed still
+    /// fail. On failure, returns the violated bound.
+    pub fn validate(&self, base: u128, limit: u128) -> Result<(), BoundViolation> {
+        // To validate the history of an aggregator, we want to ensure
+        // that there was no violation of postcondition (i.e. overflows or
+        // underflows). We can do it by emulating addition and subtraction.
+        let math = BoundedMath::new(limit);
+        math.unsigned_add(base, self.max_positive)?;
+        math.unsigned_subtract(base, self.min_negative)?;
+
+        // Failed operations are emulated the same way, but must fail.
+        if let Some(delta) = self.min_overflow_positive {
+            if math.unsigned_add(base, delta).is_ok() {
+                return Err(BoundViolation::ExpectedOverflow { base, delta, limit });
+            }
+        }
+        if let Some(delta) = self.max_underflow_negative {
+            if math.unsigned_subtract(base, delta).is_ok() {
+                return Err(BoundViolation::ExpectedUnderflow { base, delta });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns the value of an aggregator with the given `state`, `value`,
+/// `limit` and `history` after applying it to `base`, the value in storage.
+/// The history is validated first, so that the result is only returned if
+/// every operation which produced the delta would have succeeded on `base`.
+/// If the aggregator knows its value, it does not depend on `base`.
+///
+/// This is the only definition of delta application: both materialization
+/// by the extension and application of deltas by the executor use it, and so
+/// must agree on the result.
+pub fn apply_delta(
+    base: u128,
+    state: AggregatorState,
+    value: u128,
+    limit: u128,
+    history: &History,
+) -> Result<u128, BoundViolation> {
+    let delta = match state {
+        AggregatorState::Data => return Ok(value),
+        AggregatorState::PositiveDelta => SignedValue::Plus(value),
+        AggregatorState::NegativeDelta => SignedValue::Minus(value),
+    };
+    history.validate(base, limit)?;
+    BoundedMath::new(limit).expected_value(base, delta)
+}
+
+impl std::fmt::Display for History {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "max +{}, min -{}", self.max_positive, self.min_negative)?;
+        if let Some(delta) = self.min_overflow_positive {
+            write!(f, ", overflow at +{}", delta)?;
+        }
+        if let Some(delta) = self.max_underflow_negative {
+            write!(f, ", underflow at -{}", delta)?;
+        }
+        Ok(())
+    }
+}
+
+/// Layout of `History` with serialization version 0, which did not store
+/// the deltas which failed during execution.
+#[derive(Deserialize)]
+struct HistoryV0 {
+    max_positive: u128,
+    min_negative: u128,
+}
+
+impl From<HistoryV0> for History {
+    fn from(history: HistoryV0) -> Self {
+        History::from_bounds(history.max_positive, history.min_negative)
+    }
+}
+
+impl Versioned for History {
+    const VERSION: u8 = 1;
+
+    fn decode_legacy(version: u8, bytes: &[u8]) -> anyhow::Result<Self> {
+        match version {
+            0 => Ok(bcs::from_bytes::<HistoryV0>(bytes)?.into()),
+            _ => bail!("unsupported history serialization version {}", version),
+        }
+    }
+}
+
+/// Describes the conversion of a delta into a value by `read_and_materialize`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MaterializationEvent {
+    pub id: AggregatorID,
+    /// Value of the aggregator in storage.
+    pub base: u128,
+    /// Delta stored by the aggregator, together with its history.
+    pub delta: DeltaOp,
+    /// Value of the aggregator after materialization.
+    pub result: u128,
+}
+
+/// Callback invoked on every materialization of an aggregator, see
+/// `AggregatorData::on_materialize`.
+#[derive(Clone)]
+pub struct MaterializationCallback(Arc<dyn Fn(&MaterializationEvent) + Send + Sync>);
+
+impl std::fmt::Debug for MaterializationCallback {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "MaterializationCallback")
+    }
 }
 
 /// Internal aggregator data structure.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Aggregator {
     // Describes a value of an aggregator.
     value: u128,
@@ -112,17 +378,71 @@ pub struct Aggregator {
     // lower bound. If we want to support custom lower bounds, or have more
     // complex postconditions, we should factor this out in its own struct.
     limit: u128,
+    // Describes the integer type of the aggregator. The limit always fits
+    // into it.
+    width: AggregatorWidth,
+    // Describes which operations the aggregator supports.
+    kind: AggregatorKind,
     // Describes values seen by this aggregator. Note that if aggregator knows
     // its value, then storing history doesn't make sense.
     history: Option<History>,
+    // Charges gas for operations on this aggregator.
+    gas_hook: Arc<dyn GasMeterHook>,
+    // Operations which are enabled for this aggregator.
+    features: AggregatorFeatures,
+    // Invoked when the delta is converted into a value.
+    on_materialize: Option<MaterializationCallback>,
+}
+
+/// Formats the value of the aggregator, e.g. `42 (limit 1000)` if the value
+/// is known, or the delta with its history, e.g. `+42 (max +100, min -7,
+/// limit 1000)`, otherwise.
+impl std::fmt::Display for Aggregator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.delta_op() {
+            Some(delta) => write!(f, "{}", delta),
+            None => write!(f, "{} (limit {})", self.value, self.limit),
+        }
+    }
 }
 
 impl Aggregator {
+    /// Creates an aggregator which knows its value, e.g. an aggregator V2
+    /// (see `AggregatorV2Data`).
+    pub(crate) fn with_value(
+        value: u128,
+        limit: u128,
+        width: AggregatorWidth,
+        gas_hook: Arc<dyn GasMeterHook>,
+        features: AggregatorFeatures,
+    ) -> Self {
+        Self {
+            value,
+            state: AggregatorState::Data,
+            limit,
+            width,
+            kind: AggregatorKind::Bounded,
+            history: None,
+            gas_hook,
+            features,
+            on_materialize: None,
+        }
+    }
+
+    /// Charges gas for an operation on the value of this aggregator.
+    fn charge(&self, operation: AggregatorOperation) -> PartialVMResult<()> {
+        self.gas_hook.charge(operation, self.width.num_bytes())
+    }
+
     /// Records observed delta in history. Should be called after an operation
     /// to record its side-effects.
     fn record(&mut self) {
+        let unbounded = self.is_unbounded();
         if let Some(history) = self.history.as_mut() {
             match self.state {
+                // Positive deltas of unbounded aggregators are not tracked,
+                // only the final value is checked.
+                AggregatorState::PositiveDelta if unbounded => {},
                 AggregatorState::PositiveDelta => history.record_positive(self.value),
                 AggregatorState::NegativeDelta => history.record_negative(self.value),
                 AggregatorState::Data => {
@@ -132,27 +452,84 @@ impl Aggregator {
         }
     }
 
+    /// Records in history that adding `value` to the delta failed, so that
+    /// validation checks that it still fails for the value in storage.
+    /// Should be called after a failed operation, which leaves the state
+    /// unchanged.
+    fn record_failed_add(&mut self, value: u128) {
+        if let Some(history) = self.history.as_mut() {
+            match self.state {
+                AggregatorState::PositiveDelta => {
+                    history.record_overflow(self.value.saturating_add(value))
+                },
+                // Adding to -X only fails if the result +(Y-X) overflows.
+                AggregatorState::NegativeDelta if value > self.value => {
+                    history.record_overflow(value - self.value)
+                },
+                AggregatorState::NegativeDelta => {},
+                AggregatorState::Data => {
+                    unreachable!("history is not tracked when aggregator knows its value")
+                },
+            }
+        }
+    }
+
+    /// Records in history that subtracting `value` from the delta failed,
+    /// similarly to `record_failed_add`.
+    fn record_failed_sub(&mut self, value: u128) {
+        if let Some(history) = self.history.as_mut() {
+            match self.state {
+                AggregatorState::NegativeDelta => {
+                    history.record_underflow(self.value.saturating_add(value))
+                },
+                // Subtracting from +X only fails if the result -(Y-X) drops
+                // below zero.
+                AggregatorState::PositiveDelta if value > self.value => {
+                    history.record_underflow(value - self.value)
+                },
+                AggregatorState::PositiveDelta => {},
+                AggregatorState::Data => {
+                    unreachable!("history is not tracked when aggregator knows its value")
+                },
+            }
+        }
+    }
+
     /// Validates if aggregator's history is correct when applied to
     /// the `base_value`. For example, if history observed a delta of
     /// +100, and the aggregator limit is 150, then the base value of
     /// 60 will not pass validation (60 + 100 > 150), but the base value
-    /// of 30 will (30 + 100 < 150).
-    fn validate_history(&self, base_value: u128) -> PartialVMResult<()> {
-        let history = self
-            .history
+    /// of 30 will (30 + 100 < 150). On failure, returns the violated
+    /// bound.
+    ///
+    /// Executors can use it to check that the delta of the aggregator is
+    /// still valid for a base value which has changed since execution.
+    /// Panics if the aggregator knows its value, because no history is
+    /// tracked then.
+    pub fn validate_against_base(&self, base_value: u128) -> Result<(), BoundViolation> {
+        self.history
             .as_ref()
-            .expect("History should be set for validation");
-
-        // To validate the history of an aggregator, we want to ensure
-        // that there was no violation of postcondition (i.e. overflows or
-        // underflows). We can do it by emulating addition and subtraction.
-        addition(base_value, history.max_positive, self.limit)?;
-        subtraction(base_value, history.min_negative)?;
-        Ok(())
+            .expect("History should be set for validation")
+            .validate(base_value, self.limit)
     }
 
     /// Implements logic for adding to an aggregator.
     pub fn add(&mut self, value: u128) -> PartialVMResult<()> {
+        self.charge(AggregatorOperation::Add)?;
+        self.add_unmetered(value)
+    }
+
+    /// Adds to an aggregator without charging gas. If the addition fails,
+    /// the failure is recorded in history: execution may continue and
+    /// depend on it (e.g. `try_add`).
+    fn add_unmetered(&mut self, value: u128) -> PartialVMResult<()> {
+        self.apply_add(value).map_err(|err| {
+            self.record_failed_add(value);
+            err
+        })
+    }
+
+    fn apply_add(&mut self, value: u128) -> PartialVMResult<()> {
         match self.state {
             AggregatorState::Data => {
                 // If aggregator knows the value, add directly and keep the state.
@@ -186,6 +563,31 @@ impl Aggregator {
 
     /// Implements logic for subtracting from an aggregator.
     pub fn sub(&mut self, value: u128) -> PartialVMResult<()> {
+        self.check_subtractable()?;
+        self.charge(AggregatorOperation::Sub)?;
+        self.sub_unmetered(value)
+    }
+
+    /// Returns an error if the aggregator does not support subtraction.
+    fn check_subtractable(&self) -> PartialVMResult<()> {
+        match self.kind {
+            AggregatorKind::Bounded | AggregatorKind::Unbounded => Ok(()),
+            AggregatorKind::Monotonic => Err(extension_error(
+                "Cannot subtract from a monotonic aggregator",
+            )),
+        }
+    }
+
+    /// Subtracts from an aggregator without charging gas. Failures are
+    /// recorded in history, similarly to `add_unmetered`.
+    fn sub_unmetered(&mut self, value: u128) -> PartialVMResult<()> {
+        self.apply_sub(value).map_err(|err| {
+            self.record_failed_sub(value);
+            err
+        })
+    }
+
+    fn apply_sub(&mut self, value: u128) -> PartialVMResult<()> {
         match self.state {
             AggregatorState::Data => {
                 // Aggregator knows the value, therefore we can subtract
@@ -227,6 +629,113 @@ impl Aggregator {
         Ok(())
     }
 
+    /// Implements logic for adding to an aggregator, but returns `false`
+    /// instead of an error if the addition violates the limit. In that case
+    /// the value of the aggregator is left unchanged, but if it stores a
+    /// delta, the failure is recorded in history: the transaction relies on
+    /// it, and so validation checks that it still fails for the value in
+    /// storage.
+    pub fn try_add(&mut self, value: u128) -> PartialVMResult<bool> {
+        self.charge(AggregatorOperation::Add)?;
+        // Addition only fails on limit violations, which are detected before
+        // any side-effects other than recording the failure.
+        Ok(self.add_unmetered(value).is_ok())
+    }
+
+    /// Implements logic for subtracting from an aggregator, but returns
+    /// `false` instead of an error if the subtraction goes below zero. Same
+    /// as for `try_add`, the failure is recorded in history.
+    pub fn try_sub(&mut self, value: u128) -> PartialVMResult<bool> {
+        self.check_subtractable()?;
+        self.charge(AggregatorOperation::Sub)?;
+        Ok(self.sub_unmetered(value).is_ok())
+    }
+
+    /// Implements logic for adding to an aggregator, clamping the value at
+    /// the limit instead of failing on overflow. If aggregator stores a delta,
+    /// the result depends on the value in storage. Then, if the addition fits
+    /// the limit, it is recorded as a regular one, so that history validation
+    /// rejects base values for which the value would have been clamped. If
+    /// the addition overflows for any base value, the aggregator is
+    /// materialized and clamped.
+    pub fn saturating_add(
+        &mut self,
+        value: u128,
+        resolver: &dyn AggregatorResolver,
+        id: &AggregatorID,
+    ) -> PartialVMResult<()> {
+        self.charge(AggregatorOperation::Add)?;
+        if self.state != AggregatorState::Data && self.add_unmetered(value).is_ok() {
+            return Ok(());
+        }
+
+        let current = self.materialize(resolver, id)?;
+        self.value = addition(current, value, self.limit).unwrap_or(self.limit);
+        Ok(())
+    }
+
+    /// Implements logic for subtracting from an aggregator, clamping the
+    /// value at zero instead of failing on underflow. Deltas are handled the
+    /// same way as in `saturating_add`.
+    pub fn saturating_sub(
+        &mut self,
+        value: u128,
+        resolver: &dyn AggregatorResolver,
+        id: &AggregatorID,
+    ) -> PartialVMResult<()> {
+        self.check_subtractable()?;
+        self.charge(AggregatorOperation::Sub)?;
+        if self.state != AggregatorState::Data && self.sub_unmetered(value).is_ok() {
+            return Ok(());
+        }
+
+        let current = self.materialize(resolver, id)?;
+        self.value = current.saturating_sub(value);
+        Ok(())
+    }
+
+    /// Checks if the value of an aggregator is at least `threshold` without
+    /// materializing it. If aggregator stores a delta, the check is encoded
+    /// in history by subtracting and adding back `threshold`: it is assumed
+    /// to hold, and history validation rejects base values for which it does
+    /// not. The result is `false` only if the check fails for any base value.
+    /// Fails if bound checks are not enabled.
+    pub fn is_at_least(&mut self, threshold: u128) -> PartialVMResult<bool> {
+        self.features.check_bound_checks()?;
+        self.charge(AggregatorOperation::Read)?;
+        if self.state == AggregatorState::Data {
+            return Ok(self.value >= threshold);
+        }
+
+        if self.sub_unmetered(threshold).is_err() {
+            return Ok(false);
+        }
+        self.add_unmetered(threshold)?;
+        Ok(true)
+    }
+
+    /// Checks if the value of an aggregator is at most `threshold` without
+    /// materializing it. Similarly to `is_at_least`, the check is encoded in
+    /// history by adding and subtracting back the distance from `threshold`
+    /// to the limit.
+    pub fn is_at_most(&mut self, threshold: u128) -> PartialVMResult<bool> {
+        self.features.check_bound_checks()?;
+        self.charge(AggregatorOperation::Read)?;
+        if self.state == AggregatorState::Data {
+            return Ok(self.value <= threshold);
+        }
+        if threshold >= self.limit {
+            return Ok(true);
+        }
+
+        let complement = self.limit - threshold;
+        if self.add_unmetered(complement).is_err() {
+            return Ok(false);
+        }
+        self.sub_unmetered(complement)?;
+        Ok(true)
+    }
+
     /// Implements logic for reading the value of an aggregator. As a
     /// result, the aggregator knows it value (i.e. its state changes to
     /// `Data`).
@@ -234,6 +743,37 @@ impl Aggregator {
         &mut self,
         resolver: &dyn AggregatorResolver,
         id: &AggregatorID,
+    ) -> PartialVMResult<u128> {
+        self.read_and_materialize_with_mode(resolver, id, AggregatorReadMode::Precise)
+    }
+
+    /// Same as `read_and_materialize`, but reads the value from storage in
+    /// the given mode. Non-precise modes let the resolver apply pending
+    /// deltas to the base value without exactness guarantees, and so must
+    /// only be used when the output is not committed (e.g. views).
+    pub fn read_and_materialize_with_mode(
+        &mut self,
+        resolver: &dyn AggregatorResolver,
+        id: &AggregatorID,
+        mode: AggregatorReadMode,
+    ) -> PartialVMResult<u128> {
+        self.charge(AggregatorOperation::Read)?;
+        self.materialize_with_mode(resolver, id, mode)
+    }
+
+    fn materialize(
+        &mut self,
+        resolver: &dyn AggregatorResolver,
+        id: &AggregatorID,
+    ) -> PartialVMResult<u128> {
+        self.materialize_with_mode(resolver, id, AggregatorReadMode::Precise)
+    }
+
+    fn materialize_with_mode(
+        &mut self,
+        resolver: &dyn AggregatorResolver,
+        id: &AggregatorID,
+        mode: AggregatorReadMode,
     ) -> PartialVMResult<u128> {
         // If aggregator has already been read, return immediately.
         if self.state == AggregatorState::Data {
@@ -241,11 +781,78 @@ impl Aggregator {
         }
 
         // Otherwise, we have a delta and have to go to storage and apply it.
-        // In theory, any delta will be applied to existing value. However,
-        // something may go wrong, so we guard by throwing an error in
-        // extension.
-        let value_from_storage = resolver
-            .get_aggregator_v1_value(id.as_state_key(), AggregatorReadMode::Precise)
+        let value_from_storage = read_aggregator_value(resolver, id, mode)?;
+
+        // Validate history and apply the delta.
+        counters::observe(Operation::Materialize);
+        let history = self
+            .history
+            .as_ref()
+            .expect("History should be set in delta state");
+        let value = apply_delta(
+            value_from_storage,
+            self.state,
+            self.value,
+            self.limit,
+            history,
+        )
+        .map_err(|violation| {
+            counters::observe(Operation::ValidationFailure);
+            abort_error(
+                format!(
+                    "cannot materialize {} on base {}: {}",
+                    self, value_from_storage, violation
+                ),
+                violation.code(),
+            )
+        })?;
+        let maybe_delta = self.on_materialize.as_ref().and_then(|_| self.delta_op());
+        self.value = value;
+
+        // Change the state and return the new value. Also, make
+        // sure history is no longer tracked.
+        self.state = AggregatorState::Data;
+        self.history = None;
+        if let (Some(callback), Some(delta)) = (&self.on_materialize, maybe_delta) {
+            (callback.0)(&MaterializationEvent {
+                id: id.clone(),
+                base: value_from_storage,
+                delta,
+                result: self.value,
+            });
+        }
+        Ok(self.value)
+    }
+
+    /// Captures the current value of the aggregator without materializing
+    /// it. If the aggregator stores a delta, the snapshot stores the delta
+    /// together with its history, to be applied to the value in storage.
+    pub fn snapshot(&self, id: &AggregatorID) -> SnapshotValue {
+        match self.delta_op() {
+            None => SnapshotValue::Data(self.value),
+            Some(delta) => SnapshotValue::DeltaOverBase {
+                base: id.clone(),
+                delta,
+            },
+        }
+    }
+
+    /// Returns (lower, upper) bounds of the value of aggregator, without
+    /// materializing it. If aggregator stores a delta, it is applied to the
+    /// bounds of the value in storage provided by the resolver. Nothing is
+    /// recorded in history, so the bounds must not be used where the precise
+    /// value is needed for correctness.
+    pub fn read_bounds(
+        &self,
+        resolver: &dyn AggregatorResolver,
+        id: &AggregatorID,
+    ) -> PartialVMResult<(u128, u128)> {
+        let delta = match self.delta_op() {
+            None => return Ok((self.value, self.value)),
+            Some(delta) => delta,
+        };
+
+        let base_bounds = get_aggregator_value_bounds(resolver, id)
             .map_err(|e| {
                 extension_error(format!("Could not find the value of the aggregator: {}", e))
             })?
@@ -255,86 +862,514 @@ impl Aggregator {
                     id
                 ))
             })?;
+        Ok(delta.apply_to_bounds(base_bounds))
+    }
 
-        // Validate history and apply the delta.
-        self.validate_history(value_from_storage)?;
-        match self.state {
-            AggregatorState::PositiveDelta => {
-                self.value = addition(value_from_storage, self.value, self.limit)
-                    .expect("Validated delta cannot overflow");
-            },
-            AggregatorState::NegativeDelta => {
-                self.value = subtraction(value_from_storage, self.value)
-                    .expect("Validated delta cannot underflow");
-            },
-            AggregatorState::Data => {
-                unreachable!("Materialization only happens in Delta state")
-            },
+    /// Returns the delta stored by the aggregator together with its history,
+    /// or `None` if the aggregator knows its value.
+    fn delta_op(&self) -> Option<DeltaOp> {
+        let update = match self.state {
+            AggregatorState::Data => return None,
+            AggregatorState::PositiveDelta => DeltaUpdate::Plus(self.value),
+            AggregatorState::NegativeDelta => DeltaUpdate::Minus(self.value),
+        };
+        let history = self
+            .history
+            .as_ref()
+            .expect("History should be set in delta state");
+        Some(
+            DeltaOp::new(
+                update,
+                self.limit,
+                history.max_positive(),
+                history.min_negative(),
+            )
+            .with_failure_bounds(
+                history.min_overflow_positive(),
+                history.max_underflow_negative(),
+            )
+            .with_width(self.width),
+        )
+    }
+
+    /// Returns the change to apply to storage: the value is written if it is
+    /// known, and the delta is merged otherwise.
+    pub(crate) fn into_change(self) -> AggregatorChange {
+        match self.delta_op() {
+            None => AggregatorChange::Write(self.value),
+            Some(delta_op) => AggregatorChange::Merge(delta_op),
         }
+    }
 
-        // Change the state and return the new value. Also, make
-        // sure history is no longer tracked.
-        self.state = AggregatorState::Data;
-        self.history = None;
-        Ok(self.value)
+    /// Returns the values seen by the aggregator, or `None` if it knows its
+    /// value and the history is not tracked.
+    pub fn history(&self) -> Option<&History> {
+        self.history.as_ref()
+    }
+
+    /// Returns the value of the aggregator if it is known, without
+    /// materializing it.
+    pub(crate) fn known_value(&self) -> Option<u128> {
+        (self.state == AggregatorState::Data).then_some(self.value)
+    }
+
+    /// Returns whether the aggregator knows its value or stores a delta.
+    pub fn state(&self) -> AggregatorState {
+        self.state
+    }
+
+    /// Returns the upper bound of the value of the aggregator.
+    pub fn limit(&self) -> u128 {
+        self.limit
     }
 
-    /// Unpacks aggregator into its fields.
-    pub fn into(self) -> (u128, AggregatorState, u128, Option<History>) {
-        (self.value, self.state, self.limit, self.history)
+    /// Returns true if the aggregator is unbounded, see
+    /// `AggregatorKind::Unbounded`.
+    pub fn is_unbounded(&self) -> bool {
+        self.kind == AggregatorKind::Unbounded
+    }
+
+    /// Returns the integer type of the aggregator.
+    pub fn width(&self) -> AggregatorWidth {
+        self.width
+    }
+
+    /// Returns which operations the aggregator supports.
+    pub fn kind(&self) -> AggregatorKind {
+        self.kind
+    }
+
+    /// Returns the state of the aggregator, without hooks and callbacks.
+    pub fn into_state(self) -> AggregatorSnapshotState {
+        AggregatorSnapshotState {
+            value: self.value,
+            state: self.state,
+            limit: self.limit,
+            history: self.history,
+        }
     }
 }
 
-/// Stores all information about aggregators (how many have been created or
-/// removed), what are their states, etc. per single transaction).
-#[derive(Default)]
-pub struct AggregatorData {
-    // All aggregators that were created in the current transaction, stored as ids.
-    // Used to filter out aggregators that were created and destroyed in the
-    // within a single transaction.
-    new_aggregators: BTreeSet<AggregatorID>,
-    // All aggregators that were destroyed in the current transaction, stored as ids.
-    destroyed_aggregators: BTreeSet<AggregatorID>,
-    // All aggregator instances that exist in the current transaction.
-    aggregators: BTreeMap<AggregatorID, Aggregator>,
+/// Value, state, limit and history of an aggregator, see
+/// `Aggregator::into_state`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AggregatorSnapshotState {
+    value: u128,
+    state: AggregatorState,
+    limit: u128,
+    history: Option<History>,
 }
 
-impl AggregatorData {
-    /// Returns a mutable reference to an aggregator with `id` and a `limit`.
+impl AggregatorSnapshotState {
+    /// Returns the value if the state is `Data`, or the absolute value of
+    /// the delta otherwise.
+    pub fn value(&self) -> u128 {
+        self.value
+    }
+
+    pub fn state(&self) -> AggregatorState {
+        self.state
+    }
+
+    pub fn limit(&self) -> u128 {
+        self.limit
+    }
+
+    /// Returns the values seen by the aggregator, or `None` if it knows its
+    /// value.
+    pub fn history(&self) -> Option<&History> {
+        self.history.as_ref()
+    }
+}
+
+/// Aggregators used in a transaction, see `AggregatorData::into_data_change_set`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AggregatorDataChangeSet {
+    new_aggregators: BTreeSet<AggregatorID>,
+    destroyed_aggregators: BTreeSet<AggregatorID>,
+    aggregators: BTreeMap<AggregatorID, AggregatorSnapshotState>,
+}
+
+impl AggregatorDataChangeSet {
+    /// Returns aggregators created in the transaction.
+    pub fn new_aggregators(&self) -> &BTreeSet<AggregatorID> {
+        &self.new_aggregators
+    }
+
+    /// Returns aggregators created elsewhere and destroyed in the transaction.
+    pub fn destroyed_aggregators(&self) -> &BTreeSet<AggregatorID> {
+        &self.destroyed_aggregators
+    }
+
+    /// Returns states of all aggregators which exist at the end of the
+    /// transaction.
+    pub fn aggregators(&self) -> &BTreeMap<AggregatorID, AggregatorSnapshotState> {
+        &self.aggregators
+    }
+}
+
+/// Layout of `AggregatorSnapshotState` with serialization version 0, which
+/// stored history without failed deltas.
+#[derive(Deserialize)]
+struct AggregatorSnapshotStateV0 {
+    value: u128,
+    state: AggregatorState,
+    limit: u128,
+    history: Option<HistoryV0>,
+}
+
+impl From<AggregatorSnapshotStateV0> for AggregatorSnapshotState {
+    fn from(state: AggregatorSnapshotStateV0) -> Self {
+        AggregatorSnapshotState {
+            value: state.value,
+            state: state.state,
+            limit: state.limit,
+            history: state.history.map(History::from),
+        }
+    }
+}
+
+impl Versioned for AggregatorSnapshotState {
+    const VERSION: u8 = 1;
+
+    fn decode_legacy(version: u8, bytes: &[u8]) -> anyhow::Result<Self> {
+        match version {
+            0 => Ok(bcs::from_bytes::<AggregatorSnapshotStateV0>(bytes)?.into()),
+            _ => bail!(
+                "unsupported aggregator state serialization version {}",
+                version
+            ),
+        }
+    }
+}
+
+/// Layout of `AggregatorDataChangeSet` with serialization version 0, which
+/// identified aggregators by state keys of table items only, and stored
+/// states of version 0.
+#[derive(Deserialize)]
+struct AggregatorDataChangeSetV0 {
+    new_aggregators: BTreeSet<StateKey>,
+    destroyed_aggregators: BTreeSet<StateKey>,
+    aggregators: BTreeMap<StateKey, AggregatorSnapshotStateV0>,
+}
+
+impl From<AggregatorDataChangeSetV0> for AggregatorDataChangeSet {
+    fn from(change_set: AggregatorDataChangeSetV0) -> Self {
+        let id = |state_key| AggregatorID {
+            state_key,
+            group_tag: None,
+        };
+        AggregatorDataChangeSet {
+            new_aggregators: change_set.new_aggregators.into_iter().map(id).collect(),
+            destroyed_aggregators: change_set
+                .destroyed_aggregators
+                .into_iter()
+                .map(id)
+                .collect(),
+            aggregators: change_set
+                .aggregators
+                .into_iter()
+                .map(|(state_key, state)| (id(state_key), state.into()))
+                .collect(),
+        }
+    }
+}
+
+impl Versioned for AggregatorDataChangeSet {
+    const VERSION: u8 = 1;
+
+    fn decode_legacy(version: u8, bytes: &[u8]) -> anyhow::Result<Self> {
+        match version {
+            0 => Ok(bcs::from_bytes::<AggregatorDataChangeSetV0>(bytes)?.into()),
+            _ => bail!(
+                "unsupported aggregator change set serialization version {}",
+                version
+            ),
+        }
+    }
+}
+
+/// Stores all information about aggregators (how many have been created or
+/// removed), what are their states, etc. per single transaction).
+pub struct AggregatorData {
+    // All aggregators that were created in the current transaction, stored as ids.
+    // Used to filter out aggregators that were created and destroyed in the
+    // within a single transaction.
+    new_aggregators: BTreeSet<AggregatorID>,
+    // All aggregators that were destroyed in the current transaction, stored as ids.
+    destroyed_aggregators: BTreeSet<AggregatorID>,
+    // Final values of aggregators removed with `remove_and_read`.
+    removed_values: BTreeMap<AggregatorID, u128>,
+    // All aggregator instances that exist in the current transaction.
+    aggregators: BTreeMap<AggregatorID, Aggregator>,
+    // All max/min aggregator instances that exist in the current transaction.
+    observed_aggregators: BTreeMap<AggregatorID, ObservedValueAggregator>,
+    // Charges gas for operations on aggregators.
+    gas_hook: Arc<dyn GasMeterHook>,
+    // Operations which are enabled for aggregators.
+    features: AggregatorFeatures,
+    // Invoked on every materialization of an aggregator.
+    on_materialize: Option<MaterializationCallback>,
+}
+
+/// State of `AggregatorData` captured by `AggregatorData::checkpoint`.
+#[derive(Clone, Debug)]
+pub struct AggregatorDataCheckpoint {
+    new_aggregators: BTreeSet<AggregatorID>,
+    destroyed_aggregators: BTreeSet<AggregatorID>,
+    removed_values: BTreeMap<AggregatorID, u128>,
+    aggregators: BTreeMap<AggregatorID, Aggregator>,
+    observed_aggregators: BTreeMap<AggregatorID, ObservedValueAggregator>,
+}
+
+impl Default for AggregatorData {
+    fn default() -> Self {
+        Self::with_gas_hook(Arc::new(NoOpGasMeterHook))
+    }
+}
+
+impl AggregatorData {
+    /// Creates empty aggregator data, charging gas for all operations on
+    /// aggregators with `gas_hook`.
+    pub fn with_gas_hook(gas_hook: Arc<dyn GasMeterHook>) -> Self {
+        Self {
+            new_aggregators: BTreeSet::new(),
+            destroyed_aggregators: BTreeSet::new(),
+            removed_values: BTreeMap::new(),
+            aggregators: BTreeMap::new(),
+            observed_aggregators: BTreeMap::new(),
+            gas_hook,
+            features: AggregatorFeatures::default(),
+            on_materialize: None,
+        }
+    }
+
+    /// Sets the operations which are enabled for aggregators, e.g. based on
+    /// the on-chain feature flags. Must be called before any aggregators are
+    /// used.
+    pub fn with_features(mut self, features: AggregatorFeatures) -> Self {
+        self.features = features;
+        self
+    }
+
+    /// Returns the operations which are enabled for aggregators.
+    pub fn features(&self) -> AggregatorFeatures {
+        self.features
+    }
+
+    /// Returns a mutable reference to an aggregator with `id` and a `limit`.
     /// If transaction that is currently executing did not initialize it, a new aggregator instance is created.
     /// Note: when we say "aggregator instance" here we refer to Rust struct and
     /// not to the Move aggregator.
+    ///
+    /// The limit of an aggregator never changes, so if the instance exists
+    /// with a different limit, the Move-side state is corrupted and an
+    /// invariant violation is returned.
     pub fn get_aggregator(
         &mut self,
         id: AggregatorID,
         limit: u128,
     ) -> PartialVMResult<&mut Aggregator> {
+        self.get_aggregator_with_width(id, limit, AggregatorWidth::U128)
+    }
+
+    /// Same as `get_aggregator`, but for aggregators storing values of the
+    /// given `width`. Returns an error if the limit does not fit into it.
+    pub fn get_aggregator_with_width(
+        &mut self,
+        id: AggregatorID,
+        limit: u128,
+        width: AggregatorWidth,
+    ) -> PartialVMResult<&mut Aggregator> {
+        self.get_or_insert_aggregator(id, limit, width, AggregatorKind::Bounded)
+    }
+
+    /// Same as `get_aggregator`, but for aggregators which only support
+    /// additions, see `AggregatorKind::Monotonic`.
+    pub fn get_monotonic_aggregator(
+        &mut self,
+        id: AggregatorID,
+        limit: u128,
+    ) -> PartialVMResult<&mut Aggregator> {
+        self.get_or_insert_aggregator(id, limit, AggregatorWidth::U128, AggregatorKind::Monotonic)
+    }
+
+    /// Same as `get_aggregator`, but for aggregators which have no meaningful
+    /// limit, see `AggregatorKind::Unbounded`.
+    pub fn get_unbounded_aggregator(
+        &mut self,
+        id: AggregatorID,
+    ) -> PartialVMResult<&mut Aggregator> {
+        self.get_or_insert_aggregator(
+            id,
+            UNBOUNDED_LIMIT,
+            AggregatorWidth::U128,
+            AggregatorKind::Unbounded,
+        )
+    }
+
+    fn get_or_insert_aggregator(
+        &mut self,
+        id: AggregatorID,
+        limit: u128,
+        width: AggregatorWidth,
+        kind: AggregatorKind,
+    ) -> PartialVMResult<&mut Aggregator> {
+        width.check(limit)?;
+        if let Some(aggregator) = self.aggregators.get(&id) {
+            if aggregator.limit != limit {
+                return Err(mismatch_error(&id, "limit", aggregator.limit, limit));
+            }
+            if aggregator.kind != kind {
+                return Err(mismatch_error(&id, "kind", aggregator.kind, kind));
+            }
+        }
         let aggregator = self.aggregators.entry(id).or_insert(Aggregator {
             value: 0,
             state: AggregatorState::PositiveDelta,
             limit,
+            width,
+            kind,
             history: Some(History::new()),
+            gas_hook: self.gas_hook.clone(),
+            features: self.features,
+            on_materialize: self.on_materialize.clone(),
         });
         Ok(aggregator)
     }
 
+    /// Returns a mutable reference to a max or min aggregator with `id`,
+    /// creating a new instance which does not know its value if it is not
+    /// used in the current transaction yet, like `get_aggregator`. Returns an
+    /// error if the aggregator tracks a different extremum.
+    pub fn get_observed_aggregator(
+        &mut self,
+        id: AggregatorID,
+        kind: ObservedKind,
+    ) -> PartialVMResult<&mut ObservedValueAggregator> {
+        if let Some(aggregator) = self.observed_aggregators.get(&id) {
+            if aggregator.kind() != kind {
+                return Err(mismatch_error(&id, "kind", aggregator.kind(), kind));
+            }
+        }
+        let gas_hook = self.gas_hook.clone();
+        Ok(self
+            .observed_aggregators
+            .entry(id)
+            .or_insert_with(|| ObservedValueAggregator::new(kind, None, gas_hook)))
+    }
+
+    /// Creates a new max or min aggregator with `id`, which knows its initial
+    /// `value`.
+    pub fn create_new_observed_aggregator(
+        &mut self,
+        id: AggregatorID,
+        kind: ObservedKind,
+        value: u128,
+    ) {
+        let aggregator = ObservedValueAggregator::new(kind, Some(value), self.gas_hook.clone());
+        self.observed_aggregators.insert(id.clone(), aggregator);
+        self.new_aggregators.insert(id);
+        counters::observe(Operation::Create);
+    }
+
+    /// Returns an aggregator with `id` if it is used in the current
+    /// transaction. Unlike `get_aggregator`, no new instance is created, so
+    /// that inspecting an aggregator does not add it to the change set.
+    pub fn get_aggregator_if_exists(&self, id: &AggregatorID) -> Option<&Aggregator> {
+        self.aggregators.get(id)
+    }
+
     /// Returns the number of aggregators that are used in the current transaction.
     pub fn num_aggregators(&self) -> u128 {
         self.aggregators.len() as u128
     }
 
+    /// Returns the number of aggregators used in the current transaction
+    /// which are in the given state.
+    pub fn num_aggregators_in_state(&self, state: AggregatorState) -> usize {
+        self.aggregators
+            .values()
+            .filter(|aggregator| aggregator.state == state)
+            .count()
+    }
+
+    /// Iterates over all aggregators used in the current transaction, in the
+    /// order of their identifiers.
+    pub fn aggregators(&self) -> impl Iterator<Item = (&AggregatorID, &Aggregator)> {
+        self.aggregators.iter()
+    }
+
+    /// Iterates over identifiers of aggregators created in the current
+    /// transaction.
+    pub fn new_aggregator_ids(&self) -> impl Iterator<Item = &AggregatorID> {
+        self.new_aggregators.iter()
+    }
+
+    /// Iterates over identifiers of aggregators destroyed in the current
+    /// transaction, which were created elsewhere.
+    pub fn destroyed_aggregator_ids(&self) -> impl Iterator<Item = &AggregatorID> {
+        self.destroyed_aggregators.iter()
+    }
+
     /// Creates and a new Aggregator with a given `id` and a `limit`. The value
     /// of a new aggregator is always known, therefore it is created in a data
     /// state, with a zero-initialized value.
     pub fn create_new_aggregator(&mut self, id: AggregatorID, limit: u128) {
+        self.insert_new_aggregator(id, limit, AggregatorWidth::U128, AggregatorKind::Bounded);
+    }
+
+    /// Same as `create_new_aggregator`, but for aggregators which only
+    /// support additions, see `AggregatorKind::Monotonic`.
+    pub fn create_new_monotonic_aggregator(&mut self, id: AggregatorID, limit: u128) {
+        self.insert_new_aggregator(id, limit, AggregatorWidth::U128, AggregatorKind::Monotonic);
+    }
+
+    /// Same as `create_new_aggregator`, but for aggregators which have no
+    /// meaningful limit, see `AggregatorKind::Unbounded`.
+    pub fn create_new_unbounded_aggregator(&mut self, id: AggregatorID) {
+        self.insert_new_aggregator(
+            id,
+            UNBOUNDED_LIMIT,
+            AggregatorWidth::U128,
+            AggregatorKind::Unbounded,
+        );
+    }
+
+    /// Same as `create_new_aggregator`, but for aggregators storing values of
+    /// the given `width`. Returns an error if the limit does not fit into it.
+    pub fn create_new_aggregator_with_width(
+        &mut self,
+        id: AggregatorID,
+        limit: u128,
+        width: AggregatorWidth,
+    ) -> PartialVMResult<()> {
+        width.check(limit)?;
+        self.insert_new_aggregator(id, limit, width, AggregatorKind::Bounded);
+        Ok(())
+    }
+
+    fn insert_new_aggregator(
+        &mut self,
+        id: AggregatorID,
+        limit: u128,
+        width: AggregatorWidth,
+        kind: AggregatorKind,
+    ) {
         let aggregator = Aggregator {
             value: 0,
             state: AggregatorState::Data,
             limit,
+            width,
+            kind,
             history: None,
+            gas_hook: self.gas_hook.clone(),
+            features: self.features,
+            on_materialize: self.on_materialize.clone(),
         };
         self.aggregators.insert(id.clone(), aggregator);
         self.new_aggregators.insert(id);
+        counters::observe(Operation::Create);
     }
 
     /// If aggregator has been used in this transaction, it is removed. Otherwise,
@@ -342,6 +1377,7 @@ impl AggregatorData {
     pub fn remove_aggregator(&mut self, id: AggregatorID) {
         // Aggregator no longer in use during this transaction: remove it.
         self.aggregators.remove(&id);
+        self.observed_aggregators.remove(&id);
 
         if self.new_aggregators.contains(&id) {
             // Aggregator has been created in the same transaction. Therefore, no
@@ -351,24 +1387,182 @@ impl AggregatorData {
             // Otherwise, aggregator has been created somewhere else.
             self.destroyed_aggregators.insert(id);
         }
+        counters::observe(Operation::Delete);
+    }
+
+    /// Checks that all aggregators destroyed in this transaction, which were
+    /// not created in it, exist in storage. Can be called before converting
+    /// into a change set, so that an inconsistent deletion is reported as an
+    /// error instead of failing when the change set is committed.
+    pub fn validate_deletions(&self, resolver: &dyn AggregatorResolver) -> PartialVMResult<()> {
+        for id in &self.destroyed_aggregators {
+            let maybe_value = get_aggregator_value(resolver, id, AggregatorReadMode::Precise)
+                .map_err(|e| {
+                    extension_error(format!("Could not find the value of the aggregator: {}", e))
+                })?;
+            if maybe_value.is_none() {
+                return Err(extension_error(format!(
+                    "Cannot delete aggregator {:?} which does not exist in storage",
+                    id
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes an aggregator like `remove_aggregator`, returning its final
+    /// value. If aggregator stores a delta, it is materialized first, so that
+    /// the aggregator is left intact if the value cannot be read. The value is
+    /// recorded in the change set together with the deletion.
+    pub fn remove_and_read(
+        &mut self,
+        id: AggregatorID,
+        resolver: &dyn AggregatorResolver,
+    ) -> PartialVMResult<u128> {
+        let value = match self.aggregators.get_mut(&id) {
+            Some(aggregator) => aggregator.read_and_materialize(resolver, &id)?,
+            None => {
+                // Aggregator is not used in this transaction, and so its
+                // value is the one in storage.
+                self.gas_hook
+                    .charge(AggregatorOperation::Read, AggregatorWidth::U128.num_bytes())?;
+                read_aggregator_value(resolver, &id, AggregatorReadMode::Precise)?
+            },
+        };
+        self.removed_values.insert(id.clone(), value);
+        self.remove_aggregator(id);
+        Ok(value)
+    }
+
+    /// Captures the current state of all aggregators, so that
+    /// changes made afterwards (e.g. by a nested native call which failed)
+    /// can be undone with `rollback_to`.
+    pub fn checkpoint(&self) -> AggregatorDataCheckpoint {
+        AggregatorDataCheckpoint {
+            new_aggregators: self.new_aggregators.clone(),
+            destroyed_aggregators: self.destroyed_aggregators.clone(),
+            removed_values: self.removed_values.clone(),
+            aggregators: self.aggregators.clone(),
+            observed_aggregators: self.observed_aggregators.clone(),
+        }
+    }
+
+    /// Restores the state captured by `checkpoint`. All aggregators created,
+    /// removed or modified after the checkpoint are restored.
+    pub fn rollback_to(&mut self, checkpoint: AggregatorDataCheckpoint) {
+        let AggregatorDataCheckpoint {
+            new_aggregators,
+            destroyed_aggregators,
+            removed_values,
+            aggregators,
+            observed_aggregators,
+        } = checkpoint;
+        self.new_aggregators = new_aggregators;
+        self.destroyed_aggregators = destroyed_aggregators;
+        self.removed_values = removed_values;
+        self.aggregators = aggregators;
+        self.observed_aggregators = observed_aggregators;
+        // Aggregators captured before a callback was registered must invoke
+        // it as well.
+        self.propagate_materialization_callback();
     }
 
-    /// Unpacks aggregator data.
-    pub fn into(
-        self,
-    ) -> (
-        BTreeSet<AggregatorID>,
-        BTreeSet<AggregatorID>,
-        BTreeMap<AggregatorID, Aggregator>,
+    /// Registers a callback invoked whenever an aggregator which stores a
+    /// delta is materialized, e.g. to record transitions for conflict
+    /// analysis. Replaces the previously registered callback, also for
+    /// aggregators which are already used.
+    pub fn on_materialize(
+        &mut self,
+        callback: impl Fn(&MaterializationEvent) + Send + Sync + 'static,
     ) {
-        (
-            self.new_aggregators,
-            self.destroyed_aggregators,
-            self.aggregators,
-        )
+        self.on_materialize = Some(MaterializationCallback(Arc::new(callback)));
+        self.propagate_materialization_callback();
+    }
+
+    fn propagate_materialization_callback(&mut self) {
+        for aggregator in self.aggregators.values_mut() {
+            aggregator.on_materialize = self.on_materialize.clone();
+        }
+    }
+
+    /// Returns all changes made to aggregators: values of aggregators which
+    /// know their value are written, deltas are merged with values in
+    /// storage, and destroyed aggregators are deleted. Changes are ordered by
+    /// identifier and do not depend on the order of operations, because
+    /// write sets built from them are hashed.
+    pub fn into_change_set(self) -> AggregatorChangeSet {
+        let mut changes = BTreeMap::new();
+
+        // First, process all writes and deltas.
+        for (id, aggregator) in self.aggregators {
+            changes.insert(id, aggregator.into_change());
+        }
+
+        for (id, aggregator) in self.observed_aggregators {
+            if let Some(change) = aggregator.into_change() {
+                changes.insert(id, change);
+            }
+        }
+
+        // Additionally, do not forget to delete destroyed values from storage.
+        for id in self.destroyed_aggregators {
+            changes.insert(id, AggregatorChange::Delete);
+        }
+
+        AggregatorChangeSet {
+            changes,
+            removed_values: self.removed_values,
+            new_aggregators: self.new_aggregators,
+        }
+    }
+
+    /// Returns all aggregators used in the transaction, with their states,
+    /// ordered by identifier.
+    pub fn into_data_change_set(self) -> AggregatorDataChangeSet {
+        AggregatorDataChangeSet {
+            new_aggregators: self.new_aggregators,
+            destroyed_aggregators: self.destroyed_aggregators,
+            aggregators: self
+                .aggregators
+                .into_iter()
+                .map(|(id, aggregator)| (id, aggregator.into_state()))
+                .collect(),
+        }
     }
 }
 
+/// Reads the value of aggregator from storage. In theory, any delta will be
+/// applied to existing value. However, something may go wrong, so we guard by
+/// throwing an error in extension.
+pub(crate) fn read_aggregator_value(
+    resolver: &dyn AggregatorResolver,
+    id: &AggregatorID,
+    mode: AggregatorReadMode,
+) -> PartialVMResult<u128> {
+    get_aggregator_value(resolver, id, mode)
+        .map_err(|e| extension_error(format!("Could not find the value of the aggregator: {}", e)))?
+        .ok_or_else(|| {
+            extension_error(format!(
+                "Could not read from deleted aggregator at {:?}",
+                id
+            ))
+        })
+}
+
+/// Returns an invariant violation if an aggregator is used with a different
+/// limit or kind than it was created with.
+fn mismatch_error(
+    id: &AggregatorID,
+    property: &str,
+    expected: impl std::fmt::Debug,
+    found: impl std::fmt::Debug,
+) -> PartialVMError {
+    PartialVMError::new(StatusCode::UNKNOWN_INVARIANT_VIOLATION_ERROR).with_message(format!(
+        "Aggregator {:?} has {} {:?}, but is used with {} {:?}",
+        id, property, expected, property, found
+    ))
+}
+
 /// Returns partial VM error on extension failure.
 pub fn extension_error(message: impl ToString) -> PartialVMError {
     PartialVMError::new(StatusCode::VM_EXTENSION_ERROR).with_message(message.to_string())
@@ -379,9 +1573,13 @@ pub fn extension_error(message: impl ToString) -> PartialVMError {
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::{aggregator_id_for_test, AggregatorStore};
-    use claims::{assert_err, assert_ok};
+    use crate::{
+        aggregator_id_for_test, delta_change_set::delta_add, gas_hook::RecordingGasMeterHook,
+        observed_value::ObservedDelta, AggregatorStore,
+    };
+    use claims::{assert_err, assert_ok, assert_ok_eq};
     use once_cell::sync::Lazy;
+    use std::str::FromStr;
 
     #[allow(clippy::redundant_closure)]
     static TEST_RESOLVER: Lazy<AggregatorStore> = Lazy::new(|| AggregatorStore::default());
@@ -499,6 +1697,256 @@ mod test {
         assert_err!(aggregator.sub(2));
     }
 
+    #[test]
+    fn test_try_add_and_sub() {
+        let mut aggregator_data = AggregatorData::default();
+
+        let aggregator = aggregator_data
+            .get_aggregator(aggregator_id_for_test(600), 600)
+            .expect("Get aggregator failed");
+        assert_ok_eq!(aggregator.try_add(400), true);
+        assert_ok_eq!(aggregator.try_add(201), false);
+        assert_ok_eq!(aggregator.try_sub(1000), false);
+        assert_ok_eq!(aggregator.try_sub(500), true);
+
+        // Failed operations do not change the value, but are recorded in
+        // history: +601 overflows and -600 drops below zero.
+        assert_eq!(aggregator.value, 100);
+        assert_eq!(aggregator.state, AggregatorState::NegativeDelta);
+        assert_eq!(
+            aggregator.history(),
+            Some(&History::from_bounds(400, 100).with_failure_bounds(Some(601), Some(600)))
+        );
+
+        // Failures are carried over to the delta, and are validated against
+        // the value in storage together with the rest of the history. Here,
+        // subtracting 750 after +200 results in -550.
+        let id = aggregator_id_for_test(700);
+        let aggregator = aggregator_data
+            .get_aggregator(id, 700)
+            .expect("Get aggregator failed");
+        assert_ok!(aggregator.add(200));
+        assert_err!(aggregator.sub(750));
+        let delta = aggregator.delta_op().unwrap();
+        assert_eq!(delta.history().max_underflow_negative(), Some(550));
+        assert_ok!(delta.validate(500));
+        assert_err!(delta.validate(501));
+
+        // The same holds when the value is known.
+        aggregator_data.create_new_aggregator(aggregator_id_for_test(200), 200);
+        let aggregator = aggregator_data
+            .get_aggregator(aggregator_id_for_test(200), 200)
+            .expect("Get aggregator failed");
+        assert_ok_eq!(aggregator.try_sub(1), false);
+        assert_ok_eq!(aggregator.try_add(200), true);
+        assert_ok_eq!(aggregator.try_add(1), false);
+        assert_eq!(aggregator.value, 200);
+    }
+
+    #[test]
+    fn test_saturating_add_and_sub() {
+        let mut aggregator_data = AggregatorData::default();
+        let mut resolver = AggregatorStore::default();
+        resolver.set_from_id(aggregator_id_for_test(600), 300);
+
+        // Known values are clamped directly.
+        let id = aggregator_id_for_test(200);
+        aggregator_data.create_new_aggregator(id.clone(), 200);
+        let aggregator = aggregator_data
+            .get_aggregator(id.clone(), 200)
+            .expect("Get aggregator failed");
+        assert_ok!(aggregator.saturating_add(500, &resolver, &id));
+        assert_eq!(aggregator.value, 200);
+        assert_ok!(aggregator.saturating_sub(500, &resolver, &id));
+        assert_eq!(aggregator.value, 0);
+
+        // Deltas which fit the limit are speculative, and validation rejects
+        // base values which would have saturated.
+        let id = aggregator_id_for_test(600);
+        let aggregator = aggregator_data
+            .get_aggregator(id.clone(), 600)
+            .expect("Get aggregator failed");
+        assert_ok!(aggregator.saturating_add(250, &resolver, &id));
+        assert_eq!(aggregator.state, AggregatorState::PositiveDelta);
+        assert_ok!(aggregator.validate_against_base(350));
+        assert_err!(aggregator.validate_against_base(351));
+
+        // Deltas which overflow for any base value materialize.
+        assert_ok!(aggregator.saturating_add(400, &resolver, &id));
+        assert_eq!(aggregator.state, AggregatorState::Data);
+        assert_eq!(aggregator.value, 600);
+    }
+
+    #[test]
+    fn test_bound_checks() {
+        let mut aggregator_data =
+            AggregatorData::default().with_features(AggregatorFeatures::all());
+
+        aggregator_data.create_new_aggregator(aggregator_id_for_test(200), 200);
+        let aggregator = aggregator_data
+            .get_aggregator(aggregator_id_for_test(200), 200)
+            .expect("Get aggregator failed");
+        assert_ok!(aggregator.add(100));
+        assert_ok_eq!(aggregator.is_at_least(100), true);
+        assert_ok_eq!(aggregator.is_at_least(101), false);
+        assert_ok_eq!(aggregator.is_at_most(100), true);
+        assert_ok_eq!(aggregator.is_at_most(99), false);
+
+        // Checks on deltas are recorded in history, without changing the
+        // delta itself.
+        let aggregator = aggregator_data
+            .get_aggregator(aggregator_id_for_test(600), 600)
+            .expect("Get aggregator failed");
+        assert_ok!(aggregator.add(100));
+        assert_ok_eq!(aggregator.is_at_least(300), true);
+        assert_ok_eq!(aggregator.is_at_most(500), true);
+        assert_eq!(aggregator.value, 100);
+        assert_eq!(aggregator.state, AggregatorState::PositiveDelta);
+
+        // Only base values in [200, 400] satisfy both checks.
+        assert_err!(aggregator.validate_against_base(199));
+        assert_ok!(aggregator.validate_against_base(200));
+        assert_ok!(aggregator.validate_against_base(400));
+        assert_err!(aggregator.validate_against_base(401));
+
+        // Checks which cannot hold for any base value are false.
+        assert_ok_eq!(aggregator.is_at_least(601), false);
+        assert_ok!(aggregator.sub(400));
+        assert_ok_eq!(aggregator.is_at_most(0), true);
+        assert_ok_eq!(aggregator.is_at_least(301), false);
+
+        // Bound checks are disabled unless enabled by feature flags.
+        let mut aggregator_data = AggregatorData::default();
+        let aggregator = aggregator_data
+            .get_aggregator(aggregator_id_for_test(600), 600)
+            .expect("Get aggregator failed");
+        let error = assert_err!(aggregator.is_at_least(300));
+        assert_eq!(error.major_status(), StatusCode::FEATURE_UNDER_GATING);
+        let error = assert_err!(aggregator.is_at_most(500));
+        assert_eq!(error.major_status(), StatusCode::FEATURE_UNDER_GATING);
+    }
+
+    #[test]
+    fn test_checkpoint_and_rollback() {
+        let mut aggregator_data = AggregatorData::default();
+
+        aggregator_data.create_new_aggregator(aggregator_id_for_test(100), 100);
+        aggregator_data
+            .get_aggregator(aggregator_id_for_test(200), 200)
+            .expect("Get aggregator failed")
+            .add(50)
+            .unwrap();
+        let checkpoint = aggregator_data.checkpoint();
+
+        // Changes after the checkpoint: modify, create and remove.
+        aggregator_data
+            .get_aggregator(aggregator_id_for_test(200), 200)
+            .expect("Get aggregator failed")
+            .add(100)
+            .unwrap();
+        aggregator_data.create_new_aggregator(aggregator_id_for_test(300), 300);
+        aggregator_data.remove_aggregator(aggregator_id_for_test(100));
+        aggregator_data.remove_aggregator(aggregator_id_for_test(400));
+        assert_eq!(aggregator_data.num_aggregators(), 2);
+
+        aggregator_data.rollback_to(checkpoint);
+
+        let change_set = aggregator_data.into_data_change_set();
+        assert_eq!(
+            change_set.new_aggregators(),
+            &BTreeSet::from([aggregator_id_for_test(100)])
+        );
+        assert!(change_set.destroyed_aggregators().is_empty());
+        assert_eq!(change_set.aggregators().len(), 2);
+        let aggregator = change_set
+            .aggregators()
+            .get(&aggregator_id_for_test(200))
+            .unwrap();
+        assert_eq!(aggregator.value(), 50);
+        assert_eq!(aggregator.state(), AggregatorState::PositiveDelta);
+        assert_eq!(aggregator.history(), Some(&History::from_bounds(50, 0)));
+
+        let bytes = bcs::to_bytes(&change_set).unwrap();
+        assert_ok_eq!(
+            bcs::from_bytes::<AggregatorDataChangeSet>(&bytes),
+            change_set
+        );
+    }
+
+    #[test]
+    fn test_materialization_callback() {
+        let mut aggregator_data = AggregatorData::default();
+        let mut resolver = AggregatorStore::default();
+        resolver.set_from_id(aggregator_id_for_test(600), 300);
+        resolver.set_from_id(aggregator_id_for_test(700), 50);
+
+        // Aggregators used before registration invoke the callback as well.
+        aggregator_data
+            .get_aggregator(aggregator_id_for_test(600), 600)
+            .expect("Get aggregator failed")
+            .add(100)
+            .unwrap();
+        let events = Arc::new(std::sync::Mutex::new(vec![]));
+        let recorded = events.clone();
+        aggregator_data.on_materialize(move |event| recorded.lock().unwrap().push(event.clone()));
+
+        let aggregator = aggregator_data
+            .get_aggregator(aggregator_id_for_test(600), 600)
+            .expect("Get aggregator failed");
+        assert_ok_eq!(
+            aggregator.read_and_materialize(&resolver, &aggregator_id_for_test(600)),
+            400
+        );
+        // Reading a known value does not convert a delta.
+        assert_ok_eq!(
+            aggregator.read_and_materialize(&resolver, &aggregator_id_for_test(600)),
+            400
+        );
+
+        // Failed validation does not convert a delta either.
+        let aggregator = aggregator_data
+            .get_aggregator(aggregator_id_for_test(700), 700)
+            .expect("Get aggregator failed");
+        assert_ok!(aggregator.sub(100));
+        assert_err!(aggregator.read_and_materialize(&resolver, &aggregator_id_for_test(700)));
+
+        assert_eq!(*events.lock().unwrap(), vec![MaterializationEvent {
+            id: aggregator_id_for_test(600),
+            base: 300,
+            delta: DeltaOp::new(DeltaUpdate::Plus(100), 600, 100, 0),
+            result: 400,
+        }]);
+    }
+
+    #[test]
+    fn test_read_bounds() {
+        let mut aggregator_data = AggregatorData::default();
+        let mut resolver = AggregatorStore::default();
+        resolver.set_from_id(aggregator_id_for_test(600), 300);
+
+        aggregator_data.create_new_aggregator(aggregator_id_for_test(200), 200);
+        let aggregator = aggregator_data
+            .get_aggregator(aggregator_id_for_test(200), 200)
+            .expect("Get aggregator failed");
+        assert_ok!(aggregator.add(100));
+        assert_ok_eq!(
+            aggregator.read_bounds(&resolver, &aggregator_id_for_test(200)),
+            (100, 100)
+        );
+
+        // Reading bounds does not materialize the aggregator.
+        let aggregator = aggregator_data
+            .get_aggregator(aggregator_id_for_test(600), 600)
+            .expect("Get aggregator failed");
+        assert_ok!(aggregator.sub(100));
+        assert_ok_eq!(
+            aggregator.read_bounds(&resolver, &aggregator_id_for_test(600)),
+            (200, 200)
+        );
+        assert_eq!(aggregator.state, AggregatorState::NegativeDelta);
+        assert_err!(aggregator.read_bounds(&resolver, &aggregator_id_for_test(700)));
+    }
+
     #[test]
     fn test_commutative() {
         let mut aggregator_data = AggregatorData::default();
@@ -533,6 +1981,642 @@ mod test {
         assert_eq!(aggregator.state, AggregatorState::PositiveDelta);
     }
 
+    #[test]
+    fn test_versioned_serialization() {
+        let history = History::from_bounds(200, 100).with_failure_bounds(Some(500), None);
+        assert_ok_eq!(
+            History::from_versioned_bytes(&history.to_versioned_bytes()),
+            history
+        );
+
+        // History serialized before failures were tracked can still be read.
+        let mut version_0 = vec![0];
+        version_0.extend(bcs::to_bytes(&(200u128, 100u128)).unwrap());
+        assert_ok_eq!(
+            History::from_versioned_bytes(&version_0),
+            History::from_bounds(200, 100)
+        );
+
+        let mut aggregator_data = AggregatorData::default();
+        aggregator_data.create_new_aggregator(aggregator_id_for_test(100), 100);
+        assert_ok!(aggregator_data
+            .get_aggregator(aggregator_id_for_test(200), 200)
+            .expect("Get aggregator failed")
+            .add(50));
+        aggregator_data.remove_aggregator(aggregator_id_for_test(400));
+        let change_set = aggregator_data.into_data_change_set();
+        assert_ok_eq!(
+            AggregatorDataChangeSet::from_versioned_bytes(&change_set.to_versioned_bytes()),
+            change_set.clone()
+        );
+
+        // Change sets serialized before aggregators could be stored in
+        // resource groups can still be read.
+        let key = |n| aggregator_id_for_test(n).into_state_key();
+        let mut version_0 = vec![0];
+        version_0.extend(
+            bcs::to_bytes(&(
+                BTreeSet::from([key(100)]),
+                BTreeSet::from([key(400)]),
+                BTreeMap::from([
+                    (key(100), (0u128, AggregatorState::Data, 100u128, None)),
+                    (
+                        key(200),
+                        (
+                            50u128,
+                            AggregatorState::PositiveDelta,
+                            200u128,
+                            Some((50u128, 0u128)),
+                        ),
+                    ),
+                ]),
+            ))
+            .unwrap(),
+        );
+        assert_ok_eq!(
+            AggregatorDataChangeSet::from_versioned_bytes(&version_0),
+            change_set
+        );
+
+        // Unknown versions and malformed bytes are rejected.
+        assert_err!(History::from_versioned_bytes(&[History::VERSION + 1]));
+        assert_err!(AggregatorDataChangeSet::from_versioned_bytes(&[]));
+        assert_err!(AggregatorSnapshotState::from_versioned_bytes(&[
+            AggregatorSnapshotState::VERSION,
+            1
+        ]));
+    }
+
+    #[test]
+    fn test_history_access() {
+        let mut aggregator_data = AggregatorData::default();
+        aggregator_data.create_new_aggregator(aggregator_id_for_test(200), 200);
+
+        // History is not tracked for known values.
+        let aggregator = aggregator_data
+            .get_aggregator(aggregator_id_for_test(200), 200)
+            .expect("Get aggregator failed");
+        assert!(aggregator.history().is_none());
+
+        let aggregator = aggregator_data
+            .get_aggregator(aggregator_id_for_test(600), 600)
+            .expect("Get aggregator failed");
+        assert_ok!(aggregator.add(200));
+        assert_ok!(aggregator.sub(300));
+
+        let history = *aggregator.history().unwrap();
+        assert_eq!(history.max_positive(), 200);
+        assert_eq!(history.min_negative(), 100);
+
+        let bytes = bcs::to_bytes(&history).unwrap();
+        assert_ok_eq!(bcs::from_bytes::<History>(&bytes), history);
+    }
+
+    #[test]
+    fn test_validate_deletions() {
+        let mut aggregator_data = AggregatorData::default();
+        let resolver = AggregatorStore::builder()
+            .with_value(aggregator_id_for_test(500), 100)
+            .with_read_error(aggregator_id_for_test(600))
+            .build();
+
+        // Aggregators created in the same transaction are not checked.
+        aggregator_data.create_new_aggregator(aggregator_id_for_test(200), 200);
+        aggregator_data.remove_aggregator(aggregator_id_for_test(200));
+        aggregator_data.remove_aggregator(aggregator_id_for_test(500));
+        assert_ok!(aggregator_data.validate_deletions(&resolver));
+
+        let checkpoint = aggregator_data.checkpoint();
+        aggregator_data.remove_aggregator(aggregator_id_for_test(600));
+        assert_err!(aggregator_data.validate_deletions(&resolver));
+
+        aggregator_data.rollback_to(checkpoint);
+        aggregator_data.remove_aggregator(aggregator_id_for_test(700));
+        assert_err!(aggregator_data.validate_deletions(&resolver));
+    }
+
+    #[test]
+    fn test_remove_and_read() {
+        let mut aggregator_data = AggregatorData::default();
+        let resolver = AggregatorStore::builder()
+            .with_value(aggregator_id_for_test(500), 100)
+            .with_value(aggregator_id_for_test(600), 300)
+            .build();
+
+        // New aggregators are removed without side-effects.
+        aggregator_data.create_new_aggregator(aggregator_id_for_test(200), 200);
+        assert_ok!(aggregator_data
+            .get_aggregator(aggregator_id_for_test(200), 200)
+            .unwrap()
+            .add(20));
+        assert_ok_eq!(
+            aggregator_data.remove_and_read(aggregator_id_for_test(200), &resolver),
+            20
+        );
+
+        // Deltas are materialized before removal.
+        let aggregator = aggregator_data
+            .get_aggregator(aggregator_id_for_test(600), 600)
+            .expect("Get aggregator failed");
+        assert_ok!(aggregator.add(100));
+        assert_ok_eq!(
+            aggregator_data.remove_and_read(aggregator_id_for_test(600), &resolver),
+            400
+        );
+
+        // Aggregators not used in this transaction are read from storage.
+        assert_ok_eq!(
+            aggregator_data.remove_and_read(aggregator_id_for_test(500), &resolver),
+            100
+        );
+
+        // If the value cannot be read, the aggregator is not removed.
+        let aggregator = aggregator_data
+            .get_aggregator(aggregator_id_for_test(700), 700)
+            .expect("Get aggregator failed");
+        assert_ok!(aggregator.add(10));
+        assert_err!(aggregator_data.remove_and_read(aggregator_id_for_test(700), &resolver));
+        assert_eq!(aggregator_data.num_aggregators(), 1);
+
+        let change_set = aggregator_data.into_change_set();
+        assert_eq!(
+            change_set.changes,
+            BTreeMap::from([
+                (aggregator_id_for_test(500), AggregatorChange::Delete),
+                (aggregator_id_for_test(600), AggregatorChange::Delete),
+                (
+                    aggregator_id_for_test(700),
+                    AggregatorChange::Merge(delta_add(10, 700))
+                ),
+            ])
+        );
+        assert_eq!(
+            change_set.removed_values,
+            BTreeMap::from([
+                (aggregator_id_for_test(200), 20),
+                (aggregator_id_for_test(500), 100),
+                (aggregator_id_for_test(600), 400),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_change_set_ordering() {
+        let ids: Vec<_> = [300, 100, 600, 200, 500, 400]
+            .into_iter()
+            .map(aggregator_id_for_test)
+            .collect();
+        let operations: Vec<Box<dyn Fn(&mut AggregatorData)>> = vec![
+            Box::new(|data: &mut AggregatorData| {
+                data.create_new_aggregator(aggregator_id_for_test(300), 300);
+                assert_ok!(data
+                    .get_aggregator(aggregator_id_for_test(300), 300)
+                    .unwrap()
+                    .add(30));
+            }),
+            Box::new(|data: &mut AggregatorData| {
+                assert_ok!(data
+                    .get_aggregator(aggregator_id_for_test(100), 100)
+                    .unwrap()
+                    .add(10));
+            }),
+            Box::new(|data: &mut AggregatorData| {
+                assert_ok!(data
+                    .get_aggregator(aggregator_id_for_test(600), 600)
+                    .unwrap()
+                    .sub(60));
+            }),
+            Box::new(|data: &mut AggregatorData| {
+                data.remove_aggregator(aggregator_id_for_test(200))
+            }),
+            Box::new(|data: &mut AggregatorData| {
+                data.create_new_aggregator(aggregator_id_for_test(500), 500);
+                data.remove_aggregator(aggregator_id_for_test(500));
+            }),
+            Box::new(|data: &mut AggregatorData| {
+                data.create_new_aggregator(aggregator_id_for_test(400), 400)
+            }),
+        ];
+
+        let run = |order: &[usize]| {
+            let mut aggregator_data = AggregatorData::default();
+            for i in order {
+                operations[*i](&mut aggregator_data);
+            }
+            aggregator_data
+        };
+        let orders = [[0, 1, 2, 3, 4, 5], [5, 4, 3, 2, 1, 0], [2, 5, 0, 3, 1, 4]];
+
+        // Iteration order is the order of identifiers, and not the order of
+        // operations (identifiers are not ordered by their numeric keys).
+        let mut sorted_ids = ids.clone();
+        sorted_ids.sort();
+        assert_ne!(sorted_ids, ids);
+        for order in orders {
+            let aggregator_data = run(&order);
+            let used_ids: Vec<_> = aggregator_data.aggregators().map(|(id, _)| id).collect();
+            assert!(used_ids.windows(2).all(|w| w[0] < w[1]));
+            let new_ids: Vec<_> = aggregator_data.new_aggregator_ids().collect();
+            assert!(new_ids.windows(2).all(|w| w[0] < w[1]));
+        }
+
+        let data_change_sets: Vec<_> = orders
+            .iter()
+            .map(|order| run(order).into_data_change_set())
+            .collect();
+        assert!(data_change_sets.iter().all(|change_set| change_set
+            .aggregators()
+            .keys()
+            .eq(data_change_sets[0].aggregators().keys())));
+
+        let changes: Vec<Vec<_>> = orders
+            .iter()
+            .map(|order| run(order).into_change_set().changes.into_iter().collect())
+            .collect();
+        // Aggregator 500 is created and destroyed, and so is not changed.
+        sorted_ids.retain(|id| *id != aggregator_id_for_test(500));
+        assert_eq!(
+            changes[0]
+                .iter()
+                .map(|(id, _)| id.clone())
+                .collect::<Vec<_>>(),
+            sorted_ids
+        );
+        assert!(changes.iter().all(|c| *c == changes[0]));
+    }
+
+    #[test]
+    fn test_unbounded_aggregator() {
+        let mut aggregator_data = AggregatorData::default();
+        let id = aggregator_id_for_test(100);
+        let aggregator = aggregator_data
+            .get_unbounded_aggregator(id.clone())
+            .expect("Get aggregator failed");
+        assert!(aggregator.is_unbounded());
+        assert_eq!(aggregator.limit(), UNBOUNDED_LIMIT);
+
+        // Only negative deltas are tracked.
+        assert_ok!(aggregator.add(u128::MAX));
+        assert_ok!(aggregator.sub(u128::MAX));
+        assert_ok!(aggregator.sub(10));
+        assert_ok!(aggregator.add(5));
+        assert_eq!(aggregator.history(), Some(&History::from_bounds(0, 10)));
+        assert_ok!(aggregator.validate_against_base(u128::MAX));
+        assert_err!(aggregator.validate_against_base(9));
+
+        // The final value must still fit.
+        assert_ok!(aggregator.add(10));
+        assert_ok!(aggregator.validate_against_base(u128::MAX - 5));
+        let resolver = AggregatorStore::builder()
+            .with_value(id.clone(), u128::MAX)
+            .build();
+        assert_err!(aggregator.read_and_materialize(&resolver, &id));
+
+        // Unbounded aggregators must be requested explicitly.
+        assert_err!(aggregator_data.get_aggregator(id, UNBOUNDED_LIMIT));
+        let id = aggregator_id_for_test(200);
+        let aggregator = aggregator_data
+            .get_aggregator(id, UNBOUNDED_LIMIT)
+            .expect("Get aggregator failed");
+        assert!(!aggregator.is_unbounded());
+        assert_ok!(aggregator.add(u128::MAX));
+        assert_ok!(aggregator.sub(u128::MAX));
+        assert_eq!(
+            aggregator.history(),
+            Some(&History::from_bounds(u128::MAX, 0))
+        );
+        assert_err!(aggregator.validate_against_base(1));
+    }
+
+    #[test]
+    fn test_derive_id() {
+        let handle = TableHandle(AccountAddress::ONE);
+        let id = AggregatorID::derive(handle, &[0; 32], 1);
+        assert_eq!(
+            id,
+            AggregatorID::new(
+                handle,
+                AggregatorHandle(
+                    AccountAddress::from_hex(
+                        "aa7e76d8194eeb4571b217b83f9af4e7f2d2804c647c7fbdad1c887f7c5e01a2"
+                    )
+                    .unwrap()
+                )
+            )
+        );
+        assert_ne!(id, AggregatorID::derive(handle, &[0; 32], 0));
+        assert_ne!(id, AggregatorID::derive(handle, &[1; 32], 1));
+    }
+
+    #[test]
+    fn test_resource_group_aggregator() {
+        let tag = StructTag::from_str("0x1::group::Counter").unwrap();
+        let id =
+            AggregatorID::new_in_resource_group(aggregator_id_for_test(100).into_state_key(), tag);
+        let resolver = AggregatorStore::builder()
+            .with_value(id.clone(), 100)
+            .build();
+        let mut aggregator_data = AggregatorData::default();
+
+        // Deltas are materialized against the member of the group.
+        let aggregator = aggregator_data
+            .get_aggregator(id.clone(), 1000)
+            .expect("Get aggregator failed");
+        assert_ok!(aggregator.add(50));
+        assert_ok_eq!(aggregator.read_bounds(&resolver, &id), (150, 150));
+        assert_ok_eq!(aggregator.read_and_materialize(&resolver, &id), 150);
+
+        aggregator_data.remove_aggregator(id);
+        assert_ok!(aggregator_data.validate_deletions(&resolver));
+    }
+
+    #[test]
+    fn test_monotonic_aggregator() {
+        let mut aggregator_data = AggregatorData::default();
+        let resolver = AggregatorStore::builder()
+            .with_value(aggregator_id_for_test(600), 300)
+            .build();
+
+        let id = aggregator_id_for_test(600);
+        let aggregator = assert_ok!(aggregator_data.get_monotonic_aggregator(id.clone(), 600));
+        assert_eq!(aggregator.kind(), AggregatorKind::Monotonic);
+        assert_ok!(aggregator.add(100));
+        assert_ok!(aggregator.add(50));
+        assert_err!(aggregator.sub(10));
+        assert_err!(aggregator.try_sub(10));
+        assert_err!(aggregator.saturating_sub(10, &resolver, &id));
+
+        // History only tracks the delta itself.
+        let history = *aggregator.history().unwrap();
+        assert_eq!(history, History::from_bounds(150, 0));
+        assert_ok_eq!(aggregator.read_and_materialize(&resolver, &id), 450);
+
+        // Known values of monotonic aggregators cannot be subtracted from
+        // either.
+        aggregator_data.create_new_monotonic_aggregator(aggregator_id_for_test(200), 200);
+        let aggregator =
+            assert_ok!(aggregator_data.get_monotonic_aggregator(aggregator_id_for_test(200), 200));
+        assert_ok!(aggregator.add(200));
+        assert_err!(aggregator.sub(1));
+
+        // Kinds cannot be mixed.
+        assert_err!(aggregator_data.get_aggregator(aggregator_id_for_test(200), 200));
+        assert_err!(aggregator_data.get_aggregator(aggregator_id_for_test(600), 600));
+    }
+
+    #[test]
+    fn test_get_aggregator_if_exists() {
+        let mut aggregator_data = AggregatorData::default();
+        aggregator_data.create_new_aggregator(aggregator_id_for_test(200), 200);
+
+        let aggregator = aggregator_data
+            .get_aggregator_if_exists(&aggregator_id_for_test(200))
+            .unwrap();
+        assert_eq!(aggregator.state, AggregatorState::Data);
+        assert!(aggregator_data
+            .get_aggregator_if_exists(&aggregator_id_for_test(600))
+            .is_none());
+        assert_eq!(aggregator_data.num_aggregators(), 1);
+        assert_eq!(aggregator_data.into_change_set().changes.len(), 1);
+    }
+
+    #[test]
+    fn test_inspection() {
+        let mut aggregator_data = AggregatorData::default();
+        aggregator_data.create_new_aggregator(aggregator_id_for_test(100), 100);
+        aggregator_data.create_new_aggregator(aggregator_id_for_test(200), 200);
+        assert_ok!(aggregator_data
+            .get_aggregator(aggregator_id_for_test(500), 500)
+            .unwrap()
+            .sub(10));
+        assert_ok!(aggregator_data.get_aggregator(aggregator_id_for_test(600), 600));
+        aggregator_data.remove_aggregator(aggregator_id_for_test(100));
+        aggregator_data.remove_aggregator(aggregator_id_for_test(700));
+
+        let summary: Vec<_> = aggregator_data
+            .aggregators()
+            .map(|(id, aggregator)| (id.clone(), aggregator.state(), aggregator.limit()))
+            .collect();
+        assert_eq!(summary, vec![
+            (aggregator_id_for_test(200), AggregatorState::Data, 200),
+            (
+                aggregator_id_for_test(500),
+                AggregatorState::NegativeDelta,
+                500
+            ),
+            (
+                aggregator_id_for_test(600),
+                AggregatorState::PositiveDelta,
+                600
+            ),
+        ]);
+        assert_eq!(
+            aggregator_data.new_aggregator_ids().collect::<Vec<_>>(),
+            vec![&aggregator_id_for_test(200)]
+        );
+        assert_eq!(
+            aggregator_data
+                .destroyed_aggregator_ids()
+                .collect::<Vec<_>>(),
+            vec![&aggregator_id_for_test(700)]
+        );
+        for (state, count) in [
+            (AggregatorState::Data, 1),
+            (AggregatorState::PositiveDelta, 1),
+            (AggregatorState::NegativeDelta, 1),
+        ] {
+            assert_eq!(aggregator_data.num_aggregators_in_state(state), count);
+        }
+    }
+
+    #[test]
+    fn test_observed_aggregators() {
+        let mut aggregator_data = AggregatorData::default();
+        aggregator_data.create_new_observed_aggregator(
+            aggregator_id_for_test(100),
+            ObservedKind::Min,
+            50,
+        );
+        assert_ok!(aggregator_data
+            .get_observed_aggregator(aggregator_id_for_test(100), ObservedKind::Min)
+            .unwrap()
+            .observe(70));
+        assert_err!(
+            aggregator_data.get_observed_aggregator(aggregator_id_for_test(100), ObservedKind::Max)
+        );
+
+        let aggregator = aggregator_data
+            .get_observed_aggregator(aggregator_id_for_test(200), ObservedKind::Max)
+            .unwrap();
+        assert_ok!(aggregator.observe(10));
+        assert_ok!(aggregator.observe(30));
+        assert_ok!(
+            aggregator_data.get_observed_aggregator(aggregator_id_for_test(300), ObservedKind::Max)
+        );
+
+        let checkpoint = aggregator_data.checkpoint();
+        aggregator_data.remove_aggregator(aggregator_id_for_test(200));
+        aggregator_data.rollback_to(checkpoint);
+
+        // Unused aggregators do not produce any changes.
+        let mut delta = ObservedDelta::new(ObservedKind::Max);
+        delta.observe(30);
+        assert_eq!(
+            aggregator_data.into_change_set().changes,
+            BTreeMap::from([
+                (aggregator_id_for_test(100), AggregatorChange::Write(50)),
+                (
+                    aggregator_id_for_test(200),
+                    AggregatorChange::Observe(delta)
+                ),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_limit_mismatch() {
+        let mut aggregator_data = AggregatorData::default();
+        aggregator_data.create_new_aggregator(aggregator_id_for_test(200), 200);
+        assert_ok!(aggregator_data.get_aggregator(aggregator_id_for_test(600), 600));
+
+        for (id, limit) in [(200, 300), (600, 599)] {
+            let error =
+                assert_err!(aggregator_data.get_aggregator(aggregator_id_for_test(id), limit));
+            assert_eq!(
+                error.major_status(),
+                StatusCode::UNKNOWN_INVARIANT_VIOLATION_ERROR
+            );
+        }
+        assert_ok!(aggregator_data.get_aggregator(aggregator_id_for_test(200), 200));
+        assert_ok!(aggregator_data.get_aggregator(aggregator_id_for_test(600), 600));
+    }
+
+    #[test]
+    fn test_history_failure_bounds() {
+        // With the limit of 100, adding 60 overflowed and subtracting 50
+        // dropped below zero, so 40 < base < 50. Successful deltas require
+        // 10 <= base <= 60.
+        let history = History::from_bounds(40, 10).with_failure_bounds(Some(60), Some(50));
+        assert_eq!(
+            history.to_string(),
+            "max +40, min -10, overflow at +60, underflow at -50"
+        );
+        for base in 0..=100 {
+            assert_eq!(
+                history.validate(base, 100).is_ok(),
+                (41..50).contains(&base)
+            );
+        }
+
+        let history = History::from_bounds(20, 10).with_failure_bounds(Some(60), None);
+        assert_ok!(history.validate(45, 100));
+        assert_eq!(
+            assert_err!(history.validate(40, 100)),
+            BoundViolation::ExpectedOverflow {
+                base: 40,
+                delta: 60,
+                limit: 100
+            }
+        );
+        let history = History::from_bounds(20, 10).with_failure_bounds(None, Some(30));
+        assert_ok!(history.validate(29, 100));
+        assert_eq!(
+            assert_err!(history.validate(30, 100)),
+            BoundViolation::ExpectedUnderflow {
+                base: 30,
+                delta: 30
+            }
+        );
+    }
+
+    #[test]
+    fn test_apply_delta() {
+        let history = History::from_bounds(100, 7);
+        assert_ok_eq!(
+            apply_delta(10, AggregatorState::PositiveDelta, 42, 1000, &history),
+            52
+        );
+        assert_ok_eq!(
+            apply_delta(10, AggregatorState::NegativeDelta, 5, 1000, &history),
+            5
+        );
+        // History is validated even if the delta itself can be applied.
+        assert_err!(apply_delta(
+            5,
+            AggregatorState::PositiveDelta,
+            42,
+            1000,
+            &history
+        ));
+        assert_err!(apply_delta(
+            901,
+            AggregatorState::PositiveDelta,
+            42,
+            1000,
+            &history
+        ));
+        // Known values do not depend on the base.
+        assert_ok_eq!(
+            apply_delta(901, AggregatorState::Data, 42, 1000, &history),
+            42
+        );
+
+        // Extension and executor agree on the result.
+        let mut aggregator_data = AggregatorData::default();
+        let id = aggregator_id_for_test(1000);
+        let aggregator = aggregator_data
+            .get_aggregator(id.clone(), 1000)
+            .expect("Get aggregator failed");
+        assert_ok!(aggregator.sub(7));
+        assert_ok!(aggregator.add(107));
+        let delta = aggregator.delta_op().unwrap();
+        for base in [0, 6, 7, 500, 900, 901, 1000] {
+            let resolver = AggregatorStore::builder()
+                .with_value(id.clone(), base)
+                .build();
+            let mut aggregator = aggregator.clone();
+            assert_eq!(
+                aggregator.read_and_materialize(&resolver, &id).ok(),
+                delta.apply_to(base).ok()
+            );
+        }
+    }
+
+    #[test]
+    fn test_display() {
+        let mut aggregator_data = AggregatorData::default();
+        aggregator_data.create_new_aggregator(aggregator_id_for_test(200), 200);
+
+        let aggregator = aggregator_data
+            .get_aggregator(aggregator_id_for_test(200), 200)
+            .expect("Get aggregator failed");
+        assert_ok!(aggregator.add(42));
+        assert_eq!(aggregator.to_string(), "42 (limit 200)");
+        assert_eq!(aggregator.state.to_string(), "data");
+
+        let id = aggregator_id_for_test(1000);
+        let aggregator = aggregator_data
+            .get_aggregator(id.clone(), 1000)
+            .expect("Get aggregator failed");
+        assert_ok!(aggregator.sub(7));
+        assert_ok!(aggregator.add(107));
+        assert_ok!(aggregator.sub(58));
+        assert_eq!(aggregator.state.to_string(), "positive delta");
+        assert_eq!(
+            aggregator.history().unwrap().to_string(),
+            "max +100, min -7"
+        );
+        assert_eq!(aggregator.to_string(), "+42 (max +100, min -7, limit 1000)");
+
+        // Errors describe the aggregator which failed to materialize.
+        let resolver = AggregatorStore::builder().with_value(id.clone(), 5).build();
+        let error = assert_err!(aggregator.read_and_materialize(&resolver, &id));
+        assert_eq!(
+            error.all_data().2.unwrap(),
+            "cannot materialize +42 (max +100, min -7, limit 1000) on base 5: underflow when subtracting 7 from 5"
+        );
+    }
+
     #[test]
     #[should_panic]
     fn test_history_validation_in_data_state() {
@@ -545,10 +2629,42 @@ mod test {
             .get_aggregator(aggregator_id_for_test(200), 200)
             .expect("Getting an aggregator should succeed");
         aggregator
-            .validate_history(0)
+            .validate_against_base(0)
             .expect("Should not be called because validation panics");
     }
 
+    #[test]
+    fn test_u64_aggregator() {
+        let mut aggregator_data = AggregatorData::default();
+
+        // Limit of u64 aggregator must fit into u64.
+        let limit = u64::MAX as u128;
+        assert_err!(aggregator_data.create_new_aggregator_with_width(
+            aggregator_id_for_test(100),
+            limit + 1,
+            AggregatorWidth::U64
+        ));
+        assert_ok!(aggregator_data.create_new_aggregator_with_width(
+            aggregator_id_for_test(100),
+            limit,
+            AggregatorWidth::U64
+        ));
+
+        let aggregator = aggregator_data
+            .get_aggregator_with_width(aggregator_id_for_test(100), limit, AggregatorWidth::U64)
+            .expect("Get aggregator failed");
+        assert_eq!(aggregator.width(), AggregatorWidth::U64);
+        assert_ok!(aggregator.add(limit));
+        assert_err!(aggregator.add(1));
+
+        // Deltas of u64 aggregators cannot exceed u64 either.
+        let aggregator = aggregator_data
+            .get_aggregator_with_width(aggregator_id_for_test(200), limit, AggregatorWidth::U64)
+            .expect("Get aggregator failed");
+        assert_err!(aggregator.add(limit + 1));
+        assert_err!(aggregator.sub(limit + 1));
+    }
+
     #[test]
     fn test_history_validation_in_delta_state() {
         let mut aggregator_data = AggregatorData::default();
@@ -565,10 +2681,67 @@ mod test {
         aggregator.add(50).unwrap();
 
         // Valid history: 50+50-100+50.
-        assert_ok!(aggregator.validate_history(50));
+        assert_ok!(aggregator.validate_against_base(50));
 
         // Underflow and overflow are unvalidated.
-        assert_err!(aggregator.validate_history(49));
-        assert_err!(aggregator.validate_history(51));
+        assert_err!(aggregator.validate_against_base(49));
+        assert_err!(aggregator.validate_against_base(51));
+
+        // Errors describe the violated bound.
+        assert_eq!(
+            aggregator.validate_against_base(49),
+            Err(BoundViolation::Underflow {
+                base: 49,
+                delta: 50
+            })
+        );
+        assert_eq!(
+            aggregator.validate_against_base(51),
+            Err(BoundViolation::Overflow {
+                base: 51,
+                delta: 50,
+                limit: 100
+            })
+        );
+    }
+
+    #[test]
+    fn test_gas_hook() {
+        use AggregatorOperation::*;
+
+        let hook = Arc::new(RecordingGasMeterHook::default());
+        let mut aggregator_data =
+            AggregatorData::with_gas_hook(hook.clone()).with_features(AggregatorFeatures::all());
+        let mut resolver = AggregatorStore::default();
+        resolver.set_from_id(aggregator_id_for_test(600), 300);
+
+        let id = aggregator_id_for_test(600);
+        let aggregator = aggregator_data
+            .get_aggregator_with_width(id.clone(), 600, AggregatorWidth::U64)
+            .expect("Get aggregator failed");
+        assert_ok!(aggregator.add(100));
+        assert_ok_eq!(aggregator.try_sub(1000), false);
+        assert_ok_eq!(aggregator.is_at_least(50), true);
+        assert_ok!(aggregator.saturating_add(1000, &resolver, &id));
+        assert_ok_eq!(aggregator.read_and_materialize(&resolver, &id), 600);
+
+        // Every operation is charged once.
+        assert_eq!(*hook.charges.lock().unwrap(), vec![
+            (Add, 8),
+            (Sub, 8),
+            (Read, 8),
+            (Add, 8),
+            (Read, 8),
+        ]);
+
+        // Operations abort without side-effects if charging fails.
+        hook.out_of_gas
+            .store(true, std::sync::atomic::Ordering::Relaxed);
+        let aggregator = aggregator_data
+            .get_aggregator(id, 600)
+            .expect("Get aggregator failed");
+        assert_err!(aggregator.sub(100));
+        assert_err!(aggregator.try_sub(100));
+        assert_eq!(aggregator.value, 600);
     }
 }
@@ -0,0 +1,222 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+//! Conversions between aggregator identifiers and the on-chain Move struct
+//! representing an aggregator:
+//!
+//! ```move
+//! struct Aggregator has store {
+//!     handle: address,
+//!     key: address,
+//!     limit: u128,
+//! }
+//! ```
+//!
+//! Natives and off-chain tools should use these helpers instead of relying
+//! on the order of the fields themselves.
+
+use crate::{
+    aggregator_extension::{AggregatorHandle, AggregatorID},
+    module::AGGREGATOR_MODULE_IDENTIFIER,
+};
+use anyhow::{bail, ensure};
+use aptos_types::{
+    account_config::CORE_CODE_ADDRESS,
+    state_store::{state_key::StateKeyInner, table::TableHandle},
+};
+use move_core_types::{
+    account_address::AccountAddress,
+    ident_str,
+    identifier::IdentStr,
+    language_storage::StructTag,
+    value::{MoveFieldLayout, MoveStruct, MoveStructLayout, MoveTypeLayout, MoveValue},
+};
+use serde::{Deserialize, Serialize};
+
+pub const AGGREGATOR_STRUCT_IDENTIFIER: &IdentStr = ident_str!("Aggregator");
+
+/// Indices of `handle`, `key` and `limit` fields in the `Aggregator` Move
+/// struct.
+pub const HANDLE_FIELD_INDEX: usize = 0;
+pub const KEY_FIELD_INDEX: usize = 1;
+pub const LIMIT_FIELD_INDEX: usize = 2;
+pub const NUM_FIELDS: usize = 3;
+
+/// Returns the tag of the `Aggregator` Move struct.
+pub fn aggregator_struct_tag() -> StructTag {
+    StructTag {
+        address: CORE_CODE_ADDRESS,
+        module: AGGREGATOR_MODULE_IDENTIFIER.to_owned(),
+        name: AGGREGATOR_STRUCT_IDENTIFIER.to_owned(),
+        type_params: vec![],
+    }
+}
+
+/// Returns the layout of the `Aggregator` Move struct, with field names.
+pub fn aggregator_struct_layout() -> MoveStructLayout {
+    MoveStructLayout::with_fields(vec![
+        MoveFieldLayout::new(ident_str!("handle").to_owned(), MoveTypeLayout::Address),
+        MoveFieldLayout::new(ident_str!("key").to_owned(), MoveTypeLayout::Address),
+        MoveFieldLayout::new(ident_str!("limit").to_owned(), MoveTypeLayout::U128),
+    ])
+}
+
+/// Rust representation of the `Aggregator` Move struct. Serializes to the
+/// same bytes as the Move struct.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AggregatorStruct {
+    handle: AccountAddress,
+    key: AccountAddress,
+    limit: u128,
+}
+
+impl AggregatorStruct {
+    pub fn new(handle: TableHandle, key: AggregatorHandle, limit: u128) -> Self {
+        Self {
+            handle: handle.0,
+            key: key.0,
+            limit,
+        }
+    }
+
+    /// Returns the struct for an aggregator with `id` and a `limit`. Fails if
+    /// the aggregator is not stored as a table item keyed by an address, e.g.
+    /// if it is a member of a resource group.
+    pub fn from_id(id: &AggregatorID, limit: u128) -> anyhow::Result<Self> {
+        ensure!(
+            !id.is_in_resource_group(),
+            "aggregator {:?} in a resource group has no struct representation",
+            id
+        );
+        match id.as_state_key().inner() {
+            StateKeyInner::TableItem { handle, key } => {
+                let key = AccountAddress::from_bytes(key)?;
+                Ok(Self::new(*handle, AggregatorHandle(key), limit))
+            },
+            _ => bail!("aggregator {:?} is not stored as a table item", id),
+        }
+    }
+
+    pub fn handle(&self) -> TableHandle {
+        TableHandle(self.handle)
+    }
+
+    pub fn key(&self) -> AggregatorHandle {
+        AggregatorHandle(self.key)
+    }
+
+    pub fn limit(&self) -> u128 {
+        self.limit
+    }
+
+    /// Returns the identifier of the aggregator.
+    pub fn id(&self) -> AggregatorID {
+        AggregatorID::new(self.handle(), self.key())
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        bcs::to_bytes(self).expect("Unexpected serialization error in aggregator struct")
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
+        Ok(bcs::from_bytes(bytes)?)
+    }
+
+    pub fn to_move_value(&self) -> MoveValue {
+        let mut fields = vec![MoveValue::U8(0); NUM_FIELDS];
+        fields[HANDLE_FIELD_INDEX] = MoveValue::Address(self.handle);
+        fields[KEY_FIELD_INDEX] = MoveValue::Address(self.key);
+        fields[LIMIT_FIELD_INDEX] = MoveValue::U128(self.limit);
+        MoveValue::Struct(MoveStruct::new(fields))
+    }
+
+    /// Converts a value of the `Aggregator` Move struct, possibly decorated
+    /// with field names, into its Rust representation.
+    pub fn from_move_value(value: MoveValue) -> anyhow::Result<Self> {
+        let fields = match value {
+            MoveValue::Struct(move_struct) => move_struct.into_fields(),
+            _ => bail!("aggregator must be a struct, got {:?}", value),
+        };
+        ensure!(
+            fields.len() == NUM_FIELDS,
+            "aggregator struct must have {} fields, got {}",
+            NUM_FIELDS,
+            fields.len()
+        );
+        match (
+            &fields[HANDLE_FIELD_INDEX],
+            &fields[KEY_FIELD_INDEX],
+            &fields[LIMIT_FIELD_INDEX],
+        ) {
+            (MoveValue::Address(handle), MoveValue::Address(key), MoveValue::U128(limit)) => {
+                Ok(Self {
+                    handle: *handle,
+                    key: *key,
+                    limit: *limit,
+                })
+            },
+            _ => bail!("unexpected fields of aggregator struct: {:?}", fields),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::aggregator_id_for_test;
+    use claims::{assert_err, assert_ok, assert_ok_eq};
+    use std::str::FromStr;
+
+    const AGGREGATOR_SOURCE: &str =
+        include_str!("../../framework/aptos-framework/sources/aggregator/aggregator.move");
+
+    #[test]
+    fn test_struct_matches_move_source() {
+        // Fails if the Move struct changes, so that helpers are updated.
+        let source: String = AGGREGATOR_SOURCE.split_whitespace().collect();
+        assert!(source.contains("structAggregatorhasstore{handle:address,key:address,limit:u128,}"));
+        assert_eq!(aggregator_struct_layout().into_fields().len(), NUM_FIELDS);
+        assert_eq!(
+            aggregator_struct_tag(),
+            StructTag::from_str("0x1::aggregator::Aggregator").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_conversions() {
+        let id = aggregator_id_for_test(100);
+        let aggregator = assert_ok!(AggregatorStruct::from_id(&id, 1000));
+        assert_eq!(aggregator.id(), id);
+        assert_eq!(aggregator.limit(), 1000);
+
+        // Bytes are the same as for the Move value with the struct layout.
+        let bytes = aggregator.to_bytes();
+        assert_eq!(bytes.len(), 2 * AccountAddress::LENGTH + 16);
+        assert_eq!(
+            Some(bytes.clone()),
+            aggregator.to_move_value().simple_serialize()
+        );
+        let value = assert_ok!(MoveStruct::simple_deserialize(
+            &bytes,
+            &aggregator_struct_layout()
+        ));
+        assert_ok_eq!(
+            AggregatorStruct::from_move_value(MoveValue::Struct(value)),
+            aggregator
+        );
+        assert_ok_eq!(AggregatorStruct::from_bytes(&bytes), aggregator);
+        assert_err!(AggregatorStruct::from_bytes(&bytes[1..]));
+
+        // Fields in a wrong order are rejected.
+        let swapped = MoveValue::Struct(MoveStruct::new(vec![
+            MoveValue::U128(1000),
+            MoveValue::Address(AccountAddress::ONE),
+            MoveValue::Address(AccountAddress::ONE),
+        ]));
+        assert_err!(AggregatorStruct::from_move_value(swapped));
+
+        let group_id =
+            AggregatorID::new_in_resource_group(id.as_state_key().clone(), aggregator_struct_tag());
+        assert_err!(AggregatorStruct::from_id(&group_id, 1000));
+    }
+}
@@ -0,0 +1,574 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+//! Native extension for aggregators V2 and aggregator snapshots. Aggregators
+//! V2 do not have a storage key until the session is committed, and so are
+//! identified by ephemeral IDs instead. They are managed separately from
+//! `AggregatorData`, so that the behavior of aggregators V1 stays frozen
+//! while V2 evolves.
+
+use crate::{
+    aggregator_change_set::AggregatorV2ChangeSet,
+    aggregator_extension::{extension_error, Aggregator, AggregatorData, AggregatorID},
+    counters::{self, Operation},
+    features::AggregatorFeatures,
+    gas_hook::{AggregatorOperation, GasMeterHook, NoOpGasMeterHook},
+    resolver::AggregatorResolver,
+    types::{
+        AggregatorWidth, DerivedSnapshotValue, SnapshotID, SnapshotToStringFormula, SnapshotValue,
+    },
+};
+use move_binary_format::errors::PartialVMResult;
+use std::{collections::BTreeMap, sync::Arc};
+
+/// Identifies an aggregator V2 created in the current session, which does
+/// not have a storage key until the session is committed. Identifiers are
+/// assigned in increasing order.
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq, Ord, PartialOrd)]
+pub struct EphemeralID(pub u64);
+
+/// Stores all aggregators V2 and snapshots used in a single transaction.
+pub struct AggregatorV2Data {
+    // Gates creation of aggregators V2 and snapshots, which may not be
+    // enabled on chain.
+    features: AggregatorFeatures,
+    // All aggregators created in the current session which do not have a
+    // storage key yet.
+    aggregators: BTreeMap<EphemeralID, Aggregator>,
+    // Aggregators which have been assigned a storage key.
+    committed_aggregators: BTreeMap<AggregatorID, Aggregator>,
+    // Storage keys assigned to committed aggregators.
+    translations: BTreeMap<EphemeralID, AggregatorID>,
+    // Identifier of the next aggregator to create.
+    next_ephemeral_id: u64,
+    // All snapshots taken in the current transaction.
+    snapshots: BTreeMap<SnapshotID, SnapshotValue>,
+    // All string snapshots derived in the current transaction. These share
+    // identifiers with snapshots.
+    derived_snapshots: BTreeMap<SnapshotID, DerivedSnapshotValue>,
+    // Identifier of the next snapshot to take.
+    next_snapshot_id: u64,
+    // Charges gas for operations on aggregators and snapshots.
+    gas_hook: Arc<dyn GasMeterHook>,
+}
+
+/// State of `AggregatorV2Data` captured by `AggregatorV2Data::checkpoint`.
+#[derive(Clone, Debug)]
+pub struct AggregatorV2DataCheckpoint {
+    aggregators: BTreeMap<EphemeralID, Aggregator>,
+    committed_aggregators: BTreeMap<AggregatorID, Aggregator>,
+    translations: BTreeMap<EphemeralID, AggregatorID>,
+    next_ephemeral_id: u64,
+    snapshots: BTreeMap<SnapshotID, SnapshotValue>,
+    derived_snapshots: BTreeMap<SnapshotID, DerivedSnapshotValue>,
+    next_snapshot_id: u64,
+}
+
+/// Aggregators V2 are disabled by default.
+impl Default for AggregatorV2Data {
+    fn default() -> Self {
+        Self::new(AggregatorFeatures::default(), Arc::new(NoOpGasMeterHook))
+    }
+}
+
+impl AggregatorV2Data {
+    /// Creates empty data, charging gas for all operations on aggregators
+    /// and snapshots with `gas_hook`. Creating aggregators or snapshots fails
+    /// if they are not enabled in `features`.
+    pub fn new(features: AggregatorFeatures, gas_hook: Arc<dyn GasMeterHook>) -> Self {
+        Self {
+            features,
+            aggregators: BTreeMap::new(),
+            committed_aggregators: BTreeMap::new(),
+            translations: BTreeMap::new(),
+            next_ephemeral_id: 0,
+            snapshots: BTreeMap::new(),
+            derived_snapshots: BTreeMap::new(),
+            next_snapshot_id: 0,
+            gas_hook,
+        }
+    }
+
+    /// Returns the operations which are enabled for aggregators V2 and
+    /// snapshots.
+    pub fn features(&self) -> AggregatorFeatures {
+        self.features
+    }
+
+    /// Creates a new aggregator which does not have a storage key yet, and
+    /// returns its ephemeral identifier. The aggregator is created in a data
+    /// state with a zero-initialized value.
+    pub fn create_new_aggregator(
+        &mut self,
+        limit: u128,
+        width: AggregatorWidth,
+    ) -> PartialVMResult<EphemeralID> {
+        self.insert_aggregator(0, limit, width)
+    }
+
+    /// Creates an aggregator which knows its value, e.g. a value exchanged
+    /// for an identifier when a resource is read from storage.
+    pub(crate) fn insert_aggregator(
+        &mut self,
+        value: u128,
+        limit: u128,
+        width: AggregatorWidth,
+    ) -> PartialVMResult<EphemeralID> {
+        self.features.check_v2_ids()?;
+        width.check(limit)?;
+        if value > limit {
+            return Err(extension_error(format!(
+                "value {} of aggregator exceeds its limit {}",
+                value, limit
+            )));
+        }
+        let id = EphemeralID(self.next_ephemeral_id);
+        self.next_ephemeral_id += 1;
+        self.aggregators.insert(
+            id,
+            Aggregator::with_value(value, limit, width, self.gas_hook.clone(), self.features),
+        );
+        counters::observe(Operation::Create);
+        Ok(id)
+    }
+
+    /// Returns a mutable reference to an aggregator created with
+    /// `create_new_aggregator` which has not been committed yet.
+    pub fn get_aggregator(&mut self, id: EphemeralID) -> PartialVMResult<&mut Aggregator> {
+        self.aggregators
+            .get_mut(&id)
+            .ok_or_else(|| extension_error(format!("Ephemeral aggregator {:?} does not exist", id)))
+    }
+
+    /// Assigns a storage key to an aggregator once it is known, i.e. when
+    /// the session is committed. The translation is recorded so that values
+    /// referring to the ephemeral identifier can be resolved, and the value
+    /// of the aggregator is written to storage.
+    pub fn commit_aggregator(
+        &mut self,
+        ephemeral_id: EphemeralID,
+        id: AggregatorID,
+    ) -> PartialVMResult<()> {
+        if self.committed_aggregators.contains_key(&id) {
+            return Err(extension_error(format!(
+                "Cannot commit ephemeral aggregator {:?} to used identifier {:?}",
+                ephemeral_id, id
+            )));
+        }
+        let aggregator = self.aggregators.remove(&ephemeral_id).ok_or_else(|| {
+            extension_error(format!(
+                "Ephemeral aggregator {:?} does not exist",
+                ephemeral_id
+            ))
+        })?;
+        self.committed_aggregators.insert(id.clone(), aggregator);
+        self.translations.insert(ephemeral_id, id);
+        Ok(())
+    }
+
+    /// Returns the storage key assigned to a committed aggregator.
+    pub fn translate(&self, id: EphemeralID) -> Option<&AggregatorID> {
+        self.translations.get(&id)
+    }
+
+    /// Returns storage keys of all committed aggregators.
+    pub fn translations(&self) -> &BTreeMap<EphemeralID, AggregatorID> {
+        &self.translations
+    }
+
+    /// Takes a snapshot of an aggregator created with `create_new_aggregator`,
+    /// and returns its identifier.
+    pub fn snapshot(&mut self, id: EphemeralID) -> PartialVMResult<SnapshotID> {
+        let aggregator = self.aggregators.get(&id).ok_or_else(|| {
+            extension_error(format!("Cannot snapshot unknown aggregator {:?}", id))
+        })?;
+        let value = aggregator.known_value().ok_or_else(|| {
+            extension_error(format!("Aggregator {:?} does not know its value", id))
+        })?;
+        self.gas_hook.charge(
+            AggregatorOperation::Snapshot,
+            aggregator.width().num_bytes(),
+        )?;
+        self.insert_snapshot(SnapshotValue::Data(value))
+    }
+
+    /// Takes a snapshot of an aggregator V1 which is used in the current
+    /// transaction (see `AggregatorData::get_aggregator`), and returns its
+    /// identifier. The aggregator is not materialized.
+    pub fn snapshot_aggregator_v1(
+        &mut self,
+        aggregator_data: &AggregatorData,
+        id: &AggregatorID,
+    ) -> PartialVMResult<SnapshotID> {
+        let aggregator = aggregator_data
+            .get_aggregator_if_exists(id)
+            .ok_or_else(|| {
+                extension_error(format!("Cannot snapshot unused aggregator {:?}", id))
+            })?;
+        self.gas_hook.charge(
+            AggregatorOperation::Snapshot,
+            aggregator.width().num_bytes(),
+        )?;
+        self.insert_snapshot(aggregator.snapshot(id))
+    }
+
+    /// Stores a snapshot with the given value and returns its identifier.
+    pub(crate) fn insert_snapshot(&mut self, value: SnapshotValue) -> PartialVMResult<SnapshotID> {
+        let snapshot_id = self.new_snapshot_id()?;
+        self.snapshots.insert(snapshot_id, value);
+        Ok(snapshot_id)
+    }
+
+    /// Derives a string snapshot by concatenating `prefix`, the value of
+    /// snapshot `base` and `suffix`. The base can be a snapshot or a derived
+    /// string snapshot, and the result is computed only when it is read.
+    pub fn derive_string_concat(
+        &mut self,
+        base: SnapshotID,
+        prefix: Vec<u8>,
+        suffix: Vec<u8>,
+    ) -> PartialVMResult<SnapshotID> {
+        self.features.check_snapshots()?;
+        self.gas_hook.charge(
+            AggregatorOperation::DeriveString,
+            prefix.len() + suffix.len(),
+        )?;
+        let derived = match self.derived_snapshots.get(&base) {
+            Some(DerivedSnapshotValue { base, formula }) => DerivedSnapshotValue {
+                base: base.clone(),
+                formula: formula.concat(&prefix, &suffix),
+            },
+            None => DerivedSnapshotValue {
+                base: self.get_snapshot(base)?.clone(),
+                formula: SnapshotToStringFormula::Concat { prefix, suffix },
+            },
+        };
+
+        let snapshot_id = self.new_snapshot_id()?;
+        self.derived_snapshots.insert(snapshot_id, derived);
+        Ok(snapshot_id)
+    }
+
+    fn new_snapshot_id(&mut self) -> PartialVMResult<SnapshotID> {
+        self.features.check_snapshots()?;
+        let snapshot_id = SnapshotID(self.next_snapshot_id);
+        self.next_snapshot_id += 1;
+        Ok(snapshot_id)
+    }
+
+    /// Returns the value captured by a snapshot taken in the current
+    /// transaction.
+    pub fn get_snapshot(&self, id: SnapshotID) -> PartialVMResult<&SnapshotValue> {
+        self.snapshots
+            .get(&id)
+            .ok_or_else(|| extension_error(format!("Snapshot {:?} does not exist", id)))
+    }
+
+    /// Returns the value of a snapshot, reading the base value of the
+    /// aggregator from storage if the snapshot captured a delta.
+    pub fn read_snapshot(
+        &self,
+        id: SnapshotID,
+        resolver: &dyn AggregatorResolver,
+    ) -> PartialVMResult<u128> {
+        let snapshot = self.get_snapshot(id)?;
+        self.gas_hook
+            .charge(AggregatorOperation::Read, AggregatorWidth::U128.num_bytes())?;
+        snapshot.materialize(resolver)
+    }
+
+    /// Returns the string of a derived snapshot, reading the base value of
+    /// the aggregator from storage if needed.
+    pub fn read_derived_snapshot(
+        &self,
+        id: SnapshotID,
+        resolver: &dyn AggregatorResolver,
+    ) -> PartialVMResult<Vec<u8>> {
+        let bytes = self
+            .derived_snapshots
+            .get(&id)
+            .ok_or_else(|| extension_error(format!("Derived snapshot {:?} does not exist", id)))?
+            .materialize(resolver)?;
+        self.gas_hook
+            .charge(AggregatorOperation::Read, bytes.len())?;
+        Ok(bytes)
+    }
+
+    /// Returns all snapshots taken in the current transaction.
+    pub fn snapshots(&self) -> &BTreeMap<SnapshotID, SnapshotValue> {
+        &self.snapshots
+    }
+
+    /// Returns all string snapshots derived in the current transaction.
+    pub fn derived_snapshots(&self) -> &BTreeMap<SnapshotID, DerivedSnapshotValue> {
+        &self.derived_snapshots
+    }
+
+    /// Captures the current state of all aggregators and snapshots, so that
+    /// changes made afterwards can be undone with `rollback_to`.
+    pub fn checkpoint(&self) -> AggregatorV2DataCheckpoint {
+        AggregatorV2DataCheckpoint {
+            aggregators: self.aggregators.clone(),
+            committed_aggregators: self.committed_aggregators.clone(),
+            translations: self.translations.clone(),
+            next_ephemeral_id: self.next_ephemeral_id,
+            snapshots: self.snapshots.clone(),
+            derived_snapshots: self.derived_snapshots.clone(),
+            next_snapshot_id: self.next_snapshot_id,
+        }
+    }
+
+    /// Restores the state captured by `checkpoint`. Aggregators and
+    /// snapshots created after the checkpoint no longer exist.
+    pub fn rollback_to(&mut self, checkpoint: AggregatorV2DataCheckpoint) {
+        let AggregatorV2DataCheckpoint {
+            aggregators,
+            committed_aggregators,
+            translations,
+            next_ephemeral_id,
+            snapshots,
+            derived_snapshots,
+            next_snapshot_id,
+        } = checkpoint;
+        self.aggregators = aggregators;
+        self.committed_aggregators = committed_aggregators;
+        self.translations = translations;
+        self.next_ephemeral_id = next_ephemeral_id;
+        self.snapshots = snapshots;
+        self.derived_snapshots = derived_snapshots;
+        self.next_snapshot_id = next_snapshot_id;
+    }
+
+    /// Returns the values of committed aggregators, which are written to
+    /// storage. Aggregators which have not been committed only exist in the
+    /// current session, and snapshots are exchanged for values when the
+    /// resources which contain them are serialized, so neither reaches the
+    /// change set.
+    pub fn into_change_set(self) -> AggregatorV2ChangeSet {
+        AggregatorV2ChangeSet {
+            changes: self
+                .committed_aggregators
+                .into_iter()
+                .map(|(id, aggregator)| (id, aggregator.into_change()))
+                .collect(),
+            translations: self.translations,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        aggregator_change_set::AggregatorChange, aggregator_extension::AggregatorState,
+        aggregator_id_for_test, gas_hook::RecordingGasMeterHook, AggregatorStore,
+    };
+    use aptos_types::vm_status::StatusCode;
+    use claims::{assert_err, assert_ok, assert_ok_eq};
+
+    fn enabled_data() -> AggregatorV2Data {
+        AggregatorV2Data::new(AggregatorFeatures::all(), Arc::new(NoOpGasMeterHook))
+    }
+
+    #[test]
+    fn test_aggregators() {
+        let mut data = enabled_data();
+
+        let first = assert_ok!(data.create_new_aggregator(100, AggregatorWidth::U64));
+        let second = assert_ok!(data.create_new_aggregator(200, AggregatorWidth::U128));
+        assert_eq!((first, second), (EphemeralID(0), EphemeralID(1)));
+        assert_err!(data.create_new_aggregator(u128::MAX, AggregatorWidth::U64));
+
+        assert_ok!(data.get_aggregator(first).unwrap().add(60));
+
+        // Committing assigns a storage key to the aggregator.
+        let id = aggregator_id_for_test(100);
+        assert_ok!(data.commit_aggregator(first, id.clone()));
+        assert_eq!(data.translate(first), Some(&id));
+        assert_eq!(data.translate(second), None);
+        assert_err!(data.get_aggregator(first));
+        assert_err!(data.commit_aggregator(first, aggregator_id_for_test(300)));
+        assert_err!(data.commit_aggregator(second, id.clone()));
+
+        // Uncommitted aggregators never reach storage.
+        let change_set = data.into_change_set();
+        assert_eq!(
+            change_set.changes,
+            BTreeMap::from([(id.clone(), AggregatorChange::Write(60))])
+        );
+        assert_eq!(change_set.translations, BTreeMap::from([(first, id)]));
+    }
+
+    #[test]
+    fn test_feature_gating() {
+        let mut data = AggregatorV2Data::default();
+        assert!(!data.features().is_v2_ids_enabled());
+
+        let error = assert_err!(data.create_new_aggregator(100, AggregatorWidth::U64));
+        assert_eq!(error.major_status(), StatusCode::FEATURE_UNDER_GATING);
+        assert_err!(data.insert_snapshot(SnapshotValue::Data(5)));
+
+        // Aggregators V1 are not affected, but cannot be snapshotted.
+        let mut aggregator_data = AggregatorData::default();
+        let id = aggregator_id_for_test(100);
+        aggregator_data.create_new_aggregator(id.clone(), 100);
+        assert_err!(data.snapshot_aggregator_v1(&aggregator_data, &id));
+        assert!(data.into_change_set().is_empty());
+
+        // Snapshots can be enabled without aggregators V2.
+        let mut data = AggregatorV2Data::new(
+            AggregatorFeatures::default().with_snapshots(true),
+            Arc::new(NoOpGasMeterHook),
+        );
+        assert_err!(data.create_new_aggregator(100, AggregatorWidth::U64));
+        assert_ok!(data.snapshot_aggregator_v1(&aggregator_data, &id));
+    }
+
+    #[test]
+    fn test_snapshots() {
+        let mut data = enabled_data();
+        let mut aggregator_data = AggregatorData::default();
+        let mut resolver = AggregatorStore::default();
+        resolver.set_from_id(aggregator_id_for_test(600), 300);
+
+        // Snapshot of a known value is known.
+        aggregator_data.create_new_aggregator(aggregator_id_for_test(200), 200);
+        let aggregator = aggregator_data
+            .get_aggregator(aggregator_id_for_test(200), 200)
+            .expect("Get aggregator failed");
+        assert_ok!(aggregator.add(100));
+        let data_snapshot =
+            assert_ok!(data.snapshot_aggregator_v1(&aggregator_data, &aggregator_id_for_test(200)));
+        assert_eq!(
+            *data.get_snapshot(data_snapshot).unwrap(),
+            SnapshotValue::Data(100)
+        );
+
+        // Snapshot of a delta does not materialize the aggregator, and is not
+        // affected by later changes.
+        let aggregator = aggregator_data
+            .get_aggregator(aggregator_id_for_test(600), 600)
+            .expect("Get aggregator failed");
+        assert_ok!(aggregator.add(200));
+        let delta_snapshot =
+            assert_ok!(data.snapshot_aggregator_v1(&aggregator_data, &aggregator_id_for_test(600)));
+        let aggregator = aggregator_data
+            .get_aggregator(aggregator_id_for_test(600), 600)
+            .expect("Get aggregator failed");
+        assert_eq!(aggregator.state(), AggregatorState::PositiveDelta);
+        assert_ok!(aggregator.sub(50));
+
+        assert_ok_eq!(data.read_snapshot(data_snapshot, &resolver), 100);
+        assert_ok_eq!(data.read_snapshot(delta_snapshot, &resolver), 500);
+
+        // Snapshots of aggregators V2 are always known.
+        let ephemeral_id = assert_ok!(data.create_new_aggregator(100, AggregatorWidth::U64));
+        assert_ok!(data.get_aggregator(ephemeral_id).unwrap().add(40));
+        let snapshot = assert_ok!(data.snapshot(ephemeral_id));
+        assert_eq!(
+            *data.get_snapshot(snapshot).unwrap(),
+            SnapshotValue::Data(40)
+        );
+
+        // Snapshots of aggregators not used in this transaction are not allowed.
+        assert_err!(data.snapshot_aggregator_v1(&aggregator_data, &aggregator_id_for_test(700)));
+        assert_err!(data.snapshot(EphemeralID(100)));
+    }
+
+    #[test]
+    fn test_derived_snapshots() {
+        let mut data = enabled_data();
+        let mut aggregator_data = AggregatorData::default();
+        let mut resolver = AggregatorStore::default();
+        resolver.set_from_id(aggregator_id_for_test(600), 300);
+
+        let aggregator = aggregator_data
+            .get_aggregator(aggregator_id_for_test(600), 600)
+            .expect("Get aggregator failed");
+        assert_ok!(aggregator.add(7));
+        let snapshot =
+            assert_ok!(data.snapshot_aggregator_v1(&aggregator_data, &aggregator_id_for_test(600)));
+
+        // Concatenations compose, and are only computed on read.
+        let name = assert_ok!(data.derive_string_concat(snapshot, b"Token #".to_vec(), vec![]));
+        let quoted = assert_ok!(data.derive_string_concat(name, b"'".to_vec(), b"'".to_vec()));
+        assert_eq!(
+            data.derived_snapshots().get(&quoted).unwrap().formula,
+            SnapshotToStringFormula::Concat {
+                prefix: b"'Token #".to_vec(),
+                suffix: b"'".to_vec(),
+            }
+        );
+        assert_ok_eq!(
+            data.read_derived_snapshot(name, &resolver),
+            b"Token #307".to_vec()
+        );
+        assert_ok_eq!(
+            data.read_derived_snapshot(quoted, &resolver),
+            b"'Token #307'".to_vec()
+        );
+
+        // Derived snapshots are not numeric snapshots.
+        assert_err!(data.read_snapshot(name, &resolver));
+        assert_err!(data.derive_string_concat(SnapshotID(100), vec![], vec![]));
+    }
+
+    #[test]
+    fn test_checkpoint_and_rollback() {
+        let mut data = enabled_data();
+        let first = assert_ok!(data.create_new_aggregator(100, AggregatorWidth::U64));
+        let checkpoint = data.checkpoint();
+
+        let second = assert_ok!(data.create_new_aggregator(100, AggregatorWidth::U64));
+        let snapshot = assert_ok!(data.snapshot(first));
+        assert_ok!(data.commit_aggregator(first, aggregator_id_for_test(100)));
+
+        data.rollback_to(checkpoint);
+        assert_ok!(data.get_aggregator(first));
+        assert_err!(data.get_aggregator(second));
+        assert_err!(data.get_snapshot(snapshot));
+        assert_eq!(data.translate(first), None);
+        assert_ok_eq!(
+            data.create_new_aggregator(100, AggregatorWidth::U64),
+            second
+        );
+        assert_ok_eq!(data.snapshot(first), snapshot);
+    }
+
+    #[test]
+    fn test_gas_hook() {
+        use AggregatorOperation::*;
+
+        let hook = Arc::new(RecordingGasMeterHook::default());
+        let mut data = AggregatorV2Data::new(AggregatorFeatures::all(), hook.clone());
+        let mut aggregator_data = AggregatorData::default();
+        let mut resolver = AggregatorStore::default();
+        resolver.set_from_id(aggregator_id_for_test(600), 300);
+
+        let id = aggregator_id_for_test(600);
+        assert_ok!(aggregator_data
+            .get_aggregator_with_width(id.clone(), 600, AggregatorWidth::U64)
+            .expect("Get aggregator failed")
+            .add(100));
+
+        let snapshot = assert_ok!(data.snapshot_aggregator_v1(&aggregator_data, &id));
+        let derived = assert_ok!(data.derive_string_concat(snapshot, b"#".to_vec(), vec![]));
+        assert_ok_eq!(data.read_snapshot(snapshot, &resolver), 400);
+        assert_ok_eq!(
+            data.read_derived_snapshot(derived, &resolver),
+            b"#400".to_vec()
+        );
+
+        // Aggregators V2 are charged with the hook of the data.
+        let ephemeral_id = assert_ok!(data.create_new_aggregator(100, AggregatorWidth::U128));
+        assert_ok!(data.get_aggregator(ephemeral_id).unwrap().add(1));
+
+        assert_eq!(*hook.charges.lock().unwrap(), vec![
+            (Snapshot, 8),
+            (DeriveString, 1),
+            (Read, 16),
+            (Read, 4),
+            (Add, 16),
+        ]);
+    }
+}
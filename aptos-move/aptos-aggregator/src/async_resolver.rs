@@ -0,0 +1,113 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    aggregator_extension::AggregatorID,
+    resolver::{AggregatorReadMode, TAggregatorView},
+};
+use aptos_types::state_store::{state_key::StateKey, state_value::StateValue};
+use async_trait::async_trait;
+
+/// Asynchronous variant of `TAggregatorView`, for services which read
+/// aggregator values from networked storage and should not block a thread per
+/// read. Only aggregators V1 are supported, with the same semantics as in the
+/// synchronous trait.
+#[async_trait]
+pub trait AsyncAggregatorResolver: Send + Sync {
+    /// Returns:
+    ///   -  Ok(None)         if aggregator value is not in storage,
+    ///   -  Ok(Some(...))    if aggregator value exists in storage,
+    ///   -  Err(...)         otherwise (e.g. storage error).
+    async fn get_aggregator_v1_state_value(
+        &self,
+        id: &StateKey,
+        mode: AggregatorReadMode,
+    ) -> anyhow::Result<Option<StateValue>>;
+
+    async fn get_aggregator_v1_value(
+        &self,
+        id: &StateKey,
+        mode: AggregatorReadMode,
+    ) -> anyhow::Result<Option<u128>> {
+        let maybe_state_value = self.get_aggregator_v1_state_value(id, mode).await?;
+        match maybe_state_value {
+            Some(state_value) => Ok(Some(bcs::from_bytes(state_value.bytes())?)),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Adapts an asynchronous resolver to `TAggregatorView`, so that it can be
+/// used by the aggregator extension. Every read blocks the calling thread
+/// until the underlying future completes.
+pub struct BlockingAggregatorResolver<R> {
+    inner: R,
+}
+
+impl<R: AsyncAggregatorResolver> BlockingAggregatorResolver<R> {
+    pub fn new(inner: R) -> Self {
+        Self { inner }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: AsyncAggregatorResolver> TAggregatorView for BlockingAggregatorResolver<R> {
+    type IdentifierV1 = StateKey;
+    type IdentifierV2 = AggregatorID;
+
+    fn get_aggregator_v1_state_value(
+        &self,
+        id: &Self::IdentifierV1,
+        mode: AggregatorReadMode,
+    ) -> anyhow::Result<Option<StateValue>> {
+        futures::executor::block_on(self.inner.get_aggregator_v1_state_value(id, mode))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        aggregator_extension::AggregatorData, aggregator_id_for_test, delta_change_set::serialize,
+    };
+    use claims::{assert_err, assert_ok, assert_ok_eq};
+    use std::collections::HashMap;
+
+    struct AsyncStore(HashMap<StateKey, StateValue>);
+
+    #[async_trait]
+    impl AsyncAggregatorResolver for AsyncStore {
+        async fn get_aggregator_v1_state_value(
+            &self,
+            id: &StateKey,
+            _mode: AggregatorReadMode,
+        ) -> anyhow::Result<Option<StateValue>> {
+            Ok(self.0.get(id).cloned())
+        }
+    }
+
+    #[test]
+    fn test_blocking_adapter() {
+        let id = aggregator_id_for_test(600);
+        let store = AsyncStore(HashMap::from([(
+            id.as_state_key().clone(),
+            StateValue::new_legacy(serialize(&300).into()),
+        )]));
+        let resolver = BlockingAggregatorResolver::new(store);
+
+        let mut aggregator_data = AggregatorData::default();
+        let aggregator = aggregator_data
+            .get_aggregator(id.clone(), 600)
+            .expect("Get aggregator failed");
+        assert_ok!(aggregator.add(100));
+        assert_ok_eq!(aggregator.read_and_materialize(&resolver, &id), 400);
+
+        let aggregator = aggregator_data
+            .get_aggregator(aggregator_id_for_test(700), 700)
+            .expect("Get aggregator failed");
+        assert_err!(aggregator.read_and_materialize(&resolver, &aggregator_id_for_test(700)));
+    }
+}
@@ -0,0 +1,206 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+//! Arithmetic on unsigned integers bounded by a limit, shared by aggregators
+//! and delta application. All operations are checked: exceeding the limit or
+//! going below zero results in a `BoundViolation` instead of wrapping.
+
+use crate::delta_change_set::{abort_error, EADD_OVERFLOW, ESUB_UNDERFLOW};
+use move_binary_format::errors::PartialVMError;
+use move_core_types::u256::U256;
+use std::{
+    fmt::{Debug, Display},
+    ops::{Add, Sub},
+};
+
+/// Unsigned integer types which can be used with `BoundedMath`: u64, u128
+/// and u256.
+pub trait BoundedInteger:
+    Copy + Ord + Debug + Display + Add<Output = Self> + Sub<Output = Self>
+{
+}
+
+impl BoundedInteger for u64 {}
+impl BoundedInteger for u128 {}
+impl BoundedInteger for U256 {}
+
+/// Describes which bound of an aggregator was violated by an operation or by
+/// a delta applied to a base value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BoundViolation<T = u128> {
+    /// Adding `delta` to `base` exceeds `limit`.
+    Overflow { base: T, delta: T, limit: T },
+    /// Subtracting `delta` from `base` goes below zero.
+    Underflow { base: T, delta: T },
+    /// Adding `delta` to `base` was expected to exceed `limit`, but does not.
+    /// Only produced by validation of a delta history.
+    ExpectedOverflow { base: T, delta: T, limit: T },
+    /// Subtracting `delta` from `base` was expected to go below zero, but
+    /// does not. Only produced by validation of a delta history.
+    ExpectedUnderflow { base: T, delta: T },
+}
+
+impl<T> BoundViolation<T> {
+    /// Returns the abort code of the violation. Unexpected successes use the
+    /// code of the failure which was expected.
+    pub fn code(&self) -> u64 {
+        match self {
+            BoundViolation::Overflow { .. } | BoundViolation::ExpectedOverflow { .. } => {
+                EADD_OVERFLOW
+            },
+            BoundViolation::Underflow { .. } | BoundViolation::ExpectedUnderflow { .. } => {
+                ESUB_UNDERFLOW
+            },
+        }
+    }
+}
+
+impl<T: Display> Display for BoundViolation<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BoundViolation::Overflow { base, delta, limit } => write!(
+                f,
+                "overflow when adding {} to {} (limit {})",
+                delta, base, limit
+            ),
+            BoundViolation::Underflow { base, delta } => {
+                write!(f, "underflow when subtracting {} from {}", delta, base)
+            },
+            BoundViolation::ExpectedOverflow { base, delta, limit } => write!(
+                f,
+                "expected overflow when adding {} to {} (limit {})",
+                delta, base, limit
+            ),
+            BoundViolation::ExpectedUnderflow { base, delta } => write!(
+                f,
+                "expected underflow when subtracting {} from {}",
+                delta, base
+            ),
+        }
+    }
+}
+
+impl<T: Display> From<BoundViolation<T>> for PartialVMError {
+    fn from(violation: BoundViolation<T>) -> Self {
+        let code = violation.code();
+        abort_error(violation, code)
+    }
+}
+
+/// A value which is added to (`Plus`) or subtracted from (`Minus`) a base.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SignedValue<T> {
+    Plus(T),
+    Minus(T),
+}
+
+/// Checked arithmetic on values in [0, limit].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BoundedMath<T> {
+    limit: T,
+}
+
+impl<T: BoundedInteger> BoundedMath<T> {
+    pub fn new(limit: T) -> Self {
+        Self { limit }
+    }
+
+    pub fn limit(&self) -> T {
+        self.limit
+    }
+
+    /// Returns `base + value`, or an error if the result exceeds the limit.
+    /// Also fails if `base` itself is above the limit.
+    pub fn unsigned_add(&self, base: T, value: T) -> Result<T, BoundViolation<T>> {
+        if self.limit < base || value > (self.limit - base) {
+            Err(BoundViolation::Overflow {
+                base,
+                delta: value,
+                limit: self.limit,
+            })
+        } else {
+            Ok(base + value)
+        }
+    }
+
+    /// Returns `base - value`, or an error if the result is below zero.
+    pub fn unsigned_subtract(&self, base: T, value: T) -> Result<T, BoundViolation<T>> {
+        if value > base {
+            Err(BoundViolation::Underflow { base, delta: value })
+        } else {
+            Ok(base - value)
+        }
+    }
+
+    /// Returns the value expected after applying a signed value to `base`.
+    pub fn expected_value(&self, base: T, value: SignedValue<T>) -> Result<T, BoundViolation<T>> {
+        match value {
+            SignedValue::Plus(value) => self.unsigned_add(base, value),
+            SignedValue::Minus(value) => self.unsigned_subtract(base, value),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use claims::{assert_err, assert_ok_eq};
+
+    #[test]
+    fn test_bounded_math_u64() {
+        let math = BoundedMath::new(100_u64);
+        assert_ok_eq!(math.unsigned_add(40, 60), 100);
+        assert_eq!(
+            math.unsigned_add(41, 60).unwrap_err(),
+            BoundViolation::Overflow {
+                base: 41,
+                delta: 60,
+                limit: 100,
+            }
+        );
+        assert_err!(math.unsigned_add(101, 0));
+
+        assert_ok_eq!(math.unsigned_subtract(40, 40), 0);
+        assert_eq!(
+            math.unsigned_subtract(40, 41).unwrap_err(),
+            BoundViolation::Underflow {
+                base: 40,
+                delta: 41
+            }
+        );
+
+        let math = BoundedMath::new(u64::MAX);
+        assert_ok_eq!(
+            math.expected_value(u64::MAX - 1, SignedValue::Plus(1)),
+            u64::MAX
+        );
+        assert_err!(math.expected_value(u64::MAX, SignedValue::Plus(1)));
+        assert_ok_eq!(
+            math.expected_value(u64::MAX, SignedValue::Minus(u64::MAX)),
+            0
+        );
+    }
+
+    #[test]
+    fn test_bounded_math_u256() {
+        let max = U256::max_value();
+        let math = BoundedMath::new(max);
+        let one = U256::one();
+
+        assert_ok_eq!(math.unsigned_add(max - one, one), max);
+        let overflow = math.unsigned_add(max, one).unwrap_err();
+        assert_eq!(overflow.code(), EADD_OVERFLOW);
+        assert_ok_eq!(
+            math.expected_value(max, SignedValue::Minus(max)),
+            U256::zero()
+        );
+        let underflow = math
+            .expected_value(U256::zero(), SignedValue::Minus(one))
+            .unwrap_err();
+        assert_eq!(underflow.code(), ESUB_UNDERFLOW);
+        assert_eq!(
+            underflow.to_string(),
+            "underflow when subtracting 1 from 0".to_string()
+        );
+    }
+}
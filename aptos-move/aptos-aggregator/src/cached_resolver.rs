@@ -0,0 +1,138 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::resolver::{AggregatorReadMode, TAggregatorView};
+use aptos_types::state_store::{state_key::StateKey, state_value::StateValue};
+use std::{collections::HashMap, sync::Mutex};
+
+/// Wraps a resolver and memoizes values of aggregators V1 read from storage,
+/// so that repeated materializations of the same aggregator within a
+/// transaction or a block do not go to storage every time. The owner is
+/// responsible for invalidating entries when the underlying storage changes.
+///
+/// Only precise reads are cached: a precise value can be returned for a
+/// speculative read, but not the other way around. Errors are not cached.
+pub struct CachedAggregatorResolver<R> {
+    inner: R,
+    cache: Mutex<HashMap<StateKey, Option<StateValue>>>,
+}
+
+impl<R> CachedAggregatorResolver<R> {
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Removes the cached value of an aggregator, e.g. when it is written.
+    pub fn invalidate(&self, id: &StateKey) {
+        self.cache.lock().unwrap().remove(id);
+    }
+
+    /// Removes all cached values, e.g. at the end of a block.
+    pub fn invalidate_all(&self) {
+        self.cache.lock().unwrap().clear();
+    }
+
+    /// Returns the number of cached aggregator values.
+    pub fn num_cached(&self) -> usize {
+        self.cache.lock().unwrap().len()
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: TAggregatorView<IdentifierV1 = StateKey>> TAggregatorView for CachedAggregatorResolver<R> {
+    type IdentifierV1 = StateKey;
+    type IdentifierV2 = R::IdentifierV2;
+
+    fn get_aggregator_v1_state_value(
+        &self,
+        id: &Self::IdentifierV1,
+        mode: AggregatorReadMode,
+    ) -> anyhow::Result<Option<StateValue>> {
+        if let Some(cached) = self.cache.lock().unwrap().get(id) {
+            return Ok(cached.clone());
+        }
+
+        let precise = matches!(mode, AggregatorReadMode::Precise);
+        let maybe_state_value = self.inner.get_aggregator_v1_state_value(id, mode)?;
+        if precise {
+            self.cache
+                .lock()
+                .unwrap()
+                .insert(id.clone(), maybe_state_value.clone());
+        }
+        Ok(maybe_state_value)
+    }
+
+    fn get_aggregator_v2_value(
+        &self,
+        id: &Self::IdentifierV2,
+        mode: AggregatorReadMode,
+    ) -> anyhow::Result<u128> {
+        self.inner.get_aggregator_v2_value(id, mode)
+    }
+
+    fn generate_aggregator_v2_id(&self) -> Self::IdentifierV2 {
+        self.inner.generate_aggregator_v2_id()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{aggregator_id_for_test, AggregatorStore};
+    use claims::assert_ok_eq;
+
+    #[test]
+    fn test_cached_reads() {
+        let id = aggregator_id_for_test(600);
+        let resolver = CachedAggregatorResolver::new(
+            AggregatorStore::builder()
+                .with_value(id.clone(), 300)
+                .build(),
+        );
+        let key = id.as_state_key();
+
+        // Speculative reads are not cached.
+        assert_ok_eq!(
+            resolver.get_aggregator_v1_value(key, AggregatorReadMode::Speculative),
+            Some(300)
+        );
+        assert_eq!(resolver.num_cached(), 0);
+
+        // Precise reads are cached, also when the aggregator does not exist.
+        for _ in 0..3 {
+            assert_ok_eq!(
+                resolver.get_aggregator_v1_value(key, AggregatorReadMode::Precise),
+                Some(300)
+            );
+            assert_ok_eq!(
+                resolver.get_aggregator_v1_value(
+                    aggregator_id_for_test(700).as_state_key(),
+                    AggregatorReadMode::Precise
+                ),
+                None
+            );
+        }
+        assert_ok_eq!(
+            resolver.get_aggregator_v1_value(key, AggregatorReadMode::Speculative),
+            Some(300)
+        );
+        assert_eq!(resolver.inner.num_reads(), 3);
+
+        resolver.invalidate(key);
+        assert_ok_eq!(
+            resolver.get_aggregator_v1_value(key, AggregatorReadMode::Precise),
+            Some(300)
+        );
+        assert_eq!(resolver.inner.num_reads(), 4);
+
+        resolver.invalidate_all();
+        assert_eq!(resolver.num_cached(), 0);
+    }
+}
@@ -0,0 +1,103 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+//! Metrics of aggregator operations. Without the `metrics` feature, all
+//! functions in this module do nothing.
+
+#[cfg(feature = "metrics")]
+use aptos_metrics_core::{
+    exponential_buckets, register_histogram_vec, register_int_counter_vec, HistogramVec,
+    IntCounterVec,
+};
+#[cfg(feature = "metrics")]
+use once_cell::sync::Lazy;
+#[cfg(feature = "metrics")]
+use std::sync::atomic::{AtomicU64, Ordering};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Operation {
+    Create,
+    Delete,
+    Materialize,
+    ApplyDelta,
+    ValidationFailure,
+}
+
+#[cfg(feature = "metrics")]
+impl Operation {
+    const ALL: [Operation; 5] = [
+        Operation::Create,
+        Operation::Delete,
+        Operation::Materialize,
+        Operation::ApplyDelta,
+        Operation::ValidationFailure,
+    ];
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            Operation::Create => "create",
+            Operation::Delete => "delete",
+            Operation::Materialize => "materialize",
+            Operation::ApplyDelta => "apply_delta",
+            Operation::ValidationFailure => "validation_failure",
+        }
+    }
+}
+
+/// Count of aggregator operations since the start of the process.
+#[cfg(feature = "metrics")]
+static AGGREGATOR_OPERATIONS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "aptos_aggregator_operations_count",
+        "Count of aggregator operations",
+        &["operation"]
+    )
+    .unwrap()
+});
+
+/// Count of aggregator operations per block.
+#[cfg(feature = "metrics")]
+static AGGREGATOR_OPERATIONS_PER_BLOCK: Lazy<HistogramVec> = Lazy::new(|| {
+    register_histogram_vec!(
+        "aptos_aggregator_operations_per_block",
+        "Histogram of the number of aggregator operations per block",
+        &["operation"],
+        exponential_buckets(/*start=*/ 1.0, /*factor=*/ 2.0, /*count=*/ 20).unwrap(),
+    )
+    .unwrap()
+});
+
+/// Operations in the current block, indexed like `Operation::ALL`.
+#[cfg(feature = "metrics")]
+static BLOCK_OPERATIONS: [AtomicU64; Operation::ALL.len()] = [
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+];
+
+/// Records a single aggregator operation.
+#[cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+pub(crate) fn observe(operation: Operation) {
+    #[cfg(feature = "metrics")]
+    {
+        AGGREGATOR_OPERATIONS
+            .with_label_values(&[operation.as_str()])
+            .inc();
+        BLOCK_OPERATIONS[operation as usize].fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Records the number of operations observed since the previous call as
+/// operations of a single block. Should be called by the block executor once
+/// the block is executed.
+pub fn observe_block_end() {
+    #[cfg(feature = "metrics")]
+    for operation in Operation::ALL {
+        let count = BLOCK_OPERATIONS[operation as usize].swap(0, Ordering::Relaxed);
+        AGGREGATOR_OPERATIONS_PER_BLOCK
+            .with_label_values(&[operation.as_str()])
+            .observe(count as f64);
+    }
+}
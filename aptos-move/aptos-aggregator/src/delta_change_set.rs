@@ -5,66 +5,202 @@
 //! (for accessing the storage) and an operation: a partial function with a
 //! postcondition.
 
-use aptos_types::vm_status::StatusCode;
-use move_binary_format::errors::{PartialVMError, PartialVMResult};
-
-/// When `Addition` operation overflows the `limit`.
-const EADD_OVERFLOW: u64 = 0x02_0001;
-
-/// When `Subtraction` operation goes below zero.
-const ESUB_UNDERFLOW: u64 = 0x02_0002;
+pub use crate::abort_codes::{EADD_OVERFLOW, ESUB_UNDERFLOW};
+use crate::{
+    aggregator_extension::{apply_delta, AggregatorState, History},
+    bounded_math::{BoundViolation, BoundedMath, SignedValue},
+    counters::{self, Operation},
+    module::AGGREGATOR_MODULE,
+    types::AggregatorWidth,
+    versioned::Versioned,
+};
+use anyhow::bail;
+use aptos_types::vm_status::{StatusCode, VMStatus};
+use move_binary_format::errors::{Location, PartialVMError, PartialVMResult};
+use serde::{Deserialize, Serialize};
+
+/// Version of the serialized `DeltaOp`, stored as the first byte. Must be
+/// bumped whenever the layout changes.
+pub const DELTA_OP_SERIALIZATION_VERSION: u8 = 1;
 
 /// Represents an update from aggregator's operation.
-#[derive(Copy, Clone, Hash, PartialOrd, Ord, PartialEq, Eq)]
+#[derive(Copy, Clone, Hash, PartialOrd, Ord, PartialEq, Eq, Serialize, Deserialize)]
 pub struct DeltaOp {
     /// Maximum positive delta seen during execution.
     max_positive: u128,
     /// Smallest negative delta seen during execution.
     min_negative: u128,
+    /// Smallest positive delta which overflowed during execution, if any.
+    min_overflow_positive: Option<u128>,
+    /// Largest negative delta which dropped below zero during execution, if
+    /// any.
+    max_underflow_negative: Option<u128>,
     /// Postcondition: delta overflows on exceeding this limit or going below
     /// zero.
     limit: u128,
     /// Delta which is the result of the execution.
     update: DeltaUpdate,
+    /// Integer type of the aggregator the delta is applied to.
+    width: AggregatorWidth,
+}
+
+/// Layout of `DeltaOp` with serialization version 0, which did not store
+/// the deltas which failed during execution.
+#[derive(Deserialize)]
+struct DeltaOpV0 {
+    max_positive: u128,
+    min_negative: u128,
+    limit: u128,
+    update: DeltaUpdate,
+    width: AggregatorWidth,
+}
+
+impl Versioned for DeltaOp {
+    const VERSION: u8 = DELTA_OP_SERIALIZATION_VERSION;
+
+    fn decode_legacy(version: u8, bytes: &[u8]) -> anyhow::Result<Self> {
+        match version {
+            0 => Ok(bcs::from_bytes::<DeltaOpV0>(bytes)?.into()),
+            _ => bail!("unsupported delta serialization version {}", version),
+        }
+    }
+}
+
+impl From<DeltaOpV0> for DeltaOp {
+    fn from(delta: DeltaOpV0) -> Self {
+        DeltaOp::new(
+            delta.update,
+            delta.limit,
+            delta.max_positive,
+            delta.min_negative,
+        )
+        .with_width(delta.width)
+    }
 }
 
 /// Different delta functions.
-#[derive(Copy, Clone, Debug, Hash, PartialOrd, Ord, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, Hash, PartialOrd, Ord, PartialEq, Eq, Serialize, Deserialize)]
 pub enum DeltaUpdate {
     Plus(u128),
     Minus(u128),
 }
 
+impl From<DeltaUpdate> for SignedValue<u128> {
+    fn from(update: DeltaUpdate) -> Self {
+        match update {
+            DeltaUpdate::Plus(value) => SignedValue::Plus(value),
+            DeltaUpdate::Minus(value) => SignedValue::Minus(value),
+        }
+    }
+}
+
 impl DeltaOp {
-    /// Creates a new delta op.
+    /// Creates a new delta op for `u128` aggregator.
     pub fn new(update: DeltaUpdate, limit: u128, max_positive: u128, min_negative: u128) -> Self {
         Self {
             max_positive,
             min_negative,
+            min_overflow_positive: None,
+            max_underflow_negative: None,
             limit,
             update,
+            width: AggregatorWidth::U128,
         }
     }
 
+    /// Sets the deltas which failed during execution, see `History`.
+    pub fn with_failure_bounds(
+        mut self,
+        min_overflow_positive: Option<u128>,
+        max_underflow_negative: Option<u128>,
+    ) -> Self {
+        self.min_overflow_positive = min_overflow_positive;
+        self.max_underflow_negative = max_underflow_negative;
+        self
+    }
+
+    /// Sets the integer type of the aggregator the delta is applied to.
+    pub fn with_width(mut self, width: AggregatorWidth) -> Self {
+        self.width = width;
+        self
+    }
+
+    /// Returns true if applying the delta never changes the value and never
+    /// fails, i.e. the delta is +0 and no other deltas were seen during
+    /// execution.
+    pub fn is_noop(&self) -> bool {
+        matches!(self.update, DeltaUpdate::Plus(0) | DeltaUpdate::Minus(0))
+            && self.max_positive == 0
+            && self.min_negative == 0
+            && self.min_overflow_positive.is_none()
+            && self.max_underflow_negative.is_none()
+    }
+
     /// Returns the kind of update for the delta op.
     pub fn get_update(&self) -> DeltaUpdate {
         self.update
     }
 
+    /// Returns the integer type of the aggregator the delta is applied to.
+    pub fn width(&self) -> AggregatorWidth {
+        self.width
+    }
+
+    /// Returns the largest positive and smallest negative deltas seen during
+    /// execution, together with the deltas which failed.
+    pub fn history(&self) -> History {
+        History::from_bounds(self.max_positive, self.min_negative)
+            .with_failure_bounds(self.min_overflow_positive, self.max_underflow_negative)
+    }
+
+    /// Serializes the result of delta application using the width of the
+    /// aggregator, e.g. as `u64` for `u64` aggregators.
+    pub fn serialize_result(&self, value: u128) -> PartialVMResult<Vec<u8>> {
+        self.width.serialize(value)
+    }
+
     /// Returns the result of delta application to `base` or error if
     /// postcondition is not satisfied.
     pub fn apply_to(&self, base: u128) -> PartialVMResult<u128> {
-        // First, validate if delta op can be applied to `base`. Note that
-        // this is possible if the values observed during execution didn't
-        // overflow or dropped below zero. The check can be emulated by actually
-        // doing addition and subtraction.
-        addition(base, self.max_positive, self.limit)?;
-        subtraction(base, self.min_negative)?;
-
-        // If delta has been successfully validated, apply the update.
+        let (state, value) = match self.update {
+            DeltaUpdate::Plus(value) => (AggregatorState::PositiveDelta, value),
+            DeltaUpdate::Minus(value) => (AggregatorState::NegativeDelta, value),
+        };
+        let result =
+            apply_delta(base, state, value, self.limit, &self.history()).map_err(|violation| {
+                counters::observe(Operation::ValidationFailure);
+                abort_error(
+                    format!("cannot apply delta {} to {}: {}", self, base, violation),
+                    violation.code(),
+                )
+            })?;
+        counters::observe(Operation::ApplyDelta);
+        Ok(result)
+    }
+
+    /// Validates if delta op can be applied to `base`, returning the violated
+    /// bound otherwise. Note that this is possible if the values observed
+    /// during execution didn't overflow or dropped below zero. The check can
+    /// be emulated by actually doing addition and subtraction.
+    pub fn validate(&self, base: u128) -> Result<(), BoundViolation> {
+        self.history().validate(base, self.limit).map_err(|e| {
+            counters::observe(Operation::ValidationFailure);
+            e
+        })?;
+        Ok(())
+    }
+
+    /// Returns bounds of the result of delta application to any base value
+    /// within (`lower`, `upper`) bounds. Unlike `apply_to`, the history is
+    /// not validated and results are clamped to [0, limit], so that bounds can
+    /// be estimated for base values for which application would fail.
+    pub fn apply_to_bounds(&self, (lower, upper): (u128, u128)) -> (u128, u128) {
         match self.update {
-            DeltaUpdate::Plus(value) => addition(base, value, self.limit),
-            DeltaUpdate::Minus(value) => subtraction(base, value),
+            DeltaUpdate::Plus(value) => (
+                u128::min(lower.saturating_add(value), self.limit),
+                u128::min(upper.saturating_add(value), self.limit),
+            ),
+            DeltaUpdate::Minus(value) => (lower.saturating_sub(value), upper.saturating_sub(value)),
         }
     }
 
@@ -73,7 +209,7 @@ impl DeltaOp {
         match delta.update {
             // Suppose that maximum value seen is +M and we shift by +V. Then the
             // new maximum value is M+V provided addition do no overflow.
-            DeltaUpdate::Plus(value) => addition(value, self.max_positive, self.limit),
+            DeltaUpdate::Plus(value) => Ok(addition(value, self.max_positive, self.limit)?),
             // Suppose that maximum value seen is +M and we shift by -V this time.
             // If M >= V, the result is +(M-V). Otherwise, `self` should have never
             // reached any positive value. By convention, we use 0 for the latter
@@ -94,7 +230,56 @@ impl DeltaOp {
             // Otherwise, given  the minimum value of -M and the shift of -V the new
             // minimum value becomes -(M+V), which of course can overflow on addition,
             // implying that we subtracted too much and there was an underflow.
-            DeltaUpdate::Minus(value) => addition(value, self.min_negative, self.limit),
+            DeltaUpdate::Minus(value) => Ok(addition(value, self.min_negative, self.limit)?),
+        }
+    }
+
+    /// Shifts by a `delta` the minimum positive value which overflowed in
+    /// `self`. Returns `None` if the shifted value overflows for any base.
+    fn shifted_min_overflow_positive_by(&self, delta: &DeltaOp) -> PartialVMResult<Option<u128>> {
+        let overflow = match self.min_overflow_positive {
+            Some(overflow) => overflow,
+            None => return Ok(None),
+        };
+        match delta.update {
+            // Suppose that +O overflowed and we shift by +V. Then +(O+V) must
+            // overflow. If O+V exceeds the limit, it overflows for every base,
+            // so there is nothing to validate.
+            DeltaUpdate::Plus(value) => Ok(addition(value, overflow, self.limit).ok()),
+            // If we shift by -V, then +(O-V) must overflow. If O <= V, this is
+            // impossible because no base exceeds the limit.
+            DeltaUpdate::Minus(value) => match subtraction(overflow, value) {
+                Ok(shifted) if shifted > 0 => Ok(Some(shifted)),
+                _ => Err(abort_error(
+                    format!("delta {} cannot overflow after delta {}", self, delta),
+                    EADD_OVERFLOW,
+                )),
+            },
+        }
+    }
+
+    /// Shifts by a `delta` the maximum negative value which dropped below
+    /// zero in `self`. Returns `None` if the shifted value drops below zero
+    /// for any base.
+    fn shifted_max_underflow_negative_by(&self, delta: &DeltaOp) -> PartialVMResult<Option<u128>> {
+        let underflow = match self.max_underflow_negative {
+            Some(underflow) => underflow,
+            None => return Ok(None),
+        };
+        match delta.update {
+            // Symmetric to `shifted_min_overflow_positive_by`: given -U which
+            // dropped below zero and the shift of +V, -(U-V) must drop below
+            // zero, which is impossible if U <= V.
+            DeltaUpdate::Plus(value) => match subtraction(underflow, value) {
+                Ok(shifted) if shifted > 0 => Ok(Some(shifted)),
+                _ => Err(abort_error(
+                    format!("delta {} cannot underflow after delta {}", self, delta),
+                    ESUB_UNDERFLOW,
+                )),
+            },
+            // Given the shift of -V, -(U+V) must drop below zero. If U+V
+            // exceeds the limit, it does so for every base.
+            DeltaUpdate::Minus(value) => Ok(addition(value, underflow, self.limit).ok()),
         }
     }
 
@@ -106,7 +291,13 @@ impl DeltaOp {
 
         assert_eq!(
             self.limit, previous_delta.limit,
-            "Cannot merge deltas with different limits",
+            "Cannot merge deltas with different limits: {} and {}",
+            previous_delta, self,
+        );
+        assert_eq!(
+            self.width, previous_delta.width,
+            "Cannot merge deltas with different widths: {} and {}",
+            previous_delta, self,
         );
 
         // First, update the history values of this delta given that it starts from
@@ -118,6 +309,43 @@ impl DeltaOp {
         // +2+99 > 100 at some point in history and fail.
         let shifted_max_positive = self.shifted_max_positive_by(&previous_delta)?;
         let shifted_min_negative = self.shifted_min_negative_by(&previous_delta)?;
+        let shifted_min_overflow_positive =
+            self.shifted_min_overflow_positive_by(&previous_delta)?;
+        let shifted_max_underflow_negative =
+            self.shifted_max_underflow_negative_by(&previous_delta)?;
+
+        // Merge the histories. For failures, it is enough to validate that
+        // the failed deltas closest to zero still fail.
+        let max_positive = u128::max(previous_delta.max_positive, shifted_max_positive);
+        let min_negative = u128::max(previous_delta.min_negative, shifted_min_negative);
+        let min_overflow_positive = min_failure(
+            previous_delta.min_overflow_positive,
+            shifted_min_overflow_positive,
+        );
+        let max_underflow_negative = min_failure(
+            previous_delta.max_underflow_negative,
+            shifted_max_underflow_negative,
+        );
+
+        // The same delta cannot both succeed and fail.
+        if matches!(min_overflow_positive, Some(overflow) if overflow <= max_positive) {
+            return Err(abort_error(
+                format!(
+                    "merged history of {} and {} is contradictory",
+                    previous_delta, self
+                ),
+                EADD_OVERFLOW,
+            ));
+        }
+        if matches!(max_underflow_negative, Some(underflow) if underflow <= min_negative) {
+            return Err(abort_error(
+                format!(
+                    "merged history of {} and {} is contradictory",
+                    previous_delta, self
+                ),
+                ESUB_UNDERFLOW,
+            ));
+        }
 
         // Useful macro for merging deltas of the same sign, e.g. +A+B or -A-B.
         // In this cases we compute the absolute sum of deltas (A+B) and use plus
@@ -154,8 +382,10 @@ impl DeltaOp {
         }
 
         // Deltas have been merged successfully - update the history as well.
-        self.max_positive = u128::max(previous_delta.max_positive, shifted_max_positive);
-        self.min_negative = u128::max(previous_delta.min_negative, shifted_min_negative);
+        self.max_positive = max_positive;
+        self.min_negative = min_negative;
+        self.min_overflow_positive = min_overflow_positive;
+        self.max_underflow_negative = max_underflow_negative;
         Ok(())
     }
 
@@ -170,40 +400,98 @@ impl DeltaOp {
         self.merge_with_previous_delta(previous_delta)?;
         Ok(())
     }
+
+    /// Serializes the delta so that it can be sent to other processes or
+    /// persisted: a version byte followed by BCS encoding of the fields in
+    /// declaration order. The encoding is canonical, i.e. equal deltas have
+    /// equal bytes.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.to_versioned_bytes()
+    }
+
+    /// Deserializes the delta from bytes produced by `to_bytes`, including
+    /// bytes produced with older serialization versions.
+    pub fn from_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
+        Self::from_versioned_bytes(bytes)
+    }
+
+    /// Returns a single delta equivalent to applying `previous` and then
+    /// `self`, e.g. deltas of two sequential transactions. Merging is
+    /// associative, so sequences of deltas can be combined in any grouping.
+    pub fn merge_with_previous(mut self, previous: DeltaOp) -> PartialVMResult<DeltaOp> {
+        self.merge_with_previous_delta(previous)?;
+        Ok(self)
+    }
+
+    /// Merges `self` onto `accumulated`, which becomes a single delta
+    /// equivalent to applying `accumulated` and then `self`. Useful to fold
+    /// a sequence of deltas into one.
+    pub fn merge_onto(self, accumulated: &mut DeltaOp) -> PartialVMResult<()> {
+        *accumulated = self.merge_with_previous(*accumulated)?;
+        Ok(())
+    }
 }
 
-/// Implements application of `Addition` to `base`.
-pub fn addition(base: u128, value: u128, limit: u128) -> PartialVMResult<u128> {
-    if limit < base || value > (limit - base) {
-        Err(abort_error(
-            format!("overflow when adding {} to {}", value, base),
-            EADD_OVERFLOW,
-        ))
-    } else {
-        Ok(base + value)
+/// Converts an error of delta application or merging into `VMStatus`. Bound
+/// violations become aborts in the aggregator module, with `EADD_OVERFLOW` or
+/// `ESUB_UNDERFLOW` code and the limit stated in the message.
+pub fn delta_error_into_vm_status(error: PartialVMError) -> VMStatus {
+    error
+        .finish(Location::Module(AGGREGATOR_MODULE.clone()))
+        .into_vm_status()
+}
+
+/// Returns the absolute value of the failed delta closest to zero, which is
+/// the hardest one to reproduce, or `None` if there are no failed deltas.
+fn min_failure(a: Option<u128>, b: Option<u128>) -> Option<u128> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(u128::min(a, b)),
+        (a, b) => a.or(b),
     }
 }
 
+/// Implements application of `Addition` to `base`.
+pub fn addition(base: u128, value: u128, limit: u128) -> Result<u128, BoundViolation> {
+    BoundedMath::new(limit).unsigned_add(base, value)
+}
+
 /// Implements application of `Subtraction` to `base`.
-pub fn subtraction(base: u128, value: u128) -> PartialVMResult<u128> {
-    if value > base {
-        Err(abort_error(
-            format!("underflow when subtracting {} from {}", value, base),
-            ESUB_UNDERFLOW,
-        ))
-    } else {
-        Ok(base - value)
-    }
+pub fn subtraction(base: u128, value: u128) -> Result<u128, BoundViolation> {
+    BoundedMath::new(u128::MAX).unsigned_subtract(base, value)
 }
 
 /// Error for delta application. Can be used by delta partial functions
 /// to return descriptive error messages and an appropriate error code.
-fn abort_error(message: impl ToString, code: u64) -> PartialVMError {
+pub(crate) fn abort_error(message: impl ToString, code: u64) -> PartialVMError {
     PartialVMError::new(StatusCode::ABORTED)
         .with_message(message.to_string())
         .with_sub_status(code)
 }
 
+impl std::fmt::Display for DeltaUpdate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DeltaUpdate::Plus(value) => write!(f, "+{}", value),
+            DeltaUpdate::Minus(value) => write!(f, "-{}", value),
+        }
+    }
+}
+
+/// Formats the delta together with its history, e.g. `+42 (max +100,
+/// min -7, limit 1000)` or `+42 (max +100, min -7, overflow at +901, limit
+/// 1000)` if some deltas failed.
+impl std::fmt::Display for DeltaOp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} ({}, limit {})",
+            self.update,
+            self.history(),
+            self.limit
+        )
+    }
+}
+
 impl std::fmt::Debug for DeltaOp {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self.update {
@@ -252,7 +540,7 @@ mod test {
         write_set::WriteOp,
     };
     use claims::{assert_err, assert_matches, assert_ok, assert_ok_eq};
-    use move_core_types::vm_status::VMStatus;
+    use move_core_types::vm_status::AbortLocation;
     use once_cell::sync::Lazy;
 
     fn delta_add_with_history(v: u128, limit: u128, max: u128, min: u128) -> DeltaOp {
@@ -303,6 +591,25 @@ mod test {
         assert_ok_eq!(sub5.apply_to(90), 85);
     }
 
+    #[test]
+    fn test_u64_delta_application() {
+        let limit = u64::MAX as u128;
+        let add = delta_add(10, limit).with_width(AggregatorWidth::U64);
+        assert_ok_eq!(add.apply_to(limit - 10), limit);
+        assert_err!(add.apply_to(limit - 9));
+
+        // Result is serialized as u64, without widening it to u128.
+        assert_ok_eq!(
+            add.serialize_result(limit),
+            bcs::to_bytes(&u64::MAX).unwrap()
+        );
+        assert_err!(add.serialize_result(limit + 1));
+        assert_ok_eq!(
+            AggregatorWidth::U64.deserialize(&bcs::to_bytes(&u64::MAX).unwrap()),
+            limit
+        );
+    }
+
     #[test]
     fn test_delta_merge_plus() {
         use DeltaUpdate::*;
@@ -496,6 +803,188 @@ mod test {
         assert_eq!(b.update, Minus(1));
     }
 
+    #[test]
+    fn test_delta_merge_associative() {
+        let a = delta_add_with_history(30, 100, 60, 10);
+        let b = delta_sub_with_history(50, 100, 5, 50);
+        let c = delta_add_with_history(20, 100, 40, 0);
+
+        // (a, b), c
+        let ab = assert_ok!(b.merge_with_previous(a));
+        let ab_c = assert_ok!(c.merge_with_previous(ab));
+
+        // a, (b, c)
+        let bc = assert_ok!(c.merge_with_previous(b));
+        let a_bc = assert_ok!(bc.merge_with_previous(a));
+        assert_eq!(ab_c, a_bc);
+
+        // Folding gives the same result, and merged delta is applied the same
+        // way as the sequence of deltas.
+        let mut accumulated = a;
+        assert_ok!(b.merge_onto(&mut accumulated));
+        assert_ok!(c.merge_onto(&mut accumulated));
+        assert_eq!(accumulated, ab_c);
+        for base in 0..=100 {
+            let sequential = a
+                .apply_to(base)
+                .and_then(|v| b.apply_to(v))
+                .and_then(|v| c.apply_to(v));
+            assert_eq!(accumulated.apply_to(base).ok(), sequential.ok());
+        }
+    }
+
+    #[test]
+    fn test_delta_merge_failures() {
+        // Adding 80 failed, so the base must be greater than 20.
+        let a = delta_add_with_history(30, 100, 30, 0).with_failure_bounds(Some(80), None);
+        // Subtracting 60 after +30 failed, so the base must be less than 30.
+        let b = delta_sub(10, 100).with_failure_bounds(None, Some(60));
+
+        let ab = assert_ok!(b.merge_with_previous(a));
+        assert_eq!(
+            ab.history(),
+            History::from_bounds(30, 0).with_failure_bounds(Some(80), Some(30))
+        );
+        assert_eq!(
+            ab.to_string(),
+            "+20 (max +30, min -0, overflow at +80, underflow at -30, limit 100)"
+        );
+        for base in 0..=100 {
+            let sequential = a.apply_to(base).and_then(|v| b.apply_to(v));
+            assert_eq!(ab.apply_to(base).ok(), sequential.ok());
+            assert_eq!(ab.validate(base).is_ok(), (21..30).contains(&base));
+        }
+
+        // Only the failures closest to zero need to be reproduced.
+        let e = delta_add(0, 100).with_failure_bounds(None, Some(40));
+        let f = delta_add(0, 100).with_failure_bounds(None, Some(20));
+        assert_eq!(
+            assert_ok!(f.merge_with_previous(e)).history(),
+            History::from_bounds(0, 0).with_failure_bounds(None, Some(20))
+        );
+
+        // Shifted failures which fail for any base are dropped.
+        let c = delta_add(0, 100).with_failure_bounds(Some(50), None);
+        let merged = assert_ok!(c.merge_with_previous(delta_add(60, 100)));
+        assert_eq!(merged.history(), History::from_bounds(60, 0));
+
+        // After -50, adding 50 cannot overflow.
+        let error = assert_err!(c.merge_with_previous(delta_sub(50, 100)));
+        assert_matches!(
+            delta_error_into_vm_status(error),
+            VMStatus::MoveAbort(_, EADD_OVERFLOW)
+        );
+        // After -20, adding 10 cannot drop below zero.
+        let d = delta_add(0, 100).with_failure_bounds(None, Some(10));
+        let error = assert_err!(d.merge_with_previous(delta_add(20, 100)));
+        assert_matches!(
+            delta_error_into_vm_status(error),
+            VMStatus::MoveAbort(_, ESUB_UNDERFLOW)
+        );
+        // Adding 60 succeeded, so adding 50 after +10 cannot overflow.
+        let error = assert_err!(c.merge_with_previous(delta_add_with_history(10, 100, 60, 0)));
+        assert_matches!(
+            delta_error_into_vm_status(error),
+            VMStatus::MoveAbort(_, EADD_OVERFLOW)
+        );
+    }
+
+    #[test]
+    fn test_delta_serialization() {
+        let deltas = [
+            delta_add(5, 100),
+            delta_sub_with_history(20, 100, 1, 40),
+            delta_add(u64::MAX as u128, u64::MAX as u128).with_width(AggregatorWidth::U64),
+        ];
+        for delta in deltas {
+            assert_ok_eq!(DeltaOp::from_bytes(&delta.to_bytes()), delta);
+        }
+
+        // The layout is fixed: version, history, limit, update and width.
+        let mut expected = vec![DELTA_OP_SERIALIZATION_VERSION];
+        expected.extend(5u128.to_le_bytes());
+        expected.extend(0u128.to_le_bytes());
+        expected.push(0);
+        expected.push(0);
+        expected.extend(100u128.to_le_bytes());
+        expected.push(0);
+        expected.extend(5u128.to_le_bytes());
+        expected.push(1);
+        assert_eq!(delta_add(5, 100).to_bytes(), expected);
+
+        // Deltas serialized before failures were tracked can still be read.
+        let mut version_0 = vec![0];
+        version_0.extend(5u128.to_le_bytes());
+        version_0.extend(0u128.to_le_bytes());
+        version_0.extend(100u128.to_le_bytes());
+        version_0.push(0);
+        version_0.extend(5u128.to_le_bytes());
+        version_0.push(1);
+        assert_ok_eq!(DeltaOp::from_bytes(&version_0), delta_add(5, 100));
+
+        // Unknown versions and malformed bytes are rejected.
+        expected[0] = DELTA_OP_SERIALIZATION_VERSION + 1;
+        assert_err!(DeltaOp::from_bytes(&expected));
+        assert_err!(DeltaOp::from_bytes(&[]));
+        assert_err!(DeltaOp::from_bytes(&[DELTA_OP_SERIALIZATION_VERSION, 1, 2]));
+    }
+
+    #[test]
+    fn test_display() {
+        let delta = DeltaOp::new(DeltaUpdate::Plus(42), 1000, 100, 7);
+        assert_eq!(delta.to_string(), "+42 (max +100, min -7, limit 1000)");
+        assert_eq!(
+            delta_sub(5, 1000).to_string(),
+            "-5 (max +0, min -5, limit 1000)"
+        );
+
+        let error = assert_err!(delta.apply_to(901));
+        assert_eq!(
+            error.all_data().2.unwrap(),
+            "cannot apply delta +42 (max +100, min -7, limit 1000) to 901: overflow when adding 100 to 901 (limit 1000)"
+        );
+    }
+
+    #[test]
+    fn test_bound_violations() {
+        let overflow = assert_err!(addition(90, 20, 100));
+        assert_eq!(overflow, BoundViolation::Overflow {
+            base: 90,
+            delta: 20,
+            limit: 100
+        });
+        assert_eq!(
+            overflow.to_string(),
+            "overflow when adding 20 to 90 (limit 100)"
+        );
+
+        let underflow = assert_err!(subtraction(10, 20));
+        assert_eq!(underflow, BoundViolation::Underflow {
+            base: 10,
+            delta: 20
+        });
+
+        // Violations are converted into Move aborts with distinct codes.
+        let error = PartialVMError::from(overflow);
+        assert_eq!(error.major_status(), StatusCode::ABORTED);
+        assert_matches!(
+            delta_error_into_vm_status(underflow.into()),
+            VMStatus::MoveAbort(AbortLocation::Module(_), ESUB_UNDERFLOW)
+        );
+        assert_matches!(
+            delta_error_into_vm_status(overflow.into()),
+            VMStatus::MoveAbort(AbortLocation::Module(_), EADD_OVERFLOW)
+        );
+    }
+
+    #[test]
+    fn test_delta_application_to_bounds() {
+        assert_eq!(delta_add(50, 100).apply_to_bounds((10, 20)), (60, 70));
+        assert_eq!(delta_add(50, 100).apply_to_bounds((40, 60)), (90, 100));
+        assert_eq!(delta_sub(50, 100).apply_to_bounds((60, 100)), (10, 50));
+        assert_eq!(delta_sub(50, 100).apply_to_bounds((40, 60)), (0, 10));
+    }
+
     static KEY: Lazy<StateKey> = Lazy::new(|| StateKey::raw(String::from("test-key").into_bytes()));
 
     #[test]
@@ -0,0 +1,190 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    aggregator_change_set::{AggregatorChange, AggregatorChangeSet},
+    aggregator_extension::AggregatorID,
+    bounded_math::BoundViolation,
+    delta_change_set::DeltaOp,
+    resolver::{get_aggregator_value, AggregatorReadMode, AggregatorResolver},
+};
+use std::collections::BTreeMap;
+
+/// Describes why deltas of a transaction could not be resolved against base
+/// values.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DeltaResolutionError {
+    /// Base value violates the history of the delta: some operation of the
+    /// transaction would have failed, and so it must be re-executed.
+    HistoryViolation {
+        id: AggregatorID,
+        violation: BoundViolation,
+    },
+    /// Base value does not exist, e.g. because the aggregator was deleted by
+    /// an earlier transaction. The transaction must be re-executed.
+    BaseNotFound(AggregatorID),
+    /// Base value could not be read. This is not caused by the transaction,
+    /// and should be handled like other storage errors.
+    StorageError { id: AggregatorID, message: String },
+}
+
+impl DeltaResolutionError {
+    /// Returns true if the transaction which produced the deltas must be
+    /// re-executed, rather than the error propagated.
+    pub fn requires_reexecution(&self) -> bool {
+        match self {
+            DeltaResolutionError::HistoryViolation { .. }
+            | DeltaResolutionError::BaseNotFound(_) => true,
+            DeltaResolutionError::StorageError { .. } => false,
+        }
+    }
+}
+
+/// Allows a parallel executor to resolve deltas produced by the aggregator
+/// extension when base values change during re-execution or validation of
+/// earlier transactions. Deltas are checked against their history, which is
+/// enough to tell if all operations of the transaction would have succeeded,
+/// so that the transaction only has to be re-executed if they would not.
+pub trait DeltaResolution {
+    /// Returns all deltas to resolve.
+    fn deltas(&self) -> Vec<(&AggregatorID, &DeltaOp)>;
+
+    /// Checks that all deltas can be applied to base values provided by
+    /// `resolver`. Returns the first failure otherwise.
+    fn validate_deltas(
+        &self,
+        resolver: &dyn AggregatorResolver,
+    ) -> Result<(), DeltaResolutionError> {
+        for (id, delta) in self.deltas() {
+            let base = read_base(resolver, id)?;
+            delta
+                .validate(base)
+                .map_err(|violation| DeltaResolutionError::HistoryViolation {
+                    id: id.clone(),
+                    violation,
+                })?;
+        }
+        Ok(())
+    }
+
+    /// Applies all deltas to base values provided by `resolver`, returning
+    /// resulting values. Fails in the same cases as `validate_deltas`.
+    fn reapply_deltas(
+        &self,
+        resolver: &dyn AggregatorResolver,
+    ) -> Result<BTreeMap<AggregatorID, u128>, DeltaResolutionError> {
+        self.validate_deltas(resolver)?;
+        self.deltas()
+            .into_iter()
+            .map(|(id, delta)| {
+                let base = read_base(resolver, id)?;
+                let value = delta
+                    .apply_to(base)
+                    .expect("Validated delta must be applied successfully");
+                Ok((id.clone(), value))
+            })
+            .collect()
+    }
+}
+
+fn read_base(
+    resolver: &dyn AggregatorResolver,
+    id: &AggregatorID,
+) -> Result<u128, DeltaResolutionError> {
+    get_aggregator_value(resolver, id, AggregatorReadMode::Precise)
+        .map_err(|e| DeltaResolutionError::StorageError {
+            id: id.clone(),
+            message: e.to_string(),
+        })?
+        .ok_or_else(|| DeltaResolutionError::BaseNotFound(id.clone()))
+}
+
+impl DeltaResolution for AggregatorChangeSet {
+    fn deltas(&self) -> Vec<(&AggregatorID, &DeltaOp)> {
+        self.changes
+            .iter()
+            .filter_map(|(id, change)| match change {
+                AggregatorChange::Merge(delta) => Some((id, delta)),
+                // Observed values can be applied to any base value.
+                AggregatorChange::Write(_)
+                | AggregatorChange::Observe(_)
+                | AggregatorChange::Delete => None,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        aggregator_id_for_test,
+        delta_change_set::{delta_add, delta_sub},
+        AggregatorStore,
+    };
+    use claims::{assert_ok, assert_ok_eq};
+
+    #[test]
+    fn test_delta_resolution() {
+        let change_set = AggregatorChangeSet {
+            changes: BTreeMap::from([
+                (aggregator_id_for_test(100), AggregatorChange::Write(10)),
+                (
+                    aggregator_id_for_test(200),
+                    AggregatorChange::Merge(delta_add(50, 100)),
+                ),
+                (
+                    aggregator_id_for_test(300),
+                    AggregatorChange::Merge(delta_sub(20, 100)),
+                ),
+            ]),
+            ..Default::default()
+        };
+
+        let resolver = AggregatorStore::builder()
+            .with_value(aggregator_id_for_test(200), 30)
+            .with_value(aggregator_id_for_test(300), 30)
+            .build();
+        assert_ok!(change_set.validate_deltas(&resolver));
+        assert_ok_eq!(
+            change_set.reapply_deltas(&resolver),
+            BTreeMap::from([
+                (aggregator_id_for_test(200), 80),
+                (aggregator_id_for_test(300), 10),
+            ])
+        );
+
+        // Base values changed by re-execution of earlier transactions.
+        let resolver = AggregatorStore::builder()
+            .with_value(aggregator_id_for_test(200), 60)
+            .with_value(aggregator_id_for_test(300), 30)
+            .build();
+        let error = change_set.validate_deltas(&resolver).unwrap_err();
+        assert!(error.requires_reexecution());
+        assert_eq!(error, DeltaResolutionError::HistoryViolation {
+            id: aggregator_id_for_test(200),
+            violation: BoundViolation::Overflow {
+                base: 60,
+                delta: 50,
+                limit: 100,
+            },
+        });
+
+        let resolver = AggregatorStore::builder()
+            .with_value(aggregator_id_for_test(200), 30)
+            .with_read_error(aggregator_id_for_test(300))
+            .build();
+        let error = change_set.reapply_deltas(&resolver).unwrap_err();
+        assert!(!error.requires_reexecution());
+
+        let resolver = AggregatorStore::builder()
+            .with_value(aggregator_id_for_test(300), 30)
+            .build();
+        assert_eq!(
+            change_set.reapply_deltas(&resolver),
+            Err(DeltaResolutionError::BaseNotFound(aggregator_id_for_test(
+                200
+            )))
+        );
+    }
+}
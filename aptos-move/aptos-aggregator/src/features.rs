@@ -0,0 +1,118 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::abort_codes::{EAGGREGATOR_SNAPSHOTS_NOT_ENABLED, ENOT_SUPPORTED};
+use aptos_types::{on_chain_config::Features, vm_status::StatusCode};
+use move_binary_format::errors::{PartialVMError, PartialVMResult};
+
+/// Describes which aggregator behaviours are enabled, so that the same binary
+/// can execute transactions on networks with different feature flags
+/// activated. Operations which are not enabled fail with
+/// `FEATURE_UNDER_GATING`, with the abort code of the Move module as the
+/// sub-status (see `abort_codes::into_abort_code`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AggregatorFeatures {
+    // Snapshots of aggregators, including derived string snapshots.
+    snapshots: bool,
+    // Checks that the value is at least or at most some threshold without
+    // materialization, which record a bound on the value in storage in
+    // history (see `Aggregator::is_at_least` and `Aggregator::is_at_most`).
+    bound_checks: bool,
+    // Aggregators V2 identified by ephemeral identifiers (see
+    // `AggregatorV2Data`). There is no separate on-chain flag: they are only
+    // reachable through the `aggregator_v2` natives, which are themselves
+    // gated by `AGGREGATOR_SNAPSHOTS`.
+    v2_ids: bool,
+}
+
+/// Only behaviours available on all networks are enabled by default, i.e.
+/// none of them.
+impl Default for AggregatorFeatures {
+    fn default() -> Self {
+        Self {
+            snapshots: false,
+            bound_checks: false,
+            v2_ids: false,
+        }
+    }
+}
+
+impl AggregatorFeatures {
+    /// Returns features with all behaviours enabled, e.g. for tests.
+    pub fn all() -> Self {
+        Self {
+            snapshots: true,
+            bound_checks: true,
+            v2_ids: true,
+        }
+    }
+
+    pub fn with_snapshots(mut self, enabled: bool) -> Self {
+        self.snapshots = enabled;
+        self
+    }
+
+    pub fn with_bound_checks(mut self, enabled: bool) -> Self {
+        self.bound_checks = enabled;
+        self
+    }
+
+    pub fn with_v2_ids(mut self, enabled: bool) -> Self {
+        self.v2_ids = enabled;
+        self
+    }
+
+    pub fn is_snapshots_enabled(&self) -> bool {
+        self.snapshots
+    }
+
+    pub fn is_bound_checks_enabled(&self) -> bool {
+        self.bound_checks
+    }
+
+    pub fn is_v2_ids_enabled(&self) -> bool {
+        self.v2_ids
+    }
+
+    pub(crate) fn check_snapshots(&self) -> PartialVMResult<()> {
+        check(
+            self.snapshots,
+            "Aggregator snapshots",
+            EAGGREGATOR_SNAPSHOTS_NOT_ENABLED,
+        )
+    }
+
+    pub(crate) fn check_bound_checks(&self) -> PartialVMResult<()> {
+        check(self.bound_checks, "Aggregator bound checks", ENOT_SUPPORTED)
+    }
+
+    pub(crate) fn check_v2_ids(&self) -> PartialVMResult<()> {
+        check(
+            self.v2_ids,
+            "Aggregators V2",
+            EAGGREGATOR_SNAPSHOTS_NOT_ENABLED,
+        )
+    }
+}
+
+/// Snapshots and aggregators V2 are both gated by the on-chain
+/// `AGGREGATOR_SNAPSHOTS` flag, and bound checks by `AGGREGATOR_BOUND_CHECKS`.
+impl From<&Features> for AggregatorFeatures {
+    fn from(features: &Features) -> Self {
+        let snapshots = features.is_aggregator_snapshots_enabled();
+        Self::default()
+            .with_snapshots(snapshots)
+            .with_v2_ids(snapshots)
+            .with_bound_checks(features.is_aggregator_bound_checks_enabled())
+    }
+}
+
+fn check(enabled: bool, feature: &str, abort_code: u64) -> PartialVMResult<()> {
+    if enabled {
+        Ok(())
+    } else {
+        Err(PartialVMError::new(StatusCode::FEATURE_UNDER_GATING)
+            .with_message(format!("{} are not enabled", feature))
+            .with_sub_status(abort_code))
+    }
+}
@@ -0,0 +1,67 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+#[cfg(test)]
+use aptos_types::vm_status::StatusCode;
+#[cfg(test)]
+use move_binary_format::errors::PartialVMError;
+use move_binary_format::errors::PartialVMResult;
+use std::fmt::Debug;
+
+/// Kinds of aggregator operations gas is charged for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AggregatorOperation {
+    /// Addition to an aggregator, including try and saturating variants.
+    Add,
+    /// Subtraction from an aggregator, including try and saturating variants.
+    Sub,
+    /// Read of the value of an aggregator, or a check against a threshold.
+    Read,
+    /// Snapshot of an aggregator.
+    Snapshot,
+    /// Derivation of a string snapshot.
+    DeriveString,
+    /// Observation of a value by a max or min aggregator.
+    Observe,
+}
+
+/// Invoked by the aggregator extension before every operation, so that the
+/// VM can charge gas for all of them in one place instead of at every call
+/// site. Operations are charged once, even if they consist of multiple
+/// internal steps (e.g. saturating addition which materializes the value).
+pub trait GasMeterHook: Debug + Send + Sync {
+    /// Charges for `operation` on `num_bytes` bytes: the size of the value
+    /// of an aggregator, or the size of the appended bytes for derived
+    /// strings. Returning an error aborts the operation before any side
+    /// effects.
+    fn charge(&self, operation: AggregatorOperation, num_bytes: usize) -> PartialVMResult<()>;
+}
+
+/// Hook which does not charge anything, used by default.
+#[derive(Debug, Default)]
+pub struct NoOpGasMeterHook;
+
+impl GasMeterHook for NoOpGasMeterHook {
+    fn charge(&self, _operation: AggregatorOperation, _num_bytes: usize) -> PartialVMResult<()> {
+        Ok(())
+    }
+}
+
+/// Hook which records all charges, and fails once `out_of_gas` is set.
+#[cfg(test)]
+#[derive(Debug, Default)]
+pub(crate) struct RecordingGasMeterHook {
+    pub(crate) charges: std::sync::Mutex<Vec<(AggregatorOperation, usize)>>,
+    pub(crate) out_of_gas: std::sync::atomic::AtomicBool,
+}
+
+#[cfg(test)]
+impl GasMeterHook for RecordingGasMeterHook {
+    fn charge(&self, operation: AggregatorOperation, num_bytes: usize) -> PartialVMResult<()> {
+        if self.out_of_gas.load(std::sync::atomic::Ordering::Relaxed) {
+            return Err(PartialVMError::new(StatusCode::OUT_OF_GAS));
+        }
+        self.charges.lock().unwrap().push((operation, num_bytes));
+        Ok(())
+    }
+}
@@ -1,10 +1,32 @@
 // Copyright © Aptos Foundation
 // SPDX-License-Identifier: Apache-2.0
 
+pub mod abort_codes;
+pub mod aggregator_change_set;
 pub mod aggregator_extension;
+pub mod aggregator_struct;
+pub mod aggregator_v2_extension;
+#[cfg(feature = "async")]
+pub mod async_resolver;
+pub mod bounded_math;
+pub mod cached_resolver;
+pub mod counters;
 pub mod delta_change_set;
+pub mod delta_resolution;
+pub mod features;
+pub mod gas_hook;
 mod module;
+pub mod observed_value;
+pub mod pending_delta_resolver;
+#[cfg(feature = "persistent-store")]
+pub mod persistent_store;
+#[cfg(test)]
+mod proptests;
 pub mod resolver;
+pub mod sharded_aggregator_data;
+pub mod types;
+pub mod value_exchange;
+pub mod versioned;
 
 #[cfg(any(test, feature = "testing"))]
-pub use resolver::test_utils::{aggregator_id_for_test, AggregatorStore};
+pub use resolver::test_utils::{aggregator_id_for_test, AggregatorStore, AggregatorStoreBuilder};
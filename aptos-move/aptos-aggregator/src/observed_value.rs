@@ -0,0 +1,236 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+//! Aggregators which track the largest or the smallest value observed, e.g.
+//! high-water marks. Unlike for regular aggregators, observing a value never
+//! fails, and so the delta of an observed-value aggregator is just the
+//! extremum of values observed during execution. Applying it to a base value
+//! is a join (max or min), which is commutative and associative: deltas can
+//! be merged and applied without reading the base value precisely.
+
+use crate::{
+    aggregator_change_set::AggregatorChange,
+    aggregator_extension::{read_aggregator_value, AggregatorID},
+    gas_hook::{AggregatorOperation, GasMeterHook},
+    resolver::{AggregatorReadMode, AggregatorResolver},
+    types::AggregatorWidth,
+    versioned::Versioned,
+};
+use move_binary_format::errors::PartialVMResult;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// Describes which extremum an observed-value aggregator tracks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ObservedKind {
+    /// Tracks `max(value, x)` for all observed `x`.
+    Max,
+    /// Tracks `min(value, x)` for all observed `x`.
+    Min,
+}
+
+impl ObservedKind {
+    fn join(&self, a: u128, b: u128) -> u128 {
+        match self {
+            ObservedKind::Max => u128::max(a, b),
+            ObservedKind::Min => u128::min(a, b),
+        }
+    }
+}
+
+/// Represents an update of an observed-value aggregator: the extremum of all
+/// values observed during execution, if any.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ObservedDelta {
+    kind: ObservedKind,
+    observed: Option<u128>,
+}
+
+impl ObservedDelta {
+    /// Creates a delta which has not observed any values yet.
+    pub fn new(kind: ObservedKind) -> Self {
+        Self {
+            kind,
+            observed: None,
+        }
+    }
+
+    pub fn kind(&self) -> ObservedKind {
+        self.kind
+    }
+
+    /// Returns the extremum of the observed values, or `None` if no values
+    /// were observed.
+    pub fn observed(&self) -> Option<u128> {
+        self.observed
+    }
+
+    /// Records an observed value.
+    pub fn observe(&mut self, value: u128) {
+        self.observed = Some(match self.observed {
+            None => value,
+            Some(observed) => self.kind.join(observed, value),
+        });
+    }
+
+    /// Returns the result of delta application to `base`. Never fails.
+    pub fn apply_to(&self, base: u128) -> u128 {
+        match self.observed {
+            None => base,
+            Some(observed) => self.kind.join(base, observed),
+        }
+    }
+
+    /// Merges `previous` into this delta. Since joins commute, the order of
+    /// deltas does not matter.
+    pub fn merge_with_previous(&mut self, previous: ObservedDelta) {
+        assert_eq!(
+            self.kind, previous.kind,
+            "Cannot merge observed deltas of different kinds",
+        );
+        if let Some(observed) = previous.observed {
+            self.observe(observed);
+        }
+    }
+}
+
+impl Versioned for ObservedDelta {
+    const VERSION: u8 = 0;
+}
+
+/// Aggregator which tracks the largest or the smallest value observed.
+#[derive(Clone, Debug)]
+pub struct ObservedValueAggregator {
+    // Value of the aggregator, if it is known (e.g. the aggregator has been
+    // created in this transaction or materialized).
+    value: Option<u128>,
+    // Values observed while the value is not known.
+    delta: ObservedDelta,
+    // Charges gas for operations on this aggregator.
+    gas_hook: Arc<dyn GasMeterHook>,
+}
+
+impl ObservedValueAggregator {
+    pub(crate) fn new(
+        kind: ObservedKind,
+        value: Option<u128>,
+        gas_hook: Arc<dyn GasMeterHook>,
+    ) -> Self {
+        Self {
+            value,
+            delta: ObservedDelta::new(kind),
+            gas_hook,
+        }
+    }
+
+    pub fn kind(&self) -> ObservedKind {
+        self.delta.kind
+    }
+
+    /// Records an observed value. Does not require the value of the
+    /// aggregator to be known.
+    pub fn observe(&mut self, value: u128) -> PartialVMResult<()> {
+        self.gas_hook.charge(
+            AggregatorOperation::Observe,
+            AggregatorWidth::U128.num_bytes(),
+        )?;
+        match self.value.as_mut() {
+            Some(known) => *known = self.delta.kind.join(*known, value),
+            None => self.delta.observe(value),
+        }
+        Ok(())
+    }
+
+    /// Returns the value of the aggregator, applying the observed values to
+    /// the value in storage if it is not known.
+    pub fn read_and_materialize(
+        &mut self,
+        resolver: &dyn AggregatorResolver,
+        id: &AggregatorID,
+    ) -> PartialVMResult<u128> {
+        self.gas_hook
+            .charge(AggregatorOperation::Read, AggregatorWidth::U128.num_bytes())?;
+        if let Some(value) = self.value {
+            return Ok(value);
+        }
+        let value = self.delta.apply_to(read_aggregator_value(
+            resolver,
+            id,
+            AggregatorReadMode::Precise,
+        )?);
+        self.value = Some(value);
+        Ok(value)
+    }
+
+    /// Returns the change to apply to storage, or `None` if nothing was
+    /// observed and the value is not known.
+    pub(crate) fn into_change(self) -> Option<AggregatorChange> {
+        match (self.value, self.delta.observed) {
+            (Some(value), _) => Some(AggregatorChange::Write(value)),
+            (None, Some(_)) => Some(AggregatorChange::Observe(self.delta)),
+            (None, None) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{aggregator_id_for_test, gas_hook::NoOpGasMeterHook, AggregatorStore};
+    use claims::{assert_err, assert_ok, assert_ok_eq};
+
+    #[test]
+    fn test_observed_delta() {
+        let mut max = ObservedDelta::new(ObservedKind::Max);
+        assert_eq!(max.apply_to(10), 10);
+        max.observe(5);
+        max.observe(20);
+        max.observe(15);
+        assert_eq!(max.observed(), Some(20));
+        assert_eq!(max.apply_to(10), 20);
+        assert_eq!(max.apply_to(30), 30);
+
+        let mut min = ObservedDelta::new(ObservedKind::Min);
+        min.observe(15);
+        min.observe(5);
+        assert_eq!(min.apply_to(10), 5);
+        assert_eq!(min.apply_to(3), 3);
+
+        // Merging commutes.
+        let mut previous = ObservedDelta::new(ObservedKind::Max);
+        previous.observe(25);
+        let mut merged = max;
+        merged.merge_with_previous(previous);
+        previous.merge_with_previous(max);
+        assert_eq!(merged, previous);
+        assert_eq!(merged.observed(), Some(25));
+    }
+
+    #[test]
+    fn test_observed_value_aggregator() {
+        let id = aggregator_id_for_test(600);
+        let resolver = AggregatorStore::builder()
+            .with_value(id.clone(), 300)
+            .build();
+
+        let mut aggregator =
+            ObservedValueAggregator::new(ObservedKind::Max, None, Arc::new(NoOpGasMeterHook));
+        assert_ok!(aggregator.observe(100));
+        assert_ok!(aggregator.observe(400));
+        let mut delta = ObservedDelta::new(ObservedKind::Max);
+        delta.observe(400);
+        assert_eq!(
+            aggregator.clone().into_change(),
+            Some(AggregatorChange::Observe(delta))
+        );
+
+        assert_ok_eq!(aggregator.read_and_materialize(&resolver, &id), 400);
+        assert_ok!(aggregator.observe(350));
+        assert_eq!(aggregator.into_change(), Some(AggregatorChange::Write(400)));
+
+        let mut aggregator =
+            ObservedValueAggregator::new(ObservedKind::Min, None, Arc::new(NoOpGasMeterHook));
+        assert_eq!(aggregator.clone().into_change(), None);
+        assert_err!(aggregator.read_and_materialize(&resolver, &aggregator_id_for_test(700)));
+    }
+}
@@ -0,0 +1,186 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    delta_change_set::DeltaOp,
+    resolver::{AggregatorReadMode, TAggregatorView},
+};
+use aptos_types::state_store::{state_key::StateKey, state_value::StateValue};
+use move_binary_format::errors::PartialVMResult;
+use std::collections::HashMap;
+
+/// Wraps a resolver and applies deltas of aggregators V1 which have been
+/// committed (e.g. by previous transactions in a block), but are not yet
+/// written to the underlying storage.
+///
+/// For precise reads, deltas are applied exactly, and a delta which cannot
+/// be applied to the base value is an error. For speculative reads, the base
+/// value itself may be inaccurate, so deltas are applied on a best-effort
+/// basis: additions saturate at the limit and subtractions at zero.
+pub struct PendingDeltaResolver<R> {
+    inner: R,
+    pending_deltas: HashMap<StateKey, DeltaOp>,
+}
+
+impl<R> PendingDeltaResolver<R> {
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            pending_deltas: HashMap::new(),
+        }
+    }
+
+    /// Records a committed delta of an aggregator, which happened after all
+    /// deltas recorded for it so far.
+    pub fn add_pending_delta(&mut self, id: StateKey, mut delta: DeltaOp) -> PartialVMResult<()> {
+        if let Some(previous) = self.pending_deltas.remove(&id) {
+            delta.merge_with_previous_delta(previous)?;
+        }
+        self.pending_deltas.insert(id, delta);
+        Ok(())
+    }
+
+    /// Removes pending deltas of an aggregator, e.g. when its value is
+    /// written to the underlying storage.
+    pub fn clear_pending_deltas(&mut self, id: &StateKey) {
+        self.pending_deltas.remove(id);
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: TAggregatorView<IdentifierV1 = StateKey>> TAggregatorView for PendingDeltaResolver<R> {
+    type IdentifierV1 = StateKey;
+    type IdentifierV2 = R::IdentifierV2;
+
+    fn get_aggregator_v1_state_value(
+        &self,
+        id: &Self::IdentifierV1,
+        mode: AggregatorReadMode,
+    ) -> anyhow::Result<Option<StateValue>> {
+        let maybe_state_value = self.inner.get_aggregator_v1_state_value(id, mode)?;
+        let (delta, state_value) = match (self.pending_deltas.get(id), maybe_state_value) {
+            (Some(delta), Some(state_value)) => (delta, state_value),
+            (_, maybe_state_value) => return Ok(maybe_state_value),
+        };
+
+        let base = delta.width().deserialize(state_value.bytes())?;
+        let value = match mode {
+            AggregatorReadMode::Precise => delta.apply_to(base)?,
+            AggregatorReadMode::Speculative | AggregatorReadMode::Bounds => {
+                delta.apply_to_bounds((base, base)).0
+            },
+        };
+        let bytes = delta.serialize_result(value)?;
+        Ok(Some(match state_value.into_metadata() {
+            Some(metadata) => StateValue::new_with_metadata(bytes.into(), metadata),
+            None => StateValue::new_legacy(bytes.into()),
+        }))
+    }
+
+    fn get_aggregator_v1_value_bounds(
+        &self,
+        id: &Self::IdentifierV1,
+    ) -> anyhow::Result<Option<(u128, u128)>> {
+        let maybe_bounds = self.inner.get_aggregator_v1_value_bounds(id)?;
+        Ok(match self.pending_deltas.get(id) {
+            Some(delta) => maybe_bounds.map(|bounds| delta.apply_to_bounds(bounds)),
+            None => maybe_bounds,
+        })
+    }
+
+    fn get_aggregator_v2_value(
+        &self,
+        id: &Self::IdentifierV2,
+        mode: AggregatorReadMode,
+    ) -> anyhow::Result<u128> {
+        self.inner.get_aggregator_v2_value(id, mode)
+    }
+
+    fn generate_aggregator_v2_id(&self) -> Self::IdentifierV2 {
+        self.inner.generate_aggregator_v2_id()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        aggregator_extension::AggregatorData,
+        aggregator_id_for_test,
+        delta_change_set::{delta_add, delta_sub},
+        AggregatorStore,
+    };
+    use claims::{assert_err, assert_ok, assert_ok_eq};
+
+    #[test]
+    fn test_pending_deltas() {
+        let id = aggregator_id_for_test(600);
+        let mut resolver = PendingDeltaResolver::new(
+            AggregatorStore::builder()
+                .with_value(id.clone(), 300)
+                .build(),
+        );
+        let key = id.as_state_key();
+        let read = |resolver: &PendingDeltaResolver<AggregatorStore>, mode| {
+            resolver.get_aggregator_v1_value(key, mode)
+        };
+
+        assert_ok!(resolver.add_pending_delta(key.clone(), delta_add(200, 600)));
+        assert_ok!(resolver.add_pending_delta(key.clone(), delta_sub(50, 600)));
+        assert_ok_eq!(read(&resolver, AggregatorReadMode::Precise), Some(450));
+        assert_ok_eq!(read(&resolver, AggregatorReadMode::Speculative), Some(450));
+        assert_ok_eq!(
+            resolver.get_aggregator_v1_value_bounds(key),
+            Some((450, 450))
+        );
+
+        // Deltas which cannot be applied exactly saturate for speculative
+        // reads.
+        assert_ok!(resolver.add_pending_delta(key.clone(), delta_sub(500, 600)));
+        assert_err!(read(&resolver, AggregatorReadMode::Precise));
+        assert_ok_eq!(read(&resolver, AggregatorReadMode::Speculative), Some(0));
+
+        resolver.clear_pending_deltas(key);
+        assert_ok_eq!(read(&resolver, AggregatorReadMode::Precise), Some(300));
+        assert_ok_eq!(read(&resolver, AggregatorReadMode::Speculative), Some(300));
+
+        // Deltas of aggregators which do not exist are not applied.
+        let missing = aggregator_id_for_test(700).into_state_key();
+        assert_ok!(resolver.add_pending_delta(missing.clone(), delta_add(10, 100)));
+        assert_ok_eq!(
+            resolver.get_aggregator_v1_value(&missing, AggregatorReadMode::Speculative),
+            None
+        );
+    }
+
+    #[test]
+    fn test_speculative_materialization() {
+        let id = aggregator_id_for_test(600);
+        let mut resolver = PendingDeltaResolver::new(
+            AggregatorStore::builder()
+                .with_value(id.clone(), 300)
+                .build(),
+        );
+        assert_ok!(resolver.add_pending_delta(id.as_state_key().clone(), delta_sub(400, 600)));
+
+        // The pending delta cannot be applied to the base value, so only a
+        // speculative read succeeds.
+        let mut aggregator_data = AggregatorData::default();
+        let aggregator = aggregator_data
+            .get_aggregator(id.clone(), 600)
+            .expect("Get aggregator failed");
+        assert_ok!(aggregator.add(100));
+        assert_err!(aggregator.clone().read_and_materialize(&resolver, &id));
+        assert_ok_eq!(
+            aggregator.read_and_materialize_with_mode(
+                &resolver,
+                &id,
+                AggregatorReadMode::Speculative
+            ),
+            100
+        );
+    }
+}
@@ -0,0 +1,93 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    aggregator_extension::AggregatorID,
+    delta_change_set::serialize,
+    resolver::{AggregatorReadMode, TAggregatorView},
+};
+use aptos_types::state_store::{state_key::StateKey, state_value::StateValue};
+use rocksdb::{Options, DB};
+use std::path::Path;
+
+/// Stores values of aggregators V1 in RocksDB, so that integration tests and
+/// benchmarks can materialize aggregators against real storage, including
+/// reopening it after a restart. Keys are encoded state keys, and values are
+/// serialized in the same way as in `AggregatorStore`.
+pub struct PersistentAggregatorStore {
+    db: DB,
+}
+
+impl PersistentAggregatorStore {
+    /// Opens the store at `path`, creating it if it does not exist.
+    pub fn open(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let mut options = Options::default();
+        options.create_if_missing(true);
+        Ok(Self {
+            db: DB::open(&options, path)?,
+        })
+    }
+
+    pub fn set_from_id(&self, id: &AggregatorID, value: u128) -> anyhow::Result<()> {
+        self.set_from_state_key(id.as_state_key(), value)
+    }
+
+    pub fn set_from_state_key(&self, state_key: &StateKey, value: u128) -> anyhow::Result<()> {
+        self.db.put(state_key.encode()?, serialize(&value))?;
+        Ok(())
+    }
+
+    /// Removes the value of an aggregator, as if it was deleted.
+    pub fn delete(&self, id: &AggregatorID) -> anyhow::Result<()> {
+        self.db.delete(id.as_state_key().encode()?)?;
+        Ok(())
+    }
+}
+
+impl TAggregatorView for PersistentAggregatorStore {
+    type IdentifierV1 = StateKey;
+    type IdentifierV2 = AggregatorID;
+
+    fn get_aggregator_v1_state_value(
+        &self,
+        state_key: &Self::IdentifierV1,
+        _mode: AggregatorReadMode,
+    ) -> anyhow::Result<Option<StateValue>> {
+        let maybe_bytes = self.db.get(state_key.encode()?)?;
+        Ok(maybe_bytes.map(|bytes| StateValue::new_legacy(bytes.into())))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{aggregator_extension::AggregatorData, aggregator_id_for_test};
+    use aptos_temppath::TempPath;
+    use claims::{assert_err, assert_ok, assert_ok_eq};
+
+    #[test]
+    fn test_materialization_after_restart() {
+        let path = TempPath::new();
+        path.create_as_dir().unwrap();
+        let id = aggregator_id_for_test(600);
+
+        let store = assert_ok!(PersistentAggregatorStore::open(path.path()));
+        assert_ok!(store.set_from_id(&id, 300));
+        assert_ok!(store.set_from_id(&aggregator_id_for_test(700), 400));
+        assert_ok!(store.delete(&aggregator_id_for_test(700)));
+        drop(store);
+
+        let store = assert_ok!(PersistentAggregatorStore::open(path.path()));
+        let mut aggregator_data = AggregatorData::default();
+        let aggregator = aggregator_data
+            .get_aggregator(id.clone(), 600)
+            .expect("Get aggregator failed");
+        assert_ok!(aggregator.add(100));
+        assert_ok_eq!(aggregator.read_and_materialize(&store, &id), 400);
+
+        let aggregator = aggregator_data
+            .get_aggregator(aggregator_id_for_test(700), 700)
+            .expect("Get aggregator failed");
+        assert_err!(aggregator.read_and_materialize(&store, &aggregator_id_for_test(700)));
+    }
+}
@@ -0,0 +1,158 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+//! Property-based tests checking that aggregators in delta state behave as if
+//! operations were applied sequentially to the value in storage.
+
+use crate::{
+    aggregator_change_set::AggregatorChange,
+    aggregator_extension::AggregatorData,
+    aggregator_id_for_test,
+    delta_change_set::{addition, subtraction, DeltaOp},
+    AggregatorStore,
+};
+use move_binary_format::errors::PartialVMResult;
+use proptest::{collection::vec, prelude::*};
+
+#[derive(Clone, Copy, Debug)]
+enum Op {
+    Add(u128),
+    Sub(u128),
+    Read,
+}
+
+fn op_strategy(limit: u128) -> impl Strategy<Value = Op> {
+    prop_oneof![
+        4 => (0..=limit).prop_map(Op::Add),
+        4 => (0..=limit).prop_map(Op::Sub),
+        1 => Just(Op::Read),
+    ]
+}
+
+fn ops_strategy(with_reads: bool) -> impl Strategy<Value = (u128, Vec<Op>)> {
+    (1u128..50).prop_flat_map(move |limit| {
+        let ops = vec(op_strategy(limit), 0..10).prop_map(move |ops| {
+            ops.into_iter()
+                .filter(|op| with_reads || !matches!(op, Op::Read))
+                .collect::<Vec<_>>()
+        });
+        (Just(limit), ops)
+    })
+}
+
+/// Applies operations one by one to `base`, returning values observed by
+/// reads and the final value, or an error on the first bound violation.
+fn run_sequential(ops: &[Op], limit: u128, base: u128) -> PartialVMResult<(Vec<u128>, u128)> {
+    let mut value = base;
+    let mut reads = vec![];
+    for op in ops {
+        match op {
+            Op::Add(v) => value = addition(value, *v, limit)?,
+            Op::Sub(v) => value = subtraction(value, *v)?,
+            Op::Read => reads.push(value),
+        }
+    }
+    Ok((reads, value))
+}
+
+/// Applies operations to an aggregator which does not know its value, and
+/// materializes it at the end using `base` as the value in storage.
+fn run_aggregator(ops: &[Op], limit: u128, base: u128) -> PartialVMResult<(Vec<u128>, u128)> {
+    let id = aggregator_id_for_test(100);
+    let resolver = AggregatorStore::builder()
+        .with_value(id.clone(), base)
+        .build();
+    let mut aggregator_data = AggregatorData::default();
+    let aggregator = aggregator_data.get_aggregator(id.clone(), limit)?;
+
+    let mut reads = vec![];
+    for op in ops {
+        match op {
+            Op::Add(v) => aggregator.add(*v)?,
+            Op::Sub(v) => aggregator.sub(*v)?,
+            Op::Read => reads.push(aggregator.read_and_materialize(&resolver, &id)?),
+        }
+    }
+    let value = aggregator.read_and_materialize(&resolver, &id)?;
+    Ok((reads, value))
+}
+
+/// Returns the delta produced by applying operations to an aggregator which
+/// does not know its value, or `None` if some operation fails.
+fn delta_of(ops: &[Op], limit: u128) -> Option<DeltaOp> {
+    let id = aggregator_id_for_test(100);
+    let mut aggregator_data = AggregatorData::default();
+    let aggregator = aggregator_data.get_aggregator(id.clone(), limit).ok()?;
+    for op in ops {
+        match op {
+            Op::Add(v) => aggregator.add(*v).ok()?,
+            Op::Sub(v) => aggregator.sub(*v).ok()?,
+            Op::Read => unreachable!("deltas are produced without reads"),
+        }
+    }
+    match aggregator_data.into_change_set().changes.remove(&id) {
+        Some(AggregatorChange::Merge(delta)) => Some(delta),
+        change => unreachable!("unexpected change {:?}", change),
+    }
+}
+
+proptest! {
+    #[test]
+    fn history_validation_matches_sequential_application(
+        (limit, ops) in ops_strategy(true),
+    ) {
+        for base in 0..=limit {
+            let expected = run_sequential(&ops, limit, base);
+            let actual = run_aggregator(&ops, limit, base);
+            match (expected, actual) {
+                (Ok(expected), Ok(actual)) => prop_assert_eq!(expected, actual),
+                (Err(_), Err(_)) => (),
+                (expected, actual) => prop_assert!(
+                    false,
+                    "base {}: sequential {:?}, aggregator {:?}",
+                    base,
+                    expected,
+                    actual
+                ),
+            }
+        }
+    }
+
+    #[test]
+    fn delta_application_commutes_with_merging(
+        (limit, first) in ops_strategy(false),
+        second in vec(any::<u128>(), 0..10),
+    ) {
+        // Interpret the second sequence using the same limit as the first one.
+        let second: Vec<Op> = second
+            .into_iter()
+            .map(|v| if v % 2 == 0 { Op::Add(v % (limit + 1)) } else { Op::Sub(v % (limit + 1)) })
+            .collect();
+        let (first_delta, second_delta) = match (delta_of(&first, limit), delta_of(&second, limit)) {
+            (Some(first_delta), Some(second_delta)) => (first_delta, second_delta),
+            _ => return Ok(()),
+        };
+
+        let merged = second_delta.merge_with_previous(first_delta);
+        for base in 0..=limit {
+            let sequential = first_delta
+                .apply_to(base)
+                .and_then(|value| second_delta.apply_to(value));
+            match &merged {
+                Ok(merged) => match (sequential, merged.apply_to(base)) {
+                    (Ok(expected), Ok(actual)) => prop_assert_eq!(expected, actual),
+                    (Err(_), Err(_)) => (),
+                    (expected, actual) => prop_assert!(
+                        false,
+                        "base {}: sequential {:?}, merged {:?}",
+                        base,
+                        expected,
+                        actual
+                    ),
+                },
+                // Merging fails only if deltas cannot be applied to any base.
+                Err(_) => prop_assert!(sequential.is_err()),
+            }
+        }
+    }
+}
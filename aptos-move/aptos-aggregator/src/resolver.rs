@@ -3,8 +3,7 @@
 
 use crate::{
     aggregator_extension::AggregatorID,
-    delta_change_set::{serialize, DeltaOp},
-    module::AGGREGATOR_MODULE,
+    delta_change_set::{delta_error_into_vm_status, DeltaOp},
 };
 use aptos_types::{
     state_store::{
@@ -13,18 +12,27 @@ use aptos_types::{
     },
     write_set::WriteOp,
 };
-use move_binary_format::errors::Location;
-use move_core_types::vm_status::{StatusCode, VMStatus};
+use move_core_types::{
+    language_storage::StructTag,
+    vm_status::{StatusCode, VMStatus},
+};
+use std::collections::BTreeMap;
 
 /// Defines different ways `AggregatorResolver` can be used to read its value
 /// from the state.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum AggregatorReadMode {
     /// The returned value is guaranteed to be correct.
     Precise,
     /// The returned value is based on speculation or an approximation. For
     /// example, while reading and accumulating deltas only some of them can be
-    /// taken into account.
+    /// taken into account. Only used for reads which do not affect consensus,
+    /// such as views, see `PendingDeltaResolver`.
     Speculative,
+    /// Only bounds of the value are needed, e.g. for estimates in gas
+    /// simulation or indexing. The returned value can be any value the
+    /// aggregator may have, see `get_aggregator_v1_value_bounds`.
+    Bounds,
 }
 
 /// Allows to query aggregator values from the state storage.
@@ -61,6 +69,19 @@ pub trait TAggregatorView {
         }
     }
 
+    /// Returns (lower, upper) bounds of the value of aggregator V1, derived
+    /// from its base value and deltas which are not yet committed. By
+    /// default, a single value is read, so that both bounds are equal.
+    /// Resolvers aware of pending deltas can return wider bounds instead of
+    /// waiting for them to be applied.
+    fn get_aggregator_v1_value_bounds(
+        &self,
+        id: &Self::IdentifierV1,
+    ) -> anyhow::Result<Option<(u128, u128)>> {
+        let maybe_value = self.get_aggregator_v1_value(id, AggregatorReadMode::Bounds)?;
+        Ok(maybe_value.map(|value| (value, value)))
+    }
+
     /// Because aggregator V1 is a state item, it also can have metadata (for
     /// example used to calculate storage refunds).
     fn get_aggregator_v1_state_value_metadata(
@@ -108,12 +129,9 @@ pub trait TAggregatorView {
             })?;
         delta_op
             .apply_to(base)
-            .map_err(|partial_error| {
-                partial_error
-                    .finish(Location::Module(AGGREGATOR_MODULE.clone()))
-                    .into_vm_status()
-            })
-            .map(|result| WriteOp::Modification(serialize(&result).into()))
+            .and_then(|result| delta_op.serialize_result(result))
+            .map_err(delta_error_into_vm_status)
+            .map(|bytes| WriteOp::Modification(bytes.into()))
     }
 }
 
@@ -127,6 +145,51 @@ impl<T: TAggregatorView<IdentifierV1 = StateKey, IdentifierV2 = AggregatorID>> A
 {
 }
 
+/// Returns the value of an aggregator, if it exists in storage. Aggregators
+/// which are members of resource groups are read through the group blob,
+/// which maps tags of resources to their serialized values.
+pub fn get_aggregator_value(
+    resolver: &dyn AggregatorResolver,
+    id: &AggregatorID,
+    mode: AggregatorReadMode,
+) -> anyhow::Result<Option<u128>> {
+    match id.resource_group_tag() {
+        None => resolver.get_aggregator_v1_value(id.as_state_key(), mode),
+        Some(tag) => resolver
+            .get_aggregator_v1_state_value(id.as_state_key(), mode)?
+            .map_or(Ok(None), |group| {
+                read_resource_group_member(group.bytes(), tag)
+            }),
+    }
+}
+
+/// Returns (lower, upper) bounds of the value of an aggregator, see
+/// `TAggregatorView::get_aggregator_v1_value_bounds`. Resource groups are
+/// read as a whole, so for aggregators in groups both bounds are equal.
+pub fn get_aggregator_value_bounds(
+    resolver: &dyn AggregatorResolver,
+    id: &AggregatorID,
+) -> anyhow::Result<Option<(u128, u128)>> {
+    if id.is_in_resource_group() {
+        let maybe_value = get_aggregator_value(resolver, id, AggregatorReadMode::Bounds)?;
+        return Ok(maybe_value.map(|value| (value, value)));
+    }
+    resolver.get_aggregator_v1_value_bounds(id.as_state_key())
+}
+
+/// Decodes the value of the resource with the given tag from a serialized
+/// resource group. Returns `None` if the group has no such member.
+pub fn read_resource_group_member(
+    group_blob: &[u8],
+    tag: &StructTag,
+) -> anyhow::Result<Option<u128>> {
+    let mut members: BTreeMap<StructTag, Vec<u8>> = bcs::from_bytes(group_blob)?;
+    match members.remove(tag) {
+        Some(bytes) => Ok(Some(bcs::from_bytes(&bytes)?)),
+        None => Ok(None),
+    }
+}
+
 // Utils to store aggregator values in data store. Here, we
 // only care about aggregators which are state items (V1).
 #[cfg(any(test, feature = "testing"))]
@@ -137,7 +200,10 @@ pub mod test_utils {
         state_key::StateKey, state_value::StateValue, table::TableHandle,
     };
     use move_core_types::account_address::AccountAddress;
-    use std::collections::HashMap;
+    use std::{
+        collections::{BTreeMap, HashMap, HashSet},
+        sync::Mutex,
+    };
 
     /// Generates a dummy identifier for aggregator V1 based on the given key.
     pub fn aggregator_id_for_test(key: u128) -> AggregatorID {
@@ -149,18 +215,84 @@ pub mod test_utils {
         AggregatorID::new(TableHandle(AccountAddress::ZERO), key)
     }
 
+    /// In-memory resolver for aggregators V1. Besides storing values, it can
+    /// simulate storage errors and counts reads, see `AggregatorStoreBuilder`.
     #[derive(Default)]
-    pub struct AggregatorStore(HashMap<StateKey, StateValue>);
+    pub struct AggregatorStore {
+        values: HashMap<StateKey, StateValue>,
+        failing_reads: HashSet<StateKey>,
+        num_reads: Mutex<HashMap<StateKey, usize>>,
+    }
 
     impl AggregatorStore {
+        pub fn builder() -> AggregatorStoreBuilder {
+            AggregatorStoreBuilder::default()
+        }
+
+        /// Sets the value of an aggregator. Aggregators in resource groups
+        /// are written into the group blob, next to other members.
         pub fn set_from_id(&mut self, id: AggregatorID, value: u128) {
-            self.set_from_state_key(id.into_state_key(), value);
+            match id.resource_group_tag().cloned() {
+                None => self.set_from_state_key(id.into_state_key(), value),
+                Some(tag) => self.update_group(id.into_state_key(), |members| {
+                    members.insert(tag, serialize(&value));
+                }),
+            }
+        }
+
+        fn update_group(
+            &mut self,
+            group_key: StateKey,
+            update: impl FnOnce(&mut BTreeMap<StructTag, Vec<u8>>),
+        ) {
+            let mut members: BTreeMap<StructTag, Vec<u8>> = self
+                .values
+                .get(&group_key)
+                .map(|group| bcs::from_bytes(group.bytes()).unwrap())
+                .unwrap_or_default();
+            update(&mut members);
+            let blob = bcs::to_bytes(&members).unwrap();
+            self.values
+                .insert(group_key, StateValue::new_legacy(blob.into()));
         }
 
         pub fn set_from_state_key(&mut self, state_key: StateKey, value: u128) {
-            self.0
+            self.values
                 .insert(state_key, StateValue::new_legacy(serialize(&value).into()));
         }
+
+        /// Removes the value of an aggregator, as if it was deleted.
+        pub fn delete(&mut self, id: &AggregatorID) {
+            match id.resource_group_tag() {
+                None => {
+                    self.values.remove(id.as_state_key());
+                },
+                Some(tag) => self.update_group(id.as_state_key().clone(), |members| {
+                    members.remove(tag);
+                }),
+            }
+        }
+
+        /// Makes all subsequent reads of an aggregator return an error.
+        pub fn fail_reads(&mut self, id: AggregatorID) {
+            self.failing_reads.insert(id.into_state_key());
+        }
+
+        /// Returns the number of reads of all aggregators, including failed
+        /// ones.
+        pub fn num_reads(&self) -> usize {
+            self.num_reads.lock().unwrap().values().sum()
+        }
+
+        /// Returns the number of reads of an aggregator, including failed ones.
+        pub fn num_reads_of(&self, id: &AggregatorID) -> usize {
+            self.num_reads
+                .lock()
+                .unwrap()
+                .get(id.as_state_key())
+                .copied()
+                .unwrap_or(0)
+        }
     }
 
     impl TAggregatorView for AggregatorStore {
@@ -172,7 +304,133 @@ pub mod test_utils {
             state_key: &Self::IdentifierV1,
             _mode: AggregatorReadMode,
         ) -> anyhow::Result<Option<StateValue>> {
-            Ok(self.0.get(state_key).cloned())
+            *self
+                .num_reads
+                .lock()
+                .unwrap()
+                .entry(state_key.clone())
+                .or_default() += 1;
+            if self.failing_reads.contains(state_key) {
+                anyhow::bail!("Injected storage error for {:?}", state_key);
+            }
+            Ok(self.values.get(state_key).cloned())
+        }
+    }
+
+    /// Builds an `AggregatorStore` for tests, e.g.:
+    /// ```ignore
+    /// let store = AggregatorStore::builder()
+    ///     .with_value(aggregator_id_for_test(100), 50)
+    ///     .with_read_error(aggregator_id_for_test(200))
+    ///     .build();
+    /// ```
+    #[derive(Default)]
+    pub struct AggregatorStoreBuilder {
+        store: AggregatorStore,
+    }
+
+    impl AggregatorStoreBuilder {
+        /// Seeds the value of an aggregator.
+        pub fn with_value(mut self, id: AggregatorID, value: u128) -> Self {
+            self.store.set_from_id(id, value);
+            self
         }
+
+        /// Seeds values of multiple aggregators.
+        pub fn with_values(
+            mut self,
+            values: impl IntoIterator<Item = (AggregatorID, u128)>,
+        ) -> Self {
+            for (id, value) in values {
+                self.store.set_from_id(id, value);
+            }
+            self
+        }
+
+        /// Marks an aggregator as deleted, even if its value was seeded.
+        pub fn with_deletion(mut self, id: AggregatorID) -> Self {
+            self.store.delete(&id);
+            self
+        }
+
+        /// Makes all reads of an aggregator return an error.
+        pub fn with_read_error(mut self, id: AggregatorID) -> Self {
+            self.store.fail_reads(id);
+            self
+        }
+
+        pub fn build(self) -> AggregatorStore {
+            self.store
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{aggregator_id_for_test, AggregatorStore};
+    use claims::{assert_err, assert_ok_eq};
+    use std::str::FromStr;
+
+    #[test]
+    fn test_aggregator_store_builder() {
+        let store = AggregatorStore::builder()
+            .with_values([
+                (aggregator_id_for_test(100), 10),
+                (aggregator_id_for_test(200), 20),
+            ])
+            .with_deletion(aggregator_id_for_test(200))
+            .with_read_error(aggregator_id_for_test(300))
+            .build();
+        let read = |key| {
+            store.get_aggregator_v1_value(
+                aggregator_id_for_test(key).as_state_key(),
+                AggregatorReadMode::Precise,
+            )
+        };
+
+        assert_ok_eq!(read(100), Some(10));
+        assert_ok_eq!(read(100), Some(10));
+        assert_ok_eq!(read(200), None);
+        assert_err!(read(300));
+
+        assert_eq!(store.num_reads(), 4);
+        assert_eq!(store.num_reads_of(&aggregator_id_for_test(100)), 2);
+        assert_eq!(store.num_reads_of(&aggregator_id_for_test(400)), 0);
+    }
+
+    #[test]
+    fn test_resource_group_members() {
+        let group_key = aggregator_id_for_test(100).into_state_key();
+        let member = |name: &str| {
+            AggregatorID::new_in_resource_group(
+                group_key.clone(),
+                StructTag::from_str(&format!("0x1::group::{}", name)).unwrap(),
+            )
+        };
+        assert!(member("A").is_in_resource_group());
+        assert!(!aggregator_id_for_test(100).is_in_resource_group());
+        assert_ne!(member("A"), member("B"));
+        assert_eq!(member("A").as_state_key(), &group_key);
+
+        let store = AggregatorStore::builder()
+            .with_values([(member("A"), 10), (member("B"), 20), (member("C"), 30)])
+            .with_deletion(member("C"))
+            .build();
+        let read = |id| get_aggregator_value(&store, &id, AggregatorReadMode::Precise);
+
+        // Members are read through the group blob.
+        assert_ok_eq!(read(member("A")), Some(10));
+        assert_ok_eq!(read(member("B")), Some(20));
+        assert_ok_eq!(read(member("C")), None);
+        assert_ok_eq!(
+            get_aggregator_value_bounds(&store, &member("B")),
+            Some((20, 20))
+        );
+        assert_eq!(store.num_reads_of(&member("A")), 4);
+
+        // Identifiers of members are not table items.
+        assert_err!(store.get_aggregator_v1_value(&group_key, AggregatorReadMode::Precise));
+        assert_ok_eq!(read(aggregator_id_for_test(200)), None);
     }
 }
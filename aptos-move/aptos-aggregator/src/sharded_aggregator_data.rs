@@ -0,0 +1,253 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+//! Aggregator context which can be shared by multiple threads executing
+//! natives of the same session. Aggregators are partitioned into shards by
+//! their identifier, and every shard is an `AggregatorData` behind its own
+//! lock, so that operations on aggregators in different shards do not
+//! contend. All operations of `AggregatorData` are on a single aggregator,
+//! and so they can be run on its shard without locking the others.
+
+use crate::{
+    aggregator_change_set::AggregatorChangeSet,
+    aggregator_extension::{
+        AggregatorData, AggregatorDataCheckpoint, AggregatorID, MaterializationEvent,
+    },
+    gas_hook::{GasMeterHook, NoOpGasMeterHook},
+    resolver::AggregatorResolver,
+};
+use aptos_infallible::{Mutex, MutexGuard};
+use move_binary_format::errors::PartialVMResult;
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    sync::Arc,
+};
+
+/// Number of shards used by `ShardedAggregatorData::default`.
+pub const DEFAULT_NUM_SHARDS: usize = 16;
+
+/// Thread-safe version of `AggregatorData`, see module documentation.
+pub struct ShardedAggregatorData {
+    shards: Vec<Mutex<AggregatorData>>,
+}
+
+/// State of `ShardedAggregatorData` captured by
+/// `ShardedAggregatorData::checkpoint`.
+#[derive(Clone, Debug)]
+pub struct ShardedAggregatorDataCheckpoint(Vec<AggregatorDataCheckpoint>);
+
+impl Default for ShardedAggregatorData {
+    fn default() -> Self {
+        Self::new(DEFAULT_NUM_SHARDS, Arc::new(NoOpGasMeterHook))
+    }
+}
+
+impl ShardedAggregatorData {
+    /// Creates empty aggregator data with `num_shards` shards, charging gas
+    /// for all operations on aggregators with `gas_hook`.
+    pub fn new(num_shards: usize, gas_hook: Arc<dyn GasMeterHook>) -> Self {
+        assert!(num_shards > 0, "Number of shards must be positive");
+        Self {
+            shards: (0..num_shards)
+                .map(|_| Mutex::new(AggregatorData::with_gas_hook(gas_hook.clone())))
+                .collect(),
+        }
+    }
+
+    pub fn num_shards(&self) -> usize {
+        self.shards.len()
+    }
+
+    fn shard(&self, id: &AggregatorID) -> MutexGuard<'_, AggregatorData> {
+        let mut hasher = DefaultHasher::new();
+        id.hash(&mut hasher);
+        let index = (hasher.finish() % self.shards.len() as u64) as usize;
+        self.shards[index].lock()
+    }
+
+    /// Runs `f` on the shard which stores the aggregator with `id`, holding
+    /// the lock of the shard for the duration of the call. `f` must only
+    /// access the aggregator with `id`, e.g.
+    ///
+    /// ```ignore
+    /// data.with_aggregator(&id, |data| data.get_aggregator(id.clone(), limit)?.add(value))
+    /// ```
+    pub fn with_aggregator<T>(
+        &self,
+        id: &AggregatorID,
+        f: impl FnOnce(&mut AggregatorData) -> T,
+    ) -> T {
+        f(&mut self.shard(id))
+    }
+
+    /// Same as `AggregatorData::create_new_aggregator`.
+    pub fn create_new_aggregator(&self, id: AggregatorID, limit: u128) {
+        self.shard(&id).create_new_aggregator(id, limit);
+    }
+
+    /// Same as `AggregatorData::remove_aggregator`.
+    pub fn remove_aggregator(&self, id: AggregatorID) {
+        self.shard(&id).remove_aggregator(id);
+    }
+
+    /// Returns the number of aggregators that are used in the current
+    /// transaction across all shards. All shards are locked while counting,
+    /// so the count is consistent with respect to concurrent operations.
+    ///
+    /// Note that the count depends on the order in which concurrent
+    /// operations are scheduled. Identifiers of new aggregators derived from
+    /// it (see `AggregatorHandle::derive`) are only deterministic if callers
+    /// serialize creation of aggregators, e.g. by creating them from a single
+    /// thread.
+    pub fn num_aggregators(&self) -> u128 {
+        self.lock_all()
+            .iter()
+            .map(|shard| shard.num_aggregators())
+            .sum()
+    }
+
+    /// Same as `AggregatorData::validate_deletions`, for all shards.
+    pub fn validate_deletions(&self, resolver: &dyn AggregatorResolver) -> PartialVMResult<()> {
+        for shard in &self.shards {
+            shard.lock().validate_deletions(resolver)?;
+        }
+        Ok(())
+    }
+
+    /// Captures the state of all shards, see `AggregatorData::checkpoint`.
+    /// All shards are locked while the checkpoint is taken, so that it is
+    /// consistent with respect to concurrent operations.
+    pub fn checkpoint(&self) -> ShardedAggregatorDataCheckpoint {
+        let shards = self.lock_all();
+        ShardedAggregatorDataCheckpoint(shards.iter().map(|shard| shard.checkpoint()).collect())
+    }
+
+    /// Restores the state captured by `checkpoint`.
+    pub fn rollback_to(&self, checkpoint: ShardedAggregatorDataCheckpoint) {
+        assert_eq!(
+            checkpoint.0.len(),
+            self.shards.len(),
+            "Checkpoint was taken with a different number of shards",
+        );
+        let mut shards = self.lock_all();
+        for (shard, checkpoint) in shards.iter_mut().zip(checkpoint.0) {
+            shard.rollback_to(checkpoint);
+        }
+    }
+
+    /// Registers a callback invoked on materialization of aggregators in
+    /// any shard, see `AggregatorData::on_materialize`. The callback can be
+    /// invoked from multiple threads concurrently.
+    pub fn on_materialize(&self, callback: impl Fn(&MaterializationEvent) + Send + Sync + 'static) {
+        let callback = Arc::new(callback);
+        for shard in &self.shards {
+            let callback = callback.clone();
+            shard
+                .lock()
+                .unwrap()
+                .on_materialize(move |event| callback(event));
+        }
+    }
+
+    // Shards are always locked in the same order, so that operations which
+    // need all of them do not deadlock.
+    fn lock_all(&self) -> Vec<MutexGuard<'_, AggregatorData>> {
+        self.shards.iter().map(|shard| shard.lock()).collect()
+    }
+
+    /// Returns all changes made to aggregators in all shards, see
+    /// `AggregatorData::into_change_set`. Shards store disjoint sets of
+    /// aggregators, and changes are ordered by identifier, so the change set
+    /// does not depend on the number of shards.
+    pub fn into_change_set(self) -> AggregatorChangeSet {
+        let mut change_set = AggregatorChangeSet::default();
+        for shard in self.shards {
+            let shard_change_set = shard.into_inner().into_change_set();
+            change_set.changes.extend(shard_change_set.changes);
+            change_set
+                .removed_values
+                .extend(shard_change_set.removed_values);
+            change_set
+                .new_aggregators
+                .extend(shard_change_set.new_aggregators);
+        }
+        change_set
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{aggregator_id_for_test, AggregatorStore};
+    use claims::{assert_ok, assert_ok_eq};
+
+    fn run_operations(data: &ShardedAggregatorData, resolver: &AggregatorStore, id: u128) {
+        let id = aggregator_id_for_test(id);
+        assert_ok!(data.with_aggregator(&id, |data| {
+            let aggregator = data.get_aggregator(id.clone(), 1000)?;
+            aggregator.add(100)?;
+            aggregator.sub(50)?;
+            aggregator.read_and_materialize(resolver, &id)
+        }));
+    }
+
+    #[test]
+    fn test_concurrent_operations() {
+        let ids: Vec<u128> = (1..=32).collect();
+        let resolver = AggregatorStore::builder()
+            .with_values(ids.iter().map(|id| (aggregator_id_for_test(*id), *id * 10)))
+            .build();
+
+        let data = ShardedAggregatorData::default();
+        std::thread::scope(|s| {
+            for chunk in ids.chunks(8) {
+                let (data, resolver) = (&data, &resolver);
+                s.spawn(move || {
+                    for id in chunk {
+                        run_operations(data, resolver, *id);
+                    }
+                });
+            }
+        });
+        data.create_new_aggregator(aggregator_id_for_test(100), 10);
+        data.remove_aggregator(aggregator_id_for_test(1));
+        assert_eq!(data.num_aggregators(), 32);
+
+        // The result does not depend on sharding.
+        let sequential = ShardedAggregatorData::new(1, Arc::new(NoOpGasMeterHook));
+        for id in &ids {
+            run_operations(&sequential, &resolver, *id);
+        }
+        sequential.create_new_aggregator(aggregator_id_for_test(100), 10);
+        sequential.remove_aggregator(aggregator_id_for_test(1));
+        assert_eq!(data.into_change_set(), sequential.into_change_set());
+    }
+
+    #[test]
+    fn test_checkpoint_and_rollback() {
+        let resolver = AggregatorStore::default();
+        let data = ShardedAggregatorData::new(4, Arc::new(NoOpGasMeterHook));
+        let ids: Vec<_> = (1..=8).map(aggregator_id_for_test).collect();
+        for id in &ids {
+            data.create_new_aggregator(id.clone(), 100);
+        }
+
+        let checkpoint = data.checkpoint();
+        for id in &ids {
+            assert_ok!(
+                data.with_aggregator(id, |data| data.get_aggregator(id.clone(), 100)?.add(60))
+            );
+        }
+        data.rollback_to(checkpoint);
+
+        for id in &ids {
+            assert_ok_eq!(
+                data.with_aggregator(id, |data| data
+                    .get_aggregator(id.clone(), 100)?
+                    .read_and_materialize(&resolver, id)),
+                0
+            );
+        }
+    }
+}
@@ -0,0 +1,171 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    aggregator_extension::{extension_error, read_aggregator_value, AggregatorID},
+    delta_change_set::DeltaOp,
+    resolver::{AggregatorReadMode, AggregatorResolver},
+};
+use move_binary_format::errors::PartialVMResult;
+use serde::{Deserialize, Serialize};
+
+/// Restricts the operations an aggregator supports.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AggregatorKind {
+    /// Supports both additions and subtractions.
+    Bounded,
+    /// Only supports additions, e.g. for sequence numbers or supply counters.
+    /// The delta of a monotonic aggregator never decreases, and so the
+    /// largest value seen is the delta itself and merging deltas always
+    /// commutes.
+    Monotonic,
+    /// Supports both additions and subtractions, but has no meaningful limit,
+    /// e.g. for counters which never reach one. The limit is always
+    /// `UNBOUNDED_LIMIT`. Only the final value of a delta is checked to fit
+    /// into `u128`: intermediate positive deltas are not tracked in history,
+    /// so validation only checks that no delta dropped below zero. Must be
+    /// requested explicitly, aggregators with the same limit are not
+    /// unbounded.
+    Unbounded,
+}
+
+/// Integer type of aggregator values. Aggregator V1 always stores `u128`s,
+/// while Aggregator V2 is generic and also supports `u64`.
+#[derive(Clone, Copy, Debug, Hash, PartialOrd, Ord, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AggregatorWidth {
+    U64,
+    U128,
+}
+
+impl AggregatorWidth {
+    /// Returns the largest value which can be represented with this width.
+    pub fn max_value(&self) -> u128 {
+        match self {
+            AggregatorWidth::U64 => u64::MAX as u128,
+            AggregatorWidth::U128 => u128::MAX,
+        }
+    }
+
+    /// Returns the number of bytes of a serialized value of this width.
+    pub fn num_bytes(&self) -> usize {
+        match self {
+            AggregatorWidth::U64 => 8,
+            AggregatorWidth::U128 => 16,
+        }
+    }
+
+    /// Returns an error if `value` cannot be represented with this width,
+    /// e.g. if a limit of `u64` aggregator does not fit into `u64`.
+    pub fn check(&self, value: u128) -> PartialVMResult<()> {
+        if value > self.max_value() {
+            return Err(extension_error(format!(
+                "value {} does not fit into {:?} aggregator",
+                value, self
+            )));
+        }
+        Ok(())
+    }
+
+    /// Serializes the value as the Move integer of this width, so that `u64`
+    /// aggregators are not widened when written to storage.
+    pub fn serialize(&self, value: u128) -> PartialVMResult<Vec<u8>> {
+        self.check(value)?;
+        let bytes = match self {
+            AggregatorWidth::U64 => bcs::to_bytes(&(value as u64)),
+            AggregatorWidth::U128 => bcs::to_bytes(&value),
+        };
+        Ok(bytes.expect("unexpected serialization error in aggregator"))
+    }
+
+    /// Deserializes the value stored as the Move integer of this width.
+    pub fn deserialize(&self, bytes: &[u8]) -> anyhow::Result<u128> {
+        Ok(match self {
+            AggregatorWidth::U64 => bcs::from_bytes::<u64>(bytes)? as u128,
+            AggregatorWidth::U128 => bcs::from_bytes::<u128>(bytes)?,
+        })
+    }
+}
+
+/// Identifies a snapshot taken in the current transaction.
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq, Ord, PartialOrd)]
+pub struct SnapshotID(pub u64);
+
+/// Value captured by an aggregator snapshot. If the aggregator stored a delta
+/// at the time the snapshot was taken, the value is only known once the base
+/// value of the aggregator is read from storage, so that taking a snapshot
+/// does not force the aggregator to materialize.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SnapshotValue {
+    /// The aggregator knew its value.
+    Data(u128),
+    /// The value is the result of applying `delta` to the value of aggregator
+    /// `base` in storage.
+    DeltaOverBase { base: AggregatorID, delta: DeltaOp },
+}
+
+impl SnapshotValue {
+    /// Returns the captured value, reading the base value of the aggregator
+    /// from storage if needed.
+    pub fn materialize(&self, resolver: &dyn AggregatorResolver) -> PartialVMResult<u128> {
+        match self {
+            SnapshotValue::Data(value) => Ok(*value),
+            SnapshotValue::DeltaOverBase { base, delta } => {
+                let base_value =
+                    read_aggregator_value(resolver, base, AggregatorReadMode::Precise)?;
+                delta.apply_to(base_value)
+            },
+        }
+    }
+}
+
+/// Describes how a string is computed from the value of a snapshot.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SnapshotToStringFormula {
+    /// Decimal representation of the value, between `prefix` and `suffix`.
+    Concat { prefix: Vec<u8>, suffix: Vec<u8> },
+}
+
+impl SnapshotToStringFormula {
+    /// Computes the string for the given value.
+    pub fn apply(&self, value: u128) -> Vec<u8> {
+        match self {
+            SnapshotToStringFormula::Concat { prefix, suffix } => {
+                let mut result = prefix.clone();
+                result.extend(value.to_string().into_bytes());
+                result.extend(suffix);
+                result
+            },
+        }
+    }
+
+    /// Returns the formula which first applies `self`, and then concatenates
+    /// the result with `prefix` and `suffix`.
+    pub fn concat(&self, prefix: &[u8], suffix: &[u8]) -> Self {
+        match self {
+            SnapshotToStringFormula::Concat {
+                prefix: inner_prefix,
+                suffix: inner_suffix,
+            } => SnapshotToStringFormula::Concat {
+                prefix: [prefix, inner_prefix].concat(),
+                suffix: [inner_suffix, suffix].concat(),
+            },
+        }
+    }
+}
+
+/// String snapshot derived from the value of a snapshot. Like the snapshot it
+/// is derived from, the string is only computed when read, so that formatting
+/// a counter (e.g. "Token #<counter>") does not force a precise read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DerivedSnapshotValue {
+    pub base: SnapshotValue,
+    pub formula: SnapshotToStringFormula,
+}
+
+impl DerivedSnapshotValue {
+    /// Returns the string, reading the base value of the aggregator from
+    /// storage if needed.
+    pub fn materialize(&self, resolver: &dyn AggregatorResolver) -> PartialVMResult<Vec<u8>> {
+        Ok(self.formula.apply(self.base.materialize(resolver)?))
+    }
+}
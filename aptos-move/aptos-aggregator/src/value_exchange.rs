@@ -0,0 +1,144 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    aggregator_extension::extension_error,
+    aggregator_v2_extension::{AggregatorV2Data, EphemeralID},
+    resolver::AggregatorResolver,
+    types::{AggregatorWidth, SnapshotID, SnapshotValue},
+};
+use move_binary_format::errors::PartialVMResult;
+
+/// Kinds of values which are exchanged for identifiers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExchangeKind {
+    /// Value of an aggregator with the given limit.
+    Aggregator { limit: u128, width: AggregatorWidth },
+    /// Value of a numeric snapshot.
+    Snapshot,
+}
+
+/// Swaps values of aggregators and snapshots embedded in resources for
+/// identifiers and back. When a resource is read from storage, every such
+/// value is replaced by an identifier, so that the VM never operates on the
+/// value directly. When the resource is serialized (for events, views or
+/// writes to storage), identifiers are replaced by concrete values, so that
+/// internal state of unmaterialized aggregators never leaks.
+pub trait ValueToIdentifierMapping {
+    fn value_to_identifier(&mut self, kind: ExchangeKind, value: u128) -> PartialVMResult<u64>;
+
+    fn identifier_to_value(&mut self, kind: ExchangeKind, id: u64) -> PartialVMResult<u128>;
+}
+
+/// Exchanges values for identifiers of aggregators V2 and snapshots stored
+/// in `AggregatorV2Data`. Aggregators created on exchange know their
+/// value, and snapshots are materialized using the resolver when exchanged
+/// back.
+pub struct AggregatorValueExchange<'a> {
+    aggregator_v2_data: &'a mut AggregatorV2Data,
+    resolver: &'a dyn AggregatorResolver,
+}
+
+impl<'a> AggregatorValueExchange<'a> {
+    pub fn new(
+        aggregator_v2_data: &'a mut AggregatorV2Data,
+        resolver: &'a dyn AggregatorResolver,
+    ) -> Self {
+        Self {
+            aggregator_v2_data,
+            resolver,
+        }
+    }
+}
+
+impl<'a> ValueToIdentifierMapping for AggregatorValueExchange<'a> {
+    fn value_to_identifier(&mut self, kind: ExchangeKind, value: u128) -> PartialVMResult<u64> {
+        match kind {
+            ExchangeKind::Aggregator { limit, width } => {
+                let EphemeralID(id) = self
+                    .aggregator_v2_data
+                    .insert_aggregator(value, limit, width)?;
+                Ok(id)
+            },
+            ExchangeKind::Snapshot => {
+                let SnapshotID(id) = self
+                    .aggregator_v2_data
+                    .insert_snapshot(SnapshotValue::Data(value))?;
+                Ok(id)
+            },
+        }
+    }
+
+    fn identifier_to_value(&mut self, kind: ExchangeKind, id: u64) -> PartialVMResult<u128> {
+        match kind {
+            ExchangeKind::Aggregator { .. } => self
+                .aggregator_v2_data
+                .get_aggregator(EphemeralID(id))?
+                .known_value()
+                .ok_or_else(|| {
+                    extension_error(format!("Aggregator {} does not know its value", id))
+                }),
+            ExchangeKind::Snapshot => self
+                .aggregator_v2_data
+                .read_snapshot(SnapshotID(id), self.resolver),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        aggregator_extension::AggregatorData, aggregator_id_for_test, features::AggregatorFeatures,
+        gas_hook::NoOpGasMeterHook, AggregatorStore,
+    };
+    use claims::{assert_err, assert_ok, assert_ok_eq};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_value_exchange() {
+        let mut aggregator_data = AggregatorData::default();
+        let mut aggregator_v2_data =
+            AggregatorV2Data::new(AggregatorFeatures::all(), Arc::new(NoOpGasMeterHook));
+        let mut resolver = AggregatorStore::default();
+        resolver.set_from_id(aggregator_id_for_test(600), 300);
+
+        // A snapshot of an unmaterialized aggregator is only exchanged for a
+        // value on serialization.
+        let id = aggregator_id_for_test(600);
+        assert_ok!(aggregator_data
+            .get_aggregator(id.clone(), 600)
+            .expect("Get aggregator failed")
+            .add(100));
+        let SnapshotID(snapshot) =
+            assert_ok!(aggregator_v2_data.snapshot_aggregator_v1(&aggregator_data, &id));
+
+        let kind = ExchangeKind::Aggregator {
+            limit: 100,
+            width: AggregatorWidth::U64,
+        };
+        let mut exchange = AggregatorValueExchange::new(&mut aggregator_v2_data, &resolver);
+        let aggregator = assert_ok!(exchange.value_to_identifier(kind, 70));
+        let copied_snapshot = assert_ok!(exchange.value_to_identifier(ExchangeKind::Snapshot, 5));
+        assert_err!(exchange.value_to_identifier(kind, 101));
+
+        assert_ok_eq!(exchange.identifier_to_value(kind, aggregator), 70);
+        assert_ok_eq!(
+            exchange.identifier_to_value(ExchangeKind::Snapshot, snapshot),
+            400
+        );
+        assert_ok_eq!(
+            exchange.identifier_to_value(ExchangeKind::Snapshot, copied_snapshot),
+            5
+        );
+        assert_err!(exchange.identifier_to_value(kind, 100));
+
+        // Operations on exchanged aggregators are visible on serialization.
+        assert_ok!(aggregator_v2_data
+            .get_aggregator(EphemeralID(aggregator))
+            .unwrap()
+            .add(30));
+        let mut exchange = AggregatorValueExchange::new(&mut aggregator_v2_data, &resolver);
+        assert_ok_eq!(exchange.identifier_to_value(kind, aggregator), 100);
+    }
+}
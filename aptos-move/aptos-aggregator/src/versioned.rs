@@ -0,0 +1,44 @@
+// Copyright © Aptos Foundation
+// SPDX-License-Identifier: Apache-2.0
+
+//! Versioned serialization of aggregator state. Serialized values start with
+//! the version of their layout, so that bytes produced by older binaries can
+//! still be decoded (e.g. when replaying historical transactions) after the
+//! in-memory structs evolve.
+
+use anyhow::bail;
+use serde::{de::DeserializeOwned, Serialize};
+
+pub trait Versioned: Serialize + DeserializeOwned {
+    /// Version of the current layout, stored as the first byte. Must be
+    /// bumped whenever the layout changes, and bytes of the previous version
+    /// must be upgraded in `decode_legacy`.
+    const VERSION: u8;
+
+    /// Decodes BCS bytes of an older `version` of the layout, upgrading them
+    /// to the current one.
+    fn decode_legacy(version: u8, _bytes: &[u8]) -> anyhow::Result<Self> {
+        bail!("unsupported serialization version {}", version)
+    }
+
+    /// Serializes the value: a version byte followed by BCS encoding of the
+    /// current layout.
+    fn to_versioned_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![Self::VERSION];
+        bytes.extend(bcs::to_bytes(self).expect("unexpected serialization error in aggregator"));
+        bytes
+    }
+
+    /// Deserializes the value from bytes produced by `to_versioned_bytes`,
+    /// including bytes produced with older versions of the layout.
+    fn from_versioned_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
+        match bytes.split_first() {
+            Some((&version, encoded)) if version == Self::VERSION => Ok(bcs::from_bytes(encoded)?),
+            Some((&version, encoded)) if version < Self::VERSION => {
+                Self::decode_legacy(version, encoded)
+            },
+            Some((version, _)) => bail!("unsupported serialization version {}", version),
+            None => bail!("cannot deserialize from empty bytes"),
+        }
+    }
+}
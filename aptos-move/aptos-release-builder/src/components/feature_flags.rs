@@ -84,6 +84,7 @@ pub enum FeatureFlag {
     SaferResourceGroups,
     SaferMetadata,
     Secp256k1ECDSAAuthenticator,
+    AggregatorBoundChecks,
 }
 
 fn generate_features_blob(writer: &CodeWriter, data: &[u64]) {
@@ -220,6 +221,7 @@ impl From<FeatureFlag> for AptosFeatureFlag {
             FeatureFlag::Secp256k1ECDSAAuthenticator => {
                 AptosFeatureFlag::SECP256K1_ECDSA_AUTHENTICATOR
             },
+            FeatureFlag::AggregatorBoundChecks => AptosFeatureFlag::AGGREGATOR_BOUND_CHECKS,
         }
     }
 }
@@ -279,6 +281,7 @@ impl From<AptosFeatureFlag> for FeatureFlag {
             AptosFeatureFlag::SECP256K1_ECDSA_AUTHENTICATOR => {
                 FeatureFlag::Secp256k1ECDSAAuthenticator
             },
+            AptosFeatureFlag::AGGREGATOR_BOUND_CHECKS => FeatureFlag::AggregatorBoundChecks,
         }
     }
 }
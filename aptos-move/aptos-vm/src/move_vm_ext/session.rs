@@ -157,7 +157,16 @@ impl<'r, 'l> SessionExt<'r, 'l> {
             .map_err(|e| e.finish(Location::Undefined))?;
 
         let aggregator_context: NativeAggregatorContext = extensions.remove();
-        let aggregator_change_set = aggregator_context.into_change_set();
+        aggregator_context
+            .validate_deletions()
+            .map_err(|e| e.finish(Location::Undefined))?;
+        let (aggregator_change_set, aggregator_v2_change_set) =
+            aggregator_context.into_change_sets();
+        if !aggregator_v2_change_set.is_empty() {
+            return Err(PartialVMError::new(StatusCode::FEATURE_UNDER_GATING)
+                .with_message("Aggregators V2 cannot be committed to storage yet".to_string())
+                .finish(Location::Undefined));
+        }
 
         let event_context: NativeEventContext = extensions.remove();
         let events = event_context.into_events();
@@ -349,6 +358,14 @@ impl<'r, 'l> SessionExt<'r, 'l> {
         }
 
         for (id, change) in aggregator_change_set.changes {
+            // Changes to aggregators in resource groups would have to be
+            // merged into the group write, which is not supported yet.
+            if id.is_in_resource_group() {
+                return Err(VMStatus::error(
+                    StatusCode::FEATURE_UNDER_GATING,
+                    Some("Aggregators in resource groups cannot be written yet".to_string()),
+                ));
+            }
             let state_key = id.into_state_key();
             match change {
                 AggregatorChange::Write(value) => {
@@ -363,6 +380,14 @@ impl<'r, 'l> SessionExt<'r, 'l> {
                         woc.convert_aggregator(&state_key, MoveStorageOp::Delete, false)?;
                     aggregator_write_set.insert(state_key, write_op);
                 },
+                // Observed-value aggregators are not exposed to Move yet, and
+                // the executor cannot apply their deltas.
+                AggregatorChange::Observe(_) => {
+                    return Err(VMStatus::error(
+                        StatusCode::FEATURE_UNDER_GATING,
+                        Some("Observed-value aggregators are not supported".to_string()),
+                    ));
+                },
             }
         }
 
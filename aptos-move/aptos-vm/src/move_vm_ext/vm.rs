@@ -2,6 +2,7 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::move_vm_ext::{warm_vm_cache::WarmVmCache, AptosMoveResolver, SessionExt, SessionId};
+use aptos_aggregator::features::AggregatorFeatures;
 use aptos_framework::natives::{
     aggregator_natives::NativeAggregatorContext,
     code::NativeCodeContext,
@@ -166,7 +167,11 @@ impl MoveVmExt {
         extensions.add(NativeTableContext::new(txn_hash, resolver));
         extensions.add(NativeRistrettoPointContext::new());
         extensions.add(AlgebraContext::new());
-        extensions.add(NativeAggregatorContext::new(txn_hash, resolver));
+        extensions.add(NativeAggregatorContext::new(
+            txn_hash,
+            resolver,
+            AggregatorFeatures::from(&self.features),
+        ));
 
         let script_hash = match session_id {
             SessionId::Txn {
@@ -7,6 +7,7 @@ use anyhow::Error;
 #[cfg(feature = "testing")]
 use aptos_aggregator::{
     aggregator_extension::AggregatorID,
+    features::AggregatorFeatures,
     resolver::{AggregatorReadMode, TAggregatorView},
 };
 #[cfg(feature = "testing")]
@@ -151,7 +152,11 @@ fn unit_test_extensions_hook(exts: &mut NativeContextExtensions) {
         vec![1],
         ChainId::test().id(),
     )); // We use the testing environment chain ID here
-    exts.add(NativeAggregatorContext::new([0; 32], &*DUMMY_RESOLVER));
+    exts.add(NativeAggregatorContext::new(
+        [0; 32],
+        &*DUMMY_RESOLVER,
+        AggregatorFeatures::all(),
+    ));
     exts.add(NativeRistrettoPointContext::new());
     exts.add(AlgebraContext::new());
     exts.add(NativeEventContext::default());
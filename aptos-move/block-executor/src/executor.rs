@@ -755,6 +755,7 @@ where
                 base_view,
             )
         }
+        aptos_aggregator::counters::observe_block_end();
         self.executor_thread_pool.spawn(move || {
             // Explicit async drops.
             drop(signature_verified_block);
@@ -6,12 +6,16 @@ use crate::{
         add, add_and_materialize, check, destroy, initialize, materialize, materialize_and_add,
         materialize_and_sub, new, sub, sub_add, sub_and_materialize,
     },
-    assert_abort, assert_success,
+    assert_abort, assert_success, assert_vm_status,
     tests::common,
     MoveHarness,
 };
 use aptos_language_e2e_tests::account::Account;
-use aptos_types::transaction::SignedTransaction;
+use aptos_types::{
+    transaction::SignedTransaction,
+    write_set::{WriteOp, WriteSetMut},
+};
+use move_core_types::vm_status::StatusCode;
 
 fn setup() -> (MoveHarness, Account) {
     initialize(common::test_dir_path("aggregator.data/pack"))
@@ -163,3 +167,28 @@ fn test_aggregator_materialize_overflow() {
     assert_success!(h.run(txn1));
     assert_abort!(h.run(txn2), 131073);
 }
+
+#[test]
+fn test_destroy_aggregator_missing_from_storage() {
+    let (mut h, acc) = setup();
+
+    let output = h.run_raw(new(&mut h, &acc, 0, 100));
+    assert_success!(output.status().to_owned());
+
+    // The value of the new aggregator is the only 16 byte state item created
+    // by the transaction, the table entry holding the aggregator is larger.
+    let (key, _) = output
+        .write_set()
+        .iter()
+        .find(|(_, op)| op.is_creation() && op.bytes().map_or(false, |b| b.len() == 16))
+        .expect("Aggregator value should be written");
+    let deletion = WriteSetMut::new(vec![(key.clone(), WriteOp::Deletion)])
+        .freeze()
+        .unwrap();
+    h.executor.apply_write_set(&deletion);
+
+    // Deletion is validated when the session finishes, instead of producing
+    // a change set which deletes a missing state item.
+    let txn = destroy(&mut h, &acc, 0);
+    assert_vm_status!(h.run(txn), StatusCode::VM_EXTENSION_ERROR);
+}
@@ -2,13 +2,11 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::natives::aggregator_natives::{helpers::get_handle, NativeAggregatorContext};
-use aptos_aggregator::aggregator_extension::{extension_error, AggregatorHandle, AggregatorID};
-use aptos_crypto::hash::DefaultHasher;
+use aptos_aggregator::aggregator_extension::{AggregatorHandle, AggregatorID};
 use aptos_gas_schedule::gas_params::natives::aptos_framework::*;
 use aptos_native_interface::{
     safely_pop_arg, RawSafeNative, SafeNativeBuilder, SafeNativeContext, SafeNativeResult,
 };
-use aptos_types::account_address::AccountAddress;
 use move_vm_runtime::native_functions::NativeFunction;
 use move_vm_types::{
     loaded_data::runtime_types::Type,
@@ -45,15 +43,7 @@ fn native_new_aggregator(
     // the strategy from `table` implementation: taking hash of transaction and
     // number of aggregator instances created so far.
     let num_aggregators_len = aggregator_data.num_aggregators() as u32;
-
-    let mut hasher = DefaultHasher::new(&[0_u8; 0]);
-    hasher.update(&aggregator_context.txn_hash());
-    hasher.update(&num_aggregators_len.to_be_bytes());
-    let hash = hasher.finish().to_vec();
-    let key = AggregatorHandle(
-        AccountAddress::from_bytes(hash)
-            .map_err(|_| extension_error("unable to create aggregator key"))?,
-    );
+    let key = AggregatorHandle::derive(&aggregator_context.txn_hash(), num_aggregators_len);
 
     let id = AggregatorID::new(handle, key);
     aggregator_data.create_new_aggregator(id, limit);
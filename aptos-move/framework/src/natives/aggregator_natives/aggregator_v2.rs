@@ -8,6 +8,9 @@ use crate::natives::{
     },
     AccountAddress,
 };
+pub use aptos_aggregator::abort_codes::{
+    EAGGREGATOR_SNAPSHOTS_NOT_ENABLED, EUNSUPPORTED_AGGREGATOR_SNAPSHOT_TYPE,
+};
 use aptos_gas_schedule::gas_params::natives::aptos_framework::*;
 use aptos_native_interface::{
     safely_pop_arg, RawSafeNative, SafeNativeBuilder, SafeNativeContext, SafeNativeError,
@@ -22,12 +25,6 @@ use move_vm_types::{
 use smallvec::{smallvec, SmallVec};
 use std::{collections::VecDeque, ops::Deref};
 
-/// The generic type supplied to aggregator snapshots is not supported.
-pub const EUNSUPPORTED_AGGREGATOR_SNAPSHOT_TYPE: u64 = 0x03_0005;
-
-/// The aggregator snapshots feature is not enabled.
-pub const EAGGREGATOR_SNAPSHOTS_NOT_ENABLED: u64 = 0x03_0006;
-
 /// Checks if the type argument `type_arg` is a string type.
 fn is_string_type(context: &SafeNativeContext, type_arg: &Type) -> SafeNativeResult<bool> {
     let ty = context.deref().type_to_fully_annotated_layout(type_arg)?;
@@ -1,36 +1,19 @@
 // Copyright © Aptos Foundation
 // SPDX-License-Identifier: Apache-2.0
 
+pub use aptos_aggregator::aggregator_change_set::{
+    AggregatorChange, AggregatorChangeSet, AggregatorV2ChangeSet,
+};
 use aptos_aggregator::{
-    aggregator_extension::{AggregatorData, AggregatorID, AggregatorState},
-    delta_change_set::{DeltaOp, DeltaUpdate},
+    aggregator_extension::AggregatorData,
+    aggregator_v2_extension::AggregatorV2Data,
+    features::AggregatorFeatures,
+    gas_hook::NoOpGasMeterHook,
     resolver::AggregatorResolver,
 };
-use aptos_types::vm_status::VMStatus;
 use better_any::{Tid, TidAble};
-use move_binary_format::errors::Location;
-use std::{
-    cell::RefCell,
-    collections::{btree_map, BTreeMap},
-};
-
-/// Represents a single aggregator change.
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
-pub enum AggregatorChange {
-    // A value should be written to storage.
-    Write(u128),
-    // A delta should be merged with the value from storage.
-    Merge(DeltaOp),
-    // A value should be deleted from the storage.
-    Delete,
-}
-
-/// Represents changes made by all aggregators during this context. This change
-/// set can be converted into appropriate `WriteSet` and `DeltaChangeSet` by the
-/// user, e.g. VM session.
-pub struct AggregatorChangeSet {
-    pub changes: BTreeMap<AggregatorID, AggregatorChange>,
-}
+use move_binary_format::errors::PartialVMResult;
+use std::{cell::RefCell, sync::Arc};
 
 /// Native context that can be attached to VM `NativeContextExtensions`.
 ///
@@ -40,16 +23,26 @@ pub struct NativeAggregatorContext<'a> {
     txn_hash: [u8; 32],
     pub(crate) resolver: &'a dyn AggregatorResolver,
     pub(crate) aggregator_data: RefCell<AggregatorData>,
+    pub(crate) aggregator_v2_data: RefCell<AggregatorV2Data>,
 }
 
 impl<'a> NativeAggregatorContext<'a> {
     /// Creates a new instance of a native aggregator context. This must be
-    /// passed into VM session.
-    pub fn new(txn_hash: [u8; 32], resolver: &'a dyn AggregatorResolver) -> Self {
+    /// passed into VM session. Natives can only use operations which are
+    /// enabled in `features`, e.g. by on-chain feature flags.
+    pub fn new(
+        txn_hash: [u8; 32],
+        resolver: &'a dyn AggregatorResolver,
+        features: AggregatorFeatures,
+    ) -> Self {
         Self {
             txn_hash,
             resolver,
-            aggregator_data: Default::default(),
+            aggregator_data: RefCell::new(AggregatorData::default().with_features(features)),
+            aggregator_v2_data: RefCell::new(AggregatorV2Data::new(
+                features,
+                Arc::new(NoOpGasMeterHook),
+            )),
         }
     }
 
@@ -58,95 +51,38 @@ impl<'a> NativeAggregatorContext<'a> {
         self.txn_hash
     }
 
+    /// Checks that all aggregators destroyed within this context exist in
+    /// storage, see `AggregatorData::validate_deletions`.
+    pub fn validate_deletions(&self) -> PartialVMResult<()> {
+        self.aggregator_data
+            .borrow()
+            .validate_deletions(self.resolver)
+    }
+
     /// Returns all changes made within this context (i.e. by a single
-    /// transaction).
-    pub fn into_change_set(self) -> AggregatorChangeSet {
+    /// transaction) by aggregators V1 and V2 respectively.
+    pub fn into_change_sets(self) -> (AggregatorChangeSet, AggregatorV2ChangeSet) {
         let NativeAggregatorContext {
-            aggregator_data, ..
+            aggregator_data,
+            aggregator_v2_data,
+            ..
         } = self;
-        let (_, destroyed_aggregators, aggregators) = aggregator_data.into_inner().into();
-
-        let mut changes = BTreeMap::new();
-
-        // First, process all writes and deltas.
-        for (id, aggregator) in aggregators {
-            let (value, state, limit, history) = aggregator.into();
-
-            let change = match state {
-                AggregatorState::Data => AggregatorChange::Write(value),
-                AggregatorState::PositiveDelta => {
-                    let history = history.unwrap();
-                    let plus = DeltaUpdate::Plus(value);
-                    let delta_op =
-                        DeltaOp::new(plus, limit, history.max_positive, history.min_negative);
-                    AggregatorChange::Merge(delta_op)
-                },
-                AggregatorState::NegativeDelta => {
-                    let history = history.unwrap();
-                    let minus = DeltaUpdate::Minus(value);
-                    let delta_op =
-                        DeltaOp::new(minus, limit, history.max_positive, history.min_negative);
-                    AggregatorChange::Merge(delta_op)
-                },
-            };
-            changes.insert(id, change);
-        }
-
-        // Additionally, do not forget to delete destroyed values from storage.
-        for id in destroyed_aggregators {
-            changes.insert(id, AggregatorChange::Delete);
-        }
-
-        AggregatorChangeSet { changes }
-    }
-}
-
-impl AggregatorChangeSet {
-    pub fn squash(&mut self, other: Self) -> Result<(), VMStatus> {
-        for (other_id, other_change) in other.changes {
-            match self.changes.entry(other_id) {
-                // If something was changed only in `other` session, add it.
-                btree_map::Entry::Vacant(entry) => {
-                    entry.insert(other_change);
-                },
-                // Otherwise, we might need to aggregate deltas.
-                btree_map::Entry::Occupied(mut entry) => {
-                    use AggregatorChange::*;
-
-                    let entry_mut = entry.get_mut();
-                    match (*entry_mut, other_change) {
-                        (Write(_) | Merge(_), Write(data)) => *entry_mut = Write(data),
-                        (Write(_) | Merge(_), Delete) => *entry_mut = Delete,
-                        (Write(data), Merge(delta)) => {
-                            let new_data = delta
-                                .apply_to(data)
-                                .map_err(|e| e.finish(Location::Undefined).into_vm_status())?;
-                            *entry_mut = Write(new_data);
-                        },
-                        (Merge(delta1), Merge(mut delta2)) => {
-                            // `delta1` occurred before `delta2`, therefore we must ensure we merge the latter
-                            // one to the initial delta.
-                            delta2
-                                .merge_with_previous_delta(delta1)
-                                .map_err(|e| e.finish(Location::Undefined).into_vm_status())?;
-                            *entry_mut = Merge(delta2)
-                        },
-                        // Hashing properties guarantee that aggregator keys should
-                        // not collide, making this case impossible.
-                        (Delete, _) => unreachable!("resource cannot be accessed after deletion"),
-                    }
-                },
-            }
-        }
-        Ok(())
+        (
+            aggregator_data.into_inner().into_change_set(),
+            aggregator_v2_data.into_inner().into_change_set(),
+        )
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
-    use aptos_aggregator::{aggregator_id_for_test, AggregatorStore};
-    use claims::{assert_matches, assert_ok};
+    use aptos_aggregator::{
+        aggregator_id_for_test,
+        delta_change_set::{DeltaOp, DeltaUpdate},
+        AggregatorStore,
+    };
+    use claims::{assert_err, assert_matches, assert_ok};
 
     // All aggregators are initialized deterministically based on their ID,
     // with the following spec.
@@ -198,10 +134,14 @@ mod test {
     #[test]
     fn test_into_change_set() {
         let resolver = AggregatorStore::default();
-        let context = NativeAggregatorContext::new([0; 32], &resolver);
+        let context =
+            NativeAggregatorContext::new([0; 32], &resolver, AggregatorFeatures::default());
 
         test_set_up(&context);
-        let AggregatorChangeSet { changes } = context.into_change_set();
+        // Aggregators 500 and 800 are destroyed, but do not exist in storage.
+        assert_err!(context.validate_deletions());
+        let (AggregatorChangeSet { changes, .. }, v2_change_set) = context.into_change_sets();
+        assert!(v2_change_set.is_empty());
 
         assert!(!changes.contains_key(&aggregator_id_for_test(100)));
         assert_matches!(
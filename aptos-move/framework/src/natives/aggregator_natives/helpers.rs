@@ -1,7 +1,10 @@
 // Copyright © Aptos Foundation
 // SPDX-License-Identifier: Apache-2.0
 
-use aptos_aggregator::aggregator_extension::{extension_error, AggregatorHandle, AggregatorID};
+use aptos_aggregator::{
+    aggregator_extension::{extension_error, AggregatorHandle, AggregatorID},
+    aggregator_struct::{HANDLE_FIELD_INDEX, KEY_FIELD_INDEX, LIMIT_FIELD_INDEX, NUM_FIELDS},
+};
 use aptos_types::{account_address::AccountAddress, state_store::table::TableHandle};
 use move_binary_format::errors::PartialVMResult;
 use move_vm_types::values::{Reference, Struct, StructRef, Value};
@@ -13,12 +16,6 @@ const PHANTOM_TABLE_FIELD_INDEX: usize = 0;
 /// The index of the `handle` field in the `Table` Move struct.
 const TABLE_HANDLE_FIELD_INDEX: usize = 0;
 
-/// Indices of `handle`, `key` and `limit` fields in the `Aggregator` Move
-/// struct.
-const HANDLE_FIELD_INDEX: usize = 0;
-const KEY_FIELD_INDEX: usize = 1;
-const LIMIT_FIELD_INDEX: usize = 2;
-
 /// Given a reference to `AggregatorFactory` Move struct, returns the value of
 /// `handle` field (from underlying `Table` struct).
 pub(crate) fn get_handle(aggregator_table: &StructRef) -> PartialVMResult<TableHandle> {
@@ -62,7 +59,7 @@ pub(crate) fn unpack_aggregator_struct(
     aggregator_struct: Struct,
 ) -> PartialVMResult<(TableHandle, AggregatorHandle, u128)> {
     let mut fields: Vec<Value> = aggregator_struct.unpack()?.collect();
-    assert!(fields.len() == 3);
+    assert!(fields.len() == NUM_FIELDS);
 
     let pop_with_err = |vec: &mut Vec<Value>, msg: &str| {
         vec.pop()
@@ -7,7 +7,7 @@ use crate::{
     stackless_bytecode::{
         AssignKind, AttrId,
         Bytecode::{self},
-        Constant, Label, Operation, PropKind,
+        Constant, HavocKind, Label, Operation, PropKind,
     },
     COMPILED_MODULE_AVAILABLE,
 };
@@ -47,8 +47,26 @@ pub struct StacklessBytecodeGenerator<'a> {
     local_types: Vec<Type>,
     code: Vec<Bytecode>,
     fallthrough_labels: BTreeSet<Label>,
+    /// Stack depths of the forward branches to a code offset, and depths at labels already
+    /// generated, used to detect inconsistent stacks at joins.
+    branch_depths: BTreeMap<CodeOffset, Vec<usize>>,
+    label_depths: BTreeMap<CodeOffset, usize>,
+    falls_through: bool,
+    stack_diagnostics: Vec<StackDiagnostic>,
 }
 
+/// An inconsistency of the operand stack found while lifting malformed bytecode. The lifting
+/// continues with havoc values in place of the missing operands.
+#[derive(Debug, Clone)]
+pub struct StackDiagnostic {
+    pub offset: CodeOffset,
+    pub message: String,
+}
+
+/// Annotation attached to the function data when stack diagnostics were reported.
+#[derive(Debug, Clone)]
+pub struct StackDiagnostics(pub Vec<StackDiagnostic>);
+
 #[derive(Debug, Default)]
 pub struct BytecodeGeneratorContext {
     pub location_table: BTreeMap<AttrId, Loc>,
@@ -80,6 +98,10 @@ impl<'a> StacklessBytecodeGenerator<'a> {
             local_types,
             code: vec![],
             fallthrough_labels: BTreeSet::new(),
+            branch_depths: BTreeMap::new(),
+            label_depths: BTreeMap::new(),
+            falls_through: true,
+            stack_diagnostics: vec![],
         }
     }
 
@@ -146,6 +168,10 @@ impl<'a> StacklessBytecodeGenerator<'a> {
             local_types,
             code,
             fallthrough_labels: _,
+            branch_depths: _,
+            label_depths: _,
+            falls_through: _,
+            stack_diagnostics,
         } = self;
         let BytecodeGeneratorContext {
             location_table,
@@ -166,7 +192,7 @@ impl<'a> StacklessBytecodeGenerator<'a> {
             })
             .collect();
 
-        FunctionData::new(
+        let mut data = FunctionData::new(
             func_env,
             code,
             local_types,
@@ -178,7 +204,88 @@ impl<'a> StacklessBytecodeGenerator<'a> {
                 .expect(COMPILED_MODULE_AVAILABLE),
             loop_unrolling,
             loop_invariants,
-        )
+        );
+        if !stack_diagnostics.is_empty() {
+            data.annotations
+                .set(StackDiagnostics(stack_diagnostics), true);
+        }
+        data
+    }
+
+    /// Pop the temporary on top of the stack. The stack only underflows for malformed
+    /// bytecode, in which case a diagnostic is recorded and a havoc value is used instead.
+    fn pop_temp(&mut self, attr_id: AttrId, code_offset: CodeOffset) -> usize {
+        match self.temp_stack.pop() {
+            Some(temp_index) => temp_index,
+            None => {
+                self.stack_diagnostics.push(StackDiagnostic {
+                    offset: code_offset,
+                    message: "stack underflow".to_string(),
+                });
+                self.havoc_temp(attr_id)
+            },
+        }
+    }
+
+    /// Create a temporary holding an unknown value.
+    fn havoc_temp(&mut self, attr_id: AttrId) -> usize {
+        let temp_index = self.temp_count;
+        self.local_types.push(Type::Error);
+        self.temp_count += 1;
+        self.code.push(Bytecode::Call(
+            attr_id,
+            vec![temp_index],
+            Operation::Havoc(HavocKind::Value),
+            vec![],
+            None,
+        ));
+        temp_index
+    }
+
+    /// Check that all the edges into the block starting at `code_offset` agree on the stack
+    /// depth. On mismatch the stack is replaced by havoc values of the largest depth.
+    fn join_stack(&mut self, attr_id: AttrId, code_offset: CodeOffset) {
+        let mut depths = self.branch_depths.remove(&code_offset).unwrap_or_default();
+        if self.falls_through {
+            depths.push(self.temp_stack.len());
+        }
+        let depth = depths
+            .iter()
+            .copied()
+            .max()
+            .unwrap_or(self.temp_stack.len());
+        let consistent = depths.iter().all(|d| *d == depth);
+        if !consistent {
+            self.stack_diagnostics.push(StackDiagnostic {
+                offset: code_offset,
+                message: format!(
+                    "inconsistent stack depths at join: {}",
+                    depths.iter().join(", ")
+                ),
+            });
+        }
+        if !consistent || self.temp_stack.len() != depth {
+            self.temp_stack = (0..depth).map(|_| self.havoc_temp(attr_id)).collect();
+        }
+        self.label_depths.insert(code_offset, depth);
+    }
+
+    /// Record the stack depth of a branch to `target`.
+    fn branch_stack(&mut self, code_offset: CodeOffset, target: CodeOffset) {
+        let depth = self.temp_stack.len();
+        match self.label_depths.get(&target) {
+            Some(expected) if *expected != depth => {
+                self.stack_diagnostics.push(StackDiagnostic {
+                    offset: code_offset,
+                    message: format!(
+                        "inconsistent stack depths at join: {}, {} (backward branch to {})",
+                        expected, depth, target
+                    ),
+                });
+            },
+            Some(_) => {},
+            None => self.branch_depths.entry(target).or_default().push(depth),
+        }
     }
 
     /// Create a new attribute id and populate location table.
@@ -216,7 +323,12 @@ impl<'a> StacklessBytecodeGenerator<'a> {
         if let Some(label) = label_map.get(&code_offset) {
             let label_attr_id = self.new_loc_attr(code_offset);
             self.code.push(Bytecode::Label(label_attr_id, *label));
+            self.join_stack(label_attr_id, code_offset);
         }
+        self.falls_through = !matches!(
+            bytecode,
+            MoveBytecode::Branch(_) | MoveBytecode::Ret | MoveBytecode::Abort
+        );
 
         // Handle spec block if defined at this code offset.
         let mut binding = self.func_env.get_mut_spec();
@@ -266,12 +378,13 @@ impl<'a> StacklessBytecodeGenerator<'a> {
 
         match bytecode {
             MoveBytecode::Pop => {
-                let temp_index = self.temp_stack.pop().unwrap();
+                let temp_index = self.pop_temp(attr_id, code_offset);
                 self.code
                     .push(mk_call(Operation::Destroy, vec![], vec![temp_index]));
             },
             MoveBytecode::BrTrue(target) => {
-                let temp_index = self.temp_stack.pop().unwrap();
+                let temp_index = self.pop_temp(attr_id, code_offset);
+                self.branch_stack(code_offset, *target);
                 self.code.push(Bytecode::Branch(
                     attr_id,
                     *label_map.get(target).unwrap(),
@@ -281,7 +394,8 @@ impl<'a> StacklessBytecodeGenerator<'a> {
             },
 
             MoveBytecode::BrFalse(target) => {
-                let temp_index = self.temp_stack.pop().unwrap();
+                let temp_index = self.pop_temp(attr_id, code_offset);
+                self.branch_stack(code_offset, *target);
                 self.code.push(Bytecode::Branch(
                     attr_id,
                     *label_map.get(&(code_offset + 1)).unwrap(),
@@ -291,12 +405,12 @@ impl<'a> StacklessBytecodeGenerator<'a> {
             },
 
             MoveBytecode::Abort => {
-                let error_code_index = self.temp_stack.pop().unwrap();
+                let error_code_index = self.pop_temp(attr_id, code_offset);
                 self.code.push(Bytecode::Abort(attr_id, error_code_index));
             },
 
             MoveBytecode::StLoc(idx) => {
-                let operand_index = self.temp_stack.pop().unwrap();
+                let operand_index = self.pop_temp(attr_id, code_offset);
                 self.code.push(Bytecode::Assign(
                     attr_id,
                     *idx as TempIndex,
@@ -308,7 +422,7 @@ impl<'a> StacklessBytecodeGenerator<'a> {
             MoveBytecode::Ret => {
                 let mut return_temps = vec![];
                 for _ in 0..self.func_env.get_return_count() {
-                    let return_temp_index = self.temp_stack.pop().unwrap();
+                    let return_temp_index = self.pop_temp(attr_id, code_offset);
                     return_temps.push(return_temp_index);
                 }
                 return_temps.reverse();
@@ -319,6 +433,7 @@ impl<'a> StacklessBytecodeGenerator<'a> {
                 // Attempt to eliminate the common pattern `if c goto L1 else L2; L2: goto L3`
                 // and replace it with `if c goto L1 else L3`, provided L2 is a fall-through
                 // label, i.e. not referenced from elsewhere.
+                self.branch_stack(code_offset, *target);
                 let target_label = *label_map.get(target).unwrap();
                 let at = self.code.len();
                 let rewritten = if at >= 2 {
@@ -344,7 +459,7 @@ impl<'a> StacklessBytecodeGenerator<'a> {
             },
 
             MoveBytecode::FreezeRef => {
-                let mutable_ref_index = self.temp_stack.pop().unwrap();
+                let mutable_ref_index = self.pop_temp(attr_id, code_offset);
                 let mutable_ref_sig = self.local_types[mutable_ref_index].clone();
                 if let Type::Reference(kind, signature) = mutable_ref_sig {
                     if kind == ReferenceKind::Mutable {
@@ -364,7 +479,7 @@ impl<'a> StacklessBytecodeGenerator<'a> {
 
             MoveBytecode::ImmBorrowField(field_handle_index)
             | MoveBytecode::MutBorrowField(field_handle_index) => {
-                let struct_ref_index = self.temp_stack.pop().unwrap();
+                let struct_ref_index = self.pop_temp(attr_id, code_offset);
                 let (struct_id, field_offset, field_type) =
                     self.get_field_info(*field_handle_index);
                 let field_ref_index = self.temp_count;
@@ -391,7 +506,7 @@ impl<'a> StacklessBytecodeGenerator<'a> {
             MoveBytecode::ImmBorrowFieldGeneric(field_inst_index)
             | MoveBytecode::MutBorrowFieldGeneric(field_inst_index) => {
                 let field_inst = self.module.field_instantiation_at(*field_inst_index);
-                let struct_ref_index = self.temp_stack.pop().unwrap();
+                let struct_ref_index = self.pop_temp(attr_id, code_offset);
                 let (struct_id, field_offset, base_field_type) =
                     self.get_field_info(field_inst.handle);
                 let actuals = self.get_type_params(field_inst.type_parameters);
@@ -472,7 +587,7 @@ impl<'a> StacklessBytecodeGenerator<'a> {
             },
 
             MoveBytecode::CastU8 => {
-                let operand_index = self.temp_stack.pop().unwrap();
+                let operand_index = self.pop_temp(attr_id, code_offset);
                 let temp_index = self.temp_count;
                 self.temp_stack.push(temp_index);
                 self.local_types.push(Type::Primitive(PrimitiveType::U8));
@@ -482,7 +597,7 @@ impl<'a> StacklessBytecodeGenerator<'a> {
             },
 
             MoveBytecode::CastU16 => {
-                let operand_index = self.temp_stack.pop().unwrap();
+                let operand_index = self.pop_temp(attr_id, code_offset);
                 let temp_index = self.temp_count;
                 self.temp_stack.push(temp_index);
                 self.local_types.push(Type::Primitive(PrimitiveType::U16));
@@ -492,7 +607,7 @@ impl<'a> StacklessBytecodeGenerator<'a> {
             },
 
             MoveBytecode::CastU32 => {
-                let operand_index = self.temp_stack.pop().unwrap();
+                let operand_index = self.pop_temp(attr_id, code_offset);
                 let temp_index = self.temp_count;
                 self.temp_stack.push(temp_index);
                 self.local_types.push(Type::Primitive(PrimitiveType::U32));
@@ -502,7 +617,7 @@ impl<'a> StacklessBytecodeGenerator<'a> {
             },
 
             MoveBytecode::CastU64 => {
-                let operand_index = self.temp_stack.pop().unwrap();
+                let operand_index = self.pop_temp(attr_id, code_offset);
                 let temp_index = self.temp_count;
                 self.temp_stack.push(temp_index);
                 self.local_types.push(Type::Primitive(PrimitiveType::U64));
@@ -512,7 +627,7 @@ impl<'a> StacklessBytecodeGenerator<'a> {
             },
 
             MoveBytecode::CastU128 => {
-                let operand_index = self.temp_stack.pop().unwrap();
+                let operand_index = self.pop_temp(attr_id, code_offset);
                 let temp_index = self.temp_count;
                 self.temp_stack.push(temp_index);
                 self.local_types.push(Type::Primitive(PrimitiveType::U128));
@@ -522,7 +637,7 @@ impl<'a> StacklessBytecodeGenerator<'a> {
             },
 
             MoveBytecode::CastU256 => {
-                let operand_index = self.temp_stack.pop().unwrap();
+                let operand_index = self.pop_temp(attr_id, code_offset);
                 let temp_index = self.temp_count;
                 self.temp_stack.push(temp_index);
                 self.local_types.push(Type::Primitive(PrimitiveType::U256));
@@ -648,7 +763,7 @@ impl<'a> StacklessBytecodeGenerator<'a> {
                 let mut arg_temp_indices = vec![];
                 let mut return_temp_indices = vec![];
                 for _ in function_handle_view.arg_tokens() {
-                    let arg_temp_index = self.temp_stack.pop().unwrap();
+                    let arg_temp_index = self.pop_temp(attr_id, code_offset);
                     arg_temp_indices.push(arg_temp_index);
                 }
                 for return_type_view in function_handle_view.return_tokens() {
@@ -689,7 +804,7 @@ impl<'a> StacklessBytecodeGenerator<'a> {
                 let mut arg_temp_indices = vec![];
                 let mut return_temp_indices = vec![];
                 for _ in function_handle_view.arg_tokens() {
-                    let arg_temp_index = self.temp_stack.pop().unwrap();
+                    let arg_temp_index = self.pop_temp(attr_id, code_offset);
                     arg_temp_indices.push(arg_temp_index);
                 }
                 for return_type_view in function_handle_view.return_tokens() {
@@ -728,7 +843,7 @@ impl<'a> StacklessBytecodeGenerator<'a> {
                 let mut field_temp_indices = vec![];
                 let struct_temp_index = self.temp_count;
                 for _ in struct_env.get_fields() {
-                    let field_temp_index = self.temp_stack.pop().unwrap();
+                    let field_temp_index = self.pop_temp(attr_id, code_offset);
                     field_temp_indices.push(field_temp_index);
                 }
                 self.local_types.push(Type::Struct(
@@ -756,7 +871,7 @@ impl<'a> StacklessBytecodeGenerator<'a> {
                 let mut field_temp_indices = vec![];
                 let struct_temp_index = self.temp_count;
                 for _ in struct_env.get_fields() {
-                    let field_temp_index = self.temp_stack.pop().unwrap();
+                    let field_temp_index = self.pop_temp(attr_id, code_offset);
                     field_temp_indices.push(field_temp_index);
                 }
                 self.local_types.push(Type::Struct(
@@ -777,7 +892,7 @@ impl<'a> StacklessBytecodeGenerator<'a> {
             MoveBytecode::Unpack(idx) => {
                 let struct_env = self.func_env.module_env.get_struct_by_def_idx(*idx);
                 let mut field_temp_indices = vec![];
-                let struct_temp_index = self.temp_stack.pop().unwrap();
+                let struct_temp_index = self.pop_temp(attr_id, code_offset);
                 for field_env in struct_env.get_fields() {
                     let field_temp_index = self.temp_count;
                     field_temp_indices.push(field_temp_index);
@@ -800,7 +915,7 @@ impl<'a> StacklessBytecodeGenerator<'a> {
                     .module_env
                     .get_struct_by_def_idx(struct_instantiation.def);
                 let mut field_temp_indices = vec![];
-                let struct_temp_index = self.temp_stack.pop().unwrap();
+                let struct_temp_index = self.pop_temp(attr_id, code_offset);
                 for field_env in struct_env.get_fields() {
                     let field_type = field_env.get_type().instantiate(&actuals);
                     let field_temp_index = self.temp_count;
@@ -817,7 +932,7 @@ impl<'a> StacklessBytecodeGenerator<'a> {
             },
 
             MoveBytecode::ReadRef => {
-                let operand_index = self.temp_stack.pop().unwrap();
+                let operand_index = self.pop_temp(attr_id, code_offset);
                 let operand_sig = self.local_types[operand_index].clone();
                 let temp_index = self.temp_count;
                 if let Type::Reference(_, signature) = operand_sig {
@@ -830,8 +945,8 @@ impl<'a> StacklessBytecodeGenerator<'a> {
             },
 
             MoveBytecode::WriteRef => {
-                let ref_operand_index = self.temp_stack.pop().unwrap();
-                let val_operand_index = self.temp_stack.pop().unwrap();
+                let ref_operand_index = self.pop_temp(attr_id, code_offset);
+                let val_operand_index = self.pop_temp(attr_id, code_offset);
                 self.code.push(mk_call(Operation::WriteRef, vec![], vec![
                     ref_operand_index,
                     val_operand_index,
@@ -848,8 +963,8 @@ impl<'a> StacklessBytecodeGenerator<'a> {
             | MoveBytecode::Xor
             | MoveBytecode::Shl
             | MoveBytecode::Shr => {
-                let operand2_index = self.temp_stack.pop().unwrap();
-                let operand1_index = self.temp_stack.pop().unwrap();
+                let operand2_index = self.pop_temp(attr_id, code_offset);
+                let operand1_index = self.pop_temp(attr_id, code_offset);
                 let operand_type = self.local_types[operand1_index].clone();
                 let temp_index = self.temp_count;
                 self.local_types.push(operand_type);
@@ -940,8 +1055,8 @@ impl<'a> StacklessBytecodeGenerator<'a> {
                 }
             },
             MoveBytecode::Or => {
-                let operand2_index = self.temp_stack.pop().unwrap();
-                let operand1_index = self.temp_stack.pop().unwrap();
+                let operand2_index = self.pop_temp(attr_id, code_offset);
+                let operand1_index = self.pop_temp(attr_id, code_offset);
                 let temp_index = self.temp_count;
                 self.local_types.push(Type::Primitive(PrimitiveType::Bool));
                 self.temp_count += 1;
@@ -955,8 +1070,8 @@ impl<'a> StacklessBytecodeGenerator<'a> {
             },
 
             MoveBytecode::And => {
-                let operand2_index = self.temp_stack.pop().unwrap();
-                let operand1_index = self.temp_stack.pop().unwrap();
+                let operand2_index = self.pop_temp(attr_id, code_offset);
+                let operand1_index = self.pop_temp(attr_id, code_offset);
                 let temp_index = self.temp_count;
                 self.local_types.push(Type::Primitive(PrimitiveType::Bool));
                 self.temp_count += 1;
@@ -970,7 +1085,7 @@ impl<'a> StacklessBytecodeGenerator<'a> {
             },
 
             MoveBytecode::Not => {
-                let operand_index = self.temp_stack.pop().unwrap();
+                let operand_index = self.pop_temp(attr_id, code_offset);
                 let temp_index = self.temp_count;
                 self.local_types.push(Type::Primitive(PrimitiveType::Bool));
                 self.temp_count += 1;
@@ -979,8 +1094,8 @@ impl<'a> StacklessBytecodeGenerator<'a> {
                     .push(mk_unary(Operation::Not, temp_index, operand_index));
             },
             MoveBytecode::Eq => {
-                let operand2_index = self.temp_stack.pop().unwrap();
-                let operand1_index = self.temp_stack.pop().unwrap();
+                let operand2_index = self.pop_temp(attr_id, code_offset);
+                let operand1_index = self.pop_temp(attr_id, code_offset);
                 let temp_index = self.temp_count;
                 self.local_types.push(Type::Primitive(PrimitiveType::Bool));
                 self.temp_count += 1;
@@ -993,8 +1108,8 @@ impl<'a> StacklessBytecodeGenerator<'a> {
                 ));
             },
             MoveBytecode::Neq => {
-                let operand2_index = self.temp_stack.pop().unwrap();
-                let operand1_index = self.temp_stack.pop().unwrap();
+                let operand2_index = self.pop_temp(attr_id, code_offset);
+                let operand1_index = self.pop_temp(attr_id, code_offset);
                 let temp_index = self.temp_count;
                 self.local_types.push(Type::Primitive(PrimitiveType::Bool));
                 self.temp_count += 1;
@@ -1007,8 +1122,8 @@ impl<'a> StacklessBytecodeGenerator<'a> {
                 ));
             },
             MoveBytecode::Lt | MoveBytecode::Gt | MoveBytecode::Le | MoveBytecode::Ge => {
-                let operand2_index = self.temp_stack.pop().unwrap();
-                let operand1_index = self.temp_stack.pop().unwrap();
+                let operand2_index = self.pop_temp(attr_id, code_offset);
+                let operand1_index = self.pop_temp(attr_id, code_offset);
                 let temp_index = self.temp_count;
                 self.local_types.push(Type::Primitive(PrimitiveType::Bool));
                 self.temp_count += 1;
@@ -1050,7 +1165,7 @@ impl<'a> StacklessBytecodeGenerator<'a> {
                 }
             },
             MoveBytecode::Exists(struct_index) => {
-                let operand_index = self.temp_stack.pop().unwrap();
+                let operand_index = self.pop_temp(attr_id, code_offset);
                 let temp_index = self.temp_count;
                 self.local_types.push(Type::Primitive(PrimitiveType::Bool));
                 self.temp_count += 1;
@@ -1068,7 +1183,7 @@ impl<'a> StacklessBytecodeGenerator<'a> {
 
             MoveBytecode::ExistsGeneric(idx) => {
                 let struct_instantiation = self.module.struct_instantiation_at(*idx);
-                let operand_index = self.temp_stack.pop().unwrap();
+                let operand_index = self.pop_temp(attr_id, code_offset);
                 let temp_index = self.temp_count;
                 self.local_types.push(Type::Primitive(PrimitiveType::Bool));
                 self.temp_count += 1;
@@ -1089,7 +1204,7 @@ impl<'a> StacklessBytecodeGenerator<'a> {
             MoveBytecode::MutBorrowGlobal(idx) | MoveBytecode::ImmBorrowGlobal(idx) => {
                 let struct_env = self.func_env.module_env.get_struct_by_def_idx(*idx);
                 let is_mut = matches!(bytecode, MoveBytecode::MutBorrowGlobal(..));
-                let operand_index = self.temp_stack.pop().unwrap();
+                let operand_index = self.pop_temp(attr_id, code_offset);
                 let temp_index = self.temp_count;
                 self.local_types.push(Type::Reference(
                     ReferenceKind::from_is_mut(is_mut),
@@ -1121,7 +1236,7 @@ impl<'a> StacklessBytecodeGenerator<'a> {
                     .module_env
                     .get_struct_by_def_idx(struct_instantiation.def);
 
-                let operand_index = self.temp_stack.pop().unwrap();
+                let operand_index = self.pop_temp(attr_id, code_offset);
                 let temp_index = self.temp_count;
                 let actuals = self.get_type_params(struct_instantiation.type_parameters);
                 self.local_types.push(Type::Reference(
@@ -1149,7 +1264,7 @@ impl<'a> StacklessBytecodeGenerator<'a> {
 
             MoveBytecode::MoveFrom(idx) => {
                 let struct_env = self.func_env.module_env.get_struct_by_def_idx(*idx);
-                let operand_index = self.temp_stack.pop().unwrap();
+                let operand_index = self.pop_temp(attr_id, code_offset);
                 let temp_index = self.temp_count;
                 self.temp_stack.push(temp_index);
                 self.local_types.push(Type::Struct(
@@ -1175,7 +1290,7 @@ impl<'a> StacklessBytecodeGenerator<'a> {
                     .func_env
                     .module_env
                     .get_struct_by_def_idx(struct_instantiation.def);
-                let operand_index = self.temp_stack.pop().unwrap();
+                let operand_index = self.pop_temp(attr_id, code_offset);
                 let temp_index = self.temp_count;
                 self.temp_stack.push(temp_index);
                 let actuals = self.get_type_params(struct_instantiation.type_parameters);
@@ -1199,8 +1314,8 @@ impl<'a> StacklessBytecodeGenerator<'a> {
             },
 
             MoveBytecode::MoveTo(idx) => {
-                let value_operand_index = self.temp_stack.pop().unwrap();
-                let signer_operand_index = self.temp_stack.pop().unwrap();
+                let value_operand_index = self.pop_temp(attr_id, code_offset);
+                let signer_operand_index = self.pop_temp(attr_id, code_offset);
                 self.code.push(mk_call(
                     Operation::MoveTo(
                         self.func_env.module_env.get_id(),
@@ -1214,8 +1329,8 @@ impl<'a> StacklessBytecodeGenerator<'a> {
 
             MoveBytecode::MoveToGeneric(idx) => {
                 let struct_instantiation = self.module.struct_instantiation_at(*idx);
-                let value_operand_index = self.temp_stack.pop().unwrap();
-                let signer_operand_index = self.temp_stack.pop().unwrap();
+                let value_operand_index = self.pop_temp(attr_id, code_offset);
+                let signer_operand_index = self.pop_temp(attr_id, code_offset);
                 self.code.push(mk_call(
                     Operation::MoveTo(
                         self.func_env.module_env.get_id(),
@@ -1235,7 +1350,7 @@ impl<'a> StacklessBytecodeGenerator<'a> {
             // These should go to non-functional call operations
             MoveBytecode::VecLen(sig) => {
                 let tys = self.get_type_params(*sig);
-                let operand_index = self.temp_stack.pop().unwrap();
+                let operand_index = self.pop_temp(attr_id, code_offset);
                 let temp_index = self.temp_count;
                 self.local_types.push(Type::Primitive(PrimitiveType::U64));
                 self.temp_count += 1;
@@ -1255,8 +1370,8 @@ impl<'a> StacklessBytecodeGenerator<'a> {
                     _ => unreachable!(),
                 };
                 let [ty]: [Type; 1] = self.get_type_params(*sig).try_into().unwrap();
-                let operand2_index = self.temp_stack.pop().unwrap();
-                let operand1_index = self.temp_stack.pop().unwrap();
+                let operand2_index = self.pop_temp(attr_id, code_offset);
+                let operand1_index = self.pop_temp(attr_id, code_offset);
                 let temp_index = self.temp_count;
                 self.local_types.push(Type::Reference(
                     ReferenceKind::from_is_mut(is_mut),
@@ -1275,8 +1390,8 @@ impl<'a> StacklessBytecodeGenerator<'a> {
             },
             MoveBytecode::VecPushBack(sig) => {
                 let tys = self.get_type_params(*sig);
-                let operand2_index = self.temp_stack.pop().unwrap();
-                let operand1_index = self.temp_stack.pop().unwrap();
+                let operand2_index = self.pop_temp(attr_id, code_offset);
+                let operand1_index = self.pop_temp(attr_id, code_offset);
                 self.code.push(Bytecode::Call(
                     attr_id,
                     vec![],
@@ -1287,7 +1402,7 @@ impl<'a> StacklessBytecodeGenerator<'a> {
             },
             MoveBytecode::VecPopBack(sig) => {
                 let [ty]: [Type; 1] = self.get_type_params(*sig).try_into().unwrap();
-                let operand_index = self.temp_stack.pop().unwrap();
+                let operand_index = self.pop_temp(attr_id, code_offset);
                 let temp_index = self.temp_count;
                 self.local_types.push(ty.clone());
                 self.temp_count += 1;
@@ -1302,9 +1417,9 @@ impl<'a> StacklessBytecodeGenerator<'a> {
             },
             MoveBytecode::VecSwap(sig) => {
                 let tys = self.get_type_params(*sig);
-                let operand3_index = self.temp_stack.pop().unwrap();
-                let operand2_index = self.temp_stack.pop().unwrap();
-                let operand1_index = self.temp_stack.pop().unwrap();
+                let operand3_index = self.pop_temp(attr_id, code_offset);
+                let operand2_index = self.pop_temp(attr_id, code_offset);
+                let operand1_index = self.pop_temp(attr_id, code_offset);
                 self.code.push(Bytecode::Call(
                     attr_id,
                     vec![],
@@ -1354,7 +1469,7 @@ impl<'a> StacklessBytecodeGenerator<'a> {
             MoveBytecode::VecUnpack(sig, n) => {
                 let n = *n as usize;
                 let [ty]: [Type; 1] = self.get_type_params(*sig).try_into().unwrap();
-                let operand_index = self.temp_stack.pop().unwrap();
+                let operand_index = self.pop_temp(attr_id, code_offset);
                 let temps = (0..n).map(|idx| self.temp_count + idx).collect::<Vec<_>>();
                 self.local_types.extend(vec![ty.clone(); n]);
                 self.temp_count += n;
@@ -0,0 +1,163 @@
+// Copyright (c) Verichains, 2023
+
+use std::collections::HashMap;
+
+use move_model::{
+    model::GlobalEnv,
+    ty::{ReferenceKind, Type},
+};
+use move_stackless_bytecode::{
+    function_target::FunctionTarget,
+    stackless_bytecode::{Bytecode, Operation},
+};
+
+/// Function doing nothing but reading or writing a field reached from its first parameter,
+/// a reference to a struct
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Accessor {
+    /// `fun f(s: &S): T { s.a.b }`, or `&s.a.b` / `&mut s.a.b` when `reference` is set
+    /// (to whether it is mutable)
+    Getter {
+        fields: Vec<String>,
+        reference: Option<bool>,
+    },
+    /// `fun f(s: &mut S, v: T) { s.a.b = v }`
+    Setter { fields: Vec<String> },
+}
+
+impl Accessor {
+    /// Call of the accessor with `object` (the struct reference) and `value` (setters only)
+    /// as a field access
+    pub fn fold(&self, object: &str, value: Option<&str>) -> String {
+        match self {
+            Accessor::Getter { fields, reference } => format!(
+                "{}{}.{}",
+                match reference {
+                    Some(true) => "&mut ",
+                    Some(false) => "&",
+                    None => "",
+                },
+                object,
+                fields.join(".")
+            ),
+            Accessor::Setter { fields } => {
+                format!("{}.{} = {}", object, fields.join("."), value.unwrap_or("_"))
+            }
+        }
+    }
+
+    /// Number of parameters
+    pub fn arity(&self) -> usize {
+        match self {
+            Accessor::Getter { .. } => 1,
+            Accessor::Setter { .. } => 2,
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            Accessor::Getter { .. } => format!("getter of {}", self.fold("arg0", None)),
+            Accessor::Setter { .. } => format!("setter of {}", self.fold("arg0", Some("arg1"))),
+        }
+    }
+}
+
+/// What a temporary of an accessor candidate holds
+#[derive(Clone)]
+enum Value {
+    /// reference to the first parameter or to a field path inside it
+    Path(Vec<String>),
+    /// value read from a field path
+    Read(Vec<String>),
+    /// the second parameter
+    Argument,
+}
+
+/// Accessor implemented by a function, from its stackless bytecode before the decompiler
+/// processors run
+pub(crate) fn detect_accessor(env: &GlobalEnv, target: &FunctionTarget<'_>) -> Option<Accessor> {
+    let parameter_count = target.get_parameter_count();
+    if parameter_count == 0 || parameter_count > 2 {
+        return None;
+    }
+    match target.get_local_type(0) {
+        Type::Reference(_, ty) if matches!(ty.as_ref(), Type::Struct(..)) => {}
+        _ => return None,
+    }
+
+    let mut values = HashMap::new();
+    values.insert(0, Value::Path(vec![]));
+    if parameter_count == 2 {
+        values.insert(1, Value::Argument);
+    }
+    let mut written = None;
+
+    for bytecode in target.get_bytecode() {
+        match bytecode {
+            Bytecode::Label(..) | Bytecode::Nop(..) => {}
+            Bytecode::Assign(_, dest, src, _) => {
+                let value = values.get(src)?.clone();
+                values.insert(*dest, value);
+            }
+            Bytecode::Call(_, dests, Operation::BorrowField(mid, sid, _, offset), srcs, _) => {
+                let Some(Value::Path(path)) = values.get(&srcs[0]) else {
+                    return None;
+                };
+                let struct_env = env.get_module(*mid).into_struct(*sid);
+                let field = struct_env
+                    .get_fields()
+                    .find(|field| field.get_offset() == *offset)?
+                    .get_name()
+                    .display(struct_env.symbol_pool())
+                    .to_string();
+                let mut path = path.clone();
+                path.push(field);
+                values.insert(dests[0], Value::Path(path));
+            }
+            Bytecode::Call(_, dests, Operation::FreezeRef, srcs, _) => {
+                let value = values.get(&srcs[0])?.clone();
+                values.insert(dests[0], value);
+            }
+            Bytecode::Call(_, dests, Operation::ReadRef, srcs, _) => match values.get(&srcs[0]) {
+                Some(Value::Path(path)) if !path.is_empty() && parameter_count == 1 => {
+                    let path = path.clone();
+                    values.insert(dests[0], Value::Read(path));
+                }
+                _ => return None,
+            },
+            Bytecode::Call(_, _, Operation::WriteRef, srcs, _) => {
+                match (values.get(&srcs[0]), values.get(&srcs[1])) {
+                    (Some(Value::Path(path)), Some(Value::Argument))
+                        if !path.is_empty() && written.is_none() =>
+                    {
+                        written = Some(path.clone());
+                    }
+                    _ => return None,
+                }
+            }
+            Bytecode::Ret(_, srcs) => {
+                return match (srcs.as_slice(), written.take()) {
+                    ([], Some(fields)) => Some(Accessor::Setter { fields }),
+                    ([src], None) if parameter_count == 1 => match values.get(src)? {
+                        Value::Read(fields) => Some(Accessor::Getter {
+                            fields: fields.clone(),
+                            reference: None,
+                        }),
+                        Value::Path(fields) if !fields.is_empty() => Some(Accessor::Getter {
+                            fields: fields.clone(),
+                            reference: Some(matches!(
+                                target.get_local_type(*src),
+                                Type::Reference(ReferenceKind::Mutable, _)
+                            )),
+                        }),
+                        _ => None,
+                    },
+                    _ => None,
+                };
+            }
+            _ => return None,
+        }
+    }
+
+    None
+}
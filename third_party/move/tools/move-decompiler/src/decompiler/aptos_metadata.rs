@@ -0,0 +1,186 @@
+// Copyright (c) Verichains, 2023
+
+use std::collections::BTreeMap;
+
+use move_core_types::{
+    account_address::AccountAddress, errmap::ErrorDescription, metadata::Metadata,
+};
+use serde::{Deserialize, Serialize};
+
+// mirror of the metadata written by the aptos framework builder, we can not depend on
+// aptos-framework from here so only the layout is kept in sync
+const APTOS_METADATA_KEY: &[u8] = "aptos::metadata_v0".as_bytes();
+const APTOS_METADATA_KEY_V1: &[u8] = "aptos::metadata_v1".as_bytes();
+
+const LEGACY_VIEW_FUNCTION: u8 = 0;
+const VIEW_FUNCTION: u8 = 1;
+const RESOURCE_GROUP: u8 = 2;
+const RESOURCE_GROUP_MEMBER: u8 = 3;
+const EVENT: u8 = 4;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RuntimeModuleMetadata {
+    error_map: BTreeMap<u64, ErrorDescription>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RuntimeModuleMetadataV1 {
+    pub error_map: BTreeMap<u64, ErrorDescription>,
+    pub struct_attributes: BTreeMap<String, Vec<KnownAttribute>>,
+    pub fun_attributes: BTreeMap<String, Vec<KnownAttribute>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KnownAttribute {
+    pub kind: u8,
+    pub args: Vec<String>,
+}
+
+impl KnownAttribute {
+    pub fn is_view_function(&self) -> bool {
+        self.kind == LEGACY_VIEW_FUNCTION || self.kind == VIEW_FUNCTION
+    }
+
+    pub fn is_event(&self) -> bool {
+        self.kind == EVENT
+    }
+
+    /// The attribute as written in Move source, `None` for function attributes and kinds
+    /// unknown to the decompiler
+    pub fn struct_attribute_source(&self) -> Option<String> {
+        match self.kind {
+            EVENT => Some("#[event]".to_string()),
+            RESOURCE_GROUP => Some(format!("#[resource_group(scope = {})]", self.args.first()?)),
+            // the group is stored as a struct tag with a full length address
+            RESOURCE_GROUP_MEMBER => {
+                let group = self.args.first()?;
+                let group = match group.split_once("::") {
+                    Some((address, rest)) => match AccountAddress::from_hex_literal(address) {
+                        Ok(address) => format!("{}::{}", address.to_hex_literal(), rest),
+                        Err(_) => group.clone(),
+                    },
+                    None => group.clone(),
+                };
+                Some(format!("#[resource_group_member(group = {})]", group))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl RuntimeModuleMetadataV1 {
+    pub fn is_view_function(&self, name: &str) -> bool {
+        self.fun_attributes
+            .get(name)
+            .map(|attrs| attrs.iter().any(|attr| attr.is_view_function()))
+            .unwrap_or(false)
+    }
+
+    /// Source form of the attributes of a struct, see `KnownAttribute::struct_attribute_source`
+    pub fn struct_attributes_source(&self, name: &str) -> Vec<String> {
+        self.struct_attributes
+            .get(name)
+            .map(|attrs| {
+                attrs
+                    .iter()
+                    .filter_map(|attr| attr.struct_attribute_source())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn is_event_struct(&self, name: &str) -> bool {
+        self.struct_attributes
+            .get(name)
+            .map(|attrs| attrs.iter().any(|attr| attr.is_event()))
+            .unwrap_or(false)
+    }
+}
+
+/// Decode the aptos runtime metadata of a module, upgrading v0 to v1 if needed.
+/// Malformed metadata is treated as missing, the decompiler does not rely on it for correctness.
+pub fn get_metadata(md: &[Metadata]) -> Option<RuntimeModuleMetadataV1> {
+    if let Some(data) = md.iter().find(|md| md.key == APTOS_METADATA_KEY_V1) {
+        bcs::from_bytes::<RuntimeModuleMetadataV1>(&data.value).ok()
+    } else if let Some(data) = md.iter().find(|md| md.key == APTOS_METADATA_KEY) {
+        bcs::from_bytes::<RuntimeModuleMetadata>(&data.value)
+            .ok()
+            .map(|v0| RuntimeModuleMetadataV1 {
+                error_map: v0.error_map,
+                ..Default::default()
+            })
+    } else {
+        None
+    }
+}
+
+/// Why the aptos runtime metadata of a module can not be decoded, `None` if it is readable or
+/// absent. `get_metadata` ignores such metadata, this reports it.
+pub fn metadata_error(md: &[Metadata]) -> Option<String> {
+    if let Some(data) = md.iter().find(|md| md.key == APTOS_METADATA_KEY_V1) {
+        bcs::from_bytes::<RuntimeModuleMetadataV1>(&data.value)
+            .err()
+            .map(|err| format!("unreadable aptos::metadata_v1: {}", err))
+    } else if let Some(data) = md.iter().find(|md| md.key == APTOS_METADATA_KEY) {
+        bcs::from_bytes::<RuntimeModuleMetadata>(&data.value)
+            .err()
+            .map(|err| format!("unreadable aptos::metadata_v0: {}", err))
+    } else {
+        None
+    }
+}
+
+/// Mirror of the `PackageMetadata` stored by `0x1::code` and written by the aptos
+/// framework builder into `package-metadata.bcs`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PackageMetadata {
+    pub name: String,
+    pub upgrade_policy: u8,
+    pub upgrade_number: u64,
+    pub source_digest: String,
+    /// gzipped `Move.toml`
+    pub manifest: Vec<u8>,
+    pub modules: Vec<PackageModuleMetadata>,
+    pub deps: Vec<PackageDep>,
+    pub extension: Vec<PackageAny>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PackageModuleMetadata {
+    pub name: String,
+    /// gzipped source, empty if the package was published without sources
+    pub source: Vec<u8>,
+    pub source_map: Vec<u8>,
+    pub extension: Vec<PackageAny>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PackageDep {
+    pub account: AccountAddress,
+    pub package_name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PackageAny {
+    pub type_name: String,
+    pub data: Vec<u8>,
+}
+
+impl PackageMetadata {
+    pub fn from_bcs(bytes: &[u8]) -> Result<Self, anyhow::Error> {
+        Ok(bcs::from_bytes(bytes)?)
+    }
+
+    pub fn upgrade_policy_name(&self) -> String {
+        match self.upgrade_policy {
+            0 => "arbitrary".to_string(),
+            1 => "compatible".to_string(),
+            2 => "immutable".to_string(),
+            policy => format!("unknown({})", policy),
+        }
+    }
+
+    pub fn contains_module(&self, name: &str) -> bool {
+        self.modules.iter().any(|m| m.name == name)
+    }
+}
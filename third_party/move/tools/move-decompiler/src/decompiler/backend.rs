@@ -0,0 +1,88 @@
+// Copyright (c) Verichains, 2023
+
+use std::{collections::BTreeMap, fmt::Display, str::FromStr};
+
+use move_stackless_bytecode::function_target::FunctionTarget;
+
+use super::{naming::Naming, reconstruct::code_unit::SourceCodeUnit};
+
+/// How function bodies are decompiled
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Backend {
+    /// control flow reconstruction pipeline producing Move source, see `Stage`
+    #[default]
+    Cfg,
+    /// dump of the stackless bytecode of the move model with its typed locals, without
+    /// control flow reconstruction
+    Stackless,
+}
+
+impl Backend {
+    pub const ALL: [Backend; 2] = [Backend::Cfg, Backend::Stackless];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Backend::Cfg => "cfg",
+            Backend::Stackless => "stackless",
+        }
+    }
+}
+
+impl Display for Backend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.pad(self.name())
+    }
+}
+
+impl FromStr for Backend {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Backend::ALL
+            .iter()
+            .find(|backend| backend.name() == s)
+            .copied()
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "unknown backend {}, expected one of: {}",
+                    s,
+                    Backend::ALL
+                        .iter()
+                        .map(|backend| backend.name())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            })
+    }
+}
+
+/// Body of a function for the `stackless` backend: a declaration with the model type of
+/// every local that is not a parameter, then the stackless bytecode
+pub(crate) fn stackless_body(
+    function_target: &FunctionTarget<'_>,
+    naming: &Naming,
+) -> SourceCodeUnit {
+    let mut unit = SourceCodeUnit::new(0);
+    let parameter_count = function_target.get_parameter_count();
+    if parameter_count > 0 {
+        unit.add_line(format!("// parameters are $t0..$t{}", parameter_count - 1));
+    }
+    for idx in parameter_count..function_target.get_local_count() {
+        unit.add_line(format!(
+            "let $t{}: {};",
+            idx,
+            naming.ty(function_target.get_local_type(idx))
+        ));
+    }
+
+    let label_offsets = BTreeMap::new();
+    for bytecode in function_target.get_bytecode() {
+        unit.add_line(
+            bytecode
+                .display(function_target, &label_offsets)
+                .to_string(),
+        );
+    }
+
+    unit
+}
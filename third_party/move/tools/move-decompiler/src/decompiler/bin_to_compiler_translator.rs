@@ -684,8 +684,12 @@ fn create_dummy_for_non_existing_modules(
     Ok(())
 }
 
+/// Model program of the `binaries` to decompile. Modules of `dependencies` are added with
+/// their definitions but are not source modules, other modules referenced by the binaries
+/// are replaced by dummies declaring only what is used.
 pub(crate) fn create_program(
     binaries: &Vec<BinaryIndexedView>,
+    dependencies: &[CompiledModule],
     naming: &Naming,
 ) -> Result<Program, anyhow::Error> {
     let mut modules = UniqueMap::<ModuleIdent, ModuleDefinition>::new();
@@ -701,65 +705,85 @@ pub(crate) fn create_program(
         .collect();
 
     for compiled_module in &adding_modules {
-        let mut structs: UniqueMap<StructName, StructDefinition> = UniqueMap::new();
-        let mut functions: UniqueMap<FunctionName, Function> = UniqueMap::new();
-
-        for struct_ in compiled_module.struct_defs() {
-            let struct_handle = struct_.struct_handle;
-            let name_idx = compiled_module.struct_handle_at(struct_handle).name;
-            let name_str = compiled_module.identifier_at(name_idx).as_str();
-            let name = span_(Symbol::from(name_str));
-
-            structs
-                .add(
-                    StructName(name),
-                    map_struct(compiled_module, struct_, naming)?,
-                )
-                .map_err(|(name, _)| {
-                    anyhow::Error::msg(format!("Error adding struct {}", name))
-                })?;
-        }
-
-        for function_ in compiled_module.function_defs() {
-            let function_handle = function_.function;
-            let name_idx = compiled_module.function_handle_at(function_handle).name;
-            let name_str = compiled_module.identifier_at(name_idx).as_str();
-            let name = span_(Symbol::from(name_str));
+        modules
+            .add(
+                module_to_module_ident(compiled_module, &compiled_module.self_handle())?,
+                module_definition(compiled_module, true, 1000, naming)?,
+            )
+            .unwrap();
+    }
 
-            functions
-                .add(
-                    FunctionName(name),
-                    map_function(&compiled_module, function_, naming)?,
-                )
-                .map_err(|(name, _)| {
-                    anyhow::Error::msg(format!("Error adding function {}", name))
-                })?;
+    // dependencies come after the dummies and before the binaries, in dependency order
+    let dependencies = super::bundle::sort_by_dependency(dependencies.to_vec())?;
+    for (idx, compiled_module) in dependencies.iter().enumerate() {
+        let module_id = module_to_module_ident(compiled_module, &compiled_module.self_handle())?;
+        if modules.contains_key(&module_id) {
+            continue;
         }
-
         modules
             .add(
-                module_to_module_ident(compiled_module, &compiled_module.self_handle())?,
-                ModuleDefinition {
-                    package_name: None,
-                    attributes: Attributes::new(),
-                    loc: fake_loc(),
-                    is_source_module: true,
-                    dependency_order: 1000,
-                    immediate_neighbors: UniqueMap::new(),
-                    used_addresses: BTreeSet::new(),
-                    friends: UniqueMap::new(),
-                    structs,
-                    functions,
-                    constants: UniqueMap::new(),
-                    specs: Vec::new(),
-                    use_decls: Vec::new(),
-                },
+                module_id,
+                module_definition(compiled_module, false, 2 + idx, naming)?,
             )
             .unwrap();
-        ()
     }
 
-    create_dummy_for_non_existing_modules(&mut modules, &adding_modules, &naming)?;
+    let referencing_modules: Vec<_> = adding_modules.into_iter().chain(dependencies).collect();
+    create_dummy_for_non_existing_modules(&mut modules, &referencing_modules, &naming)?;
 
     Ok(Program { modules, scripts })
 }
+
+fn module_definition(
+    compiled_module: &CompiledModule,
+    is_source_module: bool,
+    dependency_order: usize,
+    naming: &Naming,
+) -> Result<ModuleDefinition, anyhow::Error> {
+    let mut structs: UniqueMap<StructName, StructDefinition> = UniqueMap::new();
+    let mut functions: UniqueMap<FunctionName, Function> = UniqueMap::new();
+
+    for struct_ in compiled_module.struct_defs() {
+        let struct_handle = struct_.struct_handle;
+        let name_idx = compiled_module.struct_handle_at(struct_handle).name;
+        let name_str = compiled_module.identifier_at(name_idx).as_str();
+        let name = span_(Symbol::from(name_str));
+
+        structs
+            .add(
+                StructName(name),
+                map_struct(compiled_module, struct_, naming)?,
+            )
+            .map_err(|(name, _)| anyhow::Error::msg(format!("Error adding struct {}", name)))?;
+    }
+
+    for function_ in compiled_module.function_defs() {
+        let function_handle = function_.function;
+        let name_idx = compiled_module.function_handle_at(function_handle).name;
+        let name_str = compiled_module.identifier_at(name_idx).as_str();
+        let name = span_(Symbol::from(name_str));
+
+        functions
+            .add(
+                FunctionName(name),
+                map_function(&compiled_module, function_, naming)?,
+            )
+            .map_err(|(name, _)| anyhow::Error::msg(format!("Error adding function {}", name)))?;
+    }
+
+    Ok(ModuleDefinition {
+        package_name: None,
+        attributes: Attributes::new(),
+        loc: fake_loc(),
+        is_source_module,
+        dependency_order,
+        immediate_neighbors: UniqueMap::new(),
+        used_addresses: BTreeSet::new(),
+        friends: UniqueMap::new(),
+        structs,
+        functions,
+        constants: UniqueMap::new(),
+        specs: Vec::new(),
+        use_decls: Vec::new(),
+    })
+}
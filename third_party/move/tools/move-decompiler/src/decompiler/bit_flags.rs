@@ -0,0 +1,110 @@
+// Copyright (c) Verichains, 2023
+
+use std::collections::BTreeMap;
+
+use move_model::ast::TempIndex;
+use move_stackless_bytecode::{
+    function_target::FunctionTarget,
+    stackless_bytecode::{Bytecode, Constant, Operation},
+};
+
+/// Power-of-two constant used as a flag, named after its bit
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct BitFlag {
+    pub name: String,
+    /// `u8` or `u64`
+    pub ty: &'static str,
+    pub bit: u32,
+}
+
+impl BitFlag {
+    pub fn declaration(&self) -> String {
+        format!("const {}: {} = 1 << {};", self.name, self.ty, self.bit)
+    }
+}
+
+/// `u8` and `u64` powers of two only ever combined with `&` and `|` in the functions of a
+/// module, from their stackless bytecode before the decompiler processors run. A type needs
+/// at least two such constants to be considered a flag set.
+pub(crate) fn detect_bit_flags<'a>(
+    targets: impl Iterator<Item = FunctionTarget<'a>>,
+) -> BTreeMap<Constant, BitFlag> {
+    let mut bitwise_only = BTreeMap::new();
+    for target in targets {
+        let code = target.get_bytecode();
+        for bytecode in code {
+            let Bytecode::Load(_, dest, constant) = bytecode else {
+                continue;
+            };
+            if flag_bit(constant).is_none() {
+                continue;
+            }
+            let mut used = false;
+            let mut only_bitwise = true;
+            for user in code.iter().filter(|x| sources(x).contains(dest)) {
+                used = true;
+                only_bitwise &= matches!(
+                    user,
+                    Bytecode::Call(_, _, Operation::BitAnd | Operation::BitOr, _, _)
+                );
+            }
+            if used {
+                *bitwise_only.entry(constant.clone()).or_insert(true) &= only_bitwise;
+            }
+        }
+    }
+
+    let flags = bitwise_only
+        .into_iter()
+        .filter(|(_, only_bitwise)| *only_bitwise)
+        .map(|(constant, _)| constant)
+        .collect::<Vec<_>>();
+    let count = |ty: &str| flags.iter().filter(|c| constant_type(c) == ty).count();
+    let flags = flags
+        .into_iter()
+        .filter(|c| count(constant_type(c)) >= 2)
+        .collect::<Vec<_>>();
+
+    let mut result = BTreeMap::new();
+    for constant in &flags {
+        let bit = flag_bit(constant).unwrap();
+        let ty = constant_type(constant);
+        // the type is only part of the name when another type has a flag on the same bit
+        let shared = flags
+            .iter()
+            .any(|other| other != constant && flag_bit(other) == Some(bit));
+        let name = if shared {
+            format!("FLAG_{}_{}", ty.to_uppercase(), bit)
+        } else {
+            format!("FLAG_{}", bit)
+        };
+        result.insert(constant.clone(), BitFlag { name, ty, bit });
+    }
+    result
+}
+
+fn sources(bytecode: &Bytecode) -> Vec<TempIndex> {
+    match bytecode {
+        Bytecode::Assign(_, _, src, _)
+        | Bytecode::Branch(_, _, _, src)
+        | Bytecode::Abort(_, src) => vec![*src],
+        Bytecode::Call(_, _, _, srcs, _) | Bytecode::Ret(_, srcs) => srcs.clone(),
+        _ => vec![],
+    }
+}
+
+fn flag_bit(constant: &Constant) -> Option<u32> {
+    let value = match constant {
+        Constant::U8(x) => *x as u64,
+        Constant::U64(x) => *x,
+        _ => return None,
+    };
+    value.is_power_of_two().then(|| value.trailing_zeros())
+}
+
+fn constant_type(constant: &Constant) -> &'static str {
+    match constant {
+        Constant::U8(_) => "u8",
+        _ => "u64",
+    }
+}
@@ -0,0 +1,310 @@
+// Copyright (c) Verichains, 2023
+
+use std::{collections::HashSet, io::Read};
+
+use anyhow::{bail, Context, Result};
+use flate2::read::{DeflateDecoder, GzDecoder};
+use move_binary_format::{access::ModuleAccess, file_format::CompiledModule};
+use move_core_types::language_storage::ModuleId;
+
+const MOVE_MAGIC: &[u8] = &[0xa1, 0x1c, 0xeb, 0x0b];
+const ZIP_LOCAL_HEADER: u32 = 0x04034b50;
+const ZIP_CENTRAL_HEADER: u32 = 0x02014b50;
+const ZIP_END_OF_CENTRAL_DIRECTORY: u32 = 0x06054b50;
+const TAR_BLOCK_SIZE: usize = 512;
+
+/// Archive of several modules accepted in place of a single module file
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BundleFormat {
+    /// zipped package build output, e.g. `build/<package>/bytecode_modules`
+    Zip,
+    Tar,
+    TarGz,
+    /// CBOR array of byte strings, one per module
+    Cbor,
+    /// BCS `vector<vector<u8>>`, the `code` argument of `0x1::code::publish_package_txn`.
+    /// The on-chain `code::PackageRegistry` only keeps the package metadata, the modules
+    /// of a published package are found in its publishing transaction.
+    Bcs,
+}
+
+impl BundleFormat {
+    /// Format of `bytes` read from `path`, detected from the content when the format has a
+    /// magic number and from the file extension otherwise. `None` for a module or a script.
+    pub fn detect(path: &str, bytes: &[u8]) -> Option<Self> {
+        if bytes.starts_with(MOVE_MAGIC) {
+            None
+        } else if bytes.starts_with(b"PK\x03\x04") || bytes.starts_with(b"PK\x05\x06") {
+            Some(Self::Zip)
+        } else if bytes.starts_with(&[0x1f, 0x8b]) {
+            Some(Self::TarGz)
+        } else if bytes.get(257..262) == Some(b"ustar".as_slice()) || path.ends_with(".tar") {
+            Some(Self::Tar)
+        } else if path.ends_with(".cbor") {
+            Some(Self::Cbor)
+        } else if path.ends_with(".bcs") {
+            Some(Self::Bcs)
+        } else {
+            None
+        }
+    }
+}
+
+/// Modules of a bundle, ordered so that every module comes after the modules of the bundle it
+/// depends on. Archives are searched for `.mv` files, skipping the `dependencies` and
+/// `bytecode_scripts` directories of package build outputs.
+pub fn read_bundle(format: BundleFormat, bytes: &[u8]) -> Result<Vec<CompiledModule>> {
+    let blobs = match format {
+        BundleFormat::Zip => zip_entries(bytes)?,
+        BundleFormat::Tar => tar_entries(bytes)?,
+        BundleFormat::TarGz => {
+            let mut tar = Vec::new();
+            GzDecoder::new(bytes)
+                .read_to_end(&mut tar)
+                .context("invalid gzip stream")?;
+            tar_entries(&tar)?
+        }
+        BundleFormat::Cbor => cbor_byte_strings(bytes)?,
+        BundleFormat::Bcs => {
+            bcs::from_bytes::<Vec<Vec<u8>>>(bytes).context("invalid BCS module vector")?
+        }
+    };
+
+    let modules = blobs
+        .iter()
+        .enumerate()
+        .map(|(idx, blob)| {
+            CompiledModule::deserialize(blob)
+                .with_context(|| format!("failed to deserialize module #{} of the bundle", idx))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    sort_by_dependency(modules)
+}
+
+/// Order `modules` so that dependencies inside the list come first, keeping the original
+/// order between independent modules
+pub fn sort_by_dependency(modules: Vec<CompiledModule>) -> Result<Vec<CompiledModule>> {
+    let ids = modules
+        .iter()
+        .map(|module| module.self_id())
+        .collect::<HashSet<_>>();
+    let mut pending = modules
+        .into_iter()
+        .map(|module| {
+            let deps = module
+                .immediate_dependencies()
+                .into_iter()
+                .filter(|dep| ids.contains(dep))
+                .collect::<Vec<ModuleId>>();
+            (module, deps)
+        })
+        .collect::<Vec<_>>();
+
+    let mut placed = HashSet::new();
+    let mut sorted = Vec::with_capacity(pending.len());
+    while !pending.is_empty() {
+        let Some(idx) = pending
+            .iter()
+            .position(|(_, deps)| deps.iter().all(|dep| placed.contains(dep)))
+        else {
+            bail!(
+                "cyclic dependency between modules {}",
+                pending
+                    .iter()
+                    .map(|(module, _)| module.self_id().to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        };
+        let (module, _) = pending.remove(idx);
+        placed.insert(module.self_id());
+        sorted.push(module);
+    }
+
+    Ok(sorted)
+}
+
+fn is_module_path(path: &str) -> bool {
+    path.ends_with(".mv")
+        && !path
+            .split('/')
+            .any(|component| component == "dependencies" || component == "bytecode_scripts")
+}
+
+fn le_u16(bytes: &[u8], at: usize) -> Result<usize> {
+    match bytes.get(at..at + 2) {
+        Some(b) => Ok(u16::from_le_bytes([b[0], b[1]]) as usize),
+        None => bail!("truncated zip archive"),
+    }
+}
+
+fn le_u32(bytes: &[u8], at: usize) -> Result<usize> {
+    match bytes.get(at..at + 4) {
+        Some(b) => Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize),
+        None => bail!("truncated zip archive"),
+    }
+}
+
+/// Files of a zip archive, read through its central directory since the sizes in the local
+/// headers are missing for streamed archives. Zip64 archives are not supported.
+fn zip_entries(bytes: &[u8]) -> Result<Vec<Vec<u8>>> {
+    // the end of central directory record is 22 bytes followed by a comment of at most 64KB
+    let Some(end) = (0..=bytes.len().saturating_sub(22))
+        .rev()
+        .take(22 + 0xffff)
+        .find(|&at| le_u32(bytes, at).ok() == Some(ZIP_END_OF_CENTRAL_DIRECTORY as usize))
+    else {
+        bail!("zip end of central directory not found");
+    };
+    let count = le_u16(bytes, end + 10)?;
+    let mut at = le_u32(bytes, end + 16)?;
+
+    let mut blobs = Vec::new();
+    for _ in 0..count {
+        if le_u32(bytes, at)? != ZIP_CENTRAL_HEADER as usize {
+            bail!("invalid zip central directory entry at {}", at);
+        }
+        let method = le_u16(bytes, at + 10)?;
+        let compressed_size = le_u32(bytes, at + 20)?;
+        let size = le_u32(bytes, at + 24)?;
+        let name_len = le_u16(bytes, at + 28)?;
+        let extra_len = le_u16(bytes, at + 30)?;
+        let comment_len = le_u16(bytes, at + 32)?;
+        let local = le_u32(bytes, at + 42)?;
+        let Some(name) = bytes.get(at + 46..at + 46 + name_len) else {
+            bail!("truncated zip archive");
+        };
+        let name = String::from_utf8_lossy(name).to_string();
+        at += 46 + name_len + extra_len + comment_len;
+
+        if !is_module_path(&name) {
+            continue;
+        }
+        if le_u32(bytes, local)? != ZIP_LOCAL_HEADER as usize {
+            bail!("invalid zip local header for {}", name);
+        }
+        let start = local + 30 + le_u16(bytes, local + 26)? + le_u16(bytes, local + 28)?;
+        let Some(data) = bytes.get(start..start + compressed_size) else {
+            bail!("truncated zip entry {}", name);
+        };
+        let blob = match method {
+            0 => data.to_vec(),
+            8 => {
+                let mut blob = Vec::with_capacity(size);
+                DeflateDecoder::new(data)
+                    .read_to_end(&mut blob)
+                    .with_context(|| format!("invalid deflate stream for {}", name))?;
+                blob
+            }
+            _ => bail!("unsupported zip compression method {} for {}", method, name),
+        };
+        blobs.push(blob);
+    }
+
+    Ok(blobs)
+}
+
+/// Regular files of a tar archive, long names of GNU and pax archives included
+fn tar_entries(bytes: &[u8]) -> Result<Vec<Vec<u8>>> {
+    let field = |header: &[u8], range: std::ops::Range<usize>| {
+        let raw = &header[range];
+        let len = raw.iter().position(|b| *b == 0).unwrap_or(raw.len());
+        String::from_utf8_lossy(&raw[..len]).to_string()
+    };
+
+    let mut blobs = Vec::new();
+    let mut long_name = None;
+    let mut at = 0;
+    while let Some(header) = bytes.get(at..at + TAR_BLOCK_SIZE) {
+        if header.iter().all(|b| *b == 0) {
+            break;
+        }
+        let size_field = field(header, 124..136);
+        let size = usize::from_str_radix(size_field.trim(), 8)
+            .with_context(|| format!("invalid tar entry size {:?}", size_field))?;
+        let start = at + TAR_BLOCK_SIZE;
+        let Some(data) = start
+            .checked_add(size)
+            .and_then(|end| bytes.get(start..end))
+        else {
+            bail!("truncated tar archive");
+        };
+        // `data` is in bounds, so rounding its size up to whole blocks cannot overflow
+        at = start + (size + TAR_BLOCK_SIZE - 1) / TAR_BLOCK_SIZE * TAR_BLOCK_SIZE;
+
+        let name = match long_name.take() {
+            Some(name) => name,
+            None if &header[257..262] == b"ustar" && header[345] != 0 => {
+                format!("{}/{}", field(header, 345..500), field(header, 0..100))
+            }
+            None => field(header, 0..100),
+        };
+        match header[156] {
+            b'0' | 0 if is_module_path(&name) => blobs.push(data.to_vec()),
+            b'L' => long_name = Some(field(data, 0..data.len())),
+            b'x' => long_name = pax_path(data),
+            _ => {}
+        }
+    }
+
+    Ok(blobs)
+}
+
+/// `path` record of a pax extended header, made of `<len> <key>=<value>\n` records
+fn pax_path(data: &[u8]) -> Option<String> {
+    String::from_utf8_lossy(data).lines().find_map(|record| {
+        let (_, pair) = record.split_once(' ')?;
+        pair.strip_prefix("path=").map(|path| path.to_string())
+    })
+}
+
+/// Byte strings of a CBOR array of definite length
+fn cbor_byte_strings(bytes: &[u8]) -> Result<Vec<Vec<u8>>> {
+    let mut at = 0;
+    let (major, count) = cbor_header(bytes, &mut at)?;
+    if major != 4 {
+        bail!("expected a CBOR array of modules");
+    }
+
+    let mut blobs = Vec::new();
+    for _ in 0..count {
+        let (major, len) = cbor_header(bytes, &mut at)?;
+        if major != 2 {
+            bail!("expected a CBOR byte string at offset {}", at);
+        }
+        let Some(blob) = at.checked_add(len).and_then(|end| bytes.get(at..end)) else {
+            bail!("truncated CBOR byte string at offset {}", at);
+        };
+        blobs.push(blob.to_vec());
+        at += blob.len();
+    }
+
+    Ok(blobs)
+}
+
+/// Major type and argument of the CBOR data item at `at`, indefinite lengths are rejected
+fn cbor_header(bytes: &[u8], at: &mut usize) -> Result<(u8, usize)> {
+    let Some(initial) = bytes.get(*at) else {
+        bail!("truncated CBOR data");
+    };
+    let extra = match initial & 0x1f {
+        info @ 0..=23 => {
+            *at += 1;
+            return Ok((initial >> 5, info as usize));
+        }
+        24 => 1,
+        25 => 2,
+        26 => 4,
+        27 => 8,
+        info => bail!("unsupported CBOR additional information {}", info),
+    };
+    let Some(argument) = bytes.get(*at + 1..*at + 1 + extra) else {
+        bail!("truncated CBOR data");
+    };
+    *at += 1 + extra;
+    let value = argument
+        .iter()
+        .fold(0u64, |value, byte| (value << 8) | *byte as u64);
+    Ok((initial >> 5, value as usize))
+}
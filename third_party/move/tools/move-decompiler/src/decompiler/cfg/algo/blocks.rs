@@ -52,6 +52,10 @@ pub fn remove_block<BlockContent: BlockContentTrait>(
         Terminator::Continue { target } => {
             update(target);
         }
+        Terminator::Switch { targets, default } => {
+            targets.iter_mut().for_each(|target| update(target));
+            update(default);
+        }
         Terminator::Ret | Terminator::Abort | Terminator::Normal => {}
     };
     for block in blocks.iter_mut() {
@@ -7,6 +7,52 @@ use super::{
     scc::{Graph, TarjanScc},
 };
 
+/// A part of the CFG in which loops are searched: the visible blocks and the block
+/// the part is entered from
+struct LoopRegion {
+    view: HashSet<usize>,
+    start_idx: usize,
+}
+
+/// A loop (SCC) found in a region, not reconstructed yet
+struct PendingLoop {
+    nodes: Vec<usize>,
+    entry: usize,
+    exits: HashSet<usize>,
+}
+
+/// Successors of every block, computed once and refreshed only for the blocks rewritten by
+/// a loop reconstruction, so that analyzing a region costs its size instead of the whole CFG
+struct SuccessorCache {
+    successors: Vec<Vec<usize>>,
+}
+
+impl SuccessorCache {
+    fn new<BlockContent: BlockContentTrait>(bbs: &[BasicBlock<usize, BlockContent>]) -> Self {
+        Self {
+            successors: bbs
+                .iter()
+                .map(|b| b.next.next_blocks().into_iter().copied().collect())
+                .collect(),
+        }
+    }
+
+    fn get(&self, idx: usize) -> &[usize] {
+        &self.successors[idx]
+    }
+
+    fn refresh<BlockContent: BlockContentTrait>(
+        &mut self,
+        bbs: &[BasicBlock<usize, BlockContent>],
+        idx: usize,
+    ) {
+        if idx >= self.successors.len() {
+            self.successors.resize(idx + 1, Vec::new());
+        }
+        self.successors[idx] = bbs[idx].next.next_blocks().into_iter().copied().collect();
+    }
+}
+
 pub fn loop_reconstruction<BlockContent: BlockContentTrait>(
     bbs: &mut Vec<BasicBlock<usize, BlockContent>>,
 ) -> Result<(), anyhow::Error> {
@@ -14,18 +60,43 @@ pub fn loop_reconstruction<BlockContent: BlockContentTrait>(
     for i in 0..bbs.len() {
         full_view.insert(i);
     }
-    loop_reconstruction_recursive(bbs, bbs.len(), &full_view, 0)
+
+    let mut successors = SuccessorCache::new(bbs);
+
+    // each item holds the loops of a region which are not reconstructed yet, in reverse order.
+    // the body of a loop is pushed right after the loop is reconstructed so it is fully
+    // processed before the next sibling loop, as a depth-first recursion would do
+    let mut worklist = vec![find_loops(
+        &successors,
+        &LoopRegion {
+            view: full_view,
+            start_idx: 0,
+        },
+    )?];
+
+    while let Some(pending_loops) = worklist.last_mut() {
+        let Some(pending_loop) = pending_loops.pop() else {
+            worklist.pop();
+            continue;
+        };
+        if let Some(body) = reconstruct_loop(bbs, &mut successors, pending_loop)? {
+            worklist.push(find_loops(&successors, &body)?);
+        }
+    }
+
+    Ok(())
 }
 
-fn loop_reconstruction_recursive<BlockContent: BlockContentTrait>(
-    bbs: &mut Vec<BasicBlock<usize, BlockContent>>,
-    original_len: usize,
-    current_view: &HashSet<usize>,
-    start_idx: usize,
-) -> Result<(), anyhow::Error> {
-    let graph = build_graph(bbs, current_view, start_idx);
+/// Find the loops of a region, in reverse order of the SCCs
+fn find_loops(
+    successors: &SuccessorCache,
+    region: &LoopRegion,
+) -> Result<Vec<PendingLoop>, anyhow::Error> {
+    let current_view = &region.view;
+    let start_idx = region.start_idx;
+    let graph = build_graph(successors, current_view, start_idx);
     if graph.nodes().len() == 0 {
-        return Ok(());
+        return Ok(Vec::new());
     }
     let scc = TarjanScc::new(&graph);
 
@@ -35,12 +106,12 @@ fn loop_reconstruction_recursive<BlockContent: BlockContentTrait>(
 
     let scc_super_graph_exit_node = usize::MAX;
 
-    for u in 0..bbs.len() {
-        if !current_view.contains(&u) {
-            continue;
-        }
+    // only the blocks of the region can be in an scc, visit them in order
+    let mut region_nodes = current_view.iter().copied().collect::<Vec<_>>();
+    region_nodes.sort();
+    for u in region_nodes {
         if let Some((scc_id, _)) = scc.scc_for_node(u) {
-            for &v in bbs[u].next.next_blocks() {
+            for &v in successors.get(u) {
                 // v is reachable so it's safe to unwrap
                 let v_scc_id = if let Some((v_scc_id, _)) = scc.scc_for_node(v) {
                     v_scc_id
@@ -71,7 +142,7 @@ fn loop_reconstruction_recursive<BlockContent: BlockContentTrait>(
             .or_insert(HashSet::new())
             .insert(start_idx);
     } else {
-        for possible_root in find_possible_root(bbs, start_idx, current_view)? {
+        for possible_root in find_possible_root(successors, start_idx, current_view)? {
             let root_scc_id = scc.scc_for_node(possible_root).unwrap().0;
             scc_super_graph_node_entries
                 .entry(root_scc_id)
@@ -85,7 +156,7 @@ fn loop_reconstruction_recursive<BlockContent: BlockContentTrait>(
         if scc_nodes.len() == 1 {
             let node: usize = *scc_nodes.iter().next().unwrap();
             // if this node has self-loop, consider it as a loop
-            if !bbs[node].next.next_blocks().iter().any(|x| **x == node) {
+            if !successors.get(node).contains(&node) {
                 continue;
             }
         }
@@ -119,12 +190,13 @@ fn loop_reconstruction_recursive<BlockContent: BlockContentTrait>(
         // }
     }
 
-    // each scc is a loop, reconstruct them
+    // each scc is a loop, collect them to be reconstructed
     let empty_hashset = HashSet::<usize>::new();
+    let mut loops = Vec::new();
     for (scc_idx, scc_nodes) in scc.sccs() {
         if scc_nodes.len() == 1 {
             let node: usize = *scc_nodes.iter().next().unwrap();
-            if !bbs[node].next.next_blocks().iter().any(|x| **x == node) {
+            if !successors.get(node).contains(&node) {
                 continue;
             }
         }
@@ -132,196 +204,241 @@ fn loop_reconstruction_recursive<BlockContent: BlockContentTrait>(
         let scc_exits = scc_super_graph_node_exits
             .get(&scc_idx)
             .unwrap_or(&empty_hashset);
-        let scc_exits = scc_exits.clone();
-        let scc_entry = *scc_entries.iter().next().unwrap();
-
-        let mut scc_exit = usize::MAX;
-        if scc_exits.len() > 1 {
-            if let Terminator::IfElse { else_block, .. } = bbs[scc_entry].next {
-                if scc_exits.contains(&else_block) {
-                    scc_exit = else_block;
-                }
-            }
-            if scc_exit == usize::MAX {
-                // heuristic: pick the exit with the largest offset
-                scc_exit = scc_exits
-                    .iter()
-                    .fold((0, 0), |(max_offset, current_exit), &i| {
-                        if bbs[i].offset > max_offset {
-                            (bbs[i].offset, bbs[i].idx)
-                        } else {
-                            (max_offset, current_exit)
-                        }
-                    })
-                    .1;
-
-                // the heuristic above is not always correct if the binary is hand-made
-                // if cfg!(debug_assertions) {
-                //     return Err(anyhow::anyhow!(
-                //         "Failed to reconstruct loop, multiple exits {:?}",
-                //         scc_exits
-                //     ));
-                // } else {
-                //     return Err(anyhow::anyhow!(
-                //         "Failed to reconstruct loop, multiple exits"
-                //     ));
-                // }
+        loops.push(PendingLoop {
+            nodes: scc_nodes.clone(),
+            entry: *scc_entries.iter().next().unwrap(),
+            exits: scc_exits.clone(),
+        });
+    }
+    loops.reverse();
+
+    Ok(loops)
+}
+
+/// Rewrite the blocks of a loop into loop constructs, return the region of its body
+fn reconstruct_loop<BlockContent: BlockContentTrait>(
+    bbs: &mut Vec<BasicBlock<usize, BlockContent>>,
+    successors: &mut SuccessorCache,
+    pending_loop: PendingLoop,
+) -> Result<Option<LoopRegion>, anyhow::Error> {
+    let PendingLoop {
+        nodes: scc_nodes,
+        entry: scc_entry,
+        exits: scc_exits,
+    } = pending_loop;
+
+    let mut scc_exit = usize::MAX;
+    if scc_exits.len() > 1 {
+        if let Terminator::IfElse { else_block, .. } = bbs[scc_entry].next {
+            if scc_exits.contains(&else_block) {
+                scc_exit = else_block;
             }
         }
-        if scc_exit == usize::MAX && scc_exits.len() == 1 {
-            scc_exit = *scc_exits.iter().next().unwrap();
+        if scc_exit == usize::MAX {
+            // heuristic: pick the exit with the largest offset
+            scc_exit = scc_exits
+                .iter()
+                .fold((0, 0), |(max_offset, current_exit), &i| {
+                    if bbs[i].offset > max_offset {
+                        (bbs[i].offset, bbs[i].idx)
+                    } else {
+                        (max_offset, current_exit)
+                    }
+                })
+                .1;
+
+            // the heuristic above is not always correct if the binary is hand-made
+            // if cfg!(debug_assertions) {
+            //     return Err(anyhow::anyhow!(
+            //         "Failed to reconstruct loop, multiple exits {:?}",
+            //         scc_exits
+            //     ));
+            // } else {
+            //     return Err(anyhow::anyhow!(
+            //         "Failed to reconstruct loop, multiple exits"
+            //     ));
+            // }
         }
+    }
+    if scc_exit == usize::MAX && scc_exits.len() == 1 {
+        scc_exit = *scc_exits.iter().next().unwrap();
+    }
 
-        let mut new_blocks: Vec<BasicBlock<usize, BlockContent>> = Vec::new();
-        let mut next_block_idx = bbs.len();
+    let mut new_blocks: Vec<BasicBlock<usize, BlockContent>> = Vec::new();
+    let mut next_block_idx = bbs.len();
 
-        let mut dummy_break = HashMap::<usize, usize>::new();
-        let mut dummy_continue = HashMap::<usize, usize>::new();
+    let mut dummy_break = HashMap::<usize, usize>::new();
+    let mut dummy_continue = HashMap::<usize, usize>::new();
 
-        let mut add_dummy_block_if_required = |base: usize, x: usize| {
-            let mut x: usize = x;
-            x = if x == scc_entry {
-                if let Some(&id) = dummy_continue.get(&base) {
-                    id
-                } else {
-                    let id = next_block_idx;
-                    next_block_idx += 1;
-                    let mut new_block: BasicBlock<usize, BlockContent> = Default::default();
-                    new_block.idx = id;
-                    new_block.offset = usize::MAX;
-                    new_block.topo_priority = Some(0);
-                    new_block.topo_after = HashSet::from([base]);
-                    new_block.topo_before = HashSet::from([scc_exit]);
-                    new_block.next = Terminator::Continue { target: scc_entry };
-
-                    new_blocks.push(new_block);
-                    dummy_continue.insert(base, id);
-                    id
-                }
+    let mut add_dummy_block_if_required = |base: usize, x: usize| {
+        let mut x: usize = x;
+        x = if x == scc_entry {
+            if let Some(&id) = dummy_continue.get(&base) {
+                id
             } else {
-                x
-            };
-            x = if x == scc_exit {
-                if let Some(&id) = dummy_break.get(&base) {
-                    id
-                } else {
-                    let id = next_block_idx;
-                    next_block_idx += 1;
-
-                    let mut new_block: BasicBlock<usize, BlockContent> = Default::default();
-                    new_block.idx = id;
-                    new_block.offset = usize::MAX;
-                    new_block.topo_priority = Some(0);
-                    new_block.topo_after = HashSet::from([base]);
-                    new_block.topo_before = HashSet::from([scc_exit]);
-                    new_block.next = Terminator::Break { target: scc_exit };
-                    new_blocks.push(new_block);
-
-                    dummy_break.insert(base, id);
-                    id
-                }
+                let id = next_block_idx;
+                next_block_idx += 1;
+                let mut new_block: BasicBlock<usize, BlockContent> = Default::default();
+                new_block.idx = id;
+                new_block.offset = usize::MAX;
+                new_block.topo_priority = Some(0);
+                new_block.topo_after = HashSet::from([base]);
+                new_block.topo_before = HashSet::from([scc_exit]);
+                new_block.next = Terminator::Continue { target: scc_entry };
+
+                new_blocks.push(new_block);
+                dummy_continue.insert(base, id);
+                id
+            }
+        } else {
+            x
+        };
+        x = if x == scc_exit {
+            if let Some(&id) = dummy_break.get(&base) {
+                id
             } else {
-                x
-            };
+                let id = next_block_idx;
+                next_block_idx += 1;
+
+                let mut new_block: BasicBlock<usize, BlockContent> = Default::default();
+                new_block.idx = id;
+                new_block.offset = usize::MAX;
+                new_block.topo_priority = Some(0);
+                new_block.topo_after = HashSet::from([base]);
+                new_block.topo_before = HashSet::from([scc_exit]);
+                new_block.next = Terminator::Break { target: scc_exit };
+                new_blocks.push(new_block);
+
+                dummy_break.insert(base, id);
+                id
+            }
+        } else {
             x
         };
+        x
+    };
 
-        for &i in scc_nodes.iter() {
-            let b = &mut bbs[i];
-            match b.next {
-                Terminator::Branch { target } => {
-                    if target == scc_entry {
-                        b.next = Terminator::Continue { target };
-                    };
-                    if target == scc_exit {
-                        b.next = Terminator::Break { target };
+    for &i in scc_nodes.iter() {
+        let b = &mut bbs[i];
+        match b.next {
+            Terminator::Branch { target } => {
+                if target == scc_entry {
+                    b.next = Terminator::Continue { target };
+                };
+                if target == scc_exit {
+                    b.next = Terminator::Break { target };
+                };
+            }
+            Terminator::IfElse {
+                if_block,
+                else_block,
+            } => {
+                if b.idx != scc_entry {
+                    b.next = Terminator::IfElse {
+                        if_block: add_dummy_block_if_required(i, if_block),
+                        else_block: add_dummy_block_if_required(i, else_block),
                     };
                 }
-                Terminator::IfElse {
-                    if_block,
-                    else_block,
-                } => {
-                    if b.idx != scc_entry {
-                        b.next = Terminator::IfElse {
-                            if_block: add_dummy_block_if_required(i, if_block),
-                            else_block: add_dummy_block_if_required(i, else_block),
-                        };
-                    }
+            }
+            Terminator::Switch {
+                ref targets,
+                default,
+            } => {
+                if b.idx != scc_entry {
+                    b.next = Terminator::Switch {
+                        targets: targets
+                            .iter()
+                            .map(|&target| add_dummy_block_if_required(i, target))
+                            .collect(),
+                        default: add_dummy_block_if_required(i, default),
+                    };
                 }
-                _ => {}
             }
+            _ => {}
+        }
+    }
+
+    let mut body_view = HashSet::<usize>::new();
+    // new blocks only contain break and continue, all of them jump to body's external nodes,
+    // so from the body's point of view, adding them or not doesn't change anything
+    for &i in scc_nodes.iter() {
+        if i != scc_entry {
+            body_view.insert(i);
         }
+    }
 
-        let mut body_view = HashSet::<usize>::new();
-        // new blocks only contain break and continue, all of them jump to body's external nodes,
-        // so from the body's point of view, adding them or not doesn't change anything
-        for &i in scc_nodes.iter() {
-            if i != scc_entry {
-                body_view.insert(i);
+    // check the entry
+    let mut is_valid_conditioned_entry = true;
+    if let Terminator::IfElse {
+        if_block,
+        else_block,
+    } = bbs[scc_entry].next
+    {
+        if !scc_nodes.contains(&if_block) && if_block != scc_exit {
+            if cfg!(debug_assertions) {
+                return Err(anyhow::anyhow!(
+                    "Failed to reconstruct loop, entry node {:?} is not in SCC {:?}",
+                    if_block,
+                    scc_nodes
+                ));
+            } else {
+                return Err(anyhow::anyhow!(
+                    "Failed to reconstruct loop, entry node is not in SCC"
+                ));
             }
         }
+        if else_block != scc_exit {
+            is_valid_conditioned_entry = false;
+        }
+    } else {
+        is_valid_conditioned_entry = false;
+    }
 
-        // check the entry
-        let mut is_valid_conditioned_entry = true;
+    if is_valid_conditioned_entry {
         if let Terminator::IfElse {
             if_block,
             else_block,
         } = bbs[scc_entry].next
         {
-            if !scc_nodes.contains(&if_block) && if_block != scc_exit {
-                if cfg!(debug_assertions) {
-                    return Err(anyhow::anyhow!(
-                        "Failed to reconstruct loop, entry node {:?} is not in SCC {:?}",
-                        if_block,
-                        scc_nodes
-                    ));
-                } else {
-                    return Err(anyhow::anyhow!(
-                        "Failed to reconstruct loop, entry node is not in SCC"
-                    ));
-                }
-            }
-            if else_block != scc_exit {
-                is_valid_conditioned_entry = false;
-            }
-        } else {
-            is_valid_conditioned_entry = false;
-        }
-
-        if is_valid_conditioned_entry {
-            if let Terminator::IfElse {
-                if_block,
-                else_block,
-            } = bbs[scc_entry].next
-            {
-                bbs[scc_entry].next = Terminator::While {
-                    inner_block: if_block,
-                    outer_block: else_block,
-                };
-            } else {
-                unreachable!();
-            }
+            bbs[scc_entry].next = Terminator::While {
+                inner_block: if_block,
+                outer_block: else_block,
+            };
         } else {
-            bbs[scc_entry].unconditional_loop_entry = Some(scc_exit);
+            unreachable!();
         }
+    } else {
+        bbs[scc_entry].unconditional_loop_entry = Some(scc_exit);
+    }
 
-        bbs.append(&mut new_blocks);
+    let first_new_block = bbs.len();
+    bbs.append(&mut new_blocks);
 
-        if body_view.len() > 0 {
-            loop_reconstruction_recursive(bbs, original_len, &body_view, scc_entry)?;
-        }
+    // only the terminators of the loop nodes were rewritten
+    for &i in scc_nodes.iter() {
+        successors.refresh(bbs, i);
+    }
+    for i in first_new_block..bbs.len() {
+        successors.refresh(bbs, i);
     }
 
-    Ok(())
+    if body_view.len() > 0 {
+        Ok(Some(LoopRegion {
+            view: body_view,
+            start_idx: scc_entry,
+        }))
+    } else {
+        Ok(None)
+    }
 }
 
-fn find_possible_root<BlockContent: BlockContentTrait>(
-    bbs: &mut Vec<BasicBlock<usize, BlockContent>>,
+fn find_possible_root(
+    successors: &SuccessorCache,
     start_idx: usize,
     current_view: &HashSet<usize>,
 ) -> Result<HashSet<usize>, anyhow::Error> {
     let mut possible_roots = HashSet::<usize>::new();
-    for &v in bbs[start_idx].next.next_blocks() {
+    for &v in successors.get(start_idx) {
         if current_view.contains(&v) {
             possible_roots.insert(v);
         }
@@ -329,8 +446,8 @@ fn find_possible_root<BlockContent: BlockContentTrait>(
     Ok(possible_roots)
 }
 
-fn build_graph<BlockContent: BlockContentTrait>(
-    blocks: &[BasicBlock<usize, BlockContent>],
+fn build_graph(
+    successors: &SuccessorCache,
     current_view: &HashSet<usize>,
     starting_idx: usize,
 ) -> Graph {
@@ -345,7 +462,7 @@ fn build_graph<BlockContent: BlockContentTrait>(
         graph.ensure_node(starting_idx);
     }
     while let Some(idx) = queue.pop_front() {
-        for &&nxt in blocks[idx].next.next_blocks().iter() {
+        for &nxt in successors.get(idx) {
             if !current_view.contains(&nxt) {
                 continue;
             }
@@ -1,14 +1,23 @@
 // Copyright (c) Verichains, 2023
 
-use std::collections::{BTreeSet, HashSet};
+use std::collections::{BTreeSet, HashMap, HashSet};
 
 use super::super::datastructs::*;
 
+/// Chain of blocks whose ordering constraints can not be satisfied, each block must be
+/// placed before the next one
+struct ConstraintCycle {
+    blocks: Vec<usize>,
+    // the last block must also be placed before the first one, otherwise the first block
+    // is never placed (it's unreachable) and blocks the whole chain
+    closed: bool,
+}
+
 fn topo_sort_stable_usize(
     edges: &Vec<Vec<usize>>,
     constraint_edges: &Vec<Vec<usize>>,
     priority: &Vec<usize>,
-) -> Result<Vec<usize>, anyhow::Error> {
+) -> Result<Vec<usize>, ConstraintCycle> {
     let n = edges.len();
 
     let mut constraint_edges = constraint_edges.clone();
@@ -122,13 +131,97 @@ fn topo_sort_stable_usize(
             queue.insert((priority[v], v));
             queued[v] = true;
         } else {
-            return Err(anyhow::anyhow!("cycle detected in constraint graph"));
+            let &(_, v) = remain.iter().next().unwrap();
+            return Err(find_constraint_cycle(v, &constraint_redge, |u| {
+                remain.contains(&(priority[u], u))
+            }));
         }
     }
 
     Ok(result)
 }
 
+/// Walk the pending constraints backward from `start` until a block is visited twice
+/// or a block that will never be placed is found
+fn find_constraint_cycle(
+    start: usize,
+    constraint_redge: &Vec<HashSet<usize>>,
+    is_remaining: impl Fn(usize) -> bool,
+) -> ConstraintCycle {
+    let mut path = Vec::<usize>::new();
+    let mut position = HashMap::<usize, usize>::new();
+    let mut v = start;
+    loop {
+        if let Some(&pos) = position.get(&v) {
+            let mut blocks = path[pos..].to_vec();
+            blocks.reverse();
+            return ConstraintCycle {
+                blocks,
+                closed: true,
+            };
+        }
+        position.insert(v, path.len());
+        path.push(v);
+        if !is_remaining(v) {
+            path.reverse();
+            return ConstraintCycle {
+                blocks: path,
+                closed: false,
+            };
+        }
+        // every remaining block has at least one pending constraint
+        v = *constraint_redge[v].iter().min().unwrap();
+    }
+}
+
+fn describe_constraint_cycle<BlockContent: BlockContentTrait>(
+    blocks: &[BasicBlock<usize, BlockContent>],
+    cycle: &ConstraintCycle,
+) -> anyhow::Error {
+    let mut pairs = cycle
+        .blocks
+        .windows(2)
+        .map(|w| (w[0], w[1]))
+        .collect::<Vec<_>>();
+    if cycle.closed {
+        pairs.push((*cycle.blocks.last().unwrap(), cycle.blocks[0]));
+    }
+
+    let mut lines = Vec::new();
+    for (before, after) in pairs {
+        let mut reasons = Vec::new();
+        if blocks[before].topo_before.contains(&after) {
+            reasons.push(format!("block {} has topo_before {}", before, after));
+        }
+        if blocks[after].topo_after.contains(&before) {
+            reasons.push(format!("block {} has topo_after {}", after, before));
+        }
+        lines.push(format!("  {} -> {}: {}", before, after, reasons.join(", ")));
+    }
+
+    let chain = cycle
+        .blocks
+        .iter()
+        .map(|x| x.to_string())
+        .collect::<Vec<_>>()
+        .join(" -> ");
+    if cycle.closed {
+        anyhow::anyhow!(
+            "cycle detected in constraint graph: {} -> {}\n{}",
+            chain,
+            cycle.blocks[0],
+            lines.join("\n")
+        )
+    } else {
+        anyhow::anyhow!(
+            "cycle detected in constraint graph: {} starts at unreachable block {}\n{}",
+            chain,
+            cycle.blocks[0],
+            lines.join("\n")
+        )
+    }
+}
+
 pub fn topo_sort<BlockContent: BlockContentTrait>(
     blocks: Vec<BasicBlock<usize, BlockContent>>,
 ) -> Result<Vec<BasicBlock<usize, BlockContent>>, anyhow::Error> {
@@ -151,26 +244,30 @@ pub fn topo_sort<BlockContent: BlockContentTrait>(
             // try to keep the original order
             usize::MAX - max_block_offset - 1 + block.offset
         };
-        match block.next {
+        match &block.next {
             Terminator::IfElse {
                 if_block,
                 else_block,
             } => {
-                edges[idx].push(if_block);
-                edges[idx].push(else_block);
+                edges[idx].push(*if_block);
+                edges[idx].push(*else_block);
             }
             Terminator::Break { target }
             | Terminator::Continue { target }
             | Terminator::Branch { target } => {
-                edges[idx].push(target);
+                edges[idx].push(*target);
             }
             Terminator::While {
                 inner_block,
                 outer_block,
             } => {
-                edges[idx].push(inner_block);
-                edges[idx].push(outer_block);
-                edges[inner_block].push(outer_block);
+                edges[idx].push(*inner_block);
+                edges[idx].push(*outer_block);
+                edges[*inner_block].push(*outer_block);
+            }
+            Terminator::Switch { targets, default } => {
+                edges[idx].extend(targets.iter().copied());
+                edges[idx].push(*default);
             }
             Terminator::Ret => {}
             Terminator::Abort => {}
@@ -198,7 +295,8 @@ pub fn topo_sort<BlockContent: BlockContentTrait>(
         }
     }
 
-    let order = topo_sort_stable_usize(&edges, &constraint_edges, &priority)?;
+    let order = topo_sort_stable_usize(&edges, &constraint_edges, &priority)
+        .map_err(|cycle| describe_constraint_cycle(&blocks, &cycle))?;
     let rorder = {
         let mut rorder = vec![0; blocks.len()];
         for (idx, &order_idx) in order.iter().enumerate() {
@@ -238,6 +336,10 @@ pub fn topo_sort<BlockContent: BlockContentTrait>(
             Terminator::Continue { target } => Terminator::Continue {
                 target: rorder[target],
             },
+            Terminator::Switch { targets, default } => Terminator::Switch {
+                targets: targets.iter().map(|&target| rorder[target]).collect(),
+                default: rorder[default],
+            },
         };
         block.topo_after = block
             .topo_after
@@ -0,0 +1,113 @@
+// Copyright (c) Verichains, 2023
+
+use super::datastructs::{BasicBlock, BlockContentTrait, Terminator};
+
+/// Construct a single basic block, fields which are not set keep the values of `BasicBlock::new`
+pub struct BasicBlockBuilder<Content: BlockContentTrait> {
+    block: BasicBlock<usize, Content>,
+}
+
+impl<Content: BlockContentTrait> BasicBlockBuilder<Content> {
+    pub fn new(idx: usize) -> Self {
+        Self {
+            block: BasicBlock::new(idx),
+        }
+    }
+
+    pub fn content(mut self, content: Content) -> Self {
+        self.block.content = content;
+        self
+    }
+
+    pub fn offset(mut self, offset: usize) -> Self {
+        self.block.offset = offset;
+        self
+    }
+
+    pub fn terminator(mut self, next: Terminator<usize>) -> Self {
+        self.block.next = next;
+        self
+    }
+
+    pub fn topo_priority(mut self, priority: usize) -> Self {
+        self.block.topo_priority = Some(priority);
+        self
+    }
+
+    pub fn topo_after(mut self, blocks: impl IntoIterator<Item = usize>) -> Self {
+        self.block.topo_after.extend(blocks);
+        self
+    }
+
+    pub fn topo_before(mut self, blocks: impl IntoIterator<Item = usize>) -> Self {
+        self.block.topo_before.extend(blocks);
+        self
+    }
+
+    pub fn build(self) -> BasicBlock<usize, Content> {
+        self.block
+    }
+}
+
+/// Construct a block vector in index order, as expected by the passes in `algo`
+/// (e.g. `topo::topo_sort`, `loop_reconstruction::loop_reconstruction`).
+/// Block 0 is the entry block.
+pub struct CfgBuilder<Content: BlockContentTrait> {
+    blocks: Vec<BasicBlock<usize, Content>>,
+}
+
+impl<Content: BlockContentTrait> Default for CfgBuilder<Content> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<Content: BlockContentTrait> CfgBuilder<Content> {
+    pub fn new() -> Self {
+        Self { blocks: Vec::new() }
+    }
+
+    /// Index of the next added block, useful to build forward references
+    pub fn next_idx(&self) -> usize {
+        self.blocks.len()
+    }
+
+    /// Append a block with the given content and terminator, returns its index
+    pub fn add_block(&mut self, content: Content, next: Terminator<usize>) -> usize {
+        self.add_block_with(|b| b.content(content).terminator(next))
+    }
+
+    /// Append a block configured by `f`, the index is assigned by the builder
+    pub fn add_block_with(
+        &mut self,
+        f: impl FnOnce(BasicBlockBuilder<Content>) -> BasicBlockBuilder<Content>,
+    ) -> usize {
+        let idx = self.next_idx();
+        self.blocks.push(f(BasicBlockBuilder::new(idx)).build());
+        idx
+    }
+
+    /// Check that every referenced block exists and return the blocks
+    pub fn build(self) -> Result<Vec<BasicBlock<usize, Content>>, anyhow::Error> {
+        let len = self.blocks.len();
+        for block in &self.blocks {
+            let referenced = block
+                .next
+                .next_blocks()
+                .into_iter()
+                .chain(block.topo_after.iter())
+                .chain(block.topo_before.iter());
+            for &target in referenced {
+                if target >= len {
+                    return Err(anyhow::anyhow!(
+                        "Block {} references block {} which does not exist",
+                        block.idx,
+                        target
+                    ));
+                }
+            }
+        }
+
+        Ok(self.blocks)
+    }
+}
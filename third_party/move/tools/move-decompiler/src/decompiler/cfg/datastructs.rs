@@ -43,6 +43,11 @@ pub enum Terminator<BlockIdentifier: BlockIdentifierTrait> {
     Continue {
         target: BlockIdentifier,
     },
+    // multi-way branch, targets[i] is taken when the discriminant is i, default otherwise
+    Switch {
+        targets: Vec<BlockIdentifier>,
+        default: BlockIdentifier,
+    },
 }
 
 #[derive(Default, Debug, Clone)]
@@ -83,6 +88,10 @@ pub enum HyperBlock<BlockIdentifier: BlockIdentifierTrait, BlockContent: BlockCo
         unconditional: bool,
         start_block: usize,
     },
+    SwitchBlocks {
+        cases: Vec<Box<WithMetadata<CodeUnitBlock<BlockIdentifier, BlockContent>>>>,
+        default: Box<WithMetadata<CodeUnitBlock<BlockIdentifier, BlockContent>>>,
+    },
 }
 
 #[derive(Debug, Clone)]
@@ -101,6 +110,21 @@ pub enum JumpType {
 }
 
 impl<BlockIdentifier: BlockIdentifierTrait> Terminator<BlockIdentifier> {
+    /// Short name of the terminator kind, used in exported representations
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Terminator::Normal => "next",
+            Terminator::Ret => "ret",
+            Terminator::Abort => "abort",
+            Terminator::IfElse { .. } => "if",
+            Terminator::Branch { .. } => "branch",
+            Terminator::While { .. } => "while",
+            Terminator::Break { .. } => "break",
+            Terminator::Continue { .. } => "continue",
+            Terminator::Switch { .. } => "switch",
+        }
+    }
+
     pub fn next_blocks(&self) -> Vec<&BlockIdentifier> {
         match self {
             Terminator::Normal => Vec::new(),
@@ -117,6 +141,9 @@ impl<BlockIdentifier: BlockIdentifierTrait> Terminator<BlockIdentifier> {
             } => vec![inner_block, outer_block],
             Terminator::Break { target } => vec![target],
             Terminator::Continue { target } => vec![target],
+            Terminator::Switch { targets, default } => {
+                targets.iter().chain(std::iter::once(default)).collect()
+            }
         }
     }
 }
@@ -132,6 +159,7 @@ impl<BlockIdentifier: BlockIdentifierTrait> Display for Terminator<BlockIdentifi
             Terminator::While { .. } => write!(fmt, "While"),
             Terminator::Break { .. } => write!(fmt, "Break"),
             Terminator::Continue { .. } => write!(fmt, "Continue"),
+            Terminator::Switch { .. } => write!(fmt, "Switch"),
         }
     }
 }
@@ -166,6 +194,23 @@ impl<BlockIdentifier: BlockIdentifierTrait, BlockContent: BlockContentTrait>
         }
     }
 
+    pub fn idx(&self) -> usize {
+        self.idx
+    }
+
+    pub fn content(&self) -> &BlockContent {
+        &self.content
+    }
+
+    pub fn terminator(&self) -> &Terminator<BlockIdentifier> {
+        &self.next
+    }
+
+    /// The exit block if this block is the entry of an unconditional loop
+    pub fn unconditional_loop_entry(&self) -> Option<usize> {
+        self.unconditional_loop_entry
+    }
+
     #[allow(dead_code)]
     fn display<Ctx: DecompileDisplayContext<BlockIdentifier, BlockContent>>(&self, ctx: &mut Ctx) {
         // ctx.add_lines(format!("// block {}, actual {}", self.idx, self.offset).as_str());
@@ -192,7 +237,8 @@ impl<BlockIdentifier: BlockIdentifierTrait, BlockContent: BlockContentTrait>
             Terminator::Normal
             | Terminator::Branch { .. }
             | Terminator::IfElse { .. }
-            | Terminator::While { .. } => {}
+            | Terminator::While { .. }
+            | Terminator::Switch { .. } => {}
         }
     }
 }
@@ -219,6 +265,13 @@ impl<BlockIdentifier: BlockIdentifierTrait, BlockContent: BlockContentTrait>
                 Box::new(inner.inner_mut().content_iter_mut())
                     .chain(outer.inner_mut().content_iter_mut()),
             ),
+
+            HyperBlock::SwitchBlocks { cases, default } => Box::new(
+                cases
+                    .iter_mut()
+                    .chain(std::iter::once(default))
+                    .flat_map(|unit| unit.inner_mut().content_iter_mut()),
+            ),
         }
     }
 
@@ -237,6 +290,13 @@ impl<BlockIdentifier: BlockIdentifierTrait, BlockContent: BlockContentTrait>
             HyperBlock::WhileBlocks { inner, outer, .. } => {
                 Box::new(Box::new(inner.inner().content_iter()).chain(outer.inner().content_iter()))
             }
+
+            HyperBlock::SwitchBlocks { cases, default } => Box::new(
+                cases
+                    .iter()
+                    .chain(std::iter::once(default))
+                    .flat_map(|unit| unit.inner().content_iter()),
+            ),
         }
     }
 
@@ -283,6 +343,25 @@ impl<BlockIdentifier: BlockIdentifierTrait, BlockContent: BlockContentTrait>
 
                 outer.inner().display(ctx);
             }
+
+            HyperBlock::SwitchBlocks { cases, default } => {
+                // the switch is opened in the previous block
+                for (idx, case) in cases.iter().enumerate() {
+                    ctx.add_lines(format!("case {} {{", idx).as_str());
+                    ctx.block(|ctx| {
+                        case.inner().display(ctx);
+                    });
+                    ctx.add_lines("}");
+                }
+
+                ctx.add_lines("default {");
+                ctx.block(|ctx| {
+                    default.inner().display(ctx);
+                });
+                ctx.add_lines("}");
+
+                ctx.add_lines("}");
+            }
         }
     }
 
@@ -302,6 +381,10 @@ impl<BlockIdentifier: BlockIdentifierTrait, BlockContent: BlockContentTrait>
                 unconditional,
                 ..
             } => (*unconditional && inner.inner().is_abort()) || outer.inner().is_abort(),
+
+            HyperBlock::SwitchBlocks { cases, default } => {
+                cases.iter().all(|case| case.inner().is_abort()) && default.inner().is_abort()
+            }
         }
     }
 
@@ -324,6 +407,11 @@ impl<BlockIdentifier: BlockIdentifierTrait, BlockContent: BlockContentTrait>
                 unconditional,
                 ..
             } => (*unconditional && inner.inner().is_terminated()) || outer.inner().is_terminated(),
+
+            HyperBlock::SwitchBlocks { cases, default } => {
+                cases.iter().all(|case| case.inner().is_terminated())
+                    && default.inner().is_terminated()
+            }
         }
     }
 
@@ -347,6 +435,13 @@ impl<BlockIdentifier: BlockIdentifierTrait, BlockContent: BlockContentTrait>
             }
 
             HyperBlock::WhileBlocks { outer, .. } => outer.inner().is_terminated_in_loop(),
+
+            HyperBlock::SwitchBlocks { cases, default } => {
+                cases
+                    .iter()
+                    .all(|case| case.inner().is_terminated_in_loop())
+                    && default.inner().is_terminated_in_loop()
+            }
         }
     }
 
@@ -381,6 +476,8 @@ impl<BlockIdentifier: BlockIdentifierTrait, BlockContent: BlockContentTrait>
             }
 
             HyperBlock::WhileBlocks { outer, .. } => outer.inner().terminator(),
+
+            HyperBlock::SwitchBlocks { .. } => None,
         }
     }
 }
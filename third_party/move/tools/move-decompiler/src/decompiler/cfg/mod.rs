@@ -1,6 +1,7 @@
 // Copyright (c) Verichains, 2023
 
 pub mod algo;
+pub mod builder;
 
 pub type StacklessBlockIdentifier = usize;
 pub type StacklessBlockContent = algo::blocks_stackless::StacklessBlockContent;
@@ -1,13 +1,14 @@
 // Copyright (c) Verichains, 2023
 
-use std::collections::{BTreeMap, BTreeSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 
-use move_stackless_bytecode::stackless_bytecode::{Bytecode, Label};
+use move_model::ast::TempIndex;
+use move_stackless_bytecode::stackless_bytecode::{Bytecode, Label, Operation};
 
 use super::{
     algo::{
         self,
-        blocks_stackless::{AnnotatedBytecodeData, StacklessBlockContent},
+        blocks_stackless::{AnnotatedBytecode, AnnotatedBytecodeData, StacklessBlockContent},
     },
     datastructs::*,
     metadata::{WithMetadata, WithMetadataExt},
@@ -16,6 +17,16 @@ use super::{
 pub fn decompile(
     insts: &[Bytecode],
 ) -> Result<WithMetadata<CodeUnitBlock<usize, StacklessBlockContent>>, anyhow::Error> {
+    let blocks = build_blocks(insts)?;
+    let blocks = reconstruct_loops(blocks)?;
+    structure(blocks)
+}
+
+/// Split the bytecode into topologically sorted basic blocks, labels are rewritten to
+/// block indices
+pub fn build_blocks(
+    insts: &[Bytecode],
+) -> Result<Vec<BasicBlock<usize, StacklessBlockContent>>, anyhow::Error> {
     let blocks: Vec<BasicBlock<usize, StacklessBlockContent>> =
         algo::blocks_stackless::split_basic_blocks_stackless_bytecode(insts)
             .map_err(|e| anyhow::anyhow!("Unable to split into basic blocks: {}", e))?;
@@ -26,12 +37,27 @@ pub fn decompile(
     cleanup_dummy_dispatch_blocks(&mut blocks)?;
     rewrite_labels(&mut blocks)?;
 
+    Ok(blocks)
+}
+
+/// Turn the loops of the blocks returned by `build_blocks` into while/break/continue terminators
+pub fn reconstruct_loops(
+    mut blocks: Vec<BasicBlock<usize, StacklessBlockContent>>,
+) -> Result<Vec<BasicBlock<usize, StacklessBlockContent>>, anyhow::Error> {
     algo::loop_reconstruction::loop_reconstruction(&mut blocks)?;
 
     let mut blocks = algo::topo::topo_sort(blocks)?;
 
     rewrite_labels(&mut blocks)?;
 
+    Ok(blocks)
+}
+
+/// Group the blocks returned by `reconstruct_loops` into if/else and while regions
+pub fn structure(
+    mut blocks: Vec<BasicBlock<usize, StacklessBlockContent>>,
+) -> Result<WithMetadata<CodeUnitBlock<usize, StacklessBlockContent>>, anyhow::Error> {
+    normalize_branch_conditions(&mut blocks);
     annotate_jumps(&mut blocks)?;
     annotate_short_circuit_jumps(&mut blocks)?;
 
@@ -82,7 +108,8 @@ fn annotate_jumps(
             | Terminator::Ret
             | Terminator::Abort
             | Terminator::IfElse { .. }
-            | Terminator::Branch { .. } => {}
+            | Terminator::Branch { .. }
+            | Terminator::Switch { .. } => {}
         }
     }
 
@@ -410,6 +437,126 @@ fn debug_dump_blocks(blocks: &[BasicBlock<usize, StacklessBlockContent>]) {
     }
 }
 
+fn temp_reads(bytecode: &Bytecode) -> Vec<TempIndex> {
+    match bytecode {
+        Bytecode::Assign(_, _, src, _) => vec![*src],
+        Bytecode::Call(_, _, _, srcs, _) | Bytecode::Ret(_, srcs) => srcs.clone(),
+        Bytecode::Branch(_, _, _, cond) => vec![*cond],
+        Bytecode::Abort(_, code) => vec![*code],
+        _ => vec![],
+    }
+}
+
+fn temp_writes(bytecode: &Bytecode) -> Vec<TempIndex> {
+    match bytecode {
+        Bytecode::Assign(_, dst, _, _) | Bytecode::Load(_, dst, _) => vec![*dst],
+        Bytecode::Call(_, dsts, _, _, _) => dsts.clone(),
+        _ => vec![],
+    }
+}
+
+fn inverted_comparison(op: &Operation) -> Option<Operation> {
+    match op {
+        Operation::Eq => Some(Operation::Neq),
+        Operation::Neq => Some(Operation::Eq),
+        Operation::Lt => Some(Operation::Ge),
+        Operation::Ge => Some(Operation::Lt),
+        Operation::Gt => Some(Operation::Le),
+        Operation::Le => Some(Operation::Gt),
+        _ => None,
+    }
+}
+
+/// Rewrite branch conditions computed with `Not` so they read as written: `!!x` becomes `x`
+/// and `!(a == b)` becomes `a != b` (same for the other comparisons).
+/// Only temporaries written and read exactly once are rewritten, and only within a block.
+pub fn normalize_branch_conditions(blocks: &mut [BasicBlock<usize, StacklessBlockContent>]) {
+    let mut reads = HashMap::<TempIndex, usize>::new();
+    let mut writes = HashMap::<TempIndex, usize>::new();
+    for bytecode in blocks.iter().flat_map(|block| block.content.code.iter()) {
+        for temp in temp_reads(&bytecode.bytecode) {
+            *reads.entry(temp).or_default() += 1;
+        }
+        for temp in temp_writes(&bytecode.bytecode) {
+            *writes.entry(temp).or_default() += 1;
+        }
+    }
+    // the rewrites only drop temporaries or move a read, the counts stay valid
+    let single_use =
+        |temp: TempIndex| reads.get(&temp) == Some(&1) && writes.get(&temp) == Some(&1);
+
+    for block in blocks.iter_mut() {
+        while normalize_branch_condition(&mut block.content.code, &single_use) {}
+    }
+}
+
+/// One rewrite of `normalize_branch_conditions`, false if there is nothing to rewrite
+fn normalize_branch_condition(
+    code: &mut Vec<AnnotatedBytecode>,
+    single_use: &impl Fn(TempIndex) -> bool,
+) -> bool {
+    let branch_pos = match code
+        .iter()
+        .rposition(|x| matches!(x.bytecode, Bytecode::Branch(..)))
+    {
+        Some(pos) => pos,
+        None => return false,
+    };
+    let cond = match code[branch_pos].bytecode {
+        Bytecode::Branch(_, _, _, cond) => cond,
+        _ => unreachable!(),
+    };
+    let definition = |temp: TempIndex| {
+        code[..branch_pos]
+            .iter()
+            .rposition(|x| temp_writes(&x.bytecode).contains(&temp))
+    };
+
+    if !single_use(cond) {
+        return false;
+    }
+    let not_pos = match definition(cond) {
+        Some(pos) => pos,
+        None => return false,
+    };
+    let negated = match &code[not_pos].bytecode {
+        Bytecode::Call(_, _, Operation::Not, srcs, None) if single_use(srcs[0]) => srcs[0],
+        _ => return false,
+    };
+    let inner_pos = match definition(negated) {
+        Some(pos) if pos < not_pos => pos,
+        _ => return false,
+    };
+
+    if let Bytecode::Call(_, dsts, op, _, None) = &mut code[inner_pos].bytecode {
+        if let Some(inverted) = inverted_comparison(op) {
+            *op = inverted;
+            dsts[0] = cond;
+            code.remove(not_pos);
+            return true;
+        }
+    }
+
+    if let Bytecode::Call(_, _, Operation::Not, srcs, None) = &code[inner_pos].bytecode {
+        let value = srcs[0];
+        // `value` is now read at the branch, it must not change in between
+        if code[inner_pos + 1..branch_pos]
+            .iter()
+            .any(|x| temp_writes(&x.bytecode).contains(&value))
+        {
+            return false;
+        }
+        if let Bytecode::Branch(_, _, _, cond) = &mut code[branch_pos].bytecode {
+            *cond = value;
+        }
+        code.remove(not_pos);
+        code.remove(inner_pos);
+        return true;
+    }
+
+    false
+}
+
 fn cleanup_tail_jumps_for_terminated_blocks(
     blocks: &mut [BasicBlock<usize, StacklessBlockContent>],
 ) -> Result<(), anyhow::Error> {
@@ -615,28 +762,31 @@ fn cleanup_dummy_dispatch_blocks(
 
         // update the jump target
         for block in blocks.iter_mut() {
-            match block.next {
-                Terminator::Branch { mut target } => {
-                    if target == from_block {
-                        target = to_block;
+            match &mut block.next {
+                Terminator::Branch { target } => {
+                    if *target == from_block {
+                        *target = to_block;
                     }
-                    block.next = Terminator::Branch { target };
                 }
 
                 Terminator::IfElse {
-                    mut if_block,
-                    mut else_block,
+                    if_block,
+                    else_block,
                 } => {
-                    if if_block == from_block {
-                        if_block = to_block;
+                    if *if_block == from_block {
+                        *if_block = to_block;
                     }
-                    if else_block == from_block {
-                        else_block = to_block;
+                    if *else_block == from_block {
+                        *else_block = to_block;
+                    }
+                }
+
+                Terminator::Switch { targets, default } => {
+                    for target in targets.iter_mut().chain(std::iter::once(default)) {
+                        if *target == from_block {
+                            *target = to_block;
+                        }
                     }
-                    block.next = Terminator::IfElse {
-                        if_block,
-                        else_block,
-                    };
                 }
 
                 Terminator::Break { .. }
@@ -766,6 +916,12 @@ fn trim_else(
                 trim_else(outer.as_mut(), in_loop, current_is_last_block_in_loop);
                 new_blocks.push(block.clone());
             }
+            HyperBlock::SwitchBlocks { cases, default } => {
+                for unit in cases.iter_mut().chain(std::iter::once(default)) {
+                    trim_else(unit.as_mut(), in_loop, current_is_last_block_in_loop);
+                }
+                new_blocks.push(block.clone());
+            }
         }
     }
     program.inner_mut().blocks = new_blocks;
@@ -817,6 +973,12 @@ fn apply_short_circuit_jumps(
                 apply_short_circuit_jumps(inner);
                 apply_short_circuit_jumps(outer);
             }
+
+            HyperBlock::SwitchBlocks { cases, default } => {
+                for unit in cases.iter_mut().chain(std::iter::once(default)) {
+                    apply_short_circuit_jumps(unit);
+                }
+            }
         }
     }
 }
@@ -913,6 +1075,13 @@ fn cleanup_jumps(
                 );
                 cleanup_jumps(&mut outer.inner_mut().blocks, &labels, &loop_labels);
             }
+
+            HyperBlock::SwitchBlocks { cases, default } => {
+                // all cases are disjoint, cleanup each of them
+                for unit in cases.iter_mut().chain(std::iter::once(default)) {
+                    cleanup_jumps(&mut unit.inner_mut().blocks, &labels, &loop_labels);
+                }
+            }
         }
     }
 }
@@ -1021,6 +1190,16 @@ fn build_program(
                         )?;
                         p.inner_mut().blocks.push(paths);
                     }
+
+                    Terminator::Switch {
+                        ref targets,
+                        default,
+                    } => {
+                        chaining_blocks.push(node.clone().with_metadata());
+                        flush(&mut chaining_blocks, &mut p);
+                        let paths = follow_switch_boundaries(&mut iter, targets, default)?;
+                        p.inner_mut().blocks.push(paths);
+                    }
                 }
             }
         } else {
@@ -1153,3 +1332,54 @@ fn follow_ifelse_boundaries(
     }
     .with_metadata())
 }
+
+fn follow_switch_boundaries(
+    iter: &mut core::slice::Iter<BasicBlock<usize, StacklessBlockContent>>,
+    targets: &[usize],
+    default: usize,
+) -> Result<WithMetadata<HyperBlock<usize, StacklessBlockContent>>, anyhow::Error> {
+    // one path per case, the default path is the last one
+    let mut paths = targets
+        .iter()
+        .chain(std::iter::once(&default))
+        .map(|&target| BTreeSet::from([target]))
+        .collect::<Vec<_>>();
+    let mut nodes = vec![Vec::new(); paths.len()];
+
+    loop {
+        let backup_iter = iter.clone();
+        if let Some(n) = iter.next() {
+            let in_paths = paths
+                .iter()
+                .enumerate()
+                .filter(|(_, path)| path.contains(&n.idx))
+                .map(|(idx, _)| idx)
+                .collect::<Vec<_>>();
+
+            if in_paths.len() > 1 {
+                // some paths are merged, this instruction is not in the switch structure
+                *iter = backup_iter;
+                break;
+            }
+
+            if let Some(&path_idx) = in_paths.first() {
+                nodes[path_idx].push(n.clone());
+                paths[path_idx].extend(n.next.next_blocks().iter().copied());
+            }
+        } else {
+            break;
+        }
+    }
+
+    let mut programs = nodes
+        .iter()
+        .map(|nodes| build_program(nodes.iter(), false).map(Box::new))
+        .collect::<Result<Vec<_>, _>>()?;
+    let default_program = programs.pop().unwrap();
+
+    Ok(HyperBlock::SwitchBlocks {
+        cases: programs,
+        default: default_program,
+    }
+    .with_metadata())
+}
@@ -0,0 +1,80 @@
+// Copyright (c) Verichains, 2023
+
+use std::collections::BTreeSet;
+
+#[cfg(feature = "remote")]
+use anyhow::{Context, Result};
+use move_binary_format::{
+    access::{ModuleAccess, ScriptAccess},
+    binary_views::BinaryIndexedView,
+    file_format::CompiledModule,
+};
+use move_core_types::language_storage::ModuleId;
+#[cfg(feature = "remote")]
+use serde::Deserialize;
+
+/// Modules called by the `binaries` that are neither among them nor in `dependencies`
+pub fn missing_dependencies(
+    binaries: &[BinaryIndexedView],
+    dependencies: &[CompiledModule],
+) -> Vec<ModuleId> {
+    let known = binaries
+        .iter()
+        .filter_map(|binary| binary.self_id())
+        .chain(dependencies.iter().map(|module| module.self_id()))
+        .collect::<BTreeSet<_>>();
+    binaries
+        .iter()
+        .flat_map(|binary| match binary {
+            BinaryIndexedView::Module(module) => module.immediate_dependencies(),
+            BinaryIndexedView::Script(script) => script.immediate_dependencies(),
+        })
+        .filter(|id| !known.contains(id))
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect()
+}
+
+/// Aptos node REST API serving published modules, e.g.
+/// `https://fullnode.mainnet.aptoslabs.com/v1`
+#[cfg(feature = "remote")]
+pub struct NodeClient {
+    url: String,
+    client: reqwest::blocking::Client,
+}
+
+#[cfg(feature = "remote")]
+#[derive(Deserialize)]
+struct MoveModuleBytecode {
+    /// hex with `0x` prefix
+    bytecode: String,
+}
+
+#[cfg(feature = "remote")]
+impl NodeClient {
+    pub fn new(url: String) -> Self {
+        Self {
+            url: url.trim_end_matches('/').to_string(),
+            client: reqwest::blocking::Client::new(),
+        }
+    }
+
+    pub fn fetch_module(&self, id: &ModuleId) -> Result<CompiledModule> {
+        let url = format!(
+            "{}/accounts/{}/module/{}",
+            self.url,
+            id.address().to_hex_literal(),
+            id.name()
+        );
+        let response = self
+            .client
+            .get(&url)
+            .send()?
+            .error_for_status()?
+            .json::<MoveModuleBytecode>()?;
+        let bytes = hex::decode(response.bytecode.trim_start_matches("0x"))
+            .with_context(|| format!("invalid bytecode returned by {}", url))?;
+        CompiledModule::deserialize(&bytes)
+            .with_context(|| format!("invalid module returned by {}", url))
+    }
+}
@@ -0,0 +1,72 @@
+// Copyright (c) Verichains, 2023
+
+use std::fmt::Display;
+
+use serde::Serialize;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    /// the output is usable but may be wrong or incomplete at this place
+    Warning,
+    /// the output is a stub at this place
+    Error,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiagnosticKind {
+    /// the stackless bytecode generator found an inconsistent operand stack
+    StackInconsistency,
+    /// the function body could not be reconstructed and was replaced by a stub
+    DecompilationFailed,
+    /// the reconstruction fell back to a guess left as a comment, e.g. `// possible: ...`
+    Heuristic,
+    /// the aptos metadata of the module could not be decoded and was ignored
+    UnreadableMetadata,
+}
+
+/// Issue found during a `decompile` call, the JSON form is written by `--diagnostics`
+#[derive(Clone, Debug, Serialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub kind: DiagnosticKind,
+    pub module: String,
+    /// full name of the function, `None` for issues of the module itself
+    pub function: Option<String>,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn new(
+        kind: DiagnosticKind,
+        module: String,
+        function: Option<String>,
+        message: String,
+    ) -> Self {
+        let severity = match kind {
+            DiagnosticKind::DecompilationFailed => Severity::Error,
+            DiagnosticKind::StackInconsistency
+            | DiagnosticKind::Heuristic
+            | DiagnosticKind::UnreadableMetadata => Severity::Warning,
+        };
+        Self {
+            severity,
+            kind,
+            module,
+            function,
+            message,
+        }
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: {}",
+            self.function.as_ref().unwrap_or(&self.module),
+            self.message
+        )
+    }
+}
@@ -26,7 +26,7 @@ pub enum ExprNodeOperation {
     LocalVariable(usize),
     Field(ExprNodeRef, String),
     Unary(String, ExprNodeRef),
-    Cast(String, ExprNodeRef),
+    Cast(String, ExprNodeRef, /* widening */ bool),
     Binary(String, ExprNodeRef, ExprNodeRef),
     Func(String, Vec<ExprNodeRef>, Vec<Type>),
 
@@ -60,11 +60,26 @@ pub enum ExprNodeOperation {
 #[derive(Clone, Debug)]
 struct ToSourceCtx {
     in_borrow: bool,
+    // the value is used as text: argument of `string::utf8` or field of an emitted event
+    in_text: bool,
 }
 
 impl ToSourceCtx {
     fn default() -> Self {
-        Self { in_borrow: false }
+        Self {
+            in_borrow: false,
+            in_text: false,
+        }
+    }
+}
+
+/// Functions whose byte vector arguments are text
+fn is_text_function(name: &str) -> bool {
+    let parts = name.rsplit("::").collect::<Vec<_>>();
+    match parts.as_slice() {
+        [function, "string", ..] => *function == "utf8" || *function == "try_utf8",
+        [function, "event", ..] => *function == "emit" || *function == "emit_event",
+        _ => false,
     }
 }
 
@@ -117,8 +132,8 @@ impl ExprNodeOperation {
             ExprNodeOperation::Unary(op, arg) => {
                 ExprNodeOperation::Unary(op.clone(), arg.borrow().copy_as_ref())
             }
-            ExprNodeOperation::Cast(op, arg) => {
-                ExprNodeOperation::Cast(op.clone(), arg.borrow().copy_as_ref())
+            ExprNodeOperation::Cast(op, arg, widening) => {
+                ExprNodeOperation::Cast(op.clone(), arg.borrow().copy_as_ref(), *widening)
             }
             ExprNodeOperation::Binary(op, lhs, rhs) => ExprNodeOperation::Binary(
                 op.clone(),
@@ -202,15 +217,15 @@ impl ExprNodeOperation {
             )
         }
     }
-    fn const_to_source(val: &Constant) -> Result<String, anyhow::Error> {
+    fn const_to_source(val: &Constant, naming: &Naming) -> Result<String, anyhow::Error> {
         match val {
             Constant::Bool(v) => Ok(format!("{}", v)),
-            Constant::U8(x) => Ok(format!("{}", x)),
-            Constant::U16(x) => Ok(format!("{}", x)),
-            Constant::U32(x) => Ok(format!("{}", x)),
-            Constant::U64(x) => Ok(format!("{}", x)),
-            Constant::U128(x) => Ok(format!("{}", x)),
-            Constant::U256(x) => Ok(format!("{}", x)),
+            Constant::U8(x) => Ok(naming.integer_literal(format!("{}", x), format!("{:x}", x))),
+            Constant::U16(x) => Ok(naming.integer_literal(format!("{}", x), format!("{:x}", x))),
+            Constant::U32(x) => Ok(naming.integer_literal(format!("{}", x), format!("{:x}", x))),
+            Constant::U64(x) => Ok(naming.integer_literal(format!("{}", x), format!("{:x}", x))),
+            Constant::U128(x) => Ok(naming.integer_literal(format!("{}", x), format!("{:x}", x))),
+            Constant::U256(x) => Ok(naming.integer_literal(format!("{}", x), format!("{:x}", x))),
             Constant::Address(x) => match x {
                 move_model::ast::Address::Numerical(val) => {
                     Ok(format!("@{}", val.to_hex_literal()))
@@ -219,43 +234,52 @@ impl ExprNodeOperation {
                     unreachable!("There must be no symbolic address in compiled binary")
                 }
             },
-            Constant::ByteArray(v) => {
-                let is_safe = v.iter().all(|x| *x >= 0x20 && *x <= 0x7e);
-                if is_safe {
-                    Ok(format!(
-                        "b\"{}\"",
-                        v.iter()
-                            .map(|x| *x as char)
-                            .collect::<String>()
-                            .replace("\\", "\\\\")
-                            .replace("\"", "\\\"")
-                    ))
-                } else {
-                    Ok(format!(
-                        "x\"{}\"",
-                        v.iter()
-                            .map(|x| format!("{:02x}", x))
-                            .collect::<Vec<_>>()
-                            .join(""),
-                    ))
-                }
-            }
+            Constant::ByteArray(v) => Ok(Self::byte_array_to_source(v, false)),
             Constant::AddressArray(v) => Ok(format!(
                 "vector[{}]",
                 v.iter()
-                    .map(|x| Self::const_to_source(&Constant::Address(x.clone())))
+                    .map(|x| Self::const_to_source(&Constant::Address(x.clone()), naming))
                     .collect::<Result<Vec<_>, _>>()?
                     .join(", "),
             )),
             Constant::Vector(v) => Ok(format!(
                 "vector[{}]",
                 v.iter()
-                    .map(|x| Self::const_to_source(x))
+                    .map(|x| Self::const_to_source(x, naming))
                     .collect::<Result<Vec<_>, _>>()?
                     .join(", "),
             )),
         }
     }
+    /// `b"..."` if printable or used as text, escaping the other bytes, `x"..."` otherwise
+    fn byte_array_to_source(v: &[u8], as_text: bool) -> String {
+        let is_safe = v.iter().all(|x| *x >= 0x20 && *x <= 0x7e);
+        if !is_safe && !as_text {
+            return format!(
+                "x\"{}\"",
+                v.iter()
+                    .map(|x| format!("{:02x}", x))
+                    .collect::<Vec<_>>()
+                    .join(""),
+            );
+        }
+        let mut result = String::from("b\"");
+        for x in v {
+            match *x {
+                b'\\' => result.push_str("\\\\"),
+                b'"' => result.push_str("\\\""),
+                b'\n' => result.push_str("\\n"),
+                b'\r' => result.push_str("\\r"),
+                b'\t' => result.push_str("\\t"),
+                b'\0' => result.push_str("\\0"),
+                0x20..=0x7e => result.push(*x as char),
+                _ => result.push_str(&format!("\\x{:02x}", x)),
+            }
+        }
+        result.push('"');
+        result
+    }
+
     pub fn to_source_decl(&self, naming: &Naming) -> Result<String, anyhow::Error> {
         match self {
             ExprNodeOperation::StructPack(name, args, types) => {
@@ -291,6 +315,8 @@ impl ExprNodeOperation {
         ctx: &ToSourceCtx,
     ) -> Result<String, anyhow::Error> {
         let mut ctx = ctx.clone();
+        // only the operands of a text function and the fields they pack are text
+        let in_text = std::mem::replace(&mut ctx.in_text, false);
         if ctx.in_borrow {
             match self {
                 ExprNodeOperation::BorrowLocal(..) => {}
@@ -307,7 +333,10 @@ impl ExprNodeOperation {
             ExprNodeOperation::Deleted => Ok("<<< !!! deleted !!! >>>".to_string()),
             ExprNodeOperation::NonTrivial => Ok("!!non-trivial!!".to_string()),
             ExprNodeOperation::Raw(x) => Ok(format!("((/*raw:*/{}))", x)),
-            ExprNodeOperation::Const(c) => Self::const_to_source(c),
+            ExprNodeOperation::Const(Constant::ByteArray(v)) => {
+                Ok(Self::byte_array_to_source(v, in_text))
+            }
+            ExprNodeOperation::Const(c) => Self::const_to_source(c, naming),
             ExprNodeOperation::Field(expr, name) => {
                 // &(&object).field -> & object.field
                 if ctx.in_borrow {
@@ -340,25 +369,69 @@ impl ExprNodeOperation {
                 op,
                 bracket_if_binary_with_ctx(expr, Some(naming), &ctx)?
             )),
-            ExprNodeOperation::Cast(ty, expr) => Ok(format!(
+            // casts from a strictly narrower type only matter to the type checker
+            ExprNodeOperation::Cast(_, expr, true) if naming.is_pseudocode() => {
+                expr.borrow().to_source_with_ctx(naming, &ctx)
+            }
+            ExprNodeOperation::Cast(ty, expr, _) => Ok(format!(
                 "{} as {}",
                 bracket_if_binary_with_ctx(expr, Some(naming), &ctx)?,
                 ty
             )),
             ExprNodeOperation::Binary(op, a, b) => {
-                let a_str = check_bracket_for_binary(a, get_precedence(op), Some(naming), &ctx)?;
-                let b_str = check_bracket_for_binary(b, get_precedence(op), Some(naming), &ctx)?;
+                let operand = |expr: &ExprNodeRef| match &expr.borrow().operation {
+                    ExprNodeOperation::Const(c) if op == "&" || op == "|" => naming.bit_flag(c),
+                    _ => None,
+                };
+                let a_str = match operand(a) {
+                    Some(flag) => flag.to_string(),
+                    None => check_bracket_for_binary(a, get_precedence(op), Some(naming), &ctx)?,
+                };
+                let b_str = match operand(b) {
+                    Some(flag) => flag.to_string(),
+                    None => check_bracket_for_binary(b, get_precedence(op), Some(naming), &ctx)?,
+                };
                 Ok(format!("{} {} {}", a_str, op, b_str))
             }
-            ExprNodeOperation::Func(name, args, types) => Ok(format!(
-                "{}{}({})",
-                name,
-                Self::typeparams_to_source(types, naming),
-                args.iter()
-                    .map(|x| x.borrow().to_source_with_ctx(naming, &ctx))
-                    .collect::<Result<Vec<String>, anyhow::Error>>()?
-                    .join(", ")
-            )),
+            ExprNodeOperation::Func(name, args, types)
+                if naming
+                    .accessor(name)
+                    .map_or(false, |accessor| accessor.arity() == args.len()) =>
+            {
+                // &object.field is the struct reference of an accessor call
+                let object = match &args[0].borrow().operation {
+                    ExprNodeOperation::BorrowLocal(inner, _) => {
+                        inner.borrow().to_source_with_ctx(naming, &ctx)?
+                    }
+                    _ => bracket_if_binary_with_ctx(&args[0], Some(naming), &ctx)?,
+                };
+                let value = args
+                    .get(1)
+                    .map(|value| value.borrow().to_source_with_ctx(naming, &ctx))
+                    .transpose()?;
+                Ok(naming
+                    .accessor(name)
+                    .unwrap()
+                    .fold(&object, value.as_deref()))
+            }
+            ExprNodeOperation::Func(name, args, types) => {
+                ctx.in_text = is_text_function(name);
+                // abort codes are left out of pseudocode
+                let args = if naming.is_pseudocode() && name == "assert!" {
+                    &args[..1]
+                } else {
+                    &args[..]
+                };
+                Ok(format!(
+                    "{}{}({})",
+                    name,
+                    Self::typeparams_to_source(types, naming),
+                    args.iter()
+                        .map(|x| x.borrow().to_source_with_ctx(naming, &ctx))
+                        .collect::<Result<Vec<String>, anyhow::Error>>()?
+                        .join(", ")
+                ))
+            }
             ExprNodeOperation::Destroy(expr) => Ok(format!(
                 "/*destroyed:{}*/",
                 expr.borrow().to_source_with_ctx(naming, &ctx)?
@@ -399,19 +472,22 @@ impl ExprNodeOperation {
                 ExprNodeOperation::ReadRef(lhs.clone()).to_source_with_ctx(naming, &ctx)?,
                 rhs.borrow().to_source_with_ctx(naming, &ctx)?
             )),
-            ExprNodeOperation::StructPack(name, args, types) => Ok(format!(
-                "{}{}{{{}}}",
-                name,
-                Self::typeparams_to_source(types, naming),
-                args.iter()
-                    .map(|x| x
-                        .1
-                        .borrow()
-                        .to_source_with_ctx(naming, &ctx)
-                        .and_then(|v| Ok(format!("{}: {}", x.0, v))))
-                    .collect::<Result<Vec<_>, _>>()?
-                    .join(", ")
-            )),
+            ExprNodeOperation::StructPack(name, args, types) => {
+                ctx.in_text = in_text;
+                Ok(format!(
+                    "{}{}{{{}}}",
+                    name,
+                    Self::typeparams_to_source(types, naming),
+                    args.iter()
+                        .map(|x| x
+                            .1
+                            .borrow()
+                            .to_source_with_ctx(naming, &ctx)
+                            .and_then(|v| Ok(format!("{}: {}", x.0, v))))
+                        .collect::<Result<Vec<_>, _>>()?
+                        .join(", ")
+                ))
+            }
             ExprNodeOperation::StructUnpack(name, keys, val, types) => Ok(format!(
                 "{}{}{{{}}} = {}",
                 name,
@@ -423,6 +499,7 @@ impl ExprNodeOperation {
                 val.borrow().to_source_with_ctx(naming, &ctx)?
             )),
             ExprNodeOperation::VariableSnapshot { value, .. } => {
+                ctx.in_text = in_text;
                 value.borrow().to_source_with_ctx(naming, &ctx)
             }
         }
@@ -457,7 +534,7 @@ impl ExprNodeOperation {
                 implicit_variables,
                 in_implicit_expr,
             ),
-            ExprNodeOperation::Cast(_, expr) => expr.borrow().collect_variables(
+            ExprNodeOperation::Cast(_, expr, _) => expr.borrow().collect_variables(
                 result_variables,
                 implicit_variables,
                 in_implicit_expr,
@@ -546,7 +623,7 @@ impl ExprNodeOperation {
                 .borrow()
                 .operation
                 .has_reference_to_any_variable(variables),
-            ExprNodeOperation::Cast(_, expr) => expr
+            ExprNodeOperation::Cast(_, expr, _) => expr
                 .borrow()
                 .operation
                 .has_reference_to_any_variable(variables),
@@ -637,7 +714,7 @@ impl ExprNodeOperation {
             }
             ExprNodeOperation::Field(expr, _)
             | ExprNodeOperation::Unary(_, expr)
-            | ExprNodeOperation::Cast(_, expr)
+            | ExprNodeOperation::Cast(_, expr, _)
             | ExprNodeOperation::Destroy(expr)
             | ExprNodeOperation::FreezeRef(expr)
             | ExprNodeOperation::ReadRef(expr)
@@ -674,9 +751,10 @@ impl ExprNodeOperation {
                 expr.borrow().commit_pending_variables(variables),
             )
             .to_node(),
-            ExprNodeOperation::Cast(typ, expr) => ExprNodeOperation::Cast(
+            ExprNodeOperation::Cast(typ, expr, widening) => ExprNodeOperation::Cast(
                 typ.clone(),
                 expr.borrow().commit_pending_variables(variables),
+                *widening,
             )
             .to_node(),
             ExprNodeOperation::Binary(op, left, right) => ExprNodeOperation::Binary(
@@ -842,7 +920,7 @@ impl Display for ExprNodeOperation {
                     bracket_if_binary_with_ctx(expr, None, &ToSourceCtx::default()).unwrap()
                 )
             }
-            ExprNodeOperation::Cast(op, expr) => {
+            ExprNodeOperation::Cast(op, expr, _) => {
                 write!(
                     f,
                     "{} as {}",
@@ -1413,6 +1491,7 @@ impl<'a> StacklessEvaluationContext<'a> {
     pub fn run(
         &mut self,
         bytecode: &Bytecode,
+        src_types: &Vec<Option<ReturnValueHint>>,
         dst_types: &Vec<Option<ReturnValueHint>>,
     ) -> Result<StacklessEvaluationRunResult, anyhow::Error> {
         if self.last_branch_expr.is_some() {
@@ -1442,6 +1521,7 @@ impl<'a> StacklessEvaluationContext<'a> {
                         .iter()
                         .map(|x| self.get_var_with_allow_undefined(*x, allow_undefined))
                         .collect(),
+                    src_types,
                     dst_types,
                 )?;
 
@@ -1555,7 +1635,23 @@ impl<'a> StacklessEvaluationContext<'a> {
                 if self.defined(dst) && self.get_var(dst).is_flushed() {
                     flushed_variables.insert(dst);
                 }
-                let expr = ExprNodeOperation::Const(value.clone()).to_expr();
+                let expr = match (value, dst_types.first()) {
+                    // the element type of an empty vector constant is only in its destination
+                    (Constant::Vector(elements), Some(Some(ReturnValueHint { ty })))
+                        if elements.is_empty() =>
+                    {
+                        let Type::Vector(element_type) = ty else {
+                            return Err(anyhow::anyhow!("vector constant of type {:?}", ty));
+                        };
+                        ExprNodeOperation::Func(
+                            "0x1::vector::empty".to_string(),
+                            vec![],
+                            vec![*element_type.clone()],
+                        )
+                        .to_expr()
+                    }
+                    _ => ExprNodeOperation::Const(value.clone()).to_expr(),
+                };
                 if self.run_assignment(dst, expr.copy()) {
                     new_variables.insert(dst);
                 }
@@ -2,11 +2,14 @@
 
 use std::mem::MaybeUninit;
 
-use move_model::ty::Type;
-use move_stackless_bytecode::stackless_bytecode::Operation;
+use move_model::ty::{PrimitiveType, Type};
+use move_stackless_bytecode::stackless_bytecode::{HavocKind, Operation};
 
 use super::{Expr, ExprNodeOperation, ExprNodeRef, ReturnValueHint, StacklessEvaluationContext};
 
+/// Value of an operand missing from the stack of malformed bytecode
+const HAVOC_MARKER: &str = "__havoc__";
+
 pub struct OperationEvaluatorResult {
     pub cannot_keep: bool,
     pub expr: Expr,
@@ -17,6 +20,7 @@ pub trait OperationEvaluator {
         &self,
         ctx: &StacklessEvaluationContext,
         args: &Vec<Expr>,
+        src_types: &Vec<Option<ReturnValueHint>>,
         dst_types: &Vec<Option<ReturnValueHint>>,
     ) -> Result<OperationEvaluatorResult, anyhow::Error>;
 }
@@ -26,6 +30,7 @@ impl OperationEvaluator for &Operation {
         &self,
         ctx: &StacklessEvaluationContext,
         args: &Vec<Expr>,
+        src_types: &Vec<Option<ReturnValueHint>>,
         dst_types: &Vec<Option<ReturnValueHint>>,
     ) -> Result<OperationEvaluatorResult, anyhow::Error> {
         fn is_mutable_reference(
@@ -242,12 +247,12 @@ impl OperationEvaluator for &Operation {
 
             Operation::Vector => todo!(),
 
-            Operation::CastU8 => cast("u8", args),
-            Operation::CastU16 => cast("u16", args),
-            Operation::CastU32 => cast("u32", args),
-            Operation::CastU64 => cast("u64", args),
-            Operation::CastU128 => cast("u128", args),
-            Operation::CastU256 => cast("u256", args),
+            Operation::CastU8 => cast("u8", args, src_types),
+            Operation::CastU16 => cast("u16", args, src_types),
+            Operation::CastU32 => cast("u32", args, src_types),
+            Operation::CastU64 => cast("u64", args, src_types),
+            Operation::CastU128 => cast("u128", args, src_types),
+            Operation::CastU256 => cast("u256", args, src_types),
             Operation::Not => unary("!", args),
             Operation::Add => binary("+", args),
             Operation::Sub => binary("-", args),
@@ -273,6 +278,13 @@ impl OperationEvaluator for &Operation {
                 Err(anyhow::anyhow!("OpaqueCall opcode is not supported"))
             }
 
+            // placeholder for a missing operand of malformed bytecode, see `StackDiagnostics`,
+            // kept visibly invalid so that it is not mistaken for code of the input
+            Operation::Havoc(HavocKind::Value) if args.is_empty() => Ok(OperationEvaluatorResult {
+                cannot_keep: false,
+                expr: ExprNodeOperation::Raw(HAVOC_MARKER.to_string()).to_expr(),
+            }),
+
             Operation::TraceLocal(_)
             | Operation::TraceReturn(_)
             | Operation::TraceAbort
@@ -359,17 +371,40 @@ fn unary(arg: &str, args: &Vec<Expr>) -> Result<OperationEvaluatorResult, anyhow
     })
 }
 
-fn cast(ty: &str, args: &Vec<Expr>) -> Result<OperationEvaluatorResult, anyhow::Error> {
+fn cast(
+    ty: &str,
+    args: &Vec<Expr>,
+    src_types: &Vec<Option<ReturnValueHint>>,
+) -> Result<OperationEvaluatorResult, anyhow::Error> {
+    let widening = match (src_types.first(), ty[1..].parse::<usize>()) {
+        (Some(Some(ReturnValueHint { ty: src })), Ok(target)) => {
+            integer_width(src).map_or(false, |width| width < target)
+        }
+        _ => false,
+    };
     Ok(OperationEvaluatorResult {
         cannot_keep: false,
         expr: ExprNodeOperation::Cast(
             ty.to_string(),
             only_one(args, &format!("unary operation '{}'", ty))?,
+            widening,
         )
         .to_expr(),
     })
 }
 
+fn integer_width(ty: &Type) -> Option<usize> {
+    match ty {
+        Type::Primitive(PrimitiveType::U8) => Some(8),
+        Type::Primitive(PrimitiveType::U16) => Some(16),
+        Type::Primitive(PrimitiveType::U32) => Some(32),
+        Type::Primitive(PrimitiveType::U64) => Some(64),
+        Type::Primitive(PrimitiveType::U128) => Some(128),
+        Type::Primitive(PrimitiveType::U256) => Some(256),
+        _ => None,
+    }
+}
+
 fn binary(arg: &str, args: &Vec<Expr>) -> Result<OperationEvaluatorResult, anyhow::Error> {
     let [l, r] = only_n::<2>(args, &format!("binary operation '{}'", arg))?;
     Ok(OperationEvaluatorResult {
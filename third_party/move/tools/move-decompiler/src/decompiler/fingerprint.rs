@@ -0,0 +1,191 @@
+// Copyright (c) Verichains, 2023
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+};
+
+use anyhow::Result;
+use move_model::model::FunctionEnv;
+use move_stackless_bytecode::{
+    function_target::FunctionTarget,
+    stackless_bytecode::{Bytecode, Operation},
+};
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Sha3_256};
+
+use super::cfg;
+
+/// Structural fingerprint of a function, made of the shape of its control flow graph, the
+/// functions it calls and the constants it loads.
+///
+/// Unlike `function_signature` it ignores the order of calls and constants, the names of the
+/// function and of the functions of its own module, so a function keeps its fingerprint
+/// across module upgrades that rename or reorder functions.
+pub fn function_fingerprint(
+    function_env: &FunctionEnv<'_>,
+    function_target: &FunctionTarget<'_>,
+) -> Result<String> {
+    let bytecode = function_target.get_bytecode();
+
+    let blocks = cfg::stackless::build_blocks(bytecode)?;
+    let positions = blocks
+        .iter()
+        .enumerate()
+        .map(|(position, block)| (block.idx(), position))
+        .collect::<HashMap<_, _>>();
+    let shape = blocks
+        .iter()
+        .map(|block| {
+            let targets = block
+                .next
+                .next_blocks()
+                .into_iter()
+                .map(|target| {
+                    positions
+                        .get(target)
+                        .map_or("?".to_string(), |p| p.to_string())
+                })
+                .collect::<Vec<_>>();
+            format!("{}({})", block.next.kind(), targets.join(","))
+        })
+        .collect::<Vec<_>>();
+
+    let own_module = function_env.module_env.get_id();
+    let env = function_env.module_env.env;
+    let mut calls = Vec::new();
+    let mut constants = Vec::new();
+    for instruction in bytecode {
+        match instruction {
+            Bytecode::Call(_, _, Operation::Function(mid, fid, _), _, _) => {
+                calls.push(if *mid == own_module {
+                    "Self".to_string()
+                } else {
+                    env.get_module(*mid)
+                        .get_function(*fid)
+                        .get_full_name_with_address()
+                });
+            }
+            Bytecode::Load(_, _, constant) => constants.push(format!("{:?}", constant)),
+            _ => {}
+        }
+    }
+    calls.sort();
+    constants.sort();
+
+    let mut hasher = Sha3_256::new();
+    hasher.update(format!(
+        "{}\n{}\n{}\n",
+        shape.join(" "),
+        calls.join(" "),
+        constants.join(" ")
+    ));
+    Ok(hex::encode(&hasher.finalize()[..8]))
+}
+
+/// Functions and structs of the decompiled modules, written as JSON by `--symbols`
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SymbolIndex {
+    pub modules: Vec<ModuleSymbols>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ModuleSymbols {
+    /// `address::module`
+    pub name: String,
+    pub functions: Vec<FunctionSymbol>,
+    pub structs: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FunctionSymbol {
+    pub name: String,
+    /// See `function_fingerprint`, `None` for native functions
+    pub fingerprint: Option<String>,
+}
+
+impl SymbolIndex {
+    fn functions(&self) -> impl Iterator<Item = &FunctionSymbol> {
+        self.modules.iter().flat_map(|m| m.functions.iter())
+    }
+
+    /// Changes of the functions since the `old` index, e.g. of an earlier version of the same
+    /// modules. Functions only found on one side are matched by fingerprint and reported as
+    /// renamed when one matches.
+    pub fn diff(&self, old: &SymbolIndex) -> Vec<FunctionChange> {
+        let old_functions = old
+            .functions()
+            .map(|f| (f.name.as_str(), f))
+            .collect::<HashMap<_, _>>();
+        let new_names = self
+            .functions()
+            .map(|f| f.name.as_str())
+            .collect::<HashSet<_>>();
+        let mut removed = old
+            .functions()
+            .filter(|f| !new_names.contains(f.name.as_str()))
+            .collect::<Vec<_>>();
+
+        let mut changes = Vec::new();
+        for function in self.functions() {
+            match old_functions.get(function.name.as_str()) {
+                Some(old) if old.fingerprint != function.fingerprint => {
+                    changes.push(FunctionChange::Modified {
+                        name: function.name.clone(),
+                    });
+                }
+                Some(_) => {}
+                None => {
+                    let renamed_from = removed.iter().position(|old| {
+                        function.fingerprint.is_some() && old.fingerprint == function.fingerprint
+                    });
+                    changes.push(match renamed_from {
+                        Some(position) => FunctionChange::Renamed {
+                            old: removed.remove(position).name.clone(),
+                            new: function.name.clone(),
+                        },
+                        None => FunctionChange::Added {
+                            name: function.name.clone(),
+                        },
+                    });
+                }
+            }
+        }
+        changes.extend(removed.into_iter().map(|f| FunctionChange::Removed {
+            name: f.name.clone(),
+        }));
+        changes
+    }
+}
+
+/// Change of a function between two symbol indexes, see `SymbolIndex::diff`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum FunctionChange {
+    /// same fingerprint under another name
+    Renamed {
+        old: String,
+        new: String,
+    },
+    /// same name, another fingerprint
+    Modified {
+        name: String,
+    },
+    Added {
+        name: String,
+    },
+    Removed {
+        name: String,
+    },
+}
+
+impl Display for FunctionChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FunctionChange::Renamed { old, new } => write!(f, "renamed {} -> {}", old, new),
+            FunctionChange::Modified { name } => write!(f, "modified {}", name),
+            FunctionChange::Added { name } => write!(f, "added {}", name),
+            FunctionChange::Removed { name } => write!(f, "removed {}", name),
+        }
+    }
+}
@@ -0,0 +1,131 @@
+// Copyright (c) Verichains, 2023
+
+/// Rewrite the generated source to follow `movefmt` conventions, so that formatting a
+/// decompiled file does not produce any change:
+/// - no trailing whitespace and no consecutive blank lines
+/// - `fun f(): u64` instead of `fun f() : u64`
+/// - `S { a: x }` instead of `S{a: x}`
+/// - `a: x` instead of the column-aligned `a  : x` in multi-line struct literals/patterns
+pub fn format_source(source: &str) -> String {
+    let mut lines: Vec<String> = Vec::new();
+
+    for line in source.lines() {
+        let line = format_line(line.trim_end());
+        if line.is_empty() && lines.last().map(|l| l.is_empty()).unwrap_or(true) {
+            continue;
+        }
+        lines.push(line);
+    }
+
+    // a closing brace is never preceded by a blank line
+    let mut result: Vec<String> = Vec::new();
+    for line in lines {
+        if line.trim_start().starts_with('}')
+            && result.last().map(|l| l.is_empty()).unwrap_or(false)
+        {
+            result.pop();
+        }
+        result.push(line);
+    }
+    while result.last().map(|l| l.is_empty()).unwrap_or(false) {
+        result.pop();
+    }
+
+    let mut output = result.join("\n");
+    output.push('\n');
+    output
+}
+
+fn format_line(line: &str) -> String {
+    let line = unalign_field(line);
+    let chars = line.chars().collect::<Vec<_>>();
+    let mut out = String::with_capacity(line.len() + 8);
+    // for every open brace of the line, whether it starts an inline struct literal
+    let mut braces: Vec<bool> = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '/' if chars.get(i + 1) == Some(&'/') => {
+                out.extend(&chars[i..]);
+                break;
+            }
+            '/' if chars.get(i + 1) == Some(&'*') => {
+                let end = find_seq(&chars, i + 2, &['*', '/']).map(|e| e + 2);
+                let end = end.unwrap_or(chars.len());
+                out.extend(&chars[i..end]);
+                i = end;
+                continue;
+            }
+            '"' => {
+                let end = string_end(&chars, i + 1);
+                out.extend(&chars[i..end]);
+                i = end;
+                continue;
+            }
+            ' ' if chars.get(i + 1) == Some(&':')
+                && chars.get(i + 2) == Some(&' ')
+                && out.ends_with(')') =>
+            {
+                // return type of a function header
+                i += 1;
+                continue;
+            }
+            '{' => {
+                let prev = out.chars().last();
+                let is_literal = prev
+                    .map(|p| p.is_ascii_alphanumeric() || p == '_' || p == '>')
+                    .unwrap_or(false);
+                if is_literal {
+                    out.push(' ');
+                }
+                out.push('{');
+                let next = chars.get(i + 1);
+                if is_literal && next.is_some() && next != Some(&'}') && next != Some(&' ') {
+                    out.push(' ');
+                }
+                braces.push(is_literal);
+            }
+            '}' => {
+                let is_literal = braces.pop().unwrap_or(false);
+                if is_literal && !out.ends_with(' ') && !out.ends_with('{') {
+                    out.push(' ');
+                }
+                out.push('}');
+            }
+            _ => out.push(c),
+        }
+        i += 1;
+    }
+
+    out
+}
+
+/// `name   : value` -> `name: value`
+fn unalign_field(line: &str) -> String {
+    let content = line.trim_start();
+    let indent = &line[..line.len() - content.len()];
+    if let Some(pos) = content.find(" : ") {
+        let name = content[..pos].trim_end();
+        if !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return format!("{}{}: {}", indent, name, &content[pos + 3..]);
+        }
+    }
+    line.to_string()
+}
+
+fn find_seq(chars: &[char], from: usize, seq: &[char]) -> Option<usize> {
+    (from..chars.len()).find(|&i| chars[i..].starts_with(seq))
+}
+
+fn string_end(chars: &[char], mut i: usize) -> usize {
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => i += 2,
+            '"' => return i + 1,
+            _ => i += 1,
+        }
+    }
+    chars.len()
+}
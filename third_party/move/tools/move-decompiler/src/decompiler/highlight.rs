@@ -0,0 +1,135 @@
+// Copyright (c) Verichains, 2023
+
+const RESET: &str = "\x1b[0m";
+const KEYWORD: &str = "\x1b[1;35m";
+const TYPE: &str = "\x1b[34m";
+const LITERAL: &str = "\x1b[36m";
+const STRING: &str = "\x1b[32m";
+const COMMENT: &str = "\x1b[2m";
+/// Comments the decompiler leaves where it fell back to a heuristic or gave up
+const LOW_CONFIDENCE: &str = "\x1b[1;33m";
+
+const KEYWORDS: &[&str] = &[
+    "abort", "acquires", "as", "break", "const", "continue", "copy", "else", "entry", "false",
+    "friend", "fun", "has", "if", "inline", "let", "loop", "module", "move", "mut", "native",
+    "phantom", "public", "return", "script", "struct", "true", "use", "while",
+];
+
+const PRIMITIVE_TYPES: &[&str] = &[
+    "address", "bool", "signer", "u8", "u16", "u32", "u64", "u128", "u256", "vector",
+];
+
+/// Markers of the comments of low-confidence output, see `Decompiler::decompile`
+const LOW_CONFIDENCE_MARKERS: &[&str] = &[
+    "warning:",
+    "possible:",
+    "decompilation failed",
+    "elided:",
+    "stopped after",
+];
+
+/// Decompiled Move source with ANSI escape codes for a terminal: keywords, types, literals
+/// and comments are colored, and the comments marking heuristic fallbacks and failed
+/// functions are highlighted as warnings
+pub fn highlight(source: &str) -> String {
+    let mut result = String::new();
+    let mut in_block_comment = false;
+    // the stackless bytecode dumped as comments after a failed function is low confidence
+    let mut in_failure = false;
+    for line in source.split_inclusive('\n') {
+        let (line, newline) = match line.strip_suffix('\n') {
+            Some(line) => (line, "\n"),
+            None => (line, ""),
+        };
+        if !line.trim_start().starts_with("//") {
+            in_failure = false;
+        } else if line.contains("decompilation failed") {
+            in_failure = true;
+        }
+        highlight_line(line, &mut in_block_comment, in_failure, &mut result);
+        result.push_str(newline);
+    }
+    result
+}
+
+fn highlight_line(line: &str, in_block_comment: &mut bool, in_failure: bool, out: &mut String) {
+    let chars = line.char_indices().collect::<Vec<_>>();
+    let end_of = |idx: usize| chars.get(idx).map_or(line.len(), |(offset, _)| *offset);
+    let mut idx = 0;
+    while idx < chars.len() {
+        let (start, c) = chars[idx];
+        let rest = &line[start..];
+
+        if *in_block_comment || rest.starts_with("/*") {
+            let close = rest
+                .get(if *in_block_comment { 0 } else { 2 }..)
+                .and_then(|body| body.find("*/"))
+                .map(|pos| pos + if *in_block_comment { 2 } else { 4 });
+            let len = close.unwrap_or(rest.len());
+            *in_block_comment = close.is_none();
+            paint(out, COMMENT, &rest[..len]);
+            idx = chars.partition_point(|(offset, _)| *offset < start + len);
+            continue;
+        }
+
+        if rest.starts_with("//") {
+            let low_confidence =
+                in_failure || LOW_CONFIDENCE_MARKERS.iter().any(|m| rest.contains(m));
+            paint(
+                out,
+                if low_confidence {
+                    LOW_CONFIDENCE
+                } else {
+                    COMMENT
+                },
+                rest,
+            );
+            return;
+        }
+
+        if (c == 'b' || c == 'x') && rest[1..].starts_with('"') {
+            let len = rest[2..].find('"').map_or(rest.len(), |pos| pos + 3);
+            paint(out, STRING, &rest[..len]);
+            idx = chars.partition_point(|(offset, _)| *offset < start + len);
+            continue;
+        }
+
+        if c.is_ascii_digit() || (c == '@' && rest[1..].starts_with(|c: char| c.is_ascii_digit())) {
+            let mut next = idx + 1;
+            while next < chars.len() && chars[next].1.is_ascii_alphanumeric() {
+                next += 1;
+            }
+            paint(out, LITERAL, &line[start..end_of(next)]);
+            idx = next;
+            continue;
+        }
+
+        if c.is_ascii_alphabetic() || c == '_' {
+            let mut next = idx + 1;
+            while next < chars.len()
+                && (chars[next].1.is_ascii_alphanumeric() || chars[next].1 == '_')
+            {
+                next += 1;
+            }
+            let word = &line[start..end_of(next)];
+            if KEYWORDS.contains(&word) {
+                paint(out, KEYWORD, word);
+            } else if PRIMITIVE_TYPES.contains(&word) || c.is_ascii_uppercase() {
+                paint(out, TYPE, word);
+            } else {
+                out.push_str(word);
+            }
+            idx = next;
+            continue;
+        }
+
+        out.push(c);
+        idx += 1;
+    }
+}
+
+fn paint(out: &mut String, color: &str, text: &str) {
+    out.push_str(color);
+    out.push_str(text);
+    out.push_str(RESET);
+}
@@ -0,0 +1,114 @@
+// Copyright (c) Verichains, 2023
+
+use std::collections::BTreeMap;
+
+use move_stackless_bytecode::function_target::FunctionTarget;
+use serde::Serialize;
+
+use super::{cfg::algo::blocks_stackless::StacklessBasicBlock, Stage};
+
+/// Version of the `--emit-ir` format, bumped on every incompatible change of the types below
+pub const IR_FORMAT_VERSION: u32 = 1;
+
+/// Intermediate representation of every decompiled function after one stage, see
+/// `DecompilerSettings::emit_ir`. Serialized as JSON:
+/// ```json
+/// { "version": 1, "stage": "blocks", "functions": [
+///     { "function": "0x1::m::f", "kind": "blocks", "blocks": [
+///         { "index": 0, "offset": 0, "instructions": ["$t1 := 1"],
+///           "terminator": "if", "targets": [1, 2] } ] } ] }
+/// ```
+#[derive(Debug, Serialize)]
+pub struct IrExport {
+    pub version: u32,
+    /// name of the stage, as accepted by `--emit-ir`
+    pub stage: String,
+    pub functions: Vec<FunctionIr>,
+}
+
+impl IrExport {
+    pub fn new(stage: Stage, functions: Vec<FunctionIr>) -> Self {
+        Self {
+            version: IR_FORMAT_VERSION,
+            stage: stage.name().to_string(),
+            functions,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct FunctionIr {
+    /// `address::module::function`
+    pub function: String,
+    #[serde(flatten)]
+    pub ir: StageIr,
+}
+
+/// The representation depends on the stage, `kind` tells which one is used
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum StageIr {
+    /// `stackless`: stackless bytecode instructions in code order
+    Instructions { instructions: Vec<String> },
+    /// `blocks`, `loops`: basic blocks in topological order
+    Blocks { blocks: Vec<BlockIr> },
+    /// `structure`, `source`: the same listing as `--dump-after`
+    Text { text: String },
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct BlockIr {
+    pub index: usize,
+    /// code offset of the first instruction, absent for blocks added by the decompiler
+    pub offset: Option<usize>,
+    pub instructions: Vec<String>,
+    /// one of next, ret, abort, if, branch, while, break, continue, switch
+    pub terminator: String,
+    /// successor block indices, in the order of the terminator (if/else, loop body/exit,
+    /// switch cases then default)
+    pub targets: Vec<usize>,
+}
+
+pub(crate) fn instructions_ir(function_target: &FunctionTarget<'_>) -> StageIr {
+    let label_offsets = BTreeMap::new();
+    StageIr::Instructions {
+        instructions: function_target
+            .get_bytecode()
+            .iter()
+            .map(|bytecode| {
+                bytecode
+                    .display(function_target, &label_offsets)
+                    .to_string()
+            })
+            .collect(),
+    }
+}
+
+pub(crate) fn blocks_ir(
+    function_target: &FunctionTarget<'_>,
+    blocks: &[StacklessBasicBlock],
+) -> StageIr {
+    let label_offsets = BTreeMap::new();
+    StageIr::Blocks {
+        blocks: blocks
+            .iter()
+            .map(|block| BlockIr {
+                index: block.idx,
+                offset: (block.offset != usize::MAX).then_some(block.offset),
+                instructions: block
+                    .content
+                    .code
+                    .iter()
+                    .filter(|code| !code.removed)
+                    .map(|code| {
+                        code.bytecode
+                            .display(function_target, &label_offsets)
+                            .to_string()
+                    })
+                    .collect(),
+                terminator: block.next.kind().to_string(),
+                targets: block.next.next_blocks().into_iter().copied().collect(),
+            })
+            .collect(),
+    }
+}
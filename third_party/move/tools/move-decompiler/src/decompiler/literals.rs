@@ -0,0 +1,87 @@
+// Copyright (c) Verichains, 2023
+
+use std::{collections::BTreeSet, fmt::Display, str::FromStr};
+
+/// How integer literals are printed
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LiteralStyle {
+    /// every integer in decimal
+    #[default]
+    Decimal,
+    /// hexadecimal for values looking like masks, addresses or magic numbers, decimal
+    /// otherwise, see `LiteralStyle::integer`
+    Auto,
+    /// every integer from 10 on in hexadecimal
+    Hex,
+}
+
+impl LiteralStyle {
+    pub const ALL: [LiteralStyle; 3] =
+        [LiteralStyle::Decimal, LiteralStyle::Auto, LiteralStyle::Hex];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            LiteralStyle::Decimal => "decimal",
+            LiteralStyle::Auto => "auto",
+            LiteralStyle::Hex => "hex",
+        }
+    }
+
+    /// Source of an integer literal given its decimal and lowercase hexadecimal digits
+    pub(crate) fn integer(&self, decimal: String, hex: String) -> String {
+        let use_hex = match self {
+            LiteralStyle::Decimal => false,
+            LiteralStyle::Hex => decimal.len() > 1,
+            LiteralStyle::Auto => looks_hexadecimal(&decimal, &hex),
+        };
+        if use_hex {
+            format!("0x{}", hex)
+        } else {
+            decimal
+        }
+    }
+}
+
+/// Heuristics of `LiteralStyle::Auto`: numbers below 100 and round decimal numbers stay
+/// decimal, others are hexadecimal when they look like
+/// - masks: 2^n - 1
+/// - addresses and hashes: 32 hex digits or more
+/// - magic numbers: above 2^32 with at most 3 distinct hex digits
+fn looks_hexadecimal(decimal: &str, hex: &str) -> bool {
+    if decimal.len() <= 2 || decimal.ends_with("000") {
+        return false;
+    }
+    let is_mask = matches!(hex.as_bytes()[0], b'1' | b'3' | b'7' | b'f')
+        && hex[1..].bytes().all(|x| x == b'f');
+    let is_address = hex.len() >= 32;
+    let is_magic = hex.len() > 8 && hex.chars().collect::<BTreeSet<_>>().len() <= 3;
+    is_mask || is_address || is_magic
+}
+
+impl Display for LiteralStyle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.pad(self.name())
+    }
+}
+
+impl FromStr for LiteralStyle {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        LiteralStyle::ALL
+            .iter()
+            .find(|style| style.name() == s)
+            .copied()
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "unknown literal style {}, expected one of: {}",
+                    s,
+                    LiteralStyle::ALL
+                        .iter()
+                        .map(|style| style.name())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            })
+    }
+}
@@ -0,0 +1,179 @@
+// Copyright (c) Verichains, 2023
+
+use std::{collections::BTreeSet, io::Read};
+
+use flate2::read::GzDecoder;
+use move_binary_format::{
+    access::{ModuleAccess, ScriptAccess},
+    binary_views::BinaryIndexedView,
+};
+use move_core_types::account_address::AccountAddress;
+
+use super::PackageMetadata;
+
+const FRAMEWORK_GIT: &str = "https://github.com/aptos-labs/aptos-core.git";
+/// Used when the framework version can not be read from the package metadata
+const DEFAULT_FRAMEWORK_REV: &str = "mainnet";
+
+/// Packages published by the framework: address, name, directory in aptos-core and whether
+/// it is the package to depend on when only the address is known, the others come with it
+const FRAMEWORK_PACKAGES: &[(u8, &str, &str, bool)] = &[
+    (1, "MoveStdlib", "aptos-move/framework/move-stdlib", false),
+    (1, "AptosStdlib", "aptos-move/framework/aptos-stdlib", false),
+    (
+        1,
+        "AptosFramework",
+        "aptos-move/framework/aptos-framework",
+        true,
+    ),
+    (3, "AptosToken", "aptos-move/framework/aptos-token", true),
+    (
+        4,
+        "AptosTokenObjects",
+        "aptos-move/framework/aptos-token-objects",
+        true,
+    ),
+];
+
+fn framework_address(account: u8) -> AccountAddress {
+    let mut address = [0u8; AccountAddress::LENGTH];
+    address[AccountAddress::LENGTH - 1] = account;
+    AccountAddress::new(address)
+}
+
+fn is_framework_address(address: &AccountAddress) -> bool {
+    FRAMEWORK_PACKAGES
+        .iter()
+        .any(|(account, ..)| *address == framework_address(*account))
+}
+
+/// `Move.toml` for a package made of the decompiled `binaries`, depending on the framework
+/// packages they use at `framework_rev` (by default the revision found in the manifest of
+/// `package`, or `mainnet`). Dependencies outside the framework can not be resolved and are
+/// listed as comments.
+pub fn move_toml(
+    binaries: &[BinaryIndexedView],
+    package: Option<&PackageMetadata>,
+    framework_rev: Option<&str>,
+) -> String {
+    let mut own_addresses = BTreeSet::new();
+    let mut dependencies = BTreeSet::new();
+    for binary in binaries {
+        match binary {
+            BinaryIndexedView::Module(module) => {
+                own_addresses.insert(*module.address());
+                dependencies.extend(module.immediate_dependencies());
+            }
+            BinaryIndexedView::Script(script) => {
+                dependencies.extend(script.immediate_dependencies());
+            }
+        }
+    }
+
+    let name = package.map_or("Decompiled".to_string(), |package| package.name.clone());
+    let mut toml = String::new();
+    toml.push_str("[package]\n");
+    toml.push_str(&format!("name = \"{}\"\n", name));
+    toml.push_str("version = \"1.0.0\"\n");
+    if let Some(policy) = package
+        .map(|package| package.upgrade_policy_name())
+        .filter(|policy| policy == "compatible" || policy == "immutable")
+    {
+        toml.push_str(&format!("upgrade_policy = \"{}\"\n", policy));
+    }
+
+    // the decompiled sources use literal addresses, the names only help extending the package
+    toml.push_str("\n[addresses]\n");
+    let address_name = named_address(&name);
+    for (idx, address) in own_addresses
+        .iter()
+        .filter(|address| !is_framework_address(address))
+        .enumerate()
+    {
+        let suffix = if idx == 0 {
+            String::new()
+        } else {
+            format!("_{}", idx + 1)
+        };
+        toml.push_str(&format!(
+            "{}{} = \"{}\"\n",
+            address_name,
+            suffix,
+            address.to_hex_literal()
+        ));
+    }
+
+    // packages declared in the metadata, else the framework packages at the addresses used
+    let used_packages = FRAMEWORK_PACKAGES
+        .iter()
+        .filter(|(account, name, _, default)| match package {
+            Some(package) => package.deps.iter().any(|dep| dep.package_name == *name),
+            None => {
+                *default
+                    && dependencies
+                        .iter()
+                        .any(|dep| *dep.address() == framework_address(*account))
+            }
+        })
+        .collect::<Vec<_>>();
+    let rev = framework_rev
+        .map(|rev| rev.to_string())
+        .or_else(|| package.and_then(|package| framework_rev_of_manifest(&package.manifest)))
+        .unwrap_or_else(|| DEFAULT_FRAMEWORK_REV.to_string());
+
+    toml.push_str("\n[dependencies]\n");
+    for (_, name, subdir, _) in used_packages {
+        toml.push_str(&format!(
+            "{} = {{ git = \"{}\", subdir = \"{}\", rev = \"{}\" }}\n",
+            name, FRAMEWORK_GIT, subdir, rev
+        ));
+    }
+    for dep in dependencies.iter().filter(|dep| {
+        !own_addresses.contains(dep.address()) && !is_framework_address(dep.address())
+    }) {
+        toml.push_str(&format!(
+            "# unresolved: {}::{}\n",
+            dep.address().to_hex_literal(),
+            dep.name()
+        ));
+    }
+
+    toml
+}
+
+/// snake_case form of a package name usable as a named address
+fn named_address(package_name: &str) -> String {
+    let mut name = String::new();
+    for (idx, c) in package_name.chars().enumerate() {
+        if c.is_ascii_uppercase() {
+            if idx > 0 && !name.ends_with('_') {
+                name.push('_');
+            }
+            name.push(c.to_ascii_lowercase());
+        } else if c.is_ascii_alphanumeric() {
+            name.push(c);
+        } else if !name.ends_with('_') {
+            name.push('_');
+        }
+    }
+    if !name.starts_with(|c: char| c.is_ascii_lowercase()) {
+        name.insert_str(0, "pkg_");
+    }
+    name
+}
+
+/// `rev` of the `AptosFramework` dependency in the gzipped `Move.toml` of a package
+fn framework_rev_of_manifest(manifest: &[u8]) -> Option<String> {
+    let mut text = String::new();
+    GzDecoder::new(manifest).read_to_string(&mut text).ok()?;
+    let line = text
+        .lines()
+        .find(|line| line.trim_start().starts_with("AptosFramework"))?;
+    let (_, rest) = line.split_once("rev")?;
+    let rest = rest
+        .trim_start()
+        .strip_prefix('=')?
+        .trim_start()
+        .strip_prefix('"')?;
+    rest.split_once('"').map(|(rev, _)| rev.to_string())
+}
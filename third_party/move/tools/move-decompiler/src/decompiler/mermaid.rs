@@ -0,0 +1,93 @@
+// Copyright (c) Verichains, 2023
+
+use std::{collections::BTreeMap, fmt::Write};
+
+use super::{
+    cfg::datastructs::{BasicBlock, BlockContentTrait, Terminator},
+    xref::{XrefKind, XrefReport},
+};
+
+/// Mermaid node labels are quoted, quotes inside them must be written as entities
+fn escape(label: &str) -> String {
+    label.replace('"', "#quot;")
+}
+
+/// Mermaid flowchart of a control flow graph, `label` gives the text of each block
+pub fn cfg_flowchart<Content: BlockContentTrait>(
+    blocks: &[BasicBlock<usize, Content>],
+    label: impl Fn(&BasicBlock<usize, Content>) -> String,
+) -> String {
+    let mut out = String::new();
+    writeln!(out, "flowchart TD").unwrap();
+    for block in blocks {
+        writeln!(out, "    B{}[\"{}\"]", block.idx, escape(&label(block))).unwrap();
+    }
+    for block in blocks {
+        let edges: Vec<(usize, Option<String>)> = match &block.next {
+            Terminator::Normal | Terminator::Ret | Terminator::Abort => vec![],
+            Terminator::IfElse {
+                if_block,
+                else_block,
+            } => vec![
+                (*if_block, Some("true".to_string())),
+                (*else_block, Some("false".to_string())),
+            ],
+            Terminator::Branch { target } => vec![(*target, None)],
+            Terminator::While {
+                inner_block,
+                outer_block,
+            } => vec![
+                (*inner_block, Some("loop".to_string())),
+                (*outer_block, Some("exit".to_string())),
+            ],
+            Terminator::Break { target } => vec![(*target, Some("break".to_string()))],
+            Terminator::Continue { target } => vec![(*target, Some("continue".to_string()))],
+            Terminator::Switch { targets, default } => targets
+                .iter()
+                .enumerate()
+                .map(|(i, target)| (*target, Some(format!("case {}", i))))
+                .chain(std::iter::once((*default, Some("default".to_string()))))
+                .collect(),
+        };
+        for (target, edge_label) in edges {
+            match edge_label {
+                Some(edge_label) => {
+                    writeln!(out, "    B{} -->|{}| B{}", block.idx, edge_label, target).unwrap()
+                }
+                None => writeln!(out, "    B{} --> B{}", block.idx, target).unwrap(),
+            }
+        }
+    }
+    out
+}
+
+/// Mermaid flowchart of the calls recorded in a cross-reference report
+pub fn call_graph(xref: &XrefReport) -> String {
+    let mut nodes = BTreeMap::<&str, usize>::new();
+    let mut edges = Vec::new();
+    for (target, locations) in xref.references() {
+        for location in locations.iter().filter(|l| l.kind == XrefKind::Call) {
+            edges.push((location.function.as_str(), target.as_str()));
+        }
+    }
+    edges.sort();
+    edges.dedup();
+    for (caller, callee) in &edges {
+        for name in [caller, callee] {
+            let next_id = nodes.len();
+            nodes.entry(name).or_insert(next_id);
+        }
+    }
+
+    let mut out = String::new();
+    writeln!(out, "flowchart LR").unwrap();
+    let mut sorted_nodes = nodes.iter().collect::<Vec<_>>();
+    sorted_nodes.sort_by_key(|(_, id)| **id);
+    for (name, id) in sorted_nodes {
+        writeln!(out, "    F{}[\"{}\"]", id, escape(name)).unwrap();
+    }
+    for (caller, callee) in edges {
+        writeln!(out, "    F{} --> F{}", nodes[caller], nodes[callee]).unwrap();
+    }
+    out
+}
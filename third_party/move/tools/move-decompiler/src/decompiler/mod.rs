@@ -1,16 +1,18 @@
 // Copyright (c) Verichains, 2023
 
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
 use anyhow::{Ok, Result};
 use move_binary_format::{
     access::ModuleAccess,
     binary_views::BinaryIndexedView,
-    file_format::{AbilitySet, StructHandle},
+    file_format::{AbilitySet, CompiledModule, StructHandle},
 };
 use move_bytecode_source_map::source_map::SourceMap;
 
 use move_model::{
     ast::Address,
-    model::{FunctionEnv, GlobalEnv, ModuleEnv, StructEnv},
+    model::{FunId, FunctionEnv, GlobalEnv, ModuleEnv, QualifiedId, StructEnv},
     ty::{PrimitiveType, ReferenceKind, Type},
 };
 use move_stackless_bytecode::{
@@ -19,25 +21,116 @@ use move_stackless_bytecode::{
     demove_livevar_analysis::LiveVarAnalysisProcessor2,
     demove_peephole_analysis::PeepHoleProcessor,
     reaching_def_analysis::ReachingDefProcessor,
+    stackless_bytecode::{Bytecode, Constant, Operation},
+    stackless_bytecode_generator::StackDiagnostics,
 };
 
+use self::accessors::Accessor;
+pub use self::aptos_metadata::PackageMetadata;
+pub use self::backend::Backend;
+use self::bit_flags::BitFlag;
+pub use self::bundle::{read_bundle, sort_by_dependency, BundleFormat};
+pub use self::dependencies::missing_dependencies;
+#[cfg(feature = "remote")]
+pub use self::dependencies::NodeClient;
+pub use self::diagnostics::{Diagnostic, DiagnosticKind, Severity};
+pub use self::fingerprint::{
+    function_fingerprint, FunctionChange, FunctionSymbol, ModuleSymbols, SymbolIndex,
+};
+pub use self::highlight::highlight;
+pub use self::ir_export::{BlockIr, FunctionIr, IrExport, StageIr, IR_FORMAT_VERSION};
+pub use self::literals::LiteralStyle;
+pub use self::pass::{Pass, PassIr};
 use self::reconstruct::code_unit::SourceCodeUnit;
 pub use self::reconstruct::OptimizerSettings;
-
+#[cfg(feature = "remote")]
+pub use self::registry::HttpSourceRegistry;
+pub use self::registry::{module_hash, SourceRegistry, VerifiedSource};
+pub use self::signatures::{function_signature, SignatureDb};
+pub use self::stage::{Stage, StageDump};
+pub use self::verifier::{verify_binary, VerificationError};
+pub use self::version::{bytecode_version, unsupported_version};
+pub use self::xref::{XrefKind, XrefLocation, XrefReport};
+
+mod accessors;
+mod aptos_metadata;
+mod backend;
 mod bin_to_compiler_translator;
-mod cfg;
+mod bit_flags;
+mod bundle;
+pub mod cfg;
+mod dependencies;
+mod diagnostics;
 mod evaluator;
+mod fingerprint;
+mod formatter;
+mod highlight;
+mod ir_export;
+mod literals;
+mod manifest;
+mod mermaid;
+mod move_ir;
 mod naming;
+mod pass;
 mod reconstruct;
+mod registry;
+mod signatures;
 mod stackless_bytecode_display;
+mod stage;
+mod test_skeleton;
 mod utils;
-
-use self::naming::Naming;
+mod verifier;
+mod version;
+mod xref;
+
+use self::{naming::Naming, stackless_bytecode_display::StacklessBycodeDisplayContext};
+
+#[derive(Default)]
+pub struct DecompilerSettings {
+    /// Only decompile `entry` and `#[view]` functions and the functions of the same module
+    /// they transitively call, everything else is emitted as a stub
+    pub entry_view_only: bool,
+    /// Post-process the output to follow `movefmt` conventions
+    pub movefmt_compatible: bool,
+    /// Stop decompiling function bodies after this stage, the bodies become stubs and the
+    /// intermediate representation of the stage is dumped
+    pub stop_after: Option<Stage>,
+    /// Dump the intermediate representation of functions after these stages
+    pub dump_after: BTreeSet<Stage>,
+    /// Print the metadata of each module as a comment block before it
+    pub metadata_header: bool,
+    /// Export the intermediate representation of functions after this stage, see `IrExport`
+    pub emit_ir: Option<Stage>,
+    /// How function bodies are decompiled
+    pub backend: Backend,
+    /// Favor readability over valid Move: ternaries for value-producing `if`s, no widening
+    /// casts and no abort codes. The output is marked as not compilable.
+    pub pseudocode: bool,
+    /// Annotate trivial getters and setters of the input, and fold their calls into field
+    /// accesses in `pseudocode`
+    pub fold_accessors: bool,
+    /// Declare the powers of two a module only uses with `&` and `|` as `FLAG_<bit>`
+    /// constants and name them in those operations
+    pub bit_flags: bool,
+    /// Decimal or hexadecimal integer literals
+    pub literal_style: LiteralStyle,
+}
 
 pub struct Decompiler<'a> {
     env: GlobalEnv,
     binaries: Vec<BinaryIndexedView<'a>>,
+    dependencies: Vec<CompiledModule>,
     optimizer_settings: OptimizerSettings,
+    settings: DecompilerSettings,
+    prepared: bool,
+    xref: XrefReport,
+    diagnostics: Vec<Diagnostic>,
+    dumps: Vec<StageDump>,
+    ir_exports: Vec<FunctionIr>,
+    passes: Vec<Box<dyn Pass + 'a>>,
+    signatures: SignatureDb,
+    registry: Option<Box<dyn SourceRegistry + 'a>>,
+    package_metadata: Option<PackageMetadata>,
 }
 
 impl<'a> Decompiler<'a> {
@@ -49,10 +142,92 @@ impl<'a> Decompiler<'a> {
         Self {
             env,
             binaries,
+            dependencies: Vec::new(),
             optimizer_settings,
+            settings: Default::default(),
+            prepared: false,
+            xref: Default::default(),
+            diagnostics: Vec::new(),
+            dumps: Vec::new(),
+            ir_exports: Vec::new(),
+            passes: Vec::new(),
+            signatures: SignatureDb::new(),
+            registry: None,
+            package_metadata: None,
         }
     }
 
+    pub fn with_settings(mut self, settings: DecompilerSettings) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    /// Modules the binaries depend on, used for the signatures, structs and acquired
+    /// resources of what the binaries reference instead of guessing them from the call sites.
+    /// They are not decompiled.
+    pub fn with_dependencies(mut self, dependencies: Vec<CompiledModule>) -> Self {
+        self.dependencies = dependencies;
+        self
+    }
+
+    /// Register a custom pass, it runs on every decompiled function body after `pass.stage()`
+    pub fn with_pass(mut self, pass: impl Pass + 'a) -> Self {
+        self.passes.push(Box::new(pass));
+        self
+    }
+
+    /// Label functions whose body matches a known library function of `signatures`
+    pub fn with_signatures(mut self, signatures: SignatureDb) -> Self {
+        self.signatures = signatures;
+        self
+    }
+
+    /// Query `registry` for every module and emit its verified source instead of
+    /// decompiling it when one exists
+    pub fn with_registry(mut self, registry: impl SourceRegistry + 'a) -> Self {
+        self.registry = Some(Box::new(registry));
+        self
+    }
+
+    /// Metadata of the package the binaries belong to, printed in the metadata header of
+    /// its modules
+    pub fn with_package_metadata(mut self, metadata: PackageMetadata) -> Self {
+        self.package_metadata = Some(metadata);
+        self
+    }
+
+    /// Verified source of a module binary from the registry, if any
+    fn verified_source(&self, binary: &BinaryIndexedView) -> Result<Option<VerifiedSource>> {
+        let (registry, compiled) = match (&self.registry, binary) {
+            (Some(registry), BinaryIndexedView::Module(compiled)) => (registry, compiled),
+            _ => return Ok(None),
+        };
+        let mut bytes = Vec::new();
+        compiled.serialize(&mut bytes)?;
+        let hash = module_hash(&bytes);
+        registry
+            .lookup(&hash)
+            .map_err(|err| err.context(format!("failed to query registry for module {}", hash)))
+    }
+
+    fn run_passes(
+        &self,
+        stage: Stage,
+        function_env: &FunctionEnv<'_>,
+        mut ir: PassIr<'_, '_>,
+    ) -> Result<()> {
+        for pass in self.passes.iter().filter(|pass| pass.stage() == stage) {
+            pass.run(function_env, &mut ir).map_err(|err| {
+                err.context(format!(
+                    "pass {} failed on {}",
+                    pass.name(),
+                    function_env.get_full_name_with_address()
+                ))
+            })?;
+        }
+        Ok(())
+    }
+
     fn inline_decompile_type(
         &self,
         current_module: &ModuleEnv<'_>,
@@ -349,6 +524,87 @@ impl<'a> Decompiler<'a> {
         Ok(buf)
     }
 
+    /// Comment block describing the metadata of a module, empty if it has none
+    fn decompile_metadata_header(
+        &self,
+        binary: &BinaryIndexedView,
+        module: &ModuleEnv<'_>,
+    ) -> SourceCodeUnit {
+        let mut unit = SourceCodeUnit::new(0);
+        let compiled = match binary {
+            BinaryIndexedView::Module(compiled) => compiled,
+            BinaryIndexedView::Script(_) => return unit,
+        };
+
+        if !compiled.metadata.is_empty() {
+            let keys = compiled
+                .metadata
+                .iter()
+                .map(|md| String::from_utf8_lossy(&md.key).to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+            unit.add_line(format!("// metadata: {}", keys));
+        }
+        let metadata = aptos_metadata::get_metadata(&compiled.metadata);
+        if let Some(metadata) = &metadata {
+            unit.add_line(format!("//   error codes: {}", metadata.error_map.len()));
+        }
+
+        let entry_functions = module
+            .get_functions()
+            .filter(|f| f.is_entry())
+            .map(|f| f.get_name_str())
+            .collect::<Vec<_>>();
+        if !entry_functions.is_empty() {
+            unit.add_line(format!(
+                "// entry functions: {}",
+                entry_functions.join(", ")
+            ));
+        }
+        if let Some(metadata) = &metadata {
+            let view_functions = module
+                .get_functions()
+                .map(|f| f.get_name_str())
+                .filter(|name| metadata.is_view_function(name))
+                .collect::<Vec<_>>();
+            if !view_functions.is_empty() {
+                unit.add_line(format!("// view functions: {}", view_functions.join(", ")));
+            }
+            let events = module
+                .get_structs()
+                .map(|s| s.get_name().display(s.symbol_pool()).to_string())
+                .filter(|name| metadata.is_event_struct(name))
+                .collect::<Vec<_>>();
+            if !events.is_empty() {
+                unit.add_line(format!("// events: {}", events.join(", ")));
+            }
+        }
+
+        let name = module.get_name().display(&self.env).to_string();
+        if let Some(package) = self
+            .package_metadata
+            .as_ref()
+            .filter(|package| package.contains_module(&name))
+        {
+            unit.add_line(format!("// package: {}", package.name));
+            unit.add_line(format!(
+                "//   upgrade policy: {}, upgrade number: {}",
+                package.upgrade_policy_name(),
+                package.upgrade_number
+            ));
+            unit.add_line(format!("//   source digest: {}", package.source_digest));
+            for dep in &package.deps {
+                unit.add_line(format!(
+                    "//   dependency: {}::{}",
+                    dep.account.to_hex_literal(),
+                    dep.package_name
+                ));
+            }
+        }
+
+        unit
+    }
+
     fn module_for_binary(&self, binary: &BinaryIndexedView) -> ModuleEnv<'_> {
         match binary {
             BinaryIndexedView::Module(compiled) => {
@@ -387,17 +643,59 @@ impl<'a> Decompiler<'a> {
         }
     }
 
-    pub fn decompile(&mut self) -> Result<String> {
-        let mut pipeline = FunctionTargetPipeline::default();
-        pipeline.add_processor(PeepHoleProcessor::new());
-        pipeline.add_processor(ReachingDefProcessor::new());
-        pipeline.add_processor(LiveVarAnalysisProcessor2::new());
+    /// Entry and view functions of the module plus everything they transitively call within it
+    fn entry_view_functions(
+        &self,
+        binary: &BinaryIndexedView,
+        module: &ModuleEnv<'_>,
+        targets: &FunctionTargetsHolder,
+    ) -> BTreeSet<FunId> {
+        let metadata = match binary {
+            BinaryIndexedView::Module(compiled) => aptos_metadata::get_metadata(&compiled.metadata),
+            BinaryIndexedView::Script(_) => None,
+        };
+
+        let mut reachable = BTreeSet::new();
+        let mut queue = VecDeque::new();
+        for f in module.get_functions() {
+            let is_view = metadata
+                .as_ref()
+                .map(|m| m.is_view_function(f.get_name_str().as_str()))
+                .unwrap_or(false);
+            if f.is_entry() || is_view {
+                reachable.insert(f.get_id());
+                queue.push_back(f.get_id());
+            }
+        }
+
+        while let Some(fid) = queue.pop_front() {
+            let f = module.get_function(fid);
+            if f.is_native() {
+                continue;
+            }
+            let function_target = targets.get_target(&f, &FunctionVariant::Baseline);
+            for bytecode in function_target.get_bytecode() {
+                if let Bytecode::Call(_, _, Operation::Function(mid, callee, _), _, _) = bytecode {
+                    if *mid == module.get_id() && reachable.insert(*callee) {
+                        queue.push_back(*callee);
+                    }
+                }
+            }
+        }
 
-        let script_pipeline = FunctionTargetPipeline::default();
+        reachable
+    }
 
-        let naming = Naming::new();
+    /// Build the model for all binaries, only done once per decompiler
+    fn prepare(&mut self, naming: &Naming) {
+        if self.prepared {
+            return;
+        }
+        self.prepared = true;
 
-        let program = bin_to_compiler_translator::create_program(&self.binaries, &naming).unwrap();
+        let program =
+            bin_to_compiler_translator::create_program(&self.binaries, &self.dependencies, naming)
+                .unwrap();
         move_model::demove_helper::run_stackless_compiler(&mut self.env, program);
 
         // all module must be populated before decompiling
@@ -417,41 +715,345 @@ impl<'a> Decompiler<'a> {
             };
         }
 
+        // the model needs the compiled dependencies to know the resources their functions acquire
+        let binary_ids = self
+            .binaries
+            .iter()
+            .filter_map(|binary| binary.self_id())
+            .collect::<BTreeSet<_>>();
+        for compiled in &self.dependencies {
+            if binary_ids.contains(&compiled.self_id()) {
+                continue;
+            }
+            self.env.attach_compiled_module(
+                self.module_for_binary(&BinaryIndexedView::Module(compiled))
+                    .get_id(),
+                compiled.clone(),
+                SourceMap::new(bin_to_compiler_translator::fake_loc(), None),
+            );
+        }
+    }
+
+    /// Unit test skeletons for every module, as `(module name, source)` pairs
+    pub fn decompile_test_skeletons(&mut self) -> Result<Vec<(String, String)>> {
+        let naming = Naming::new();
+        self.prepare(&naming);
+
+        let mut result = Vec::new();
+        for binary in self.binaries.clone() {
+            if matches!(binary, BinaryIndexedView::Script(_)) {
+                continue;
+            }
+            let module = self.module_for_binary(&binary);
+            let unit = self.decompile_test_skeleton(&module, &naming)?;
+            result.push((
+                module.get_name().display(&self.env).to_string(),
+                unit.to_string(),
+            ));
+        }
+
+        Ok(result)
+    }
+
+    /// Move IR of every module and script, which assembles back to bytecode more directly than
+    /// the decompiled source, see `move_ir::module_ir`
+    pub fn decompile_ir(&mut self) -> Result<String> {
+        let naming = Naming::new();
+        self.prepare(&naming);
+
+        let mut result = Vec::new();
+        for binary in self.binaries.clone() {
+            let module = self.module_for_binary(&binary);
+            let targets = Self::raw_function_targets(&module);
+            result.push(move_ir::module_ir(&module, &targets)?);
+        }
+
+        Ok(result.join("\n"))
+    }
+
+    /// Signature database of every function of the binaries, to label copies of them
+    /// when decompiling other binaries
+    pub fn collect_signatures(&mut self) -> SignatureDb {
+        let naming = Naming::new();
+        self.prepare(&naming);
+
+        let mut db = SignatureDb::new();
+        for binary in self.binaries.clone() {
+            if matches!(binary, BinaryIndexedView::Script(_)) {
+                continue;
+            }
+            for f in self.module_for_binary(&binary).get_functions() {
+                db.add_function(&f);
+            }
+        }
+
+        db
+    }
+
+    /// Stackless bytecode of the functions of a module, after the analyses the decompiler
+    /// relies on
+    fn function_targets(&self, module: &ModuleEnv<'_>, is_script: bool) -> FunctionTargetsHolder {
+        let mut targets = FunctionTargetsHolder::default();
+        for f in module.get_functions() {
+            targets.add_target(&f);
+        }
+
+        let mut pipeline = FunctionTargetPipeline::default();
+        if !is_script {
+            pipeline.add_processor(PeepHoleProcessor::new());
+            pipeline.add_processor(ReachingDefProcessor::new());
+            pipeline.add_processor(LiveVarAnalysisProcessor2::new());
+        }
+        pipeline.run(&self.env, &mut targets);
+
+        targets
+    }
+
+    /// Stackless bytecode of the functions of a module as generated, the processors of
+    /// `function_targets` rewrite it for source output
+    fn raw_function_targets(module: &ModuleEnv<'_>) -> FunctionTargetsHolder {
+        let mut targets = FunctionTargetsHolder::default();
+        for f in module.get_functions() {
+            targets.add_target(&f);
+        }
+        targets
+    }
+
+    /// Bit flags of a module, see `bit_flags::detect_bit_flags`
+    fn bit_flags(module: &ModuleEnv<'_>) -> BTreeMap<Constant, BitFlag> {
+        let targets = Self::raw_function_targets(module);
+        bit_flags::detect_bit_flags(
+            module
+                .get_functions()
+                .filter(|f| !f.is_native())
+                .map(|f| targets.get_target(&f, &FunctionVariant::Baseline)),
+        )
+    }
+
+    /// Accessor functions of the modules, see `accessors::detect_accessor`
+    fn accessors(&self) -> BTreeMap<QualifiedId<FunId>, Accessor> {
+        let mut accessors = BTreeMap::new();
+        for binary in &self.binaries {
+            if matches!(binary, BinaryIndexedView::Script(_)) {
+                continue;
+            }
+            let module = self.module_for_binary(binary);
+            let targets = Self::raw_function_targets(&module);
+            for f in module.get_functions() {
+                if f.is_native() {
+                    continue;
+                }
+                let target = targets.get_target(&f, &FunctionVariant::Baseline);
+                if let Some(accessor) = accessors::detect_accessor(&self.env, &target) {
+                    accessors.insert(f.get_qualified_id(), accessor);
+                }
+            }
+        }
+        accessors
+    }
+
+    /// Mermaid flowcharts of the control flow graph of every function after loop
+    /// reconstruction, as `(function name, flowchart)` pairs
+    pub fn cfg_graphs(&mut self) -> Result<Vec<(String, String)>> {
+        let naming = Naming::new();
+        self.prepare(&naming);
+
+        let mut result = Vec::new();
+        for binary in self.binaries.clone() {
+            let module = self.module_for_binary(&binary);
+            let is_script = matches!(binary, BinaryIndexedView::Script(_));
+            let targets = self.function_targets(&module, is_script);
+            for f in module.get_functions() {
+                if f.is_native() {
+                    continue;
+                }
+                let function_target = targets.get_target(&f, &FunctionVariant::Baseline);
+                let blocks = cfg::stackless::build_blocks(function_target.get_bytecode())?;
+                let blocks = cfg::stackless::reconstruct_loops(blocks)?;
+                let chart = mermaid::cfg_flowchart(&blocks, |block| {
+                    format!(
+                        "B{}: {} instructions",
+                        block.idx(),
+                        block.content().code.iter().filter(|x| !x.removed).count()
+                    )
+                });
+                result.push((f.get_full_name_with_address(), chart));
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Functions and structs of every module with the fingerprint of each function, see
+    /// `function_fingerprint`
+    pub fn symbol_index(&mut self) -> Result<SymbolIndex> {
+        let naming = Naming::new();
+        self.prepare(&naming);
+
+        let mut index = SymbolIndex::default();
+        for binary in self.binaries.clone() {
+            let module = self.module_for_binary(&binary);
+            let is_script = matches!(binary, BinaryIndexedView::Script(_));
+            let targets = self.function_targets(&module, is_script);
+            let mut functions = Vec::new();
+            for f in module.get_functions() {
+                let fingerprint = if f.is_native() {
+                    None
+                } else {
+                    let function_target = targets.get_target(&f, &FunctionVariant::Baseline);
+                    Some(function_fingerprint(&f, &function_target)?)
+                };
+                functions.push(FunctionSymbol {
+                    name: f.get_full_name_with_address(),
+                    fingerprint,
+                });
+            }
+            index.modules.push(ModuleSymbols {
+                name: module.get_full_name_str(),
+                functions,
+                structs: module
+                    .get_structs()
+                    .map(|s| s.get_full_name_with_address())
+                    .collect(),
+            });
+        }
+
+        Ok(index)
+    }
+
+    pub fn decompile(&mut self) -> Result<String> {
+        let naming = Naming::new();
+        self.prepare(&naming);
+        let naming = if self.settings.pseudocode {
+            naming.with_pseudocode()
+        } else {
+            naming
+        };
+        let naming = naming.with_literal_style(self.settings.literal_style);
+
+        let accessors = if self.settings.fold_accessors {
+            self.accessors()
+        } else {
+            BTreeMap::new()
+        };
+
         let mut result = SourceCodeUnit::new(0);
+        if self.settings.pseudocode {
+            result.add_line("// pseudocode: relaxed syntax, not compilable".to_string());
+            result.add_line("".to_string());
+        }
+        let mut xref = XrefReport::default();
+        let mut diagnostics = Vec::new();
+        let mut dumps = Vec::new();
+        let mut ir_exports = Vec::new();
 
         // decompile
         for binary in self.binaries.clone() {
+            if let Some(verified) = self.verified_source(&binary)? {
+                result.add_line(format!("// verified source from {}", verified.origin));
+                for line in verified.source.lines() {
+                    result.add_line(line.to_string());
+                }
+                continue;
+            }
+
             let module = self.module_for_binary(&binary);
             let version = binary.version();
-
-            let mut targets = FunctionTargetsHolder::default();
-            for f in module.get_functions() {
-                targets.add_target(&f);
+            if let BinaryIndexedView::Module(compiled) = &binary {
+                if let Some(message) = aptos_metadata::metadata_error(&compiled.metadata) {
+                    diagnostics.push(Diagnostic::new(
+                        DiagnosticKind::UnreadableMetadata,
+                        module.get_full_name_str(),
+                        None,
+                        message,
+                    ));
+                }
             }
 
             let is_script = matches!(binary, BinaryIndexedView::Script(_));
+            let targets = self.function_targets(&module, is_script);
 
             if is_script {
-                script_pipeline.run(&self.env, &mut targets);
                 result.add_line(format!("script {{",));
             } else {
-                pipeline.run(&self.env, &mut targets);
+                if self.settings.metadata_header {
+                    result.add_block(self.decompile_metadata_header(&binary, &module));
+                }
                 result.add_line(format!(
                     "module {} {{",
                     module.get_name().display_full(&self.env)
                 ));
             }
 
+            let decompiled_functions = if self.settings.entry_view_only {
+                Some(self.entry_view_functions(&binary, &module, &targets))
+            } else {
+                None
+            };
+
             let naming = naming.with_type_display(|t, naming| {
                 self.inline_decompile_type(&module, t, naming).unwrap()
             });
+            // accessor calls become field accesses, by the name calls have in this module
+            let naming = if self.settings.pseudocode {
+                naming.with_accessors(
+                    accessors
+                        .iter()
+                        .map(|(id, accessor)| {
+                            let name = format!(
+                                "{}{}",
+                                utils::shortest_prefix(&module, &id.module_id),
+                                self.env.get_function(*id).get_name_str()
+                            );
+                            (name, accessor.clone())
+                        })
+                        .collect(),
+                )
+            } else {
+                naming
+            };
+            let bit_flags = if self.settings.bit_flags {
+                Self::bit_flags(&module)
+            } else {
+                BTreeMap::new()
+            };
+            let naming = naming.with_bit_flags(
+                bit_flags
+                    .iter()
+                    .map(|(constant, flag)| (constant.clone(), flag.name.clone()))
+                    .collect(),
+            );
+            if !bit_flags.is_empty() {
+                let mut unit = SourceCodeUnit::new(1);
+                for flag in bit_flags.values() {
+                    unit.add_line(flag.declaration());
+                }
+                unit.add_line("".to_string());
+                result.add_block(unit);
+            }
+
+            // attributes are not part of the bytecode, only of the aptos runtime metadata
+            let metadata = match &binary {
+                BinaryIndexedView::Module(compiled) => {
+                    aptos_metadata::get_metadata(&compiled.metadata)
+                }
+                BinaryIndexedView::Script(_) => None,
+            };
 
             if let Some(defs) = binary.struct_defs() {
                 for idx in 0..defs.len() {
                     let s_idx = move_binary_format::file_format::StructDefinitionIndex(idx as u16);
                     let s = module.get_struct_by_def_idx(s_idx);
                     let s_bin = binary.struct_handle_at(binary.struct_def_at(s_idx)?.struct_handle);
-                    let mut unit = self.decompile_struct(&s_bin, &s, &naming)?;
+                    let struct_name = s.get_name().display(s.symbol_pool()).to_string();
+                    let mut unit = SourceCodeUnit::new(0);
+                    for attribute in metadata
+                        .iter()
+                        .flat_map(|m| m.struct_attributes_source(&struct_name))
+                    {
+                        unit.add_line(attribute);
+                    }
+                    unit.add_block(self.decompile_struct(&s_bin, &s, &naming)?);
                     unit.add_line("".to_string());
                     unit.add_indent(1);
                     result.add_block(unit);
@@ -460,32 +1062,127 @@ impl<'a> Decompiler<'a> {
 
             for f in module.get_functions() {
                 let mut func_unit = SourceCodeUnit::new(1);
+                for name in self.signatures.matches(&f) {
+                    func_unit.add_line(format!("// matches {}", name));
+                }
+                if let Some(accessor) = accessors.get(&f.get_qualified_id()) {
+                    func_unit.add_line(format!("// {}", accessor.describe()));
+                }
+                if metadata
+                    .as_ref()
+                    .map_or(false, |m| m.is_view_function(&f.get_name_str()))
+                {
+                    func_unit.add_line("#[view]".to_string());
+                }
+
+                if !f.is_native() {
+                    let function_target = targets.get_target(&f, &FunctionVariant::Baseline);
+                    if let Some(StackDiagnostics(stack_diagnostics)) =
+                        function_target.get_annotations().get::<StackDiagnostics>()
+                    {
+                        for diagnostic in stack_diagnostics {
+                            let warning =
+                                format!("{} at offset {}", diagnostic.message, diagnostic.offset);
+                            func_unit.add_line(format!("// warning: {}", warning));
+                            diagnostics.push(Diagnostic::new(
+                                DiagnosticKind::StackInconsistency,
+                                module.get_full_name_str(),
+                                Some(f.get_full_name_with_address()),
+                                warning,
+                            ));
+                        }
+                    }
+                    xref.collect_function(
+                        &f,
+                        &function_target,
+                        result.line_count() + func_unit.line_count() + 1,
+                    );
+                }
+
                 let f_sig = self.decompile_function_header(&f, &naming, is_script)?;
+                let elided = decompiled_functions
+                    .as_ref()
+                    .map(|fns| !fns.contains(&f.get_id()))
+                    .unwrap_or(false);
                 if f.is_native() {
                     func_unit.add_line(format!("{};", f_sig));
+                } else if elided {
+                    func_unit.add_line(format!("{} {{", f_sig));
+                    let mut stub = SourceCodeUnit::new(1);
+                    stub.add_line(
+                        "abort 0 // elided: not reachable from entry or view functions".to_string(),
+                    );
+                    func_unit.add_block(stub);
+                    func_unit.add_line("}".to_string());
+                    func_unit.add_line("".to_string());
                 } else {
                     func_unit.add_line(format!("{} {{", f_sig));
 
                     let function_target: FunctionTarget<'_> =
                         targets.get_target(&f, &FunctionVariant::Baseline);
 
-                    let mut cfg_decompiled =
-                        cfg::stackless::decompile(function_target.get_bytecode())?;
-                    // much of data from function_target should not be used because
-                    // cfg_decompiled changed the bytecodes.
-                    // variables offsets are still keeped
-
-                    let mut sgen = reconstruct::SourceGen::new(
-                        &mut cfg_decompiled,
-                        &f,
-                        &function_target,
-                        &naming,
-                    );
-
-                    let mut code_unit = sgen.generate(&self.optimizer_settings)?;
+                    let code_unit = match self.settings.backend {
+                        Backend::Cfg => self.decompile_function_body(
+                            &f,
+                            &function_target,
+                            &naming,
+                            &mut dumps,
+                            &mut ir_exports,
+                        ),
+                        Backend::Stackless => {
+                            Ok(Some(backend::stackless_body(&function_target, &naming)))
+                        }
+                    };
 
-                    code_unit.add_indent(1);
-                    func_unit.add_block(code_unit);
+                    match code_unit {
+                        Result::Ok(Some(mut code_unit)) => {
+                            let guesses = code_unit
+                                .to_string()
+                                .lines()
+                                .filter(|line| line.trim_start().starts_with("// possible:"))
+                                .count();
+                            if guesses > 0 {
+                                diagnostics.push(Diagnostic::new(
+                                    DiagnosticKind::Heuristic,
+                                    module.get_full_name_str(),
+                                    Some(f.get_full_name_with_address()),
+                                    format!(
+                                        "{} variable declarations left as `// possible:` comments",
+                                        guesses
+                                    ),
+                                ));
+                            }
+                            code_unit.add_indent(1);
+                            func_unit.add_block(code_unit);
+                        }
+                        Result::Ok(None) => {
+                            let mut stub = SourceCodeUnit::new(1);
+                            stub.add_line(format!(
+                                "abort 0 // stopped after {}",
+                                self.settings.stop_after.unwrap()
+                            ));
+                            func_unit.add_block(stub);
+                        }
+                        // keep going with the other functions, the stackless bytecode is
+                        // the best output available for this one
+                        Err(err) => {
+                            diagnostics.push(Diagnostic::new(
+                                DiagnosticKind::DecompilationFailed,
+                                module.get_full_name_str(),
+                                Some(f.get_full_name_with_address()),
+                                format!("decompilation failed: {:#}", err),
+                            ));
+                            let mut stub = SourceCodeUnit::new(1);
+                            for line in format!("decompilation failed: {:#}", err).lines() {
+                                stub.add_line(format!("// {}", line));
+                            }
+                            for line in function_target.to_string().lines() {
+                                stub.add_line(format!("// {}", line));
+                            }
+                            stub.add_line("abort 0".to_string());
+                            func_unit.add_block(stub);
+                        }
+                    }
                     func_unit.add_line("}".to_string());
                     func_unit.add_line("".to_string());
                 }
@@ -500,6 +1197,165 @@ impl<'a> Decompiler<'a> {
             result.add_line("}".to_string());
         }
 
-        Ok(result.to_string())
+        self.xref = xref;
+        self.diagnostics = diagnostics;
+        self.dumps = dumps;
+        self.ir_exports = ir_exports;
+
+        if self.settings.movefmt_compatible {
+            Ok(formatter::format_source(&result.to_string()))
+        } else {
+            Ok(result.to_string())
+        }
+    }
+
+    /// References to functions and structs found by the last `decompile` call
+    pub fn xref(&self) -> &XrefReport {
+        &self.xref
+    }
+
+    /// Issues found during the last `decompile` call: stack inconsistencies, functions that
+    /// failed to decompile, heuristic fallbacks and unreadable metadata
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    /// `diagnostics` as messages prefixed by the function or module name
+    pub fn warnings(&self) -> Vec<String> {
+        self.diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.to_string())
+            .collect()
+    }
+
+    /// Mermaid flowchart of the calls found by the last `decompile` call
+    pub fn call_graph(&self) -> String {
+        mermaid::call_graph(&self.xref)
+    }
+
+    /// `Move.toml` turning the decompiled modules into a buildable package, with framework
+    /// dependencies pinned to `framework_rev` or the revision found in the package metadata
+    pub fn move_toml(&self, framework_rev: Option<&str>) -> String {
+        manifest::move_toml(
+            &self.binaries,
+            self.package_metadata.as_ref(),
+            framework_rev,
+        )
+    }
+
+    /// Intermediate representation requested by `emit_ir` during the last `decompile` call
+    pub fn ir_export(&self) -> Option<IrExport> {
+        self.settings
+            .emit_ir
+            .map(|stage| IrExport::new(stage, self.ir_exports.clone()))
+    }
+
+    /// Intermediate representations requested by `dump_after`/`stop_after` during the last
+    /// `decompile` call, in pipeline order for each function
+    pub fn dumps(&self) -> &[StageDump] {
+        &self.dumps
+    }
+
+    /// Run the pipeline stages on a function body, `None` if stopped before the source stage
+    fn decompile_function_body(
+        &self,
+        function_env: &FunctionEnv<'_>,
+        function_target: &FunctionTarget<'_>,
+        naming: &Naming,
+        dumps: &mut Vec<StageDump>,
+        ir_exports: &mut Vec<FunctionIr>,
+    ) -> Result<Option<SourceCodeUnit>> {
+        let function = function_env.get_full_name_with_address();
+        // `export` gives the structured form for `emit_ir`, the text listing is used otherwise
+        let mut after_stage =
+            |stage: Stage, ir: &dyn Fn() -> String, export: Option<&dyn Fn() -> StageIr>| -> bool {
+                let stop = self.settings.stop_after == Some(stage);
+                if stop || self.settings.dump_after.contains(&stage) {
+                    dumps.push(StageDump {
+                        function: function.clone(),
+                        stage,
+                        ir: ir(),
+                    });
+                }
+                if self.settings.emit_ir == Some(stage) {
+                    ir_exports.push(FunctionIr {
+                        function: function.clone(),
+                        ir: match export {
+                            Some(export) => export(),
+                            None => StageIr::Text { text: ir() },
+                        },
+                    });
+                }
+                stop
+            };
+
+        self.run_passes(
+            Stage::Stackless,
+            function_env,
+            PassIr::Stackless(function_target),
+        )?;
+        if after_stage(
+            Stage::Stackless,
+            &|| function_target.to_string(),
+            Some(&|| ir_export::instructions_ir(function_target)),
+        ) {
+            return Ok(None);
+        }
+
+        let display_blocks = |blocks: &[cfg::algo::blocks_stackless::StacklessBasicBlock]| {
+            let mut ctx = StacklessBycodeDisplayContext::new(function_target);
+            ctx.display_blocks(blocks);
+            ctx.result().to_string()
+        };
+
+        let mut blocks = cfg::stackless::build_blocks(function_target.get_bytecode())?;
+        self.run_passes(Stage::Blocks, function_env, PassIr::Blocks(&mut blocks))?;
+        if after_stage(
+            Stage::Blocks,
+            &|| display_blocks(&blocks),
+            Some(&|| ir_export::blocks_ir(function_target, &blocks)),
+        ) {
+            return Ok(None);
+        }
+
+        let mut blocks = cfg::stackless::reconstruct_loops(blocks)?;
+        self.run_passes(Stage::Loops, function_env, PassIr::Blocks(&mut blocks))?;
+        if after_stage(
+            Stage::Loops,
+            &|| display_blocks(&blocks),
+            Some(&|| ir_export::blocks_ir(function_target, &blocks)),
+        ) {
+            return Ok(None);
+        }
+
+        let mut cfg_decompiled = cfg::stackless::structure(blocks)?;
+        // much of data from function_target should not be used because
+        // cfg_decompiled changed the bytecodes.
+        // variables offsets are still keeped
+        self.run_passes(
+            Stage::Structure,
+            function_env,
+            PassIr::Structure(&mut cfg_decompiled),
+        )?;
+        if after_stage(
+            Stage::Structure,
+            &|| {
+                let mut ctx = StacklessBycodeDisplayContext::new(function_target);
+                cfg_decompiled.inner().display(&mut ctx);
+                ctx.result().to_string()
+            },
+            None,
+        ) {
+            return Ok(None);
+        }
+
+        let mut sgen =
+            reconstruct::SourceGen::new(&mut cfg_decompiled, function_env, function_target, naming);
+
+        let mut code_unit = sgen.generate(&self.optimizer_settings)?;
+        self.run_passes(Stage::Source, function_env, PassIr::Source(&mut code_unit))?;
+        after_stage(Stage::Source, &|| code_unit.to_string(), None);
+
+        Ok(Some(code_unit))
     }
 }
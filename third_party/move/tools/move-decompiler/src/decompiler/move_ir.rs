@@ -0,0 +1,698 @@
+// Copyright (c) Verichains, 2023
+
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, BTreeSet},
+};
+
+use anyhow::{bail, Result};
+use move_binary_format::{
+    access::ModuleAccess,
+    file_format::{AbilitySet, CodeOffset, StructDefinitionIndex},
+};
+use move_core_types::account_address::AccountAddress;
+use move_model::{
+    ast::Address,
+    model::{FunctionEnv, ModuleEnv, ModuleId, StructEnv, StructId},
+    ty::{PrimitiveType, ReferenceKind, Type},
+};
+use move_stackless_bytecode::{
+    function_target::FunctionTarget,
+    function_target_pipeline::{FunctionTargetsHolder, FunctionVariant},
+    stackless_bytecode::{AssignKind, Bytecode, Constant, Label, Operation},
+};
+
+/// Functions of `0x1::vector` the stackless bytecode uses for the vector instructions, with
+/// the Move IR builtin producing the same instruction
+const VECTOR_BUILTINS: &[(&str, &str)] = &[
+    ("empty", "vec_pack_0"),
+    ("length", "vec_len"),
+    ("borrow", "vec_imm_borrow"),
+    ("borrow_mut", "vec_mut_borrow"),
+    ("push_back", "vec_push_back"),
+    ("pop_back", "vec_pop_back"),
+    ("swap", "vec_swap"),
+    ("destroy_empty", "vec_unpack_0"),
+];
+
+/// Move IR of a module or script, as accepted by `move-ir-compiler`. Function bodies are the
+/// stackless bytecode with one local per temporary, operands are copied when the temporary
+/// is read again later and moved otherwise. `targets` must not have been transformed by the
+/// decompiler processors so that the IR assembles back to equivalent bytecode.
+pub(crate) fn module_ir(module: &ModuleEnv<'_>, targets: &FunctionTargetsHolder) -> Result<String> {
+    let writer = IrWriter {
+        module,
+        imports: RefCell::new(BTreeMap::new()),
+    };
+    let is_script = module.is_script_module();
+
+    let mut body = Vec::new();
+    if !is_script {
+        for friend in module.get_friend_modules() {
+            body.push(format!("friend {};", writer.module_ident(friend)?));
+        }
+        if let Some(compiled) = module.get_verified_module() {
+            for idx in 0..compiled.struct_defs().len() {
+                let def_idx = StructDefinitionIndex(idx as u16);
+                let handle =
+                    compiled.struct_handle_at(compiled.struct_def_at(def_idx).struct_handle);
+                let phantoms = handle
+                    .type_parameters
+                    .iter()
+                    .map(|param| param.is_phantom)
+                    .collect::<Vec<_>>();
+                body.push(writer.struct_decl(&module.get_struct_by_def_idx(def_idx), &phantoms)?);
+            }
+        }
+    }
+    for function in module.get_functions() {
+        let target = if function.is_native() {
+            None
+        } else {
+            Some(targets.get_target(&function, &FunctionVariant::Baseline))
+        };
+        body.push(writer.function_decl(&function, target.as_ref(), is_script)?);
+    }
+
+    let imports = writer
+        .imports
+        .borrow()
+        .iter()
+        .map(|(mid, alias)| {
+            let ident = writer.module_ident(*mid)?;
+            Ok(if ident.ends_with(&format!(".{}", alias)) {
+                format!("import {};", ident)
+            } else {
+                format!("import {} as {};", ident, alias)
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut ir = String::new();
+    if is_script {
+        for import in imports {
+            ir.push_str(&format!("{}\n", import));
+        }
+        for decl in body {
+            ir.push_str(&format!("{}\n", decl));
+        }
+    } else {
+        ir.push_str(&format!(
+            "module {} {{\n",
+            writer.module_ident(module.get_id())?
+        ));
+        for decl in imports.into_iter().chain(body) {
+            for line in decl.lines() {
+                ir.push_str(&format!("    {}\n", line).replace("    \n", "\n"));
+            }
+        }
+        ir.push_str("}\n");
+    }
+    Ok(ir)
+}
+
+struct IrWriter<'a, 'env> {
+    module: &'a ModuleEnv<'env>,
+    /// alias of every module referenced so far, imported at the top of the module
+    imports: RefCell<BTreeMap<ModuleId, String>>,
+}
+
+impl<'a, 'env> IrWriter<'a, 'env> {
+    /// `0x1.name`
+    fn module_ident(&self, mid: ModuleId) -> Result<String> {
+        let env = self.module.env;
+        let module = env.get_module(mid);
+        let Address::Numerical(address) = module.get_name().addr() else {
+            bail!("symbolic address of module {}", module.get_full_name_str());
+        };
+        Ok(format!(
+            "{}.{}",
+            address.to_hex_literal(),
+            module.get_name().name().display(env.symbol_pool())
+        ))
+    }
+
+    fn module_alias(&self, mid: ModuleId) -> String {
+        if mid == self.module.get_id() {
+            return "Self".to_string();
+        }
+        let mut imports = self.imports.borrow_mut();
+        if let Some(alias) = imports.get(&mid) {
+            return alias.clone();
+        }
+        let env = self.module.env;
+        let name = env
+            .get_module(mid)
+            .get_name()
+            .name()
+            .display(env.symbol_pool())
+            .to_string();
+        let mut alias = name.clone();
+        let mut suffix = 1;
+        while alias == "Self" || imports.values().any(|used| *used == alias) {
+            suffix += 1;
+            alias = format!("{}_{}", name, suffix);
+        }
+        imports.insert(mid, alias.clone());
+        alias
+    }
+
+    fn abilities(abilities: AbilitySet) -> Vec<&'static str> {
+        [
+            (abilities.has_copy(), "copy"),
+            (abilities.has_drop(), "drop"),
+            (abilities.has_store(), "store"),
+            (abilities.has_key(), "key"),
+        ]
+        .into_iter()
+        .filter_map(|(has, name)| has.then_some(name))
+        .collect()
+    }
+
+    fn type_parameter(idx: usize, abilities: AbilitySet, is_phantom: bool) -> String {
+        let abilities = Self::abilities(abilities);
+        format!(
+            "{}T{}{}",
+            if is_phantom { "phantom " } else { "" },
+            idx,
+            if abilities.is_empty() {
+                String::new()
+            } else {
+                format!(": {}", abilities.join(" + "))
+            }
+        )
+    }
+
+    fn ty(&self, ty: &Type) -> Result<String> {
+        Ok(match ty {
+            Type::Primitive(PrimitiveType::Bool) => "bool".to_string(),
+            Type::Primitive(PrimitiveType::U8) => "u8".to_string(),
+            Type::Primitive(PrimitiveType::U16) => "u16".to_string(),
+            Type::Primitive(PrimitiveType::U32) => "u32".to_string(),
+            Type::Primitive(PrimitiveType::U64) => "u64".to_string(),
+            Type::Primitive(PrimitiveType::U128) => "u128".to_string(),
+            Type::Primitive(PrimitiveType::U256) => "u256".to_string(),
+            Type::Primitive(PrimitiveType::Address) => "address".to_string(),
+            Type::Primitive(PrimitiveType::Signer) => "signer".to_string(),
+            Type::Vector(ty) => format!("vector<{}>", self.ty(ty)?),
+            Type::TypeParameter(idx) => format!("T{}", idx),
+            Type::Reference(ReferenceKind::Immutable, ty) => format!("&{}", self.ty(ty)?),
+            Type::Reference(ReferenceKind::Mutable, ty) => format!("&mut {}", self.ty(ty)?),
+            Type::Struct(mid, sid, tys) => format!(
+                "{}.{}",
+                self.module_alias(*mid),
+                self.struct_name(*mid, *sid, tys)?
+            ),
+            _ => bail!("type {:?} has no Move IR form", ty),
+        })
+    }
+
+    fn type_actuals(&self, tys: &[Type]) -> Result<String> {
+        if tys.is_empty() {
+            return Ok(String::new());
+        }
+        Ok(format!(
+            "<{}>",
+            tys.iter()
+                .map(|ty| self.ty(ty))
+                .collect::<Result<Vec<_>>>()?
+                .join(", ")
+        ))
+    }
+
+    /// Unqualified struct name with its type actuals, as used by pack, unpack and the global
+    /// storage builtins
+    fn struct_name(&self, mid: ModuleId, sid: StructId, tys: &[Type]) -> Result<String> {
+        let struct_env = self.module.env.get_module(mid).into_struct(sid);
+        Ok(format!(
+            "{}{}",
+            struct_env.get_name().display(struct_env.symbol_pool()),
+            self.type_actuals(tys)?
+        ))
+    }
+
+    fn field_name(&self, mid: ModuleId, sid: StructId, offset: usize) -> String {
+        let struct_env = self.module.env.get_module(mid).into_struct(sid);
+        let field = struct_env.get_field_by_offset(offset);
+        field
+            .get_name()
+            .display(struct_env.symbol_pool())
+            .to_string()
+    }
+
+    fn field_names(&self, mid: ModuleId, sid: StructId) -> Vec<String> {
+        let struct_env = self.module.env.get_module(mid).into_struct(sid);
+        struct_env
+            .get_fields()
+            .map(|field| {
+                field
+                    .get_name()
+                    .display(struct_env.symbol_pool())
+                    .to_string()
+            })
+            .collect()
+    }
+
+    fn struct_decl(&self, struct_env: &StructEnv<'_>, phantoms: &[bool]) -> Result<String> {
+        let type_parameters = struct_env
+            .get_type_parameters()
+            .iter()
+            .enumerate()
+            .map(|(idx, param)| {
+                Self::type_parameter(
+                    idx,
+                    param.1.abilities,
+                    phantoms.get(idx).copied().unwrap_or(false),
+                )
+            })
+            .collect::<Vec<_>>();
+        let abilities = Self::abilities(struct_env.get_abilities());
+        let fields = struct_env
+            .get_fields()
+            .map(|field| {
+                Ok(format!(
+                    "{}: {}",
+                    field.get_name().display(struct_env.symbol_pool()),
+                    self.ty(&field.get_type())?
+                ))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(format!(
+            "struct {}{}{} {{ {} }}",
+            struct_env.get_name().display(struct_env.symbol_pool()),
+            if type_parameters.is_empty() {
+                String::new()
+            } else {
+                format!("<{}>", type_parameters.join(", "))
+            },
+            if abilities.is_empty() {
+                String::new()
+            } else {
+                format!(" has {}", abilities.join(", "))
+            },
+            fields.join(", ")
+        ))
+    }
+
+    fn function_decl(
+        &self,
+        function: &FunctionEnv<'_>,
+        target: Option<&FunctionTarget<'_>>,
+        is_script: bool,
+    ) -> Result<String> {
+        let mut header = String::new();
+        if function.is_native() {
+            header.push_str("native ");
+        }
+        if !is_script {
+            header.push_str(function.visibility_str());
+            if function.is_entry() {
+                header.push_str("entry ");
+            }
+            header.push_str(&function.get_name_str());
+        } else {
+            header.push_str("main");
+        }
+
+        let type_parameters = function.get_type_parameters();
+        if !type_parameters.is_empty() {
+            header.push_str(&format!(
+                "<{}>",
+                type_parameters
+                    .iter()
+                    .enumerate()
+                    .map(|(idx, param)| Self::type_parameter(idx, param.1.abilities, false))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
+
+        header.push_str(&format!(
+            "({})",
+            function
+                .get_parameters()
+                .iter()
+                .enumerate()
+                .map(|(idx, param)| Ok(format!("t{}: {}", idx, self.ty(&param.1)?)))
+                .collect::<Result<Vec<_>>>()?
+                .join(", ")
+        ));
+
+        match function.get_result_type() {
+            Type::Tuple(tys) if tys.is_empty() => {}
+            Type::Tuple(tys) => header.push_str(&format!(
+                ": {}",
+                tys.iter()
+                    .map(|ty| self.ty(ty))
+                    .collect::<Result<Vec<_>>>()?
+                    .join(" * ")
+            )),
+            ty => header.push_str(&format!(": {}", self.ty(&ty)?)),
+        }
+
+        if let Some(resources) = function
+            .get_acquires_global_resources()
+            .filter(|resources| !resources.is_empty())
+        {
+            header.push_str(&format!(
+                " acquires {}",
+                resources
+                    .iter()
+                    .map(|sid| {
+                        let struct_env = self.module.get_struct(*sid);
+                        struct_env
+                            .get_name()
+                            .display(struct_env.symbol_pool())
+                            .to_string()
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
+
+        let Some(target) = target else {
+            return Ok(format!("{};", header));
+        };
+
+        let mut lines = vec![format!("{} {{", header)];
+        for idx in target.get_parameter_count()..target.get_local_count() {
+            lines.push(format!(
+                "    let t{}: {};",
+                idx,
+                self.ty(target.get_local_type(idx))?
+            ));
+        }
+        if !matches!(target.get_bytecode().first(), Some(Bytecode::Label(..))) {
+            lines.push("label entry:".to_string());
+        }
+        let code = target.get_bytecode();
+        for (bytecode, live) in code.iter().zip(live_after(code)) {
+            for statement in self.statements(target, bytecode, &live)? {
+                if statement.starts_with("label ") {
+                    lines.push(statement);
+                } else {
+                    lines.push(format!("    {}", statement));
+                }
+            }
+        }
+        lines.push("}".to_string());
+        Ok(lines.join("\n"))
+    }
+
+    /// Statements of `bytecode`, `live` are the temporaries read after it
+    fn statements(
+        &self,
+        target: &FunctionTarget<'_>,
+        bytecode: &Bytecode,
+        live: &BTreeSet<usize>,
+    ) -> Result<Vec<String>> {
+        let label = |label: &Label| format!("l{}", label.as_usize());
+        // an operand is copied if it is read again, by a later operand or instruction
+        let operands = |srcs: &[usize]| {
+            srcs.iter()
+                .enumerate()
+                .map(|(pos, idx)| {
+                    if live.contains(idx) || srcs[pos + 1..].contains(idx) {
+                        format!("copy(t{})", idx)
+                    } else {
+                        format!("move(t{})", idx)
+                    }
+                })
+                .collect::<Vec<_>>()
+        };
+        let is_mut = |idx: usize| {
+            matches!(
+                target.get_local_type(idx),
+                Type::Reference(ReferenceKind::Mutable, _)
+            )
+        };
+
+        Ok(match bytecode {
+            Bytecode::Label(_, l) => vec![format!("label {}:", label(l))],
+            Bytecode::Jump(_, l) => vec![format!("jump {};", label(l))],
+            Bytecode::Branch(_, then_label, else_label, cond) => vec![
+                format!("jump_if ({}) {};", operands(&[*cond])[0], label(then_label)),
+                format!("jump {};", label(else_label)),
+            ],
+            Bytecode::Ret(_, srcs) if srcs.is_empty() => vec!["return;".to_string()],
+            Bytecode::Ret(_, srcs) => vec![format!("return {};", operands(srcs).join(", "))],
+            Bytecode::Abort(_, src) => vec![format!("abort {};", operands(&[*src])[0])],
+            Bytecode::Nop(_) => vec!["nop;".to_string()],
+            Bytecode::Assign(_, dest, src, kind) => vec![format!(
+                "t{} = {}(t{});",
+                dest,
+                if *kind == AssignKind::Copy {
+                    "copy"
+                } else {
+                    "move"
+                },
+                src
+            )],
+            Bytecode::Load(_, dest, constant) => vec![format!(
+                "t{} = {};",
+                dest,
+                self.constant(constant, target.get_local_type(*dest))?
+            )],
+            Bytecode::Call(_, dests, operation, srcs, _) => {
+                let args = operands(srcs);
+                let expr = match operation {
+                    Operation::Unpack(mid, sid, tys) => {
+                        let bindings = self
+                            .field_names(*mid, *sid)
+                            .iter()
+                            .zip(dests)
+                            .map(|(field, dest)| format!("{}: t{}", field, dest))
+                            .collect::<Vec<_>>();
+                        return Ok(vec![format!(
+                            "{} {{ {} }} = {};",
+                            self.struct_name(*mid, *sid, tys)?,
+                            bindings.join(", "),
+                            args[0]
+                        )]);
+                    }
+                    Operation::WriteRef => {
+                        return Ok(vec![format!("*{} = {};", args[0], args[1])]);
+                    }
+                    Operation::Destroy => return Ok(vec![format!("_ = {};", args[0])]),
+                    Operation::Function(mid, fid, tys) => {
+                        let module = self.module.env.get_module(*mid);
+                        let name = module.get_function(*fid).get_name_str();
+                        let builtin = VECTOR_BUILTINS
+                            .iter()
+                            .find(|(function, _)| *function == name)
+                            .filter(|_| {
+                                module
+                                    .get_name()
+                                    .name()
+                                    .display(module.symbol_pool())
+                                    .to_string()
+                                    == "vector"
+                                    && *module.get_name().addr()
+                                        == Address::Numerical(AccountAddress::ONE)
+                            });
+                        match builtin {
+                            Some((_, builtin)) => {
+                                format!(
+                                    "{}{}({})",
+                                    builtin,
+                                    self.type_actuals(tys)?,
+                                    args.join(", ")
+                                )
+                            }
+                            None => format!(
+                                "{}.{}{}({})",
+                                self.module_alias(*mid),
+                                name,
+                                self.type_actuals(tys)?,
+                                args.join(", ")
+                            ),
+                        }
+                    }
+                    Operation::Pack(mid, sid, tys) => format!(
+                        "{} {{ {} }}",
+                        self.struct_name(*mid, *sid, tys)?,
+                        self.field_names(*mid, *sid)
+                            .iter()
+                            .zip(&args)
+                            .map(|(field, arg)| format!("{}: {}", field, arg))
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    ),
+                    Operation::MoveTo(mid, sid, tys) => format!(
+                        "move_to<{}>({}, {})",
+                        self.struct_name(*mid, *sid, tys)?,
+                        args[1],
+                        args[0]
+                    ),
+                    Operation::MoveFrom(mid, sid, tys) => format!(
+                        "move_from<{}>({})",
+                        self.struct_name(*mid, *sid, tys)?,
+                        args[0]
+                    ),
+                    Operation::Exists(mid, sid, tys) => format!(
+                        "exists<{}>({})",
+                        self.struct_name(*mid, *sid, tys)?,
+                        args[0]
+                    ),
+                    Operation::BorrowGlobal(mid, sid, tys) => format!(
+                        "{}<{}>({})",
+                        if is_mut(dests[0]) {
+                            "borrow_global_mut"
+                        } else {
+                            "borrow_global"
+                        },
+                        self.struct_name(*mid, *sid, tys)?,
+                        args[0]
+                    ),
+                    Operation::BorrowLoc => format!(
+                        "{}t{}",
+                        if is_mut(dests[0]) { "&mut " } else { "&" },
+                        srcs[0]
+                    ),
+                    Operation::BorrowField(mid, sid, tys, offset) => format!(
+                        "{}{}.{}::{}",
+                        if is_mut(dests[0]) { "&mut " } else { "&" },
+                        args[0],
+                        self.struct_name(*mid, *sid, tys)?,
+                        self.field_name(*mid, *sid, *offset)
+                    ),
+                    Operation::ReadRef => format!("*{}", args[0]),
+                    Operation::FreezeRef => format!("freeze({})", args[0]),
+                    Operation::CastU8 => format!("to_u8({})", args[0]),
+                    Operation::CastU16 => format!("to_u16({})", args[0]),
+                    Operation::CastU32 => format!("to_u32({})", args[0]),
+                    Operation::CastU64 => format!("to_u64({})", args[0]),
+                    Operation::CastU128 => format!("to_u128({})", args[0]),
+                    Operation::CastU256 => format!("to_u256({})", args[0]),
+                    Operation::Not => format!("!{}", args[0]),
+                    Operation::Add => format!("{} + {}", args[0], args[1]),
+                    Operation::Sub => format!("{} - {}", args[0], args[1]),
+                    Operation::Mul => format!("{} * {}", args[0], args[1]),
+                    Operation::Div => format!("{} / {}", args[0], args[1]),
+                    Operation::Mod => format!("{} % {}", args[0], args[1]),
+                    Operation::BitOr => format!("{} | {}", args[0], args[1]),
+                    Operation::BitAnd => format!("{} & {}", args[0], args[1]),
+                    Operation::Xor => format!("{} ^ {}", args[0], args[1]),
+                    Operation::Shl => format!("{} << {}", args[0], args[1]),
+                    Operation::Shr => format!("{} >> {}", args[0], args[1]),
+                    Operation::Lt => format!("{} < {}", args[0], args[1]),
+                    Operation::Gt => format!("{} > {}", args[0], args[1]),
+                    Operation::Le => format!("{} <= {}", args[0], args[1]),
+                    Operation::Ge => format!("{} >= {}", args[0], args[1]),
+                    Operation::Or => format!("{} || {}", args[0], args[1]),
+                    Operation::And => format!("{} && {}", args[0], args[1]),
+                    Operation::Eq => format!("{} == {}", args[0], args[1]),
+                    Operation::Neq => format!("{} != {}", args[0], args[1]),
+                    _ => bail!("operation {:?} has no Move IR form", operation),
+                };
+                if dests.is_empty() {
+                    vec![format!("{};", expr)]
+                } else {
+                    vec![format!(
+                        "{} = {};",
+                        dests
+                            .iter()
+                            .map(|dest| format!("t{}", dest))
+                            .collect::<Vec<_>>()
+                            .join(", "),
+                        expr
+                    )]
+                }
+            }
+            _ => bail!("instruction {:?} has no Move IR form", bytecode),
+        })
+    }
+
+    fn constant(&self, constant: &Constant, ty: &Type) -> Result<String> {
+        let element_type = match ty {
+            Type::Vector(ty) => Some(ty.as_ref()),
+            _ => None,
+        };
+        Ok(match constant {
+            Constant::Bool(value) => value.to_string(),
+            Constant::U8(value) => format!("{}u8", value),
+            Constant::U16(value) => format!("{}u16", value),
+            Constant::U32(value) => format!("{}u32", value),
+            Constant::U64(value) => value.to_string(),
+            Constant::U128(value) => format!("{}u128", value),
+            Constant::U256(value) => format!("{}u256", value),
+            Constant::Address(Address::Numerical(address)) => address.to_hex_literal(),
+            Constant::ByteArray(bytes) => format!("h\"{}\"", hex::encode(bytes)),
+            Constant::AddressArray(addresses) => self.vector_constant(
+                addresses
+                    .iter()
+                    .map(|address| Constant::Address(address.clone()))
+                    .collect(),
+                &Type::Primitive(PrimitiveType::Address),
+            )?,
+            Constant::Vector(elements) => match element_type {
+                Some(element_type) => self.vector_constant(elements.clone(), element_type)?,
+                None => bail!("vector constant of type {:?}", ty),
+            },
+            Constant::Address(Address::Symbolic(_)) => bail!("symbolic address constant"),
+        })
+    }
+
+    /// `vec_pack_<n><T>(elements)`, nested vectors included
+    fn vector_constant(&self, elements: Vec<Constant>, element_type: &Type) -> Result<String> {
+        Ok(format!(
+            "vec_pack_{}<{}>({})",
+            elements.len(),
+            self.ty(element_type)?,
+            elements
+                .iter()
+                .map(|element| self.constant(element, element_type))
+                .collect::<Result<Vec<_>>>()?
+                .join(", ")
+        ))
+    }
+}
+
+/// Temporaries read by `bytecode`, borrowed locals included
+fn sources(bytecode: &Bytecode) -> Vec<usize> {
+    match bytecode {
+        Bytecode::Branch(_, _, _, cond) => vec![*cond],
+        Bytecode::Ret(_, srcs) | Bytecode::Call(_, _, _, srcs, _) => srcs.clone(),
+        Bytecode::Abort(_, src) | Bytecode::Assign(_, _, src, _) => vec![*src],
+        _ => vec![],
+    }
+}
+
+/// Temporaries written by `bytecode`
+fn dests(bytecode: &Bytecode) -> Vec<usize> {
+    match bytecode {
+        Bytecode::Assign(_, dest, _, _) | Bytecode::Load(_, dest, _) => vec![*dest],
+        Bytecode::Call(_, dests, _, _, _) => dests.clone(),
+        _ => vec![],
+    }
+}
+
+/// Temporaries read after each instruction of `code` before being written again
+fn live_after(code: &[Bytecode]) -> Vec<BTreeSet<usize>> {
+    let label_offsets = Bytecode::label_offsets(code);
+    let mut live_before = vec![BTreeSet::new(); code.len()];
+    let mut live_after = vec![BTreeSet::new(); code.len()];
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for pc in (0..code.len()).rev() {
+            let after: BTreeSet<usize> =
+                Bytecode::get_successors(pc as CodeOffset, code, &label_offsets)
+                    .into_iter()
+                    .filter(|succ| (*succ as usize) < code.len())
+                    .flat_map(|succ| live_before[succ as usize].iter().copied())
+                    .collect();
+            let mut before = after.clone();
+            for dest in dests(&code[pc]) {
+                before.remove(&dest);
+            }
+            before.extend(sources(&code[pc]));
+            if before != live_before[pc] {
+                live_before[pc] = before;
+                changed = true;
+            }
+            live_after[pc] = after;
+        }
+    }
+    live_after
+}
@@ -1,8 +1,15 @@
 // Copyright (c) Verichains, 2023
 
-use std::{cell::RefCell, collections::HashSet, rc::Rc};
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap, HashSet},
+    rc::Rc,
+};
 
 use move_model::ty::Type;
+use move_stackless_bytecode::stackless_bytecode::Constant;
+
+use super::{accessors::Accessor, literals::LiteralStyle};
 
 fn default_display(ty: &Type, _: &Naming) -> String {
     format!("{:?}", ty)
@@ -12,6 +19,15 @@ pub struct Naming<'a> {
     arg_count: usize,
     type_display: Rc<RefCell<dyn Fn(&Type, &Naming) -> String + 'a>>,
     referenced_vairables: Option<HashSet<usize>>,
+    /// relaxed, non-compilable syntax, see `DecompilerSettings::pseudocode`
+    pseudocode: bool,
+    /// accessors whose calls are folded into field accesses, by the name their calls have
+    accessors: Rc<HashMap<String, Accessor>>,
+    /// names of the constants detected as bit flags, used as operands of `&` and `|`
+    bit_flags: Rc<BTreeMap<Constant, String>>,
+    literal_style: LiteralStyle,
+    /// names of the locals introduced by the optimizers, by variable index
+    variable_names: Rc<HashMap<usize, String>>,
 }
 
 impl Clone for Naming<'_> {
@@ -20,6 +36,11 @@ impl Clone for Naming<'_> {
             arg_count: self.arg_count,
             type_display: self.type_display.clone(),
             referenced_vairables: self.referenced_vairables.clone(),
+            pseudocode: self.pseudocode,
+            accessors: self.accessors.clone(),
+            bit_flags: self.bit_flags.clone(),
+            literal_style: self.literal_style,
+            variable_names: self.variable_names.clone(),
         }
     }
 }
@@ -30,9 +51,65 @@ impl<'a> Naming<'a> {
             arg_count: 0,
             type_display: Rc::new(RefCell::new(default_display)),
             referenced_vairables: None,
+            pseudocode: false,
+            accessors: Rc::new(HashMap::new()),
+            bit_flags: Rc::new(BTreeMap::new()),
+            literal_style: LiteralStyle::Decimal,
+            variable_names: Rc::new(HashMap::new()),
+        }
+    }
+
+    pub fn with_pseudocode(&self) -> Self {
+        Naming {
+            pseudocode: true,
+            ..self.clone()
+        }
+    }
+
+    pub fn is_pseudocode(&self) -> bool {
+        self.pseudocode
+    }
+
+    pub fn with_accessors(&self, accessors: HashMap<String, Accessor>) -> Self {
+        Naming {
+            accessors: Rc::new(accessors),
+            ..self.clone()
         }
     }
 
+    pub fn accessor(&self, function_name: &str) -> Option<&Accessor> {
+        self.accessors.get(function_name)
+    }
+
+    pub fn with_bit_flags(&self, bit_flags: BTreeMap<Constant, String>) -> Self {
+        Naming {
+            bit_flags: Rc::new(bit_flags),
+            ..self.clone()
+        }
+    }
+
+    pub fn bit_flag(&self, constant: &Constant) -> Option<&str> {
+        self.bit_flags.get(constant).map(|name| name.as_str())
+    }
+
+    pub fn with_literal_style(&self, literal_style: LiteralStyle) -> Self {
+        Naming {
+            literal_style,
+            ..self.clone()
+        }
+    }
+
+    pub fn with_variable_names(&self, variable_names: HashMap<usize, String>) -> Self {
+        Naming {
+            variable_names: Rc::new(variable_names),
+            ..self.clone()
+        }
+    }
+
+    pub fn integer_literal(&self, decimal: String, hex: String) -> String {
+        self.literal_style.integer(decimal, hex)
+    }
+
     pub fn with_arg_count<'b>(&self, arg_count: usize) -> Naming<'b>
     where
         'a: 'b,
@@ -65,6 +142,11 @@ impl<'a> Naming<'a> {
             referenced_vairables: Some(referenced_vairables.clone()),
             type_display: self.type_display.clone(),
             arg_count: self.arg_count,
+            pseudocode: self.pseudocode,
+            accessors: self.accessors.clone(),
+            bit_flags: self.bit_flags.clone(),
+            literal_style: self.literal_style,
+            variable_names: self.variable_names.clone(),
         }
     }
 
@@ -82,6 +164,9 @@ impl<'a> Naming<'a> {
                 return self.place_holder();
             }
         }
+        if let Some(name) = self.variable_names.get(&idx) {
+            return name.clone();
+        }
         if idx < self.arg_count {
             self.argument(idx)
         } else {
@@ -0,0 +1,40 @@
+// Copyright (c) Verichains, 2023
+
+use anyhow::Result;
+use move_model::model::FunctionEnv;
+use move_stackless_bytecode::function_target::FunctionTarget;
+
+use super::{
+    cfg::{
+        algo::blocks_stackless::StacklessBasicBlock,
+        datastructs::{CodeUnitBlock, WithMetadata},
+        StacklessBlockContent,
+    },
+    reconstruct::code_unit::SourceCodeUnit,
+    Stage,
+};
+
+/// Intermediate representation handed to a `Pass`, the variant matches the stage the pass
+/// is registered after
+pub enum PassIr<'a, 'env> {
+    /// stackless bytecode, read-only as later stages re-read it from the function target
+    Stackless(&'a FunctionTarget<'env>),
+    /// basic blocks after `Stage::Blocks` or `Stage::Loops`
+    Blocks(&'a mut Vec<StacklessBasicBlock>),
+    /// structured program after `Stage::Structure`
+    Structure(&'a mut WithMetadata<CodeUnitBlock<usize, StacklessBlockContent>>),
+    /// generated source of the function body after `Stage::Source`
+    Source(&'a mut SourceCodeUnit),
+}
+
+/// A custom analysis or rewriting pass inserted into the decompilation pipeline,
+/// see `Decompiler::with_pass`
+pub trait Pass {
+    /// Name used in error messages
+    fn name(&self) -> &str;
+
+    /// Stage after which the pass runs, passes of the same stage run in registration order
+    fn stage(&self) -> Stage;
+
+    fn run(&self, function: &FunctionEnv<'_>, ir: &mut PassIr<'_, '_>) -> Result<()>;
+}
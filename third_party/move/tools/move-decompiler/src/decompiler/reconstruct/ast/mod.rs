@@ -2,6 +2,8 @@
 
 use std::collections::{HashMap, HashSet};
 
+use move_stackless_bytecode::stackless_bytecode::Constant;
+
 use crate::decompiler::evaluator::stackless::{ExprNodeOperation, ExprNodeRef};
 
 use super::super::naming::Naming;
@@ -90,6 +92,16 @@ impl DecompiledExpr {
         }
     }
 
+    /// The value of a branch made of nothing but its result, inlined as a ternary in
+    /// pseudocode
+    fn single_value(&self) -> Option<&DecompiledExprRef> {
+        if self.blocks.is_empty() {
+            self.exit.as_ref()
+        } else {
+            None
+        }
+    }
+
     pub fn has_reference_to_any_variable(&self, variables: &HashSet<usize>) -> bool {
         match self {
             DecompiledExpr::Undefined => false,
@@ -253,6 +265,7 @@ pub(crate) enum DecompiledCodeItem {
         structure_visible_name: String,
         variables: Vec<(String, usize)>,
         value: DecompiledExprRef,
+        is_decl: bool,
     },
     Statement {
         expr: DecompiledExprRef,
@@ -466,6 +479,13 @@ impl DecompiledCodeUnit {
                     }
                 }
 
+                DecompiledCodeItem::AbortStatement(_) if naming.is_pseudocode() => {
+                    source.add_line(format!(
+                        "abort{}",
+                        if iter.peek().is_none() { "" } else { ";" }
+                    ));
+                }
+
                 DecompiledCodeItem::AbortStatement(expr) => {
                     to_decl_source(
                         &mut source,
@@ -504,7 +524,12 @@ impl DecompiledCodeUnit {
                     if *is_decl {
                         to_decl_source(
                             &mut source,
-                            format!("let {} = ", naming.variable(*variable)).as_str(),
+                            format!(
+                                "let {}{} = ",
+                                naming.variable(*variable),
+                                literal_type_annotation(value)
+                            )
+                            .as_str(),
                             ";",
                             value,
                             naming,
@@ -539,6 +564,7 @@ impl DecompiledCodeUnit {
                     structure_visible_name,
                     variables,
                     value,
+                    ..
                 } => {
                     if variables.len() >= 2 {
                         source.add_line(format!("let {} {{", structure_visible_name));
@@ -594,6 +620,26 @@ impl DecompiledCodeUnit {
                         ResultUsageType::BlockResult => String::new(),
                     };
 
+                    if naming.is_pseudocode() && use_as_result != &ResultUsageType::Abort {
+                        if let (Some(if_value), Some(else_value)) =
+                            (if_unit.single_value(), else_unit.single_value())
+                        {
+                            source.add_line(format!(
+                                "{}{} ? {} : {}{}",
+                                prefix,
+                                cond.to_source(naming)?,
+                                if_value.to_source(naming)?,
+                                else_value.to_source(naming)?,
+                                if use_as_result == &ResultUsageType::None {
+                                    ";"
+                                } else {
+                                    ""
+                                }
+                            ));
+                            continue;
+                        }
+                    }
+
                     source.add_line(format!("{}if ({}) {{", prefix, cond.to_source(naming)?,));
 
                     let mut if_b = if_unit.to_source(naming, false)?;
@@ -670,6 +716,37 @@ fn to_decl_source(
     Ok(())
 }
 
+/// Type annotation for a `let` whose value is a literal of a type only inferred from later
+/// uses: integers default to `u64` when there are none, `vector[]` has no element type
+fn literal_type_annotation(value: &DecompiledExpr) -> String {
+    let DecompiledExpr::EvaluationExpr(expr) = value else {
+        return String::new();
+    };
+    match &expr.value().borrow().operation {
+        ExprNodeOperation::Const(constant) => {
+            literal_type(constant).map_or(String::new(), |ty| format!(": {}", ty))
+        }
+        _ => String::new(),
+    }
+}
+
+/// Type of a literal which does not follow from the literal itself
+fn literal_type(constant: &Constant) -> Option<String> {
+    match constant {
+        Constant::U8(_) => Some("u8".to_string()),
+        Constant::U16(_) => Some("u16".to_string()),
+        Constant::U32(_) => Some("u32".to_string()),
+        Constant::U128(_) => Some("u128".to_string()),
+        Constant::U256(_) => Some("u256".to_string()),
+        Constant::AddressArray(v) if v.is_empty() => Some("vector<address>".to_string()),
+        Constant::Vector(v) => v
+            .first()
+            .and_then(literal_type)
+            .map(|ty| format!("vector<{}>", ty)),
+        _ => None,
+    }
+}
+
 fn let_assigment_or_empty(result_variables: &Vec<usize>, naming: &Naming) -> String {
     if result_variables.is_empty() {
         String::new()
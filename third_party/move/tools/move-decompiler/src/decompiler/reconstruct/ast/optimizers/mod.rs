@@ -13,6 +13,9 @@ use self::transform::{
     cleanup_tail_exit::*, non_source_blocks::*,
     variables::*, assert::*,
     let_return::*, loops::*, if_else::*,
+    gas_hints::*, foreach::*, join_variables::*, branch_tails::*,
+    loop_invariants::*, call_ordering::*, tuple_bindings::*,
+    common_subexpressions::*, early_returns::*, conditions::*,
 };
 
 use super::super::DecompiledCodeUnitRef;
@@ -25,12 +28,32 @@ use variable_declaration::*;
 
 pub struct OptimizerSettings {
     pub disable_optimize_variables_declaration: bool,
+    /// Comment storage accesses, table operations and vector-bounded loops
+    pub annotate_gas_hints: bool,
+    /// Comment loops iterating over a vector by index with their foreach form
+    pub annotate_foreach_loops: bool,
+    /// Comment loops with the storage reads they repeat with the same arguments
+    pub annotate_loop_invariants: bool,
+    /// Comment resource writes preceded by calls into other modules since the resource was read
+    pub annotate_call_ordering: bool,
+    /// Compute pure expressions repeated in a block, like `vector::length(&v)`, once into
+    /// a named local
+    pub eliminate_common_subexpressions: bool,
+    /// Turn if/else with a short branch that returns or aborts into a guard clause followed
+    /// by the other branch
+    pub rewrite_early_returns: bool,
 }
 
 impl Default for OptimizerSettings {
     fn default() -> Self {
         Self {
             disable_optimize_variables_declaration: false,
+            annotate_gas_hints: false,
+            annotate_foreach_loops: false,
+            annotate_loop_invariants: false,
+            annotate_call_ordering: false,
+            eliminate_common_subexpressions: false,
+            rewrite_early_returns: false,
         }
     }
 }
@@ -40,7 +63,14 @@ pub(crate) fn run(
     func_target: &FunctionTarget<'_>,
     naming: &Naming,
     settings: &OptimizerSettings,
-) -> Result<(DecompiledCodeUnitRef, HashSet<usize>), anyhow::Error> {
+) -> Result<
+    (
+        DecompiledCodeUnitRef,
+        HashSet<usize>,
+        HashMap<usize, String>,
+    ),
+    anyhow::Error,
+> {
     let mut unit = unit.clone();
 
     cleanup_tail_exit(&mut unit)?;
@@ -57,9 +87,42 @@ pub(crate) fn run(
     }
 
     let mut unit = remove_non_source_blocks(&unit)?;
+    merge_join_variables(&mut unit)?;
+    bind_copied_values(&mut unit)?;
+    hoist_branch_tails(&mut unit, naming)?;
+    if settings.rewrite_early_returns {
+        rewrite_early_returns(&mut unit)?;
+    }
+    normalize_conditions(&mut unit)?;
 
     rename_variables_by_order(&mut unit, func_target);
 
+    let mut variable_names = HashMap::new();
+    if settings.eliminate_common_subexpressions {
+        eliminate_common_subexpressions(
+            &mut unit,
+            naming,
+            func_target.get_local_count(),
+            &mut variable_names,
+        )?;
+    }
+
+    if settings.annotate_foreach_loops {
+        annotate_foreach_loops(&mut unit, naming)?;
+    }
+
+    if settings.annotate_gas_hints {
+        annotate_gas_hints(&mut unit)?;
+    }
+
+    if settings.annotate_loop_invariants {
+        annotate_loop_invariants(&mut unit, naming)?;
+    }
+
+    if settings.annotate_call_ordering {
+        annotate_call_ordering(&mut unit, naming)?;
+    }
+
     let mut referenced_variables = HashSet::new();
     let mut implicit_referenced_variables = HashSet::new();
     collect_referenced_variables(
@@ -68,7 +131,7 @@ pub(crate) fn run(
         &mut implicit_referenced_variables,
     );
 
-    Ok((unit, referenced_variables))
+    Ok((unit, referenced_variables, variable_names))
 }
 
 fn rename_variables_by_order(unit: &mut DecompiledCodeUnitRef, func_target: &FunctionTarget<'_>) {
@@ -232,11 +295,13 @@ fn optimize_variables_declaration(
                     structure_visible_name,
                     variables,
                     value,
+                    is_decl,
                 } => {
                     new_unit.blocks.push(I::AssignStructureStatement {
                         structure_visible_name: structure_visible_name.clone(),
                         variables: variables.clone(),
                         value: value.commit_pending_variables(should_declare),
+                        is_decl: *is_decl,
                     });
                 }
                 I::Statement { expr } => {
@@ -0,0 +1,189 @@
+// Copyright (c) Verichains, 2023
+
+use std::collections::HashSet;
+
+use crate::decompiler::{
+    naming::Naming,
+    reconstruct::{
+        ast::ResultUsageType, DecompiledCodeItem, DecompiledCodeUnit, DecompiledExprRef,
+    },
+};
+
+use super::super::utils::is_effective_code_item;
+
+/// Hoist the statements ending both branches of an if/else after it
+/// ```ignore
+///   if (c) {             | if (c) {
+///     a = 1;             |   a = 1;
+///     foo(a);            | } else {
+///   } else {             |   a = 2;
+///     a = 2;             | };
+///     foo(a);            | foo(a);
+///   };                   |
+/// ```
+/// The if branch keeps at least one statement, statements using variables declared inside
+/// the branches stay in place.
+pub(crate) fn hoist_branch_tails(
+    unit: &mut DecompiledCodeUnit,
+    naming: &Naming,
+) -> Result<(), anyhow::Error> {
+    let mut new_blocks = Vec::new();
+
+    for mut item in std::mem::take(&mut unit.blocks) {
+        let mut hoisted = Vec::new();
+        match &mut item {
+            DecompiledCodeItem::IfElseStatement {
+                if_unit,
+                else_unit,
+                result_variables,
+                use_as_result,
+                ..
+            } => {
+                hoist_branch_tails(if_unit, naming)?;
+                hoist_branch_tails(else_unit, naming)?;
+
+                if result_variables.is_empty()
+                    && *use_as_result == ResultUsageType::None
+                    && if_unit.exit.is_none()
+                    && else_unit.exit.is_none()
+                {
+                    hoisted = common_tail(if_unit, else_unit, naming)?;
+                }
+            }
+
+            DecompiledCodeItem::WhileStatement { body, .. } => {
+                hoist_branch_tails(body, naming)?;
+            }
+
+            _ => {}
+        }
+
+        new_blocks.push(item);
+        new_blocks.extend(hoisted);
+    }
+
+    unit.blocks = new_blocks;
+
+    Ok(())
+}
+
+/// Remove the common trailing statements of both units and return them in order
+fn common_tail(
+    if_unit: &mut DecompiledCodeUnit,
+    else_unit: &mut DecompiledCodeUnit,
+    naming: &Naming,
+) -> Result<Vec<DecompiledCodeItem>, anyhow::Error> {
+    let mut declared = HashSet::new();
+    collect_declared_variables(if_unit, &mut declared);
+    collect_declared_variables(else_unit, &mut declared);
+
+    let mut tail = Vec::new();
+    while let (Some(if_last), Some(else_last)) = (if_unit.blocks.last(), else_unit.blocks.last()) {
+        let Some(source) = tail_item_source(if_last, &declared, naming)? else {
+            break;
+        };
+        if tail_item_source(else_last, &declared, naming)?.as_ref() != Some(&source)
+            || !if_unit.blocks[..if_unit.blocks.len() - 1]
+                .iter()
+                .any(is_effective_code_item)
+        {
+            break;
+        }
+        else_unit.blocks.pop();
+        tail.push(if_unit.blocks.pop().unwrap());
+    }
+
+    tail.reverse();
+    Ok(tail)
+}
+
+/// Source of a statement that can be moved out of a branch, `None` if it cannot
+fn tail_item_source(
+    item: &DecompiledCodeItem,
+    declared: &HashSet<usize>,
+    naming: &Naming,
+) -> Result<Option<String>, anyhow::Error> {
+    let uses_declared = |expr: &DecompiledExprRef| expr.has_reference_to_any_variable(declared);
+
+    Ok(match item {
+        DecompiledCodeItem::Statement { expr } if !uses_declared(expr) => {
+            Some(format!("{};", expr.to_source(naming)?))
+        }
+        DecompiledCodeItem::AssignStatement {
+            variable,
+            value,
+            is_decl: false,
+        } if !declared.contains(variable) && !uses_declared(value) => Some(format!(
+            "{} = {};",
+            naming.variable(*variable),
+            value.to_source(naming)?
+        )),
+        DecompiledCodeItem::AssignTupleStatement {
+            variables,
+            value,
+            is_decl: false,
+        } if !variables.iter().any(|v| declared.contains(v)) && !uses_declared(value) => {
+            Some(format!(
+                "({}) = {};",
+                variables
+                    .iter()
+                    .map(|v| naming.variable(*v))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                value.to_source(naming)?
+            ))
+        }
+        DecompiledCodeItem::ReturnStatement(expr) if !uses_declared(expr) => {
+            Some(format!("return {}", expr.to_source(naming)?))
+        }
+        DecompiledCodeItem::AbortStatement(expr) if !uses_declared(expr) => {
+            Some(format!("abort {}", expr.to_source(naming)?))
+        }
+        DecompiledCodeItem::BreakStatement => Some("break".to_string()),
+        DecompiledCodeItem::ContinueStatement => Some("continue".to_string()),
+        _ => None,
+    })
+}
+
+fn collect_declared_variables(unit: &DecompiledCodeUnit, declared: &mut HashSet<usize>) {
+    for item in unit.blocks.iter() {
+        match item {
+            DecompiledCodeItem::PossibleAssignStatement {
+                variable,
+                is_decl: true,
+                ..
+            }
+            | DecompiledCodeItem::AssignStatement {
+                variable,
+                is_decl: true,
+                ..
+            } => {
+                declared.insert(*variable);
+            }
+            DecompiledCodeItem::AssignTupleStatement {
+                variables,
+                is_decl: true,
+                ..
+            } => {
+                declared.extend(variables.iter().copied());
+            }
+            DecompiledCodeItem::AssignStructureStatement { variables, .. } => {
+                declared.extend(variables.iter().map(|(_, v)| *v));
+            }
+            DecompiledCodeItem::IfElseStatement {
+                if_unit,
+                else_unit,
+                result_variables,
+                ..
+            } => {
+                declared.extend(result_variables.iter().copied());
+                collect_declared_variables(if_unit, declared);
+                collect_declared_variables(else_unit, declared);
+            }
+            DecompiledCodeItem::WhileStatement { body, .. } => {
+                collect_declared_variables(body, declared);
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,248 @@
+// Copyright (c) Verichains, 2023
+
+use std::collections::HashMap;
+
+use move_model::ty::Type;
+
+use crate::decompiler::{
+    evaluator::stackless::{ExprNodeOperation, ExprNodeRef},
+    naming::Naming,
+    reconstruct::{DecompiledCodeItem, DecompiledCodeUnit, DecompiledExpr},
+};
+
+use super::super::utils::{is_function_of, PURE_MODULES};
+
+/// Storage effect of an expression, in evaluation order
+enum Event {
+    Read(Type),
+    Write(Type),
+    ExternalCall(String),
+}
+
+/// Resources read so far in the function, with the external calls made since each read
+#[derive(Default)]
+struct OrderingState {
+    reads: Vec<(Type, Vec<String>)>,
+    /// variables holding a `borrow_global_mut` of a resource
+    mutable_borrows: HashMap<usize, Type>,
+}
+
+/// Comment statements writing a resource that was read earlier in the function, when calls
+/// into other modules happen between the read and the write
+/// ```ignore
+///   let v = borrow_global<C>(a).x;
+///   coin::deposit(a, c);
+///   /* ordering: coin::deposit called between read and write of C */
+///   borrow_global_mut<C>(a).x = v + 1;
+/// ```
+/// Statements are visited in source order, branches and loop bodies once each.
+pub(crate) fn annotate_call_ordering(
+    unit: &mut DecompiledCodeUnit,
+    naming: &Naming,
+) -> Result<(), anyhow::Error> {
+    let mut state = OrderingState::default();
+    annotate_unit(unit, naming, &mut state);
+    Ok(())
+}
+
+fn annotate_unit(unit: &mut DecompiledCodeUnit, naming: &Naming, state: &mut OrderingState) {
+    let mut new_blocks = Vec::new();
+
+    for mut item in std::mem::take(&mut unit.blocks) {
+        let mut events = Vec::new();
+        match &mut item {
+            DecompiledCodeItem::ReturnStatement(expr)
+            | DecompiledCodeItem::AbortStatement(expr)
+            | DecompiledCodeItem::Statement { expr }
+            | DecompiledCodeItem::AssignTupleStatement { value: expr, .. }
+            | DecompiledCodeItem::AssignStructureStatement { value: expr, .. } => {
+                collect_expr_events(expr, state, &mut events);
+            }
+
+            DecompiledCodeItem::AssignStatement {
+                variable, value, ..
+            }
+            | DecompiledCodeItem::PossibleAssignStatement {
+                variable, value, ..
+            } => {
+                collect_expr_events(value, state, &mut events);
+                let borrow = match &**value {
+                    DecompiledExpr::EvaluationExpr(expr) => mutable_borrow_of(expr.value(), state),
+                    _ => None,
+                };
+                match borrow {
+                    Some(ty) => state.mutable_borrows.insert(*variable, ty),
+                    None => state.mutable_borrows.remove(variable),
+                };
+            }
+
+            DecompiledCodeItem::IfElseStatement {
+                cond,
+                if_unit,
+                else_unit,
+                ..
+            } => {
+                collect_expr_events(cond, state, &mut events);
+                new_blocks.extend(apply_events(events, naming, state));
+                annotate_unit(if_unit, naming, state);
+                annotate_unit(else_unit, naming, state);
+                new_blocks.push(item);
+                continue;
+            }
+
+            DecompiledCodeItem::WhileStatement { cond, body } => {
+                if let Some(cond) = cond {
+                    collect_expr_events(cond, state, &mut events);
+                }
+                new_blocks.extend(apply_events(events, naming, state));
+                annotate_unit(body, naming, state);
+                new_blocks.push(item);
+                continue;
+            }
+
+            DecompiledCodeItem::BreakStatement
+            | DecompiledCodeItem::ContinueStatement
+            | DecompiledCodeItem::CommentStatement(_) => {}
+        }
+
+        new_blocks.extend(apply_events(events, naming, state));
+        new_blocks.push(item);
+    }
+
+    if let Some(exit) = &unit.exit {
+        let mut events = Vec::new();
+        collect_expr_events(exit, state, &mut events);
+        new_blocks.extend(apply_events(events, naming, state));
+    }
+
+    unit.blocks = new_blocks;
+}
+
+/// Update the state with the events of a statement, returning the comments to put before it
+fn apply_events(
+    events: Vec<Event>,
+    naming: &Naming,
+    state: &mut OrderingState,
+) -> Vec<DecompiledCodeItem> {
+    let mut comments = Vec::new();
+    for event in events {
+        match event {
+            Event::Read(ty) => {
+                if !state.reads.iter().any(|(read, _)| *read == ty) {
+                    state.reads.push((ty, Vec::new()));
+                }
+            }
+            Event::ExternalCall(name) => {
+                for (_, calls) in state.reads.iter_mut() {
+                    if !calls.contains(&name) {
+                        calls.push(name.clone());
+                    }
+                }
+            }
+            Event::Write(ty) => {
+                if let Some((_, calls)) = state.reads.iter_mut().find(|(read, _)| *read == ty) {
+                    if !calls.is_empty() {
+                        comments.push(DecompiledCodeItem::CommentStatement(format!(
+                            "ordering: {} called between read and write of {}",
+                            calls.join(", "),
+                            naming.ty(&ty)
+                        )));
+                        calls.clear();
+                    }
+                }
+            }
+        }
+    }
+    comments
+}
+
+/// Resource a mutable reference points into: a `borrow_global_mut`, possibly followed by
+/// field borrows, or a variable holding such a reference
+fn mutable_borrow_of(expr: &ExprNodeRef, state: &OrderingState) -> Option<Type> {
+    let mut expr = expr.clone();
+    loop {
+        let next = match &expr.borrow().operation {
+            ExprNodeOperation::Func(name, _, types) if name == "borrow_global_mut" => {
+                return types.first().cloned();
+            }
+            ExprNodeOperation::LocalVariable(idx) => {
+                return state.mutable_borrows.get(idx).cloned();
+            }
+            ExprNodeOperation::Field(inner, _)
+            | ExprNodeOperation::BorrowLocal(inner, true)
+            | ExprNodeOperation::VariableSnapshot { value: inner, .. } => inner.clone(),
+            _ => return None,
+        };
+        expr = next;
+    }
+}
+
+fn collect_expr_events(expr: &DecompiledExpr, state: &OrderingState, events: &mut Vec<Event>) {
+    match expr {
+        DecompiledExpr::EvaluationExpr(expr) => collect_node_events(expr.value(), state, events),
+        DecompiledExpr::Tuple(exprs) => {
+            for expr in exprs {
+                collect_expr_events(expr, state, events);
+            }
+        }
+        DecompiledExpr::Undefined | DecompiledExpr::Variable(_) => {}
+    }
+}
+
+fn collect_node_events(expr: &ExprNodeRef, state: &OrderingState, events: &mut Vec<Event>) {
+    match &expr.borrow().operation {
+        ExprNodeOperation::Func(name, args, types) => {
+            for arg in args {
+                collect_node_events(arg, state, events);
+            }
+            let resource = types.first().cloned();
+            match (name.as_str(), resource) {
+                // a mutable borrow only writes through a later `WriteRef`
+                ("borrow_global", Some(ty))
+                | ("borrow_global_mut", Some(ty))
+                | ("exists", Some(ty)) => events.push(Event::Read(ty)),
+                ("move_to", Some(ty)) | ("move_from", Some(ty)) => events.push(Event::Write(ty)),
+                _ => {
+                    if name.contains("::")
+                        && !PURE_MODULES.iter().any(|m| is_function_of(name, &[*m], ""))
+                    {
+                        events.push(Event::ExternalCall(name.clone()));
+                    }
+                }
+            }
+        }
+        ExprNodeOperation::WriteRef(dst, src) => {
+            collect_node_events(dst, state, events);
+            collect_node_events(src, state, events);
+            if let Some(ty) = mutable_borrow_of(dst, state) {
+                events.push(Event::Write(ty));
+            }
+        }
+        ExprNodeOperation::StructPack(_, args, _) => {
+            for (_, arg) in args {
+                collect_node_events(arg, state, events);
+            }
+        }
+        ExprNodeOperation::Binary(_, a, b) => {
+            collect_node_events(a, state, events);
+            collect_node_events(b, state, events);
+        }
+        ExprNodeOperation::Field(expr, _)
+        | ExprNodeOperation::Unary(_, expr)
+        | ExprNodeOperation::Cast(_, expr, _)
+        | ExprNodeOperation::Destroy(expr)
+        | ExprNodeOperation::FreezeRef(expr)
+        | ExprNodeOperation::ReadRef(expr)
+        | ExprNodeOperation::BorrowLocal(expr, _)
+        | ExprNodeOperation::StructUnpack(_, _, expr, _)
+        | ExprNodeOperation::VariableSnapshot { value: expr, .. } => {
+            collect_node_events(expr, state, events)
+        }
+        ExprNodeOperation::Ignored
+        | ExprNodeOperation::Deleted
+        | ExprNodeOperation::NonTrivial
+        | ExprNodeOperation::Raw(_)
+        | ExprNodeOperation::Const(_)
+        | ExprNodeOperation::LocalVariable(_) => {}
+    }
+}
@@ -0,0 +1,433 @@
+// Copyright (c) Verichains, 2023
+
+use std::collections::{HashMap, HashSet};
+
+use crate::decompiler::{
+    evaluator::stackless::{Expr, ExprNode, ExprNodeOperation, ExprNodeRef},
+    naming::Naming,
+    reconstruct::{DecompiledCodeItem, DecompiledCodeUnit, DecompiledExpr},
+};
+
+use super::super::utils::is_function_of;
+
+/// Functions without side effects and that cannot abort, with the suffix of the name of
+/// the local holding their result
+const PURE_FUNCTIONS: &[(&str, &str, &str)] = &[
+    ("vector", "length", "len"),
+    ("vector", "is_empty", "is_empty"),
+    ("string", "length", "len"),
+    ("string", "is_empty", "is_empty"),
+    ("option", "is_some", "is_some"),
+    ("option", "is_none", "is_none"),
+    ("signer", "address_of", "addr"),
+    ("table", "length", "len"),
+    ("table_with_length", "length", "len"),
+    ("smart_table", "length", "len"),
+    ("simple_map", "length", "len"),
+];
+
+/// Calls that do not modify locals or what references point to
+const NON_MUTATING_CALLS: &[&str] = &["assert!", "borrow_global", "exists"];
+
+/// What a statement may modify
+struct Effects {
+    /// anything reachable from a local, through a call, a write or a mutable borrow
+    all: bool,
+    assigned_variables: HashSet<usize>,
+}
+
+/// Occurrences of a candidate in consecutive statements not modifying its variables
+struct Group {
+    first: usize,
+    last: usize,
+    count: usize,
+    template: ExprNodeRef,
+    variables: HashSet<usize>,
+}
+
+/// Compute pure expressions repeated in a block, calls to `PURE_FUNCTIONS` and immutable
+/// borrows of fields, once into a local named after them
+/// ```ignore
+///   assert!(vector::length(&v0) > 0, 1);   | let v0_len = vector::length(&v0);
+///   let v1 = vector::length(&v0) - 1;      | assert!(v0_len > 0, 1);
+///                                          | let v1 = v0_len - 1;
+/// ```
+/// Occurrences are only shared between statements of the same block with no call, reference
+/// write or mutable borrow between them and no assignment to their variables. The names of
+/// the new locals, numbered from `first_free_variable`, are added to `names`.
+pub(crate) fn eliminate_common_subexpressions(
+    unit: &mut DecompiledCodeUnit,
+    naming: &Naming,
+    first_free_variable: usize,
+    names: &mut HashMap<usize, String>,
+) -> Result<(), anyhow::Error> {
+    let mut next_variable = first_free_variable;
+    eliminate_in(unit, naming, &mut next_variable, names)
+}
+
+fn eliminate_in(
+    unit: &mut DecompiledCodeUnit,
+    naming: &Naming,
+    next_variable: &mut usize,
+    names: &mut HashMap<usize, String>,
+) -> Result<(), anyhow::Error> {
+    for item in unit.blocks.iter_mut() {
+        match item {
+            DecompiledCodeItem::IfElseStatement {
+                if_unit, else_unit, ..
+            } => {
+                eliminate_in(if_unit, naming, next_variable, names)?;
+                eliminate_in(else_unit, naming, next_variable, names)?;
+            }
+            DecompiledCodeItem::WhileStatement { body, .. } => {
+                eliminate_in(body, naming, next_variable, names)?;
+            }
+            _ => {}
+        }
+    }
+
+    // the exit expression is the statement after the last one
+    let mut groups: HashMap<String, Group> = HashMap::new();
+    let mut shared = Vec::new();
+    for idx in 0..=unit.blocks.len() {
+        let (exprs, effects) = match unit.blocks.get(idx) {
+            Some(item) => statement_effects(item),
+            None => (
+                unit.exit.iter().map(|x| &**x).collect(),
+                Effects {
+                    all: false,
+                    assigned_variables: HashSet::new(),
+                },
+            ),
+        };
+
+        if effects.all {
+            shared.extend(groups.drain().filter(|(_, group)| group.count >= 2));
+            continue;
+        }
+
+        let mut candidates = Vec::new();
+        for expr in exprs {
+            collect_decompiled_candidates(expr, &mut candidates);
+        }
+        for candidate in candidates {
+            let key = candidate.borrow().to_source(naming)?;
+            let group = groups.entry(key).or_insert_with(|| {
+                let mut variables = HashSet::new();
+                collect_variables(&candidate, &mut variables);
+                Group {
+                    first: idx,
+                    last: idx,
+                    count: 0,
+                    template: candidate.borrow().copy_as_ref(),
+                    variables,
+                }
+            });
+            group.last = idx;
+            group.count += 1;
+        }
+
+        let invalidated = groups
+            .iter()
+            .filter(|(_, group)| !group.variables.is_disjoint(&effects.assigned_variables))
+            .map(|(key, _)| key.clone())
+            .collect::<Vec<_>>();
+        for key in invalidated {
+            let group = groups.remove(&key).unwrap();
+            if group.count >= 2 {
+                shared.push((key, group));
+            }
+        }
+    }
+    shared.extend(groups.into_iter().filter(|(_, group)| group.count >= 2));
+    shared.sort_by(|(ka, a), (kb, b)| a.first.cmp(&b.first).then(ka.cmp(kb)));
+
+    let mut declarations = Vec::new();
+    for (key, group) in shared {
+        let variable = *next_variable;
+        *next_variable += 1;
+        names.insert(variable, local_name(&group.template, naming, names));
+
+        for idx in group.first..=group.last {
+            match unit.blocks.get_mut(idx) {
+                Some(item) => {
+                    for expr in statement_exprs_mut(item) {
+                        *expr = expr.copy_as_ref();
+                        replace_in_decompiled(expr, &key, naming, variable)?;
+                    }
+                }
+                None => {
+                    if let Some(exit) = unit.exit.as_mut() {
+                        *exit = exit.copy_as_ref();
+                        replace_in_decompiled(exit, &key, naming, variable)?;
+                    }
+                }
+            }
+        }
+        declarations.push((group.first, variable, group.template));
+    }
+
+    // later positions first, so that the earlier ones stay valid
+    declarations.sort_by(|a, b| b.0.cmp(&a.0).then(b.1.cmp(&a.1)));
+    for (position, variable, template) in declarations {
+        unit.blocks.insert(
+            position,
+            DecompiledCodeItem::AssignStatement {
+                variable,
+                value: DecompiledExpr::EvaluationExpr(Expr::new(template)).boxed(),
+                is_decl: true,
+            },
+        );
+    }
+
+    Ok(())
+}
+
+/// Expressions evaluated by a statement itself, not by nested blocks, and what it modifies
+fn statement_effects(item: &DecompiledCodeItem) -> (Vec<&DecompiledExpr>, Effects) {
+    let mut assigned_variables = HashSet::new();
+    let exprs: Vec<&DecompiledExpr> = match item {
+        DecompiledCodeItem::ReturnStatement(expr)
+        | DecompiledCodeItem::AbortStatement(expr)
+        | DecompiledCodeItem::Statement { expr } => vec![&**expr],
+        DecompiledCodeItem::AssignStatement {
+            variable, value, ..
+        }
+        | DecompiledCodeItem::PossibleAssignStatement {
+            variable, value, ..
+        } => {
+            assigned_variables.insert(*variable);
+            vec![&**value]
+        }
+        DecompiledCodeItem::AssignTupleStatement {
+            variables, value, ..
+        } => {
+            assigned_variables.extend(variables.iter());
+            vec![&**value]
+        }
+        DecompiledCodeItem::AssignStructureStatement {
+            variables, value, ..
+        } => {
+            assigned_variables.extend(variables.iter().map(|(_, v)| *v));
+            vec![&**value]
+        }
+        DecompiledCodeItem::IfElseStatement { .. } | DecompiledCodeItem::WhileStatement { .. } => {
+            return (
+                vec![],
+                Effects {
+                    all: true,
+                    assigned_variables,
+                },
+            );
+        }
+        DecompiledCodeItem::BreakStatement
+        | DecompiledCodeItem::ContinueStatement
+        | DecompiledCodeItem::CommentStatement(_) => vec![],
+    };
+    let all = exprs.iter().any(|expr| decompiled_may_mutate(expr));
+    (
+        exprs,
+        Effects {
+            all,
+            assigned_variables,
+        },
+    )
+}
+
+fn statement_exprs_mut(item: &mut DecompiledCodeItem) -> Vec<&mut Box<DecompiledExpr>> {
+    match item {
+        DecompiledCodeItem::ReturnStatement(expr)
+        | DecompiledCodeItem::AbortStatement(expr)
+        | DecompiledCodeItem::Statement { expr }
+        | DecompiledCodeItem::AssignStatement { value: expr, .. }
+        | DecompiledCodeItem::PossibleAssignStatement { value: expr, .. }
+        | DecompiledCodeItem::AssignTupleStatement { value: expr, .. }
+        | DecompiledCodeItem::AssignStructureStatement { value: expr, .. } => vec![expr],
+        DecompiledCodeItem::IfElseStatement { .. }
+        | DecompiledCodeItem::WhileStatement { .. }
+        | DecompiledCodeItem::BreakStatement
+        | DecompiledCodeItem::ContinueStatement
+        | DecompiledCodeItem::CommentStatement(_) => vec![],
+    }
+}
+
+fn pure_function_suffix(name: &str) -> Option<&'static str> {
+    PURE_FUNCTIONS
+        .iter()
+        .find(|(module, function, _)| is_function_of(name, &[*module], function))
+        .map(|(_, _, suffix)| *suffix)
+}
+
+/// Variables, constants and the fields, dereferences and immutable borrows of them
+fn is_pure_operand(expr: &ExprNodeRef) -> bool {
+    match &expr.borrow().operation {
+        ExprNodeOperation::LocalVariable(_) | ExprNodeOperation::Const(_) => true,
+        ExprNodeOperation::Field(inner, _)
+        | ExprNodeOperation::ReadRef(inner)
+        | ExprNodeOperation::FreezeRef(inner)
+        | ExprNodeOperation::BorrowLocal(inner, false) => is_pure_operand(inner),
+        _ => false,
+    }
+}
+
+/// Call of a pure function on pure operands, or immutable borrow of a field of a variable
+fn is_candidate(expr: &ExprNodeRef) -> bool {
+    match &expr.borrow().operation {
+        ExprNodeOperation::Func(name, args, _) => {
+            pure_function_suffix(name).is_some() && args.iter().all(is_pure_operand)
+        }
+        ExprNodeOperation::BorrowLocal(inner, false) => {
+            matches!(inner.borrow().operation, ExprNodeOperation::Field(..))
+                && is_pure_operand(inner)
+        }
+        _ => false,
+    }
+}
+
+fn children(expr: &ExprNodeRef) -> Vec<ExprNodeRef> {
+    match &expr.borrow().operation {
+        ExprNodeOperation::Func(_, args, _) => args.clone(),
+        ExprNodeOperation::StructPack(_, args, _) => args.iter().map(|(_, x)| x.clone()).collect(),
+        ExprNodeOperation::Binary(_, a, b) | ExprNodeOperation::WriteRef(a, b) => {
+            vec![a.clone(), b.clone()]
+        }
+        ExprNodeOperation::Field(inner, _)
+        | ExprNodeOperation::Unary(_, inner)
+        | ExprNodeOperation::Cast(_, inner, _)
+        | ExprNodeOperation::Destroy(inner)
+        | ExprNodeOperation::FreezeRef(inner)
+        | ExprNodeOperation::ReadRef(inner)
+        | ExprNodeOperation::BorrowLocal(inner, _)
+        | ExprNodeOperation::StructUnpack(_, _, inner, _)
+        | ExprNodeOperation::VariableSnapshot { value: inner, .. } => vec![inner.clone()],
+        ExprNodeOperation::Ignored
+        | ExprNodeOperation::Deleted
+        | ExprNodeOperation::NonTrivial
+        | ExprNodeOperation::Raw(_)
+        | ExprNodeOperation::Const(_)
+        | ExprNodeOperation::LocalVariable(_) => vec![],
+    }
+}
+
+fn decompiled_children(expr: &DecompiledExpr) -> Vec<ExprNodeRef> {
+    match expr {
+        DecompiledExpr::EvaluationExpr(expr) => vec![expr.value().clone()],
+        DecompiledExpr::Tuple(exprs) => exprs.iter().flat_map(|x| decompiled_children(x)).collect(),
+        DecompiledExpr::Undefined | DecompiledExpr::Variable(_) => vec![],
+    }
+}
+
+fn decompiled_may_mutate(expr: &DecompiledExpr) -> bool {
+    decompiled_children(expr).iter().any(may_mutate)
+}
+
+fn may_mutate(expr: &ExprNodeRef) -> bool {
+    let mutates = match &expr.borrow().operation {
+        ExprNodeOperation::Func(name, _, _) => {
+            pure_function_suffix(name).is_none() && !NON_MUTATING_CALLS.contains(&name.as_str())
+        }
+        ExprNodeOperation::WriteRef(..) | ExprNodeOperation::BorrowLocal(_, true) => true,
+        _ => false,
+    };
+    mutates || children(expr).iter().any(may_mutate)
+}
+
+fn collect_decompiled_candidates(expr: &DecompiledExpr, candidates: &mut Vec<ExprNodeRef>) {
+    for child in decompiled_children(expr) {
+        collect_candidates(&child, candidates);
+    }
+}
+
+fn collect_candidates(expr: &ExprNodeRef, candidates: &mut Vec<ExprNodeRef>) {
+    if is_candidate(expr) {
+        candidates.push(expr.clone());
+        return;
+    }
+    for child in children(expr) {
+        collect_candidates(&child, candidates);
+    }
+}
+
+fn collect_variables(expr: &ExprNodeRef, variables: &mut HashSet<usize>) {
+    if let ExprNodeOperation::LocalVariable(idx) = &expr.borrow().operation {
+        variables.insert(*idx);
+    }
+    for child in children(expr) {
+        collect_variables(&child, variables);
+    }
+}
+
+fn replace_in_decompiled(
+    expr: &mut DecompiledExpr,
+    key: &str,
+    naming: &Naming,
+    variable: usize,
+) -> Result<(), anyhow::Error> {
+    for child in decompiled_children(expr) {
+        replace(&child, key, naming, variable)?;
+    }
+    Ok(())
+}
+
+/// Replace the candidates rendered as `key` by `variable`, the same way they were collected
+fn replace(
+    expr: &ExprNodeRef,
+    key: &str,
+    naming: &Naming,
+    variable: usize,
+) -> Result<(), anyhow::Error> {
+    if is_candidate(expr) {
+        if expr.borrow().to_source(naming)? == key {
+            *expr.borrow_mut() = ExprNode {
+                operation: ExprNodeOperation::LocalVariable(variable),
+            };
+        }
+        return Ok(());
+    }
+    for child in children(expr) {
+        replace(&child, key, naming, variable)?;
+    }
+    Ok(())
+}
+
+/// `<variable>_<suffix>` for calls, `<variable>_<fields>` for field borrows
+fn local_name(template: &ExprNodeRef, naming: &Naming, names: &HashMap<usize, String>) -> String {
+    fn root(expr: &ExprNodeRef, fields: &mut Vec<String>) -> Option<usize> {
+        match &expr.borrow().operation {
+            ExprNodeOperation::LocalVariable(idx) => Some(*idx),
+            ExprNodeOperation::Field(inner, name) => {
+                let idx = root(inner, fields);
+                fields.push(name.clone());
+                idx
+            }
+            ExprNodeOperation::ReadRef(inner)
+            | ExprNodeOperation::FreezeRef(inner)
+            | ExprNodeOperation::BorrowLocal(inner, _) => root(inner, fields),
+            _ => None,
+        }
+    }
+
+    let mut fields = Vec::new();
+    let (variable, suffix) = match &template.borrow().operation {
+        ExprNodeOperation::Func(name, args, _) => (
+            args.first().and_then(|arg| root(arg, &mut Vec::new())),
+            pure_function_suffix(name).unwrap_or("value").to_string(),
+        ),
+        _ => {
+            let variable = root(template, &mut fields);
+            (variable, fields.join("_"))
+        }
+    };
+    let base = match variable {
+        Some(idx) => format!("{}_{}", naming.variable(idx), suffix),
+        None => suffix,
+    };
+
+    let mut name = base.clone();
+    let mut counter = 2;
+    while names.values().any(|x| *x == name) {
+        name = format!("{}_{}", base, counter);
+        counter += 1;
+    }
+    name
+}
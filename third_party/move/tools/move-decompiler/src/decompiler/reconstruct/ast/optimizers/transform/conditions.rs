@@ -0,0 +1,65 @@
+// Copyright (c) Verichains, 2023
+
+use crate::decompiler::{
+    evaluator::stackless::{ExprNodeOperation, ExprNodeRef},
+    reconstruct::{DecompiledCodeItem, DecompiledCodeUnit, DecompiledExpr, DecompiledExprRef},
+};
+
+use super::super::utils::negate_node;
+
+/// Rewrite negated conditions of if/else and while statements left by structuring, like
+/// `if (!(a != b))` into `if (a == b)` and `while (!!c)` into `while (c)`
+pub(crate) fn normalize_conditions(unit: &mut DecompiledCodeUnit) -> Result<(), anyhow::Error> {
+    for item in unit.blocks.iter_mut() {
+        match item {
+            DecompiledCodeItem::IfElseStatement {
+                cond,
+                if_unit,
+                else_unit,
+                ..
+            } => {
+                normalize_condition(cond)?;
+                normalize_conditions(if_unit)?;
+                normalize_conditions(else_unit)?;
+            }
+
+            DecompiledCodeItem::WhileStatement { cond, body } => {
+                if let Some(cond) = cond {
+                    normalize_condition(cond)?;
+                }
+                normalize_conditions(body)?;
+            }
+
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+fn normalize_condition(cond: &mut DecompiledExprRef) -> Result<(), anyhow::Error> {
+    if !matches!(cond.as_ref(), DecompiledExpr::EvaluationExpr(..)) {
+        return Ok(());
+    }
+    let mut expr = cond.to_expr()?;
+    let mut changed = false;
+    while let Some(simplified) = simplify_not(&expr) {
+        expr = simplified;
+        changed = true;
+    }
+    if changed {
+        let expr = expr.borrow().operation.to_expr();
+        *cond = DecompiledExpr::EvaluationExpr(expr).boxed();
+    }
+
+    Ok(())
+}
+
+/// `!x` without the `!`, if `x` can be negated without one
+fn simplify_not(expr: &ExprNodeRef) -> Option<ExprNodeRef> {
+    match &expr.borrow().operation {
+        ExprNodeOperation::Unary(op, inner) if op == "!" => negate_node(inner),
+        ExprNodeOperation::VariableSnapshot { value, .. } => simplify_not(value),
+        _ => None,
+    }
+}
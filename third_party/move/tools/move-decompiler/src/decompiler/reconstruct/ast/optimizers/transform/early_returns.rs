@@ -0,0 +1,101 @@
+// Copyright (c) Verichains, 2023
+
+use crate::decompiler::reconstruct::{
+    ast::ResultUsageType, DecompiledCodeItem, DecompiledCodeUnit, DecompiledExpr, DecompiledExprRef,
+};
+
+use super::super::utils::{expr_not, is_effective_code_item, negate_node};
+
+/// Largest number of statements of a branch turned into a guard clause
+const MAX_GUARD_STATEMENTS: usize = 3;
+
+/// Turn an if/else whose short branch always returns or aborts into a guard clause followed
+/// by the statements of the other branch
+/// ```ignore
+///   if (c) {             | if (!c) {
+///     foo();             |   return 1
+///     bar();             | };
+///   } else {             | foo();
+///     return 1           | bar();
+///   };                   |
+/// ```
+/// The guard branch has at most `MAX_GUARD_STATEMENTS` statements and fewer than the other
+/// branch, the if/else must not produce a value.
+pub(crate) fn rewrite_early_returns(unit: &mut DecompiledCodeUnit) -> Result<(), anyhow::Error> {
+    let mut new_blocks = Vec::new();
+
+    for mut item in std::mem::take(&mut unit.blocks) {
+        match &mut item {
+            DecompiledCodeItem::IfElseStatement {
+                cond,
+                if_unit,
+                else_unit,
+                result_variables,
+                use_as_result,
+            } => {
+                rewrite_early_returns(if_unit)?;
+                rewrite_early_returns(else_unit)?;
+
+                if result_variables.is_empty() && *use_as_result == ResultUsageType::None {
+                    if is_guard(else_unit, if_unit) {
+                        let body = std::mem::take(&mut if_unit.blocks);
+                        *cond = negate(cond)?;
+                        std::mem::swap(if_unit, else_unit);
+                        new_blocks.push(item);
+                        new_blocks.extend(body);
+                        continue;
+                    }
+
+                    if is_guard(if_unit, else_unit) {
+                        let body = std::mem::take(&mut else_unit.blocks);
+                        new_blocks.push(item);
+                        new_blocks.extend(body);
+                        continue;
+                    }
+                }
+            }
+
+            DecompiledCodeItem::WhileStatement { body, .. } => {
+                rewrite_early_returns(body)?;
+            }
+
+            _ => {}
+        }
+
+        new_blocks.push(item);
+    }
+
+    unit.blocks = new_blocks;
+
+    Ok(())
+}
+
+/// `guard` is short, always ends with a return or an abort and `body` is longer
+fn is_guard(guard: &DecompiledCodeUnit, body: &DecompiledCodeUnit) -> bool {
+    if guard.exit.is_some() || body.exit.is_some() {
+        return false;
+    }
+
+    let effective = |unit: &DecompiledCodeUnit| {
+        unit.blocks
+            .iter()
+            .filter(|x| is_effective_code_item(x))
+            .collect::<Vec<_>>()
+    };
+    let guard_statements = effective(guard);
+    let body_statements = effective(body);
+
+    matches!(
+        guard_statements.last(),
+        Some(DecompiledCodeItem::ReturnStatement(_) | DecompiledCodeItem::AbortStatement(_))
+    ) && guard_statements.len() <= MAX_GUARD_STATEMENTS
+        && body_statements.len() > guard_statements.len()
+}
+
+/// Negated condition, inverting comparisons and removing double negations
+fn negate(cond: &DecompiledExpr) -> Result<DecompiledExprRef, anyhow::Error> {
+    let cond = cond.to_expr()?;
+    let negated = negate_node(&cond).unwrap_or_else(|| expr_not(cond));
+    let negated = negated.borrow().operation.to_expr();
+    Ok(DecompiledExpr::EvaluationExpr(negated).boxed())
+}
@@ -0,0 +1,258 @@
+// Copyright (c) Verichains, 2023
+
+use move_stackless_bytecode::stackless_bytecode::Constant;
+
+use crate::decompiler::{
+    evaluator::stackless::{ExprNodeOperation, ExprNodeRef},
+    naming::Naming,
+    reconstruct::{DecompiledCodeItem, DecompiledCodeUnit, DecompiledExpr},
+};
+
+use super::super::utils::is_function_of;
+
+/// Comment loops iterating over a vector by index with their foreach form
+/// ```ignore
+///   let len = vector::length(v);        | let len = vector::length(v);
+///   while (i < len) {                   | /* for each &elem in v (index i) */
+///     let elem = vector::borrow(v, i);  | while (i < len) {
+///     [body]                            |   ...
+///     i = i + 1;                        |
+///   }                                   |
+/// ```
+/// The length may also be read in the condition directly, the element is named when the
+/// body starts by borrowing it.
+pub(crate) fn annotate_foreach_loops(
+    unit: &mut DecompiledCodeUnit,
+    naming: &Naming,
+) -> Result<(), anyhow::Error> {
+    let mut new_blocks = Vec::new();
+
+    for mut item in std::mem::take(&mut unit.blocks) {
+        match &mut item {
+            DecompiledCodeItem::IfElseStatement {
+                if_unit, else_unit, ..
+            } => {
+                annotate_foreach_loops(if_unit, naming)?;
+                annotate_foreach_loops(else_unit, naming)?;
+            }
+
+            DecompiledCodeItem::WhileStatement { cond, body } => {
+                if let Some(cond) = cond {
+                    if let Some(comment) = foreach_comment(&new_blocks, cond, body, naming)? {
+                        new_blocks.push(DecompiledCodeItem::CommentStatement(comment));
+                    }
+                }
+                annotate_foreach_loops(body, naming)?;
+            }
+
+            _ => {}
+        }
+
+        new_blocks.push(item);
+    }
+
+    unit.blocks = new_blocks;
+
+    Ok(())
+}
+
+fn foreach_comment(
+    preceding: &[DecompiledCodeItem],
+    cond: &DecompiledExpr,
+    body: &DecompiledCodeUnit,
+    naming: &Naming,
+) -> Result<Option<String>, anyhow::Error> {
+    let Some(cond) = evaluation_expr(cond) else {
+        return Ok(None);
+    };
+    let (index, bound) = match &cond.borrow().operation {
+        ExprNodeOperation::Binary(op, a, b) if op == "<" => match local_variable(a) {
+            Some(index) => (index, b.clone()),
+            None => return Ok(None),
+        },
+        _ => return Ok(None),
+    };
+
+    // the length is either read in the condition or hoisted before the loop
+    let vector = match vector_length_arg(&bound) {
+        Some(vector) => vector,
+        None => {
+            let Some(len) = local_variable(&bound) else {
+                return Ok(None);
+            };
+            let hoisted = preceding.iter().rev().find_map(|item| match item {
+                DecompiledCodeItem::AssignStatement {
+                    variable, value, ..
+                } if *variable == len => {
+                    Some(evaluation_expr(value).and_then(|value| vector_length_arg(&value)))
+                }
+                _ => None,
+            });
+            match hoisted.flatten() {
+                Some(vector) if !assigns_variable(body, len) => vector,
+                _ => return Ok(None),
+            }
+        }
+    };
+
+    if !ends_with_increment(body, index) || count_assignments(body, index) != 1 {
+        return Ok(None);
+    }
+
+    let vector_source = vector.borrow().to_source(naming)?;
+    let index_name = naming.variable(index);
+
+    // name the element after the variable it is borrowed into at the start of the body
+    let element = body.blocks.iter().find_map(|item| match item {
+        DecompiledCodeItem::AssignStatement {
+            variable,
+            value,
+            is_decl: true,
+        } => evaluation_expr(value)
+            .and_then(|value| vector_borrow(&value, &vector_source, index, naming))
+            .map(|is_mut| (*variable, is_mut)),
+        _ => None,
+    });
+
+    Ok(Some(match element {
+        Some((variable, is_mut)) => format!(
+            "for each {}{} in {} (index {})",
+            if is_mut { "&mut " } else { "&" },
+            naming.variable(variable),
+            vector_source,
+            index_name
+        ),
+        None => format!(
+            "for each element of {} (index {})",
+            vector_source, index_name
+        ),
+    }))
+}
+
+fn evaluation_expr(expr: &DecompiledExpr) -> Option<ExprNodeRef> {
+    match expr {
+        DecompiledExpr::EvaluationExpr(expr) => Some(expr.value().clone()),
+        _ => None,
+    }
+}
+
+fn local_variable(expr: &ExprNodeRef) -> Option<usize> {
+    match &expr.borrow().operation {
+        ExprNodeOperation::LocalVariable(v) => Some(*v),
+        ExprNodeOperation::VariableSnapshot { variable, .. } => Some(*variable),
+        _ => None,
+    }
+}
+
+/// `v` of `vector::length(v)`
+fn vector_length_arg(expr: &ExprNodeRef) -> Option<ExprNodeRef> {
+    match &expr.borrow().operation {
+        ExprNodeOperation::Func(name, args, _)
+            if args.len() == 1 && is_function_of(name, &["vector"], "length") =>
+        {
+            Some(args[0].clone())
+        }
+        _ => None,
+    }
+}
+
+/// `Some(is_mut)` if `expr` is `vector::borrow(_mut)(vector, index)`
+fn vector_borrow(
+    expr: &ExprNodeRef,
+    vector_source: &str,
+    index: usize,
+    naming: &Naming,
+) -> Option<bool> {
+    match &expr.borrow().operation {
+        ExprNodeOperation::Func(name, args, _) if args.len() == 2 => {
+            let is_mut = if is_function_of(name, &["vector"], "borrow") {
+                false
+            } else if is_function_of(name, &["vector"], "borrow_mut") {
+                true
+            } else {
+                return None;
+            };
+            let same_vector = args[0]
+                .borrow()
+                .to_source(naming)
+                .map_or(false, |source| source == vector_source);
+            (same_vector && local_variable(&args[1]) == Some(index)).then_some(is_mut)
+        }
+        _ => None,
+    }
+}
+
+fn ends_with_increment(body: &DecompiledCodeUnit, index: usize) -> bool {
+    let last = body
+        .blocks
+        .iter()
+        .rev()
+        .find(|item| !matches!(item, DecompiledCodeItem::CommentStatement(_)));
+    let Some(DecompiledCodeItem::AssignStatement {
+        variable,
+        value,
+        is_decl: false,
+    }) = last
+    else {
+        return false;
+    };
+    let Some(value) = evaluation_expr(value).filter(|_| *variable == index) else {
+        return false;
+    };
+    let node = value.borrow();
+    match &node.operation {
+        ExprNodeOperation::Binary(op, a, b) if op == "+" => {
+            local_variable(a) == Some(index)
+                && matches!(
+                    &b.borrow().operation,
+                    ExprNodeOperation::Const(
+                        Constant::U8(1)
+                            | Constant::U16(1)
+                            | Constant::U32(1)
+                            | Constant::U64(1)
+                            | Constant::U128(1)
+                    )
+                )
+        }
+        _ => false,
+    }
+}
+
+fn assigns_variable(unit: &DecompiledCodeUnit, variable: usize) -> bool {
+    count_assignments(unit, variable) > 0
+}
+
+fn count_assignments(unit: &DecompiledCodeUnit, variable: usize) -> usize {
+    unit.blocks
+        .iter()
+        .map(|item| match item {
+            DecompiledCodeItem::AssignStatement { variable: v, .. }
+            | DecompiledCodeItem::PossibleAssignStatement { variable: v, .. } => {
+                (*v == variable) as usize
+            }
+            DecompiledCodeItem::AssignTupleStatement { variables, .. } => {
+                variables.iter().filter(|v| **v == variable).count()
+            }
+            DecompiledCodeItem::AssignStructureStatement { variables, .. } => {
+                variables.iter().filter(|(_, v)| *v == variable).count()
+            }
+            DecompiledCodeItem::IfElseStatement {
+                if_unit,
+                else_unit,
+                result_variables,
+                ..
+            } => {
+                count_assignments(if_unit, variable)
+                    + count_assignments(else_unit, variable)
+                    + result_variables.iter().filter(|v| **v == variable).count()
+            }
+            DecompiledCodeItem::WhileStatement { body, .. } => count_assignments(body, variable),
+            DecompiledCodeItem::ReturnStatement(_)
+            | DecompiledCodeItem::AbortStatement(_)
+            | DecompiledCodeItem::BreakStatement
+            | DecompiledCodeItem::ContinueStatement
+            | DecompiledCodeItem::CommentStatement(_)
+            | DecompiledCodeItem::Statement { .. } => 0,
+        })
+        .sum()
+}
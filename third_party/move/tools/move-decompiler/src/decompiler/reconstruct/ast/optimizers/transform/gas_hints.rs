@@ -0,0 +1,157 @@
+// Copyright (c) Verichains, 2023
+
+use crate::decompiler::{
+    evaluator::stackless::{ExprNodeOperation, ExprNodeRef},
+    reconstruct::{DecompiledCodeItem, DecompiledCodeUnit, DecompiledExpr},
+};
+
+use super::super::utils::is_function_of;
+
+const STORAGE_OPERATIONS: &[&str] = &[
+    "borrow_global",
+    "borrow_global_mut",
+    "move_to",
+    "move_from",
+    "exists",
+];
+
+const TABLE_MODULES: &[&str] = &["table", "table_with_length", "smart_table"];
+
+/// Add a comment before statements touching global storage or tables, and before loops
+/// bounded by the length of a vector, as those dominate the gas cost of a function
+pub(crate) fn annotate_gas_hints(unit: &mut DecompiledCodeUnit) -> Result<(), anyhow::Error> {
+    let mut new_blocks = Vec::new();
+
+    for mut item in std::mem::take(&mut unit.blocks) {
+        let mut names = Vec::new();
+        match &mut item {
+            DecompiledCodeItem::ReturnStatement(expr)
+            | DecompiledCodeItem::AbortStatement(expr)
+            | DecompiledCodeItem::Statement { expr }
+            | DecompiledCodeItem::AssignStatement { value: expr, .. }
+            | DecompiledCodeItem::AssignTupleStatement { value: expr, .. }
+            | DecompiledCodeItem::AssignStructureStatement { value: expr, .. } => {
+                collect_decompiled_expr_function_names(expr, &mut names);
+            }
+
+            DecompiledCodeItem::IfElseStatement {
+                cond,
+                if_unit,
+                else_unit,
+                ..
+            } => {
+                collect_decompiled_expr_function_names(cond, &mut names);
+                annotate_gas_hints(if_unit)?;
+                annotate_gas_hints(else_unit)?;
+            }
+
+            DecompiledCodeItem::WhileStatement { cond, body } => {
+                if let Some(cond) = cond {
+                    let mut cond_names = Vec::new();
+                    collect_decompiled_expr_function_names(cond, &mut cond_names);
+                    if cond_names
+                        .iter()
+                        .any(|x| is_function_of(x, &["vector"], "length"))
+                    {
+                        new_blocks.push(DecompiledCodeItem::CommentStatement(
+                            "gas: loop bounded by a vector length, cost grows with its size"
+                                .to_string(),
+                        ));
+                    }
+                    names.extend(cond_names);
+                }
+                annotate_gas_hints(body)?;
+            }
+
+            DecompiledCodeItem::BreakStatement
+            | DecompiledCodeItem::ContinueStatement
+            | DecompiledCodeItem::CommentStatement(_)
+            | DecompiledCodeItem::PossibleAssignStatement { .. } => {}
+        }
+
+        let mut storage = Vec::new();
+        let mut tables = Vec::new();
+        for name in names {
+            if STORAGE_OPERATIONS.contains(&name.as_str()) {
+                if !storage.contains(&name) {
+                    storage.push(name);
+                }
+            } else if TABLE_MODULES
+                .iter()
+                .any(|m| is_function_of(&name, &[*m], ""))
+            {
+                if !tables.contains(&name) {
+                    tables.push(name);
+                }
+            }
+        }
+
+        if !storage.is_empty() {
+            new_blocks.push(DecompiledCodeItem::CommentStatement(format!(
+                "gas: global storage access ({})",
+                storage.join(", ")
+            )));
+        }
+        if !tables.is_empty() {
+            new_blocks.push(DecompiledCodeItem::CommentStatement(format!(
+                "gas: table access ({})",
+                tables.join(", ")
+            )));
+        }
+
+        new_blocks.push(item);
+    }
+
+    unit.blocks = new_blocks;
+
+    Ok(())
+}
+
+fn collect_decompiled_expr_function_names(expr: &DecompiledExpr, names: &mut Vec<String>) {
+    match expr {
+        DecompiledExpr::EvaluationExpr(expr) => collect_function_names(expr.value(), names),
+        DecompiledExpr::Tuple(exprs) => {
+            for expr in exprs {
+                collect_decompiled_expr_function_names(expr, names);
+            }
+        }
+        DecompiledExpr::Undefined | DecompiledExpr::Variable(_) => {}
+    }
+}
+
+fn collect_function_names(expr: &ExprNodeRef, names: &mut Vec<String>) {
+    match &expr.borrow().operation {
+        ExprNodeOperation::Func(name, args, _) => {
+            names.push(name.clone());
+            for arg in args {
+                collect_function_names(arg, names);
+            }
+        }
+        ExprNodeOperation::StructPack(_, args, _) => {
+            for (_, arg) in args {
+                collect_function_names(arg, names);
+            }
+        }
+        ExprNodeOperation::Binary(_, a, b) | ExprNodeOperation::WriteRef(a, b) => {
+            collect_function_names(a, names);
+            collect_function_names(b, names);
+        }
+        ExprNodeOperation::Field(expr, _)
+        | ExprNodeOperation::Unary(_, expr)
+        | ExprNodeOperation::Cast(_, expr, _)
+        | ExprNodeOperation::Destroy(expr)
+        | ExprNodeOperation::FreezeRef(expr)
+        | ExprNodeOperation::ReadRef(expr)
+        | ExprNodeOperation::BorrowLocal(expr, _)
+        | ExprNodeOperation::StructUnpack(_, _, expr, _)
+        | ExprNodeOperation::VariableSnapshot { value: expr, .. } => {
+            collect_function_names(expr, names)
+        }
+        ExprNodeOperation::Ignored
+        | ExprNodeOperation::Deleted
+        | ExprNodeOperation::NonTrivial
+        | ExprNodeOperation::Raw(_)
+        | ExprNodeOperation::Const(_)
+        | ExprNodeOperation::LocalVariable(_) => {}
+    }
+}
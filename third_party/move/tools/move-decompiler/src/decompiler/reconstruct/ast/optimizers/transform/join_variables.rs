@@ -0,0 +1,116 @@
+// Copyright (c) Verichains, 2023
+
+use std::collections::HashSet;
+
+use crate::decompiler::reconstruct::{DecompiledCodeItem, DecompiledCodeUnit};
+
+use super::super::utils::{count_references, last_effective_statements};
+
+/// Merge the temporaries flowing into a variable assigned at the end of both branches
+/// ```ignore
+///   if (c) {             | if (c) {
+///     let v1 = a;        |   v0 = a;
+///     v0 = v1;           | } else {
+///   } else {             |   v0 = b;
+///     let v2 = b;        | };
+///     v0 = v2;           |
+///   };                   |
+/// ```
+/// Only temporaries that are not referenced anywhere else in the function are merged.
+pub(crate) fn merge_join_variables(unit: &mut DecompiledCodeUnit) -> Result<(), anyhow::Error> {
+    let root = unit.clone();
+    merge_join_variables_in(unit, &root);
+    Ok(())
+}
+
+fn merge_join_variables_in(unit: &mut DecompiledCodeUnit, root: &DecompiledCodeUnit) {
+    for item in unit.blocks.iter_mut() {
+        match item {
+            DecompiledCodeItem::IfElseStatement {
+                if_unit, else_unit, ..
+            } => {
+                merge_join_variables_in(if_unit, root);
+                merge_join_variables_in(else_unit, root);
+
+                let if_join = join_copy(if_unit, root);
+                let else_join = join_copy(else_unit, root);
+                if let (
+                    Some((if_tmp, if_copy, if_target)),
+                    Some((else_tmp, else_copy, else_target)),
+                ) = (if_join, else_join)
+                {
+                    if if_target == else_target {
+                        merge_copy(if_unit, if_tmp, if_copy, if_target);
+                        merge_copy(else_unit, else_tmp, else_copy, else_target);
+                    }
+                }
+            }
+
+            DecompiledCodeItem::WhileStatement { body, .. } => {
+                merge_join_variables_in(body, root);
+            }
+
+            DecompiledCodeItem::ReturnStatement(_)
+            | DecompiledCodeItem::AbortStatement(_)
+            | DecompiledCodeItem::BreakStatement
+            | DecompiledCodeItem::ContinueStatement
+            | DecompiledCodeItem::CommentStatement(_)
+            | DecompiledCodeItem::PossibleAssignStatement { .. }
+            | DecompiledCodeItem::AssignStatement { .. }
+            | DecompiledCodeItem::AssignTupleStatement { .. }
+            | DecompiledCodeItem::AssignStructureStatement { .. }
+            | DecompiledCodeItem::Statement { .. } => {}
+        }
+    }
+}
+
+/// `let tmp = expr; target = tmp;` at the end of a branch without a result value,
+/// returns the indices of both assignments and the target variable
+fn join_copy(
+    unit: &DecompiledCodeUnit,
+    root: &DecompiledCodeUnit,
+) -> Option<(usize, usize, usize)> {
+    if unit.exit.is_some() {
+        return None;
+    }
+
+    let [(tmp_idx, tmp_assign), (copy_idx, copy)] = last_effective_statements::<2>(&unit.blocks)?;
+    let (
+        DecompiledCodeItem::AssignStatement {
+            variable: tmp,
+            value,
+            ..
+        },
+        DecompiledCodeItem::AssignStatement {
+            variable: target,
+            value: copied,
+            is_decl: false,
+        },
+    ) = (tmp_assign, copy)
+    else {
+        return None;
+    };
+
+    let tmp_set = HashSet::from([*tmp]);
+    if copied.is_single_variable_expr() != Some(*tmp)
+        || tmp == target
+        || value.has_reference_to_any_variable(&tmp_set)
+        || count_references(root, *tmp) != 2
+    {
+        return None;
+    }
+
+    Some((tmp_idx, copy_idx, *target))
+}
+
+/// Replace `let tmp = expr; target = tmp;` by `target = expr;`
+fn merge_copy(unit: &mut DecompiledCodeUnit, tmp_idx: usize, copy_idx: usize, target: usize) {
+    unit.blocks.remove(copy_idx);
+    if let DecompiledCodeItem::AssignStatement {
+        variable, is_decl, ..
+    } = &mut unit.blocks[tmp_idx]
+    {
+        *variable = target;
+        *is_decl = false;
+    }
+}
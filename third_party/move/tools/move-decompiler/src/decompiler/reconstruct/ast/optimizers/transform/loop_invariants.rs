@@ -0,0 +1,248 @@
+// Copyright (c) Verichains, 2023
+
+use std::collections::HashSet;
+
+use move_model::ty::Type;
+
+use crate::decompiler::{
+    evaluator::stackless::{ExprNodeOperation, ExprNodeRef},
+    naming::Naming,
+    reconstruct::{DecompiledCodeItem, DecompiledCodeUnit, DecompiledExpr},
+};
+
+use super::super::utils::{is_function_of, PURE_MODULES};
+
+const STORAGE_READS: &[&str] = &["borrow_global", "borrow_global_mut", "exists"];
+const STORAGE_WRITES: &[&str] = &["move_to", "move_from", "borrow_global_mut"];
+
+/// What the condition and body of a loop do
+#[derive(Default)]
+struct LoopFacts {
+    storage_reads: Vec<ExprNodeRef>,
+    /// type arguments of the `move_to`, `move_from` and `borrow_global_mut` calls
+    written_resources: Vec<Vec<Type>>,
+    /// calls which may reach global storage, like functions of the same or other modules
+    opaque_calls: bool,
+    assigned_variables: HashSet<usize>,
+}
+
+/// Comment loops with the storage reads and borrows they repeat on every iteration with
+/// the same arguments, as these are candidates for hoisting out of the loop
+/// ```ignore
+///   while (i < n) {                   | /* loop-invariant: borrow_global<C>(addr) */
+///     let c = borrow_global<C>(addr); | while (i < n) {
+///     ...                             |   ...
+/// ```
+/// A read is invariant when the variables of its arguments are not assigned or mutably
+/// borrowed in the loop, the resource is not moved in or out of storage or mutably
+/// borrowed there, and the loop makes no call which may modify storage.
+pub(crate) fn annotate_loop_invariants(
+    unit: &mut DecompiledCodeUnit,
+    naming: &Naming,
+) -> Result<(), anyhow::Error> {
+    let mut new_blocks = Vec::new();
+
+    for mut item in std::mem::take(&mut unit.blocks) {
+        match &mut item {
+            DecompiledCodeItem::IfElseStatement {
+                if_unit, else_unit, ..
+            } => {
+                annotate_loop_invariants(if_unit, naming)?;
+                annotate_loop_invariants(else_unit, naming)?;
+            }
+
+            DecompiledCodeItem::WhileStatement { cond, body } => {
+                let mut facts = LoopFacts::default();
+                if let Some(cond) = cond {
+                    collect_expr_facts(cond, &mut facts);
+                }
+                collect_unit_facts(body, &mut facts);
+
+                let mut invariants = Vec::new();
+                for read in &facts.storage_reads {
+                    if is_invariant(read, &facts) {
+                        let source = read.borrow().operation.to_source(naming)?;
+                        if !invariants.contains(&source) {
+                            invariants.push(source);
+                        }
+                    }
+                }
+                for source in invariants {
+                    new_blocks.push(DecompiledCodeItem::CommentStatement(format!(
+                        "loop-invariant: {}",
+                        source
+                    )));
+                }
+
+                annotate_loop_invariants(body, naming)?;
+            }
+
+            _ => {}
+        }
+
+        new_blocks.push(item);
+    }
+
+    unit.blocks = new_blocks;
+
+    Ok(())
+}
+
+fn is_invariant(read: &ExprNodeRef, facts: &LoopFacts) -> bool {
+    let ExprNodeOperation::Func(_, args, types) = &read.borrow().operation else {
+        return false;
+    };
+    if facts.opaque_calls || facts.written_resources.contains(types) {
+        return false;
+    }
+    let mut variables = HashSet::new();
+    args.iter()
+        .all(|arg| collect_pure_variables(arg, &mut variables))
+        && variables.is_disjoint(&facts.assigned_variables)
+}
+
+/// Variables read by an expression made only of variables, constants and field reads,
+/// false for anything else
+fn collect_pure_variables(expr: &ExprNodeRef, variables: &mut HashSet<usize>) -> bool {
+    match &expr.borrow().operation {
+        ExprNodeOperation::LocalVariable(idx) => {
+            variables.insert(*idx);
+            true
+        }
+        ExprNodeOperation::Const(_) => true,
+        ExprNodeOperation::Field(expr, _)
+        | ExprNodeOperation::ReadRef(expr)
+        | ExprNodeOperation::FreezeRef(expr)
+        | ExprNodeOperation::BorrowLocal(expr, false) => collect_pure_variables(expr, variables),
+        _ => false,
+    }
+}
+
+fn collect_unit_facts(unit: &DecompiledCodeUnit, facts: &mut LoopFacts) {
+    for item in &unit.blocks {
+        match item {
+            DecompiledCodeItem::ReturnStatement(expr)
+            | DecompiledCodeItem::AbortStatement(expr)
+            | DecompiledCodeItem::Statement { expr } => collect_expr_facts(expr, facts),
+
+            DecompiledCodeItem::AssignStatement {
+                variable, value, ..
+            }
+            | DecompiledCodeItem::PossibleAssignStatement {
+                variable, value, ..
+            } => {
+                facts.assigned_variables.insert(*variable);
+                collect_expr_facts(value, facts);
+            }
+
+            DecompiledCodeItem::AssignTupleStatement {
+                variables, value, ..
+            } => {
+                facts.assigned_variables.extend(variables.iter());
+                collect_expr_facts(value, facts);
+            }
+
+            DecompiledCodeItem::AssignStructureStatement {
+                variables, value, ..
+            } => {
+                facts
+                    .assigned_variables
+                    .extend(variables.iter().map(|(_, v)| *v));
+                collect_expr_facts(value, facts);
+            }
+
+            DecompiledCodeItem::IfElseStatement {
+                cond,
+                if_unit,
+                else_unit,
+                result_variables,
+                ..
+            } => {
+                facts.assigned_variables.extend(result_variables.iter());
+                collect_expr_facts(cond, facts);
+                collect_unit_facts(if_unit, facts);
+                collect_unit_facts(else_unit, facts);
+            }
+
+            DecompiledCodeItem::WhileStatement { cond, body } => {
+                if let Some(cond) = cond {
+                    collect_expr_facts(cond, facts);
+                }
+                collect_unit_facts(body, facts);
+            }
+
+            DecompiledCodeItem::BreakStatement
+            | DecompiledCodeItem::ContinueStatement
+            | DecompiledCodeItem::CommentStatement(_) => {}
+        }
+    }
+    if let Some(exit) = &unit.exit {
+        collect_expr_facts(exit, facts);
+    }
+}
+
+fn collect_expr_facts(expr: &DecompiledExpr, facts: &mut LoopFacts) {
+    match expr {
+        DecompiledExpr::EvaluationExpr(expr) => collect_node_facts(expr.value(), facts),
+        DecompiledExpr::Tuple(exprs) => {
+            for expr in exprs {
+                collect_expr_facts(expr, facts);
+            }
+        }
+        DecompiledExpr::Undefined | DecompiledExpr::Variable(_) => {}
+    }
+}
+
+fn collect_node_facts(expr: &ExprNodeRef, facts: &mut LoopFacts) {
+    match &expr.borrow().operation {
+        ExprNodeOperation::Func(name, args, types) => {
+            if STORAGE_READS.contains(&name.as_str()) {
+                facts.storage_reads.push(expr.clone());
+            }
+            if STORAGE_WRITES.contains(&name.as_str()) {
+                facts.written_resources.push(types.clone());
+            } else if !STORAGE_READS.contains(&name.as_str())
+                && name != "assert!"
+                && !PURE_MODULES.iter().any(|m| is_function_of(name, &[*m], ""))
+            {
+                facts.opaque_calls = true;
+            }
+            for arg in args {
+                collect_node_facts(arg, facts);
+            }
+        }
+        ExprNodeOperation::BorrowLocal(inner, mutable) => {
+            if let (true, ExprNodeOperation::LocalVariable(idx)) =
+                (*mutable, &inner.borrow().operation)
+            {
+                facts.assigned_variables.insert(*idx);
+            }
+            collect_node_facts(inner, facts);
+        }
+        ExprNodeOperation::StructPack(_, args, _) => {
+            for (_, arg) in args {
+                collect_node_facts(arg, facts);
+            }
+        }
+        ExprNodeOperation::Binary(_, a, b) | ExprNodeOperation::WriteRef(a, b) => {
+            collect_node_facts(a, facts);
+            collect_node_facts(b, facts);
+        }
+        ExprNodeOperation::Field(expr, _)
+        | ExprNodeOperation::Unary(_, expr)
+        | ExprNodeOperation::Cast(_, expr, _)
+        | ExprNodeOperation::Destroy(expr)
+        | ExprNodeOperation::FreezeRef(expr)
+        | ExprNodeOperation::ReadRef(expr)
+        | ExprNodeOperation::StructUnpack(_, _, expr, _)
+        | ExprNodeOperation::VariableSnapshot { value: expr, .. } => {
+            collect_node_facts(expr, facts)
+        }
+        ExprNodeOperation::Ignored
+        | ExprNodeOperation::Deleted
+        | ExprNodeOperation::NonTrivial
+        | ExprNodeOperation::Raw(_)
+        | ExprNodeOperation::Const(_)
+        | ExprNodeOperation::LocalVariable(_) => {}
+    }
+}
@@ -7,3 +7,13 @@ pub mod assert;
 pub mod let_return;
 pub mod loops;
 pub mod if_else;
+pub mod gas_hints;
+pub mod foreach;
+pub mod join_variables;
+pub mod branch_tails;
+pub mod loop_invariants;
+pub mod call_ordering;
+pub mod tuple_bindings;
+pub mod common_subexpressions;
+pub mod early_returns;
+pub mod conditions;
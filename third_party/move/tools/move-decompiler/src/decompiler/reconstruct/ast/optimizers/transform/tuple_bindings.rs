@@ -0,0 +1,106 @@
+// Copyright (c) Verichains, 2023
+
+use crate::decompiler::reconstruct::{DecompiledCodeItem, DecompiledCodeUnit};
+
+use super::super::utils::count_references;
+
+/// Bind the results of a call returning several values, and the fields of an unpacked struct,
+/// directly to the variables they are copied into right after the binding
+/// ```ignore
+///   let (v0, v1) = f(x);       | let (v2, v1) = f(x);
+///   let v2 = v0;               |
+///   let S { a: v3, b: _ } = s; | let S { a: v4, b: _ } = s;
+///   let v4 = v3;               |
+/// ```
+/// Only declarations binding new variables are rewritten, and only values that are not
+/// referenced anywhere else in the function are rebound. Values that are not used at all
+/// are already printed as `_`.
+pub(crate) fn bind_copied_values(unit: &mut DecompiledCodeUnit) -> Result<(), anyhow::Error> {
+    let root = unit.clone();
+    bind_copied_values_in(unit, &root);
+    Ok(())
+}
+
+fn bind_copied_values_in(unit: &mut DecompiledCodeUnit, root: &DecompiledCodeUnit) {
+    let mut idx = 0;
+    while idx < unit.blocks.len() {
+        match &mut unit.blocks[idx] {
+            DecompiledCodeItem::IfElseStatement {
+                if_unit, else_unit, ..
+            } => {
+                bind_copied_values_in(if_unit, root);
+                bind_copied_values_in(else_unit, root);
+            }
+
+            DecompiledCodeItem::WhileStatement { body, .. } => {
+                bind_copied_values_in(body, root);
+            }
+
+            DecompiledCodeItem::AssignTupleStatement { is_decl: true, .. }
+            | DecompiledCodeItem::AssignStructureStatement { is_decl: true, .. } => {
+                while idx + 1 < unit.blocks.len() {
+                    let Some((position, target)) =
+                        bound_value_copy(&unit.blocks[idx], &unit.blocks[idx + 1], root)
+                    else {
+                        break;
+                    };
+                    match &mut unit.blocks[idx] {
+                        DecompiledCodeItem::AssignTupleStatement { variables, .. } => {
+                            variables[position] = target;
+                        }
+                        DecompiledCodeItem::AssignStructureStatement { variables, .. } => {
+                            variables[position].1 = target;
+                        }
+                        _ => unreachable!(),
+                    }
+                    unit.blocks.remove(idx + 1);
+                }
+            }
+
+            DecompiledCodeItem::ReturnStatement(_)
+            | DecompiledCodeItem::AbortStatement(_)
+            | DecompiledCodeItem::BreakStatement
+            | DecompiledCodeItem::ContinueStatement
+            | DecompiledCodeItem::CommentStatement(_)
+            | DecompiledCodeItem::PossibleAssignStatement { .. }
+            | DecompiledCodeItem::AssignStatement { .. }
+            | DecompiledCodeItem::AssignTupleStatement { .. }
+            | DecompiledCodeItem::AssignStructureStatement { .. }
+            | DecompiledCodeItem::Statement { .. } => {}
+        }
+        idx += 1;
+    }
+}
+
+/// `let target = value;` declaring a new variable from a value bound by the tuple or struct
+/// declaration before it, returns the position of the value in the declaration and the
+/// target variable
+fn bound_value_copy(
+    binding: &DecompiledCodeItem,
+    copy: &DecompiledCodeItem,
+    root: &DecompiledCodeUnit,
+) -> Option<(usize, usize)> {
+    let variables = match binding {
+        DecompiledCodeItem::AssignTupleStatement { variables, .. } => variables.clone(),
+        DecompiledCodeItem::AssignStructureStatement { variables, .. } => {
+            variables.iter().map(|(_, v)| *v).collect()
+        }
+        _ => return None,
+    };
+    let DecompiledCodeItem::AssignStatement {
+        variable: target,
+        value,
+        is_decl: true,
+    } = copy
+    else {
+        return None;
+    };
+
+    let bound = value.is_single_variable_expr()?;
+    let position = variables.iter().position(|v| *v == bound)?;
+    if variables.contains(target) || count_references(root, bound) != 2 {
+        return None;
+    }
+
+    Some((position, *target))
+}
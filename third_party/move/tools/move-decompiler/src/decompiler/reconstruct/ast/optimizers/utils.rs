@@ -8,7 +8,7 @@ use crate::decompiler::evaluator::stackless::{
     effective_operation, ExprNodeOperation, ExprNodeRef,
 };
 
-use super::super::{DecompiledCodeItem, DecompiledCodeUnit};
+use super::super::{DecompiledCodeItem, DecompiledCodeUnit, DecompiledExprRef};
 
 pub(crate) fn collect_referenced_variables(
     unit: &DecompiledCodeUnit,
@@ -314,7 +314,6 @@ pub(crate) fn has_effective_statement(blocks: &Vec<DecompiledCodeItem>) -> bool
     blocks.iter().any(|x| is_effective_code_item(x))
 }
 
-#[allow(dead_code)]
 pub(crate) fn expr_not(expr: ExprNodeRef) -> ExprNodeRef {
     if let Some(v) = effective_operation(&[&expr], &mut |[expr]| match &expr.borrow().operation {
         ExprNodeOperation::Const(Constant::Bool(x)) => {
@@ -334,6 +333,31 @@ pub(crate) fn expr_not(expr: ExprNodeRef) -> ExprNodeRef {
         .value_copied()
 }
 
+/// Negation of `cond` without a `!`: the inverted comparison, or the operand of a negation
+pub(crate) fn negate_node(cond: &ExprNodeRef) -> Option<ExprNodeRef> {
+    match &cond.borrow().operation {
+        ExprNodeOperation::Unary(op, inner) if op == "!" => Some(inner.clone()),
+        ExprNodeOperation::VariableSnapshot { value, .. } => negate_node(value),
+        ExprNodeOperation::Binary(op, a, b) => {
+            let inverted = match op.as_str() {
+                "==" => "!=",
+                "!=" => "==",
+                "<" => ">=",
+                ">=" => "<",
+                ">" => "<=",
+                "<=" => ">",
+                _ => return None,
+            };
+            Some(
+                ExprNodeOperation::Binary(inverted.to_string(), a.clone(), b.clone())
+                    .to_expr()
+                    .value_copied(),
+            )
+        }
+        _ => None,
+    }
+}
+
 pub(crate) fn expr_and(expr1: ExprNodeRef, expr2: ExprNodeRef) -> ExprNodeRef {
     if let Some(v) = effective_operation(&[&expr1, &expr2], &mut |&[expr1, expr2]| match (
         &expr1.borrow().operation,
@@ -368,3 +392,80 @@ pub(crate) fn expr_or(expr1: ExprNodeRef, expr2: ExprNodeRef) -> ExprNodeRef {
         .to_expr()
         .value_copied()
 }
+
+/// Standard library modules whose functions cannot reach back into global storage
+pub(crate) const PURE_MODULES: &[&str] = &[
+    "vector",
+    "option",
+    "string",
+    "signer",
+    "error",
+    "bcs",
+    "from_bcs",
+    "hash",
+    "type_info",
+    "math64",
+    "math128",
+    "fixed_point32",
+    "fixed_point64",
+];
+
+/// `name` is a (possibly module-qualified) function name, check that it belongs to one of
+/// `modules` and, if `function` is not empty, that it is named `function`
+pub(crate) fn is_function_of(name: &str, modules: &[&str], function: &str) -> bool {
+    let parts = name.rsplit("::").collect::<Vec<_>>();
+    parts.len() >= 2 && modules.contains(&parts[1]) && (function.is_empty() || parts[0] == function)
+}
+
+/// Number of statements and expressions reading or writing `variable`
+pub(crate) fn count_references(unit: &DecompiledCodeUnit, variable: usize) -> usize {
+    let variables = HashSet::from([variable]);
+    let in_expr =
+        |expr: &DecompiledExprRef| expr.has_reference_to_any_variable(&variables) as usize;
+
+    let in_blocks: usize = unit
+        .blocks
+        .iter()
+        .map(|item| match item {
+            DecompiledCodeItem::ReturnStatement(expr)
+            | DecompiledCodeItem::AbortStatement(expr)
+            | DecompiledCodeItem::Statement { expr } => in_expr(expr),
+            DecompiledCodeItem::BreakStatement
+            | DecompiledCodeItem::ContinueStatement
+            | DecompiledCodeItem::CommentStatement(_) => 0,
+            DecompiledCodeItem::PossibleAssignStatement {
+                variable: v, value, ..
+            }
+            | DecompiledCodeItem::AssignStatement {
+                variable: v, value, ..
+            } => (*v == variable || in_expr(value) > 0) as usize,
+            DecompiledCodeItem::AssignTupleStatement {
+                variables: vs,
+                value,
+                ..
+            } => (vs.contains(&variable) || in_expr(value) > 0) as usize,
+            DecompiledCodeItem::AssignStructureStatement {
+                variables: vs,
+                value,
+                ..
+            } => (vs.iter().any(|(_, v)| *v == variable) || in_expr(value) > 0) as usize,
+            DecompiledCodeItem::IfElseStatement {
+                cond,
+                if_unit,
+                else_unit,
+                result_variables,
+                ..
+            } => {
+                in_expr(cond)
+                    + result_variables.contains(&variable) as usize
+                    + count_references(if_unit, variable)
+                    + count_references(else_unit, variable)
+            }
+            DecompiledCodeItem::WhileStatement { cond, body } => {
+                cond.as_ref().map_or(0, in_expr) + count_references(body, variable)
+            }
+        })
+        .sum();
+
+    in_blocks + unit.exit.as_ref().map_or(0, in_expr)
+}
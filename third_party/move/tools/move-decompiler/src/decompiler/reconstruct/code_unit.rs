@@ -24,6 +24,16 @@ impl SourceCodeUnit {
         self.code.is_empty()
     }
 
+    pub fn line_count(&self) -> usize {
+        self.code
+            .iter()
+            .map(|item| match item {
+                SourceCodeItem::Line(_) => 1,
+                SourceCodeItem::Block(block) => block.line_count(),
+            })
+            .sum()
+    }
+
     pub fn add_indent(&mut self, indent: i32) {
         self.indent += indent;
     }
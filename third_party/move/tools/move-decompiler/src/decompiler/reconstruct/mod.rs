@@ -10,7 +10,7 @@ use super::{
 };
 use anyhow::Ok;
 use move_model::model::FunctionEnv;
-use move_stackless_bytecode::function_target::FunctionTarget;
+use move_stackless_bytecode::{function_target::FunctionTarget, stackless_bytecode::Constant};
 
 use self::{
     stackless_var_usage::{VarUsage, VarUsageSnapshot},
@@ -22,7 +22,7 @@ use super::{
         datastructs::{BasicBlock, CodeUnitBlock, HyperBlock},
         StacklessBlockContent,
     },
-    evaluator::stackless::{ReturnValueHint, StacklessEvaluationContext},
+    evaluator::stackless::{ExprNodeOperation, ReturnValueHint, StacklessEvaluationContext},
 };
 
 pub use self::ast::optimizers::OptimizerSettings;
@@ -111,10 +111,13 @@ impl<'a> SourceGen<'a> {
             return Err(anyhow::anyhow!("final branch condition stack not empty"));
         }
 
-        let (ast, referenced_vairables) =
+        let (ast, referenced_vairables, variable_names) =
             ast::optimizers::run(&ast, self.func_target, &self.naming, optimizer_settings)?;
 
-        let final_naming = self.naming.with_referenced_variables(&referenced_vairables);
+        let final_naming = self
+            .naming
+            .with_referenced_variables(&referenced_vairables)
+            .with_variable_names(variable_names);
 
         Ok(ast.to_source(&final_naming, true)?)
     }
@@ -463,6 +466,78 @@ impl<'a> SourceGen<'a> {
 
                 codeunit.extends(outer)?;
             }
+
+            HyperBlock::SwitchBlocks { cases, default } => {
+                // the discriminant is pushed like a branch condition by the dispatching block,
+                // the switch is emitted as an if-else chain over its value
+                let discriminant = evaluation_ctx.pop_branch_condition();
+                if discriminant.is_none() {
+                    return Err(anyhow::anyhow!("fail to obtain switch discriminant"));
+                }
+                let discriminant = discriminant.unwrap();
+
+                let units = cases
+                    .iter()
+                    .chain(std::iter::once(default))
+                    .collect::<Vec<_>>();
+
+                for unit in &units {
+                    if !find_need_propagate_inner_defining_variables(unit, evaluation_ctx)
+                        .is_empty()
+                    {
+                        return Err(anyhow::anyhow!(
+                            "switch branch defining variables is not supported"
+                        ));
+                    }
+                }
+
+                let mut ctxs = Vec::new();
+                let mut decompiled_units = Vec::new();
+                for unit in &units {
+                    let mut u_ctx = evaluation_ctx.clone();
+                    let mut u_s_ctx = s_ctx.clone();
+                    u_s_ctx.enter_block();
+                    decompiled_units.push(self.visit_codeunit(&mut u_ctx, &u_s_ctx, unit)?);
+                    ctxs.push(u_ctx);
+                }
+
+                let meta = block.meta();
+                let var_usage = meta.get::<VarUsageSnapshot<VarUsage>>().unwrap();
+
+                for v in evaluation_ctx.merge_branches(&ctxs.iter().collect(), true) {
+                    let has_future_usage = if let Some(usage) = var_usage.forward.1.get(&v) {
+                        usage.read_cnt + usage.write_cnt > 0
+                    } else {
+                        false
+                    };
+                    if !has_future_usage {
+                        continue;
+                    }
+                    evaluation_ctx.flush_local_value(v, Some(true));
+                }
+
+                let mut else_unit = decompiled_units.pop().unwrap();
+                for (case, if_unit) in decompiled_units.into_iter().enumerate().rev() {
+                    let cond = ExprNodeOperation::Binary(
+                        "==".to_string(),
+                        discriminant.value_copied(),
+                        ExprNodeOperation::Const(Constant::U64(case as u64)).to_node(),
+                    )
+                    .to_expr();
+
+                    let mut chain = DecompiledCodeUnit::new();
+                    chain.add(DecompiledCodeItem::IfElseStatement {
+                        cond: DecompiledExpr::EvaluationExpr(cond).boxed(),
+                        if_unit,
+                        else_unit,
+                        result_variables: Vec::new(),
+                        use_as_result: ResultUsageType::None,
+                    });
+                    else_unit = chain;
+                }
+
+                codeunit.extends(else_unit)?;
+            }
         }
 
         Ok(codeunit)
@@ -500,21 +575,29 @@ impl<'a> SourceGen<'a> {
                 _ => vec![],
             };
 
-            let dst_types: Vec<_> = dst_tmps
-                .iter()
-                .map(|x| {
-                    Some(ReturnValueHint {
-                        ty: self.func_target.get_local_type(*x).clone(),
+            let src_tmps = match &bytecode.bytecode {
+                Call(_, _, _, srcs, _) => srcs.clone(),
+                _ => vec![],
+            };
+
+            let local_types = |tmps: &Vec<usize>| -> Vec<_> {
+                tmps.iter()
+                    .map(|x| {
+                        Some(ReturnValueHint {
+                            ty: self.func_target.get_local_type(*x).clone(),
+                        })
                     })
-                })
-                .collect();
+                    .collect()
+            };
+            let src_types = local_types(&src_tmps);
+            let dst_types = local_types(&dst_tmps);
 
             let StacklessEvaluationRunResult {
                 results: result,
                 new_variables,
                 flushed_variables: pre_flushed,
                 cannot_keep_as_expr,
-            } = evaluation_ctx.run(&bytecode.bytecode, &dst_types)?;
+            } = evaluation_ctx.run(&bytecode.bytecode, &src_types, &dst_types)?;
 
             if result.should_ignore() {
                 continue;
@@ -575,6 +658,7 @@ impl<'a> SourceGen<'a> {
                                     val.borrow().operation.to_expr(),
                                 )
                                 .boxed(),
+                                is_decl: true,
                             });
                         } else {
                             if fields.len() != dsts.len() {
@@ -592,6 +676,7 @@ impl<'a> SourceGen<'a> {
                                     val.borrow().operation.to_expr(),
                                 )
                                 .boxed(),
+                                is_decl: dsts.iter().all(|dst| new_variables.contains(dst)),
                             });
 
                             dsts.iter().for_each(|&dst| {
@@ -747,7 +832,8 @@ impl<'a> SourceGen<'a> {
             | Terminator::Abort
             | Terminator::IfElse { .. }
             | Terminator::Branch { .. }
-            | Terminator::While { .. } => {}
+            | Terminator::While { .. }
+            | Terminator::Switch { .. } => {}
             Terminator::Break { .. } => {
                 evaluation_ctx.add_break_alias_point();
                 if !block.implicit_terminator {
@@ -262,6 +262,21 @@ impl VarPipelineRunner<BackwardVisitorConfig, VarUsage> for StacklessVarUsagePip
                     })
                     .boxed();
             }
+
+            HyperBlock::SwitchBlocks { cases, default } => {
+                let state_time_id = state.time_id();
+                let t = *config.t.borrow();
+
+                let mut states = Vec::new();
+                for unit in cases.iter_mut().chain(std::iter::once(default)) {
+                    states.push(self.run_unit(config, &state, unit.as_mut())?);
+                }
+                state = state
+                    .merge_branches(states.iter().map(|s| s.as_ref()).collect(), |s| {
+                        update_rw_with_time_id_check(s, state_time_id, t)
+                    })
+                    .boxed();
+            }
         };
         Ok(state)
     }
@@ -367,6 +382,21 @@ impl VarPipelineRunner<ForwardVisitorConfig, VarUsage> for StacklessVarUsagePipe
 
                 state = self.run_unit(config, &state, outer.as_mut())?;
             }
+
+            HyperBlock::SwitchBlocks { cases, default } => {
+                let state_time_id = state.time_id();
+                let t = *config.t.borrow();
+
+                let mut states = Vec::new();
+                for unit in cases.iter_mut().chain(std::iter::once(default)) {
+                    states.push(self.run_unit(config, &state, unit.as_mut())?);
+                }
+                state = state
+                    .merge_branches(states.iter().map(|s| s.as_ref()).collect(), |x| {
+                        update_rw_with_time_id_check(x, state_time_id, t)
+                    })
+                    .boxed();
+            }
         }
 
         hyper_block
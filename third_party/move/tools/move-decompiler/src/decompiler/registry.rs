@@ -0,0 +1,56 @@
+// Copyright (c) Verichains, 2023
+
+use anyhow::Result;
+use sha3::{Digest, Sha3_256};
+
+/// Source code of a module published by a source verification service
+pub struct VerifiedSource {
+    /// Where the source was found, printed in the output
+    pub origin: String,
+    pub source: String,
+}
+
+/// A source verification service queried by module hash, see `Decompiler::with_registry`
+pub trait SourceRegistry {
+    /// Verified source of the module with this hash, `None` if the module is unknown
+    fn lookup(&self, module_hash: &str) -> Result<Option<VerifiedSource>>;
+}
+
+/// Registry queried over HTTP, `{hash}` in the URL is replaced by the module hash.
+/// A successful response body is the module source, 404 means unknown module.
+#[cfg(feature = "remote")]
+pub struct HttpSourceRegistry {
+    url: String,
+    client: reqwest::blocking::Client,
+}
+
+#[cfg(feature = "remote")]
+impl HttpSourceRegistry {
+    pub fn new(url: String) -> Self {
+        Self {
+            url,
+            client: reqwest::blocking::Client::new(),
+        }
+    }
+}
+
+#[cfg(feature = "remote")]
+impl SourceRegistry for HttpSourceRegistry {
+    fn lookup(&self, module_hash: &str) -> Result<Option<VerifiedSource>> {
+        let url = self.url.replace("{hash}", module_hash);
+        let response = self.client.get(&url).send()?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let source = response.error_for_status()?.text()?;
+        Ok(Some(VerifiedSource {
+            origin: url,
+            source,
+        }))
+    }
+}
+
+/// Hex encoded sha3-256 of the serialized module, the key used to query registries
+pub fn module_hash(bytes: &[u8]) -> String {
+    hex::encode(Sha3_256::digest(bytes))
+}
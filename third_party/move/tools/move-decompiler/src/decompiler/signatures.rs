@@ -0,0 +1,303 @@
+// Copyright (c) Verichains, 2023
+
+use std::{collections::BTreeMap, fmt::Display};
+
+use move_binary_format::{
+    access::ModuleAccess,
+    file_format::{
+        Bytecode, CompiledModule, FieldHandleIndex, FieldInstantiationIndex, FunctionHandleIndex,
+        FunctionInstantiationIndex, ModuleHandleIndex, Signature, SignatureIndex, SignatureToken,
+        StructDefInstantiationIndex, StructDefinitionIndex, StructHandleIndex,
+    },
+};
+use move_model::model::FunctionEnv;
+use sha3::{Digest, Sha3_256};
+
+/// Bodies shorter than this are too generic to identify a library function
+const MIN_INSTRUCTIONS: usize = 8;
+
+/// Known library functions indexed by the hash of their normalized body.
+///
+/// The text format has one `<hash> <address::module::function>` entry per line,
+/// empty lines and lines starting with `#` are ignored.
+#[derive(Debug, Default)]
+pub struct SignatureDb {
+    entries: BTreeMap<String, Vec<String>>,
+}
+
+impl SignatureDb {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn parse(text: &str) -> Result<Self, anyhow::Error> {
+        let mut db = Self::new();
+        for (line_no, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (hash, name) = line.split_once(char::is_whitespace).ok_or_else(|| {
+                anyhow::anyhow!("invalid signature entry at line {}: {}", line_no + 1, line)
+            })?;
+            db.insert(hash.to_string(), name.trim().to_string());
+        }
+        Ok(db)
+    }
+
+    /// Add all entries of `other`
+    pub fn extend(&mut self, other: SignatureDb) {
+        for (hash, names) in other.entries {
+            for name in names {
+                self.insert(hash.clone(), name);
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn insert(&mut self, hash: String, name: String) {
+        let names = self.entries.entry(hash).or_insert_with(Vec::new);
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+
+    /// Hash and record a function, returns false if its body is too short to be recorded
+    pub fn add_function(&mut self, function: &FunctionEnv<'_>) -> bool {
+        match function_signature(function) {
+            Some(hash) => {
+                self.insert(hash, function.get_full_name_with_address());
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Known functions with the same normalized body as `function`, excluding itself
+    pub fn matches(&self, function: &FunctionEnv<'_>) -> Vec<&str> {
+        let own_name = function.get_full_name_with_address();
+        function_signature(function)
+            .and_then(|hash| self.entries.get(&hash))
+            .map(|names| {
+                names
+                    .iter()
+                    .filter(|name| **name != own_name)
+                    .map(|name| name.as_str())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+impl Display for SignatureDb {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (hash, names) in &self.entries {
+            for name in names {
+                writeln!(f, "{} {}", hash, name)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Hash of the signature and body of a function, independent of the module it is
+/// defined in: pool indices are resolved to names and references to the defining
+/// module are written as `Self`. `None` for native and short functions.
+pub fn function_signature(function: &FunctionEnv<'_>) -> Option<String> {
+    let module = function.module_env.get_verified_module()?;
+    let def = module.function_def_at(function.get_def_idx()?);
+    let code = def.code.as_ref()?;
+    if code.code.len() < MIN_INSTRUCTIONS {
+        return None;
+    }
+
+    let normalizer = Normalizer { module };
+    let handle = module.function_handle_at(def.function);
+    let mut hasher = Sha3_256::new();
+    hasher.update(format!(
+        "({}): ({}) locals ({})\n",
+        normalizer.signature(module.signature_at(handle.parameters)),
+        normalizer.signature(module.signature_at(handle.return_)),
+        normalizer.signature(module.signature_at(code.locals)),
+    ));
+    for instruction in &code.code {
+        hasher.update(normalizer.instruction(instruction));
+        hasher.update("\n");
+    }
+
+    Some(hex::encode(&hasher.finalize()[..8]))
+}
+
+struct Normalizer<'a> {
+    module: &'a CompiledModule,
+}
+
+impl<'a> Normalizer<'a> {
+    fn module_name(&self, idx: ModuleHandleIndex) -> String {
+        if idx == self.module.self_handle_idx() {
+            return "Self".to_string();
+        }
+        let handle = self.module.module_handle_at(idx);
+        format!(
+            "{}::{}",
+            self.module
+                .address_identifier_at(handle.address)
+                .to_hex_literal(),
+            self.module.identifier_at(handle.name)
+        )
+    }
+
+    fn function(&self, idx: FunctionHandleIndex) -> String {
+        let handle = self.module.function_handle_at(idx);
+        format!(
+            "{}::{}",
+            self.module_name(handle.module),
+            self.module.identifier_at(handle.name)
+        )
+    }
+
+    fn function_instantiation(&self, idx: FunctionInstantiationIndex) -> String {
+        let inst = self.module.function_instantiation_at(idx);
+        format!(
+            "{}<{}>",
+            self.function(inst.handle),
+            self.signature_at(inst.type_parameters)
+        )
+    }
+
+    fn struct_handle(&self, idx: StructHandleIndex) -> String {
+        let handle = self.module.struct_handle_at(idx);
+        format!(
+            "{}::{}",
+            self.module_name(handle.module),
+            self.module.identifier_at(handle.name)
+        )
+    }
+
+    fn struct_def(&self, idx: StructDefinitionIndex) -> String {
+        self.struct_handle(self.module.struct_def_at(idx).struct_handle)
+    }
+
+    fn struct_instantiation(&self, idx: StructDefInstantiationIndex) -> String {
+        let inst = self.module.struct_instantiation_at(idx);
+        format!(
+            "{}<{}>",
+            self.struct_def(inst.def),
+            self.signature_at(inst.type_parameters)
+        )
+    }
+
+    fn field(&self, idx: FieldHandleIndex) -> String {
+        let handle = self.module.field_handle_at(idx);
+        format!("{}.{}", self.struct_def(handle.owner), handle.field)
+    }
+
+    fn field_instantiation(&self, idx: FieldInstantiationIndex) -> String {
+        let inst = self.module.field_instantiation_at(idx);
+        format!(
+            "{}<{}>",
+            self.field(inst.handle),
+            self.signature_at(inst.type_parameters)
+        )
+    }
+
+    fn token(&self, token: &SignatureToken) -> String {
+        match token {
+            SignatureToken::Vector(inner) => format!("vector<{}>", self.token(inner)),
+            SignatureToken::Struct(idx) => self.struct_handle(*idx),
+            SignatureToken::StructInstantiation(idx, tys) => format!(
+                "{}<{}>",
+                self.struct_handle(*idx),
+                tys.iter()
+                    .map(|ty| self.token(ty))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            SignatureToken::Reference(inner) => format!("&{}", self.token(inner)),
+            SignatureToken::MutableReference(inner) => format!("&mut {}", self.token(inner)),
+            SignatureToken::TypeParameter(idx) => format!("T{}", idx),
+            SignatureToken::Bool
+            | SignatureToken::U8
+            | SignatureToken::U16
+            | SignatureToken::U32
+            | SignatureToken::U64
+            | SignatureToken::U128
+            | SignatureToken::U256
+            | SignatureToken::Address
+            | SignatureToken::Signer => format!("{:?}", token),
+        }
+    }
+
+    fn signature(&self, signature: &Signature) -> String {
+        signature
+            .0
+            .iter()
+            .map(|token| self.token(token))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    fn signature_at(&self, idx: SignatureIndex) -> String {
+        self.signature(self.module.signature_at(idx))
+    }
+
+    fn instruction(&self, instruction: &Bytecode) -> String {
+        match instruction {
+            Bytecode::LdConst(idx) => {
+                let constant = self.module.constant_at(*idx);
+                format!(
+                    "LdConst({}, {})",
+                    self.token(&constant.type_),
+                    hex::encode(&constant.data)
+                )
+            }
+            Bytecode::Call(idx) => format!("Call({})", self.function(*idx)),
+            Bytecode::CallGeneric(idx) => format!("Call({})", self.function_instantiation(*idx)),
+            Bytecode::Pack(idx) => format!("Pack({})", self.struct_def(*idx)),
+            Bytecode::PackGeneric(idx) => format!("Pack({})", self.struct_instantiation(*idx)),
+            Bytecode::Unpack(idx) => format!("Unpack({})", self.struct_def(*idx)),
+            Bytecode::UnpackGeneric(idx) => {
+                format!("Unpack({})", self.struct_instantiation(*idx))
+            }
+            Bytecode::MutBorrowField(idx) => format!("MutBorrowField({})", self.field(*idx)),
+            Bytecode::MutBorrowFieldGeneric(idx) => {
+                format!("MutBorrowField({})", self.field_instantiation(*idx))
+            }
+            Bytecode::ImmBorrowField(idx) => format!("ImmBorrowField({})", self.field(*idx)),
+            Bytecode::ImmBorrowFieldGeneric(idx) => {
+                format!("ImmBorrowField({})", self.field_instantiation(*idx))
+            }
+            Bytecode::MutBorrowGlobal(idx) => format!("MutBorrowGlobal({})", self.struct_def(*idx)),
+            Bytecode::MutBorrowGlobalGeneric(idx) => {
+                format!("MutBorrowGlobal({})", self.struct_instantiation(*idx))
+            }
+            Bytecode::ImmBorrowGlobal(idx) => format!("ImmBorrowGlobal({})", self.struct_def(*idx)),
+            Bytecode::ImmBorrowGlobalGeneric(idx) => {
+                format!("ImmBorrowGlobal({})", self.struct_instantiation(*idx))
+            }
+            Bytecode::Exists(idx) => format!("Exists({})", self.struct_def(*idx)),
+            Bytecode::ExistsGeneric(idx) => format!("Exists({})", self.struct_instantiation(*idx)),
+            Bytecode::MoveFrom(idx) => format!("MoveFrom({})", self.struct_def(*idx)),
+            Bytecode::MoveFromGeneric(idx) => {
+                format!("MoveFrom({})", self.struct_instantiation(*idx))
+            }
+            Bytecode::MoveTo(idx) => format!("MoveTo({})", self.struct_def(*idx)),
+            Bytecode::MoveToGeneric(idx) => format!("MoveTo({})", self.struct_instantiation(*idx)),
+            Bytecode::VecPack(idx, n) => format!("VecPack({}, {})", self.signature_at(*idx), n),
+            Bytecode::VecUnpack(idx, n) => {
+                format!("VecUnpack({}, {})", self.signature_at(*idx), n)
+            }
+            Bytecode::VecLen(idx) => format!("VecLen({})", self.signature_at(*idx)),
+            Bytecode::VecImmBorrow(idx) => format!("VecImmBorrow({})", self.signature_at(*idx)),
+            Bytecode::VecMutBorrow(idx) => format!("VecMutBorrow({})", self.signature_at(*idx)),
+            Bytecode::VecPushBack(idx) => format!("VecPushBack({})", self.signature_at(*idx)),
+            Bytecode::VecPopBack(idx) => format!("VecPopBack({})", self.signature_at(*idx)),
+            Bytecode::VecSwap(idx) => format!("VecSwap({})", self.signature_at(*idx)),
+            // the remaining instructions do not reference the module pools
+            _ => format!("{:?}", instruction),
+        }
+    }
+}
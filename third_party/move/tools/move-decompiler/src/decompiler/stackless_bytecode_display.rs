@@ -4,7 +4,11 @@ use std::collections::BTreeMap;
 use std::fmt::Write;
 
 use move_stackless_bytecode::{function_target::FunctionTarget, stackless_bytecode::Bytecode};
-use super::cfg::{self, StacklessBlockIdentifier, StacklessBlockContent, datastructs::Terminator};
+use super::cfg::{
+    self,
+    datastructs::{DecompileDisplayContext, Terminator},
+    StacklessBlockContent, StacklessBlockIdentifier,
+};
 
 pub struct StacklessBycodeDisplayContext<'a> {
     function_target: &'a FunctionTarget<'a>,
@@ -30,6 +34,34 @@ impl<'a> StacklessBycodeDisplayContext<'a> {
     pub fn result(&self) -> &str {
         &self.buf
     }
+
+    /// Flat listing of basic blocks, before they are grouped into regions
+    pub fn display_blocks(
+        &mut self,
+        blocks: &[cfg::datastructs::BasicBlock<StacklessBlockIdentifier, StacklessBlockContent>],
+    ) {
+        for block in blocks {
+            let mut header = format!("// block {}", block.idx);
+            if block.offset != usize::MAX {
+                header.push_str(&format!(" @{}", block.offset));
+            }
+            header.push_str(&format!(" -> {:?}", block.next));
+            if let Some(exit) = block.unconditional_loop_entry {
+                header.push_str(&format!(", loop entry exiting to {}", exit));
+            }
+            self.add_lines(&header);
+
+            self.block(|ctx| {
+                for code in block.content.code.iter().filter(|x| !x.removed) {
+                    let line = code
+                        .bytecode
+                        .display(ctx.function_target, &ctx.label_offsets)
+                        .to_string();
+                    ctx.add_lines(&line);
+                }
+            });
+        }
+    }
 }
 
 impl<'a> cfg::datastructs::DecompileDisplayContext<StacklessBlockIdentifier, StacklessBlockContent>
@@ -46,7 +78,10 @@ impl<'a> cfg::datastructs::DecompileDisplayContext<StacklessBlockIdentifier, Sta
             if iter.peek().is_none() {
                 // last instruction
                 match node.next {
-                    Terminator::Normal | Terminator::Ret | Terminator::Abort => {}
+                    Terminator::Normal
+                    | Terminator::Ret
+                    | Terminator::Abort
+                    | Terminator::Switch { .. } => {}
 
                     Terminator::Break { .. } | Terminator::Continue { .. } => {
                         if let Bytecode::Jump(..) = code.bytecode {
@@ -109,6 +144,10 @@ impl<'a> cfg::datastructs::DecompileDisplayContext<StacklessBlockIdentifier, Sta
                 }
             }
         }
+
+        if let Terminator::Switch { .. } = node.next {
+            self.add_lines("switch {");
+        }
     }
 
     fn block(&mut self, f: impl FnOnce(&mut Self)) {
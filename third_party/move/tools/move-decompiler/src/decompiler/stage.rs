@@ -0,0 +1,82 @@
+// Copyright (c) Verichains, 2023
+
+use std::{fmt::Display, str::FromStr};
+
+/// Stages of the per-function decompilation pipeline, in execution order
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Stage {
+    /// stack-based bytecode lifted to stackless bytecode
+    Stackless,
+    /// stackless bytecode split into topologically sorted basic blocks
+    Blocks,
+    /// loops reconstructed into while/break/continue terminators
+    Loops,
+    /// blocks grouped into if/else and while regions
+    Structure,
+    /// source code generated and optimized
+    Source,
+}
+
+impl Stage {
+    pub const ALL: [Stage; 5] = [
+        Stage::Stackless,
+        Stage::Blocks,
+        Stage::Loops,
+        Stage::Structure,
+        Stage::Source,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Stage::Stackless => "stackless",
+            Stage::Blocks => "blocks",
+            Stage::Loops => "loops",
+            Stage::Structure => "structure",
+            Stage::Source => "source",
+        }
+    }
+}
+
+impl Display for Stage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.pad(self.name())
+    }
+}
+
+impl FromStr for Stage {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Stage::ALL
+            .iter()
+            .find(|stage| stage.name() == s)
+            .copied()
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "unknown stage {}, expected one of: {}",
+                    s,
+                    Stage::ALL
+                        .iter()
+                        .map(|stage| stage.name())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            })
+    }
+}
+
+/// Intermediate representation of a function after a stage
+#[derive(Clone, Debug)]
+pub struct StageDump {
+    /// `address::module::function` the dump belongs to
+    pub function: String,
+    pub stage: Stage,
+    pub ir: String,
+}
+
+impl Display for StageDump {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "// ===== {} after {} =====", self.function, self.stage)?;
+        write!(f, "{}", self.ir)
+    }
+}
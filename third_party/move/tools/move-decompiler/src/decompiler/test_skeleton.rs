@@ -0,0 +1,161 @@
+// Copyright (c) Verichains, 2023
+
+use anyhow::{Ok, Result};
+use move_binary_format::file_format::Visibility;
+use move_model::{
+    ast::Address,
+    model::{FunctionEnv, ModuleEnv},
+    ty::{PrimitiveType, ReferenceKind, Type},
+};
+
+use super::{naming::Naming, reconstruct::code_unit::SourceCodeUnit, Decompiler};
+
+impl<'a> Decompiler<'a> {
+    /// A `#[test_only]` module with one `#[test]` per public function of `module`,
+    /// arguments that can not be trivially constructed are left as TODOs
+    pub(super) fn decompile_test_skeleton(
+        &self,
+        module: &ModuleEnv<'_>,
+        naming: &Naming,
+    ) -> Result<SourceCodeUnit> {
+        let module_name = module.get_name().display(&self.env).to_string();
+        let address = match module.get_name().addr() {
+            Address::Numerical(addr) => addr.to_hex_literal(),
+            Address::Symbolic(_) => {
+                unreachable!("There must be no symbolic address in compiled binary")
+            }
+        };
+
+        let mut res = SourceCodeUnit::new(0);
+        res.add_line("#[test_only]".to_string());
+        res.add_line(format!("module {}::{}_tests {{", address, module_name));
+
+        let mut body = SourceCodeUnit::new(1);
+        body.add_line(format!("use {}::{};", address, module_name));
+        for f in module.get_functions() {
+            if f.visibility() != Visibility::Public {
+                continue;
+            }
+            body.add_line("".to_string());
+            body.add_block(self.decompile_test_function(&f, &module_name, &address, naming)?);
+        }
+        res.add_block(body);
+        res.add_line("}".to_string());
+
+        Ok(res)
+    }
+
+    fn decompile_test_function(
+        &self,
+        function_env: &FunctionEnv<'_>,
+        module_name: &str,
+        address: &str,
+        naming: &Naming,
+    ) -> Result<SourceCodeUnit> {
+        let function_name = function_env.get_name_str();
+
+        let mut signers = Vec::new();
+        let mut lines = Vec::new();
+        let mut args = Vec::new();
+        let mut complete = function_env.get_type_parameter_count() == 0;
+        if !complete {
+            lines.push("// TODO: instantiate the type parameters".to_string());
+        }
+
+        for (idx, param) in function_env.get_parameters().iter().enumerate() {
+            let arg = naming.argument(idx);
+            match &param.1 {
+                Type::Primitive(PrimitiveType::Signer) => {
+                    signers.push(arg.clone());
+                    args.push(arg);
+                }
+                Type::Reference(_, ty) if **ty == Type::Primitive(PrimitiveType::Signer) => {
+                    signers.push(arg.clone());
+                    args.push(format!("&{}", arg));
+                }
+                ty => {
+                    let ty_str =
+                        self.inline_decompile_type(&function_env.module_env, ty, naming)?;
+                    match placeholder_value(ty) {
+                        Some(value) => {
+                            lines.push(format!("let {}: {} = {}; // TODO", arg, ty_str, value));
+                            args.push(arg);
+                        }
+                        None => {
+                            lines.push(format!("// TODO: construct {}: {}", arg, ty_str));
+                            complete = false;
+                            args.push(match ty {
+                                Type::Reference(ReferenceKind::Mutable, _) => {
+                                    format!("&mut {}", arg)
+                                }
+                                Type::Reference(ReferenceKind::Immutable, _) => {
+                                    format!("&{}", arg)
+                                }
+                                _ => arg,
+                            });
+                        }
+                    }
+                }
+            }
+        }
+
+        let call = format!("{}::{}({})", module_name, function_name, args.join(", "));
+        let call = if function_env.get_return_count() > 0 {
+            format!("let _result = {};", call)
+        } else {
+            format!("{};", call)
+        };
+        lines.push(if complete {
+            call
+        } else {
+            format!("// {}", call)
+        });
+        lines.push("// TODO: check the results".to_string());
+
+        let mut res = SourceCodeUnit::new(0);
+        if signers.is_empty() {
+            res.add_line("#[test]".to_string());
+        } else {
+            res.add_line(format!(
+                "#[test({})]",
+                signers
+                    .iter()
+                    .map(|s| format!("{} = @{}", s, address))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
+        res.add_line(format!(
+            "fun test_{}({}) {{",
+            function_name,
+            signers
+                .iter()
+                .map(|s| format!("{}: signer", s))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+        let mut body = SourceCodeUnit::new(1);
+        for line in lines {
+            body.add_line(line);
+        }
+        res.add_block(body);
+        res.add_line("}".to_string());
+
+        Ok(res)
+    }
+}
+
+fn placeholder_value(ty: &Type) -> Option<String> {
+    match ty {
+        Type::Primitive(PrimitiveType::Bool) => Some("false".to_string()),
+        Type::Primitive(PrimitiveType::U8) => Some("0u8".to_string()),
+        Type::Primitive(PrimitiveType::U16) => Some("0u16".to_string()),
+        Type::Primitive(PrimitiveType::U32) => Some("0u32".to_string()),
+        Type::Primitive(PrimitiveType::U64) => Some("0u64".to_string()),
+        Type::Primitive(PrimitiveType::U128) => Some("0u128".to_string()),
+        Type::Primitive(PrimitiveType::U256) => Some("0u256".to_string()),
+        Type::Primitive(PrimitiveType::Address) => Some("@0x1".to_string()),
+        Type::Vector(inner) if placeholder_value(inner).is_some() => Some("vector[]".to_string()),
+        _ => None,
+    }
+}
@@ -0,0 +1,80 @@
+// Copyright (c) Verichains, 2023
+
+use std::fmt::Display;
+
+use move_binary_format::{
+    access::ModuleAccess,
+    binary_views::BinaryIndexedView,
+    errors::VMError,
+    file_format::{CodeOffset, FunctionDefinitionIndex},
+    IndexKind,
+};
+use move_core_types::vm_status::StatusCode;
+
+/// Rejection of a binary by the Move bytecode verifier, located as precisely as the verifier
+/// reports it
+#[derive(Debug, Clone)]
+pub struct VerificationError {
+    /// `0x1::name` of the module, or `script`
+    pub binary: String,
+    pub function: Option<String>,
+    pub offset: Option<CodeOffset>,
+    pub status: StatusCode,
+    pub message: Option<String>,
+}
+
+impl Display for VerificationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.binary)?;
+        if let Some(function) = &self.function {
+            write!(f, "::{}", function)?;
+        }
+        if let Some(offset) = self.offset {
+            write!(f, " at offset {}", offset)?;
+        }
+        write!(f, ": {:?}", self.status)?;
+        if let Some(message) = &self.message {
+            write!(f, ": {}", message)?;
+        }
+        std::fmt::Result::Ok(())
+    }
+}
+
+/// Run the bytecode verifier on a module or script. The decompiler assumes verified input,
+/// anything else tends to fail deep in the pipeline with errors unrelated to the cause.
+pub fn verify_binary(binary: &BinaryIndexedView) -> Result<(), VerificationError> {
+    let result = match binary {
+        BinaryIndexedView::Module(module) => move_bytecode_verifier::verify_module(module),
+        BinaryIndexedView::Script(script) => move_bytecode_verifier::verify_script(script),
+    };
+    result.map_err(|err| verification_error(binary, &err))
+}
+
+fn verification_error(binary: &BinaryIndexedView, err: &VMError) -> VerificationError {
+    let function_idx = err.offsets().first().map(|(idx, _)| *idx).or_else(|| {
+        err.indices()
+            .iter()
+            .find(|(kind, _)| *kind == IndexKind::FunctionDefinition)
+            .map(|(_, idx)| FunctionDefinitionIndex(*idx))
+    });
+    let function = function_idx.and_then(|idx| match binary {
+        BinaryIndexedView::Module(module) => {
+            module.function_defs().get(idx.0 as usize).map(|def| {
+                module
+                    .identifier_at(module.function_handle_at(def.function).name)
+                    .to_string()
+            })
+        }
+        BinaryIndexedView::Script(_) => Some("main".to_string()),
+    });
+    VerificationError {
+        binary: match binary {
+            BinaryIndexedView::Module(module) => module.self_id().short_str_lossless(),
+            BinaryIndexedView::Script(_) => "script".to_string(),
+        },
+        function,
+        offset: err.offsets().first().map(|(_, offset)| *offset),
+        status: err.major_status(),
+        message: err.message().cloned(),
+    }
+}
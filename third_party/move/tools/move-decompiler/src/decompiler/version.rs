@@ -0,0 +1,28 @@
+// Copyright (c) Verichains, 2023
+
+use move_binary_format::file_format_common::{BinaryConstants, VERSION_MAX};
+
+/// Bytecode version in the header of a module or script blob
+pub fn bytecode_version(bytes: &[u8]) -> Option<u32> {
+    let magic = bytes.get(..BinaryConstants::MOVE_MAGIC_SIZE)?;
+    if magic != BinaryConstants::MOVE_MAGIC {
+        return None;
+    }
+    let version =
+        bytes.get(BinaryConstants::MOVE_MAGIC_SIZE..BinaryConstants::MOVE_MAGIC_SIZE + 4)?;
+    Some(u32::from_le_bytes(version.try_into().ok()?))
+}
+
+/// Why a blob newer than the bytecode format this decompiler is built on cannot be
+/// deserialized. Later versions add opcodes, such as the function values (closures and
+/// higher-order calls) of Move 2, that neither the deserializer nor the lifter know about.
+pub fn unsupported_version(bytes: &[u8]) -> Option<String> {
+    let version = bytecode_version(bytes)?;
+    (version > VERSION_MAX).then(|| {
+        format!(
+            "bytecode version {} is newer than the supported version {}, function values \
+             (closures) and other later features cannot be decompiled",
+            version, VERSION_MAX
+        )
+    })
+}
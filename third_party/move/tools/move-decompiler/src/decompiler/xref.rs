@@ -0,0 +1,145 @@
+// Copyright (c) Verichains, 2023
+
+use std::{collections::BTreeMap, fmt::Display};
+
+use move_model::model::{FunctionEnv, GlobalEnv, ModuleId, StructId};
+use move_stackless_bytecode::{
+    function_target::FunctionTarget,
+    stackless_bytecode::{Bytecode, Operation},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum XrefKind {
+    Call,
+    Pack,
+    Unpack,
+    Read,
+    Write,
+}
+
+impl Display for XrefKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.pad(match self {
+            XrefKind::Call => "call",
+            XrefKind::Pack => "pack",
+            XrefKind::Unpack => "unpack",
+            XrefKind::Read => "read",
+            XrefKind::Write => "write",
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct XrefLocation {
+    pub kind: XrefKind,
+    /// `address::module::function` the reference is made from
+    pub function: String,
+    /// line of the function header in the decompiled output
+    pub line: usize,
+}
+
+/// Where every function and struct is referenced across the decompiled binaries
+#[derive(Debug, Default)]
+pub struct XrefReport {
+    references: BTreeMap<String, Vec<XrefLocation>>,
+}
+
+impl XrefReport {
+    pub fn references(&self) -> &BTreeMap<String, Vec<XrefLocation>> {
+        &self.references
+    }
+
+    pub fn get(&self, target: &str) -> Option<&Vec<XrefLocation>> {
+        self.references.get(target)
+    }
+
+    fn add(&mut self, target: String, location: XrefLocation) {
+        let locations = self.references.entry(target).or_insert_with(Vec::new);
+        if !locations.contains(&location) {
+            locations.push(location);
+        }
+    }
+
+    pub(crate) fn collect_function(
+        &mut self,
+        function_env: &FunctionEnv<'_>,
+        function_target: &FunctionTarget<'_>,
+        line: usize,
+    ) {
+        let env = function_env.module_env.env;
+        let function = function_env.get_full_name_with_address();
+
+        for bytecode in function_target.get_bytecode() {
+            let Bytecode::Call(_, dsts, oper, _, _) = bytecode else {
+                continue;
+            };
+            let is_mutable_result = || {
+                dsts.len() == 1
+                    && function_target
+                        .get_local_type(dsts[0])
+                        .is_mutable_reference()
+            };
+            let (target, kind) = match oper {
+                Operation::Function(mid, fid, _) => (
+                    env.get_module(*mid)
+                        .get_function(*fid)
+                        .get_full_name_with_address(),
+                    XrefKind::Call,
+                ),
+                Operation::Pack(mid, sid, _) => (struct_name(env, *mid, *sid), XrefKind::Pack),
+                Operation::Unpack(mid, sid, _) => (struct_name(env, *mid, *sid), XrefKind::Unpack),
+                Operation::Exists(mid, sid, _) | Operation::GetField(mid, sid, ..) => {
+                    (struct_name(env, *mid, *sid), XrefKind::Read)
+                }
+                Operation::MoveTo(mid, sid, _) | Operation::MoveFrom(mid, sid, _) => {
+                    (struct_name(env, *mid, *sid), XrefKind::Write)
+                }
+                Operation::BorrowGlobal(mid, sid, _) | Operation::BorrowField(mid, sid, ..) => (
+                    struct_name(env, *mid, *sid),
+                    if is_mutable_result() {
+                        XrefKind::Write
+                    } else {
+                        XrefKind::Read
+                    },
+                ),
+                _ => continue,
+            };
+            self.add(
+                target,
+                XrefLocation {
+                    kind,
+                    function: function.clone(),
+                    line,
+                },
+            );
+        }
+    }
+}
+
+fn struct_name(env: &GlobalEnv, mid: ModuleId, sid: StructId) -> String {
+    let module = env.get_module(mid);
+    let struct_env = module.get_struct(sid);
+    format!(
+        "{}::{}",
+        module.get_full_name_str(),
+        struct_env.get_name().display(env.symbol_pool())
+    )
+}
+
+impl Display for XrefReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (target, locations) in self.references.iter() {
+            writeln!(f, "{}", target)?;
+            let mut locations = locations.clone();
+            locations.sort();
+            for location in locations {
+                writeln!(
+                    f,
+                    "    {:<6} {} (line {})",
+                    location.kind, location.function, location.line
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -2,31 +2,252 @@
 
 #![forbid(unsafe_code)]
 
-use std::fs;
+mod profile;
+mod remote;
+#[cfg(feature = "serve")]
+mod serve;
 
-use clap::Parser;
+/// Stand-in when built without the `serve` feature, `run` always fails
+#[cfg(not(feature = "serve"))]
+mod serve {
+    pub fn run(
+        _address: std::net::SocketAddr,
+        _max_concurrency: usize,
+        _timeout: std::time::Duration,
+    ) -> anyhow::Result<()> {
+        anyhow::bail!("built without the HTTP server, rebuild with `--features serve`")
+    }
+}
+
+use std::{fs, io::IsTerminal, net::SocketAddr, path::Path, time::Duration};
+
+use clap::{Parser, Subcommand};
+use profile::Flamegraph;
 
 use move_binary_format::{
     binary_views::BinaryIndexedView,
     file_format::{CompiledModule, CompiledScript},
 };
-use move_decompiler::decompiler::{Decompiler, OptimizerSettings};
+use move_decompiler::decompiler::{
+    highlight, read_bundle, unsupported_version, verify_binary, Backend, BundleFormat, Decompiler,
+    DecompilerSettings, LiteralStyle, OptimizerSettings, PackageMetadata, SignatureDb, Stage,
+    SymbolIndex,
+};
 #[derive(Debug, Parser)]
 #[clap(author, version, about)]
 struct Args {
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+
     /// Treat input file as a script (default is to treat file as a module)
     #[clap(short = 's', long = "script")]
     pub is_script: bool,
 
-    // Input files
+    // Input files, serialized modules or scripts, or bundles of modules (zip, tar, tar.gz,
+    // `.cbor` array of byte strings, `.bcs` module vector of a publishing transaction)
     #[clap(short = 'b', long = "bytecode")]
     pub files: Vec<String>,
 
+    /// Run the bytecode verifier on the input and stop with its errors if it is rejected
+    #[clap(long = "verify")]
+    pub verify: bool,
+
+    /// Decompile input rejected by `--verify` anyway, its errors become warnings
+    #[clap(long = "force", requires = "verify")]
+    pub force: bool,
+
+    /// Module or bundle the input depends on, used for the signatures and structs it
+    /// references, can be repeated
+    #[clap(long = "dependency")]
+    pub dependencies: Vec<String>,
+
+    /// Aptos node REST API (e.g. https://fullnode.mainnet.aptoslabs.com/v1) to fetch the
+    /// modules called by the input that are not given with `--dependency`, requires building
+    /// with `--features remote`
+    #[clap(long = "node")]
+    pub node: Option<String>,
+
     #[clap(
         long = "disable-variable-declaration-optimization",
         default_value = "false"
     )]
     pub disable_variable_declaration_optimization: bool,
+
+    /// Only decompile entry and view functions and what they call, other functions become stubs
+    #[clap(long = "entry-view-only")]
+    pub entry_view_only: bool,
+
+    /// Annotate storage accesses, table operations and vector-bounded loops with gas hints
+    #[clap(long = "gas-hints")]
+    pub gas_hints: bool,
+
+    /// Comment loops with the storage reads and borrows repeated on every iteration
+    #[clap(long = "loop-invariant-hints")]
+    pub loop_invariant_hints: bool,
+
+    /// Comment resource writes preceded by calls into other modules since the resource was read
+    #[clap(long = "ordering-hints")]
+    pub ordering_hints: bool,
+
+    /// Compute pure expressions repeated in a block, like `vector::length(&v)`, once into a
+    /// named local
+    #[clap(long = "cse")]
+    pub cse: bool,
+
+    /// Write if/else with a short branch that returns or aborts as a guard clause, reducing
+    /// nesting
+    #[clap(long = "early-returns")]
+    pub early_returns: bool,
+
+    /// Comment loops iterating over a vector by index with their foreach form
+    #[clap(long = "foreach-hints")]
+    pub foreach_hints: bool,
+
+    /// Write `#[test]` skeletons for the public functions of each module into this directory
+    #[clap(long = "emit-tests")]
+    pub emit_tests: Option<String>,
+
+    /// Write the warnings and errors of the run (failed functions, heuristic fallbacks,
+    /// unreadable metadata) as a JSON array into this file
+    #[clap(long = "diagnostics")]
+    pub diagnostics: Option<String>,
+
+    /// Write a cross-reference report of functions and structs into this file
+    #[clap(long = "xref")]
+    pub xref: Option<String>,
+
+    /// Format the output following movefmt conventions
+    #[clap(long = "movefmt")]
+    pub movefmt: bool,
+
+    /// Syntax-highlight the output and mark low-confidence regions in a warning color:
+    /// `auto` (default) when printing to a terminal, `always` or `never`
+    #[clap(long = "color", default_value = "auto", possible_values = ["auto", "always", "never"])]
+    pub color: String,
+
+    /// Relaxed, non-compilable output for reading: ternaries, no widening casts and no abort
+    /// codes
+    #[clap(long = "pseudocode")]
+    pub pseudocode: bool,
+
+    /// Annotate trivial getters and setters, with `--pseudocode` their calls are folded into
+    /// field accesses
+    #[clap(long = "fold-accessors")]
+    pub fold_accessors: bool,
+
+    /// Declare powers of two only used with `&` and `|` as `FLAG_<bit>` constants and use
+    /// them in those operations
+    #[clap(long = "bit-flags")]
+    pub bit_flags: bool,
+
+    /// Integer literals in `decimal`, in `hex`, or `auto`: hex for masks, addresses and magic
+    /// numbers
+    #[clap(long = "literals", default_value = "decimal")]
+    pub literals: LiteralStyle,
+
+    /// How function bodies are decompiled: `cfg` (default) reconstructs Move source,
+    /// `stackless` dumps the typed stackless bytecode of the move model instead
+    #[clap(long = "backend", default_value = "cfg")]
+    pub backend: Backend,
+
+    /// Stop after this stage (stackless, blocks, loops, structure, source) and dump its
+    /// intermediate representation to stderr, function bodies become stubs
+    #[clap(long = "stop-after")]
+    pub stop_after: Option<Stage>,
+
+    /// Dump the intermediate representation after this stage to stderr, can be repeated
+    #[clap(long = "dump-after")]
+    pub dump_after: Vec<Stage>,
+
+    /// Export the intermediate representation after this stage as versioned JSON, written to
+    /// `--emit-ir-file` or stderr
+    #[clap(long = "emit-ir")]
+    pub emit_ir: Option<Stage>,
+
+    /// File receiving the output of `--emit-ir`
+    #[clap(long = "emit-ir-file", requires = "emit_ir")]
+    pub emit_ir_file: Option<String>,
+
+    /// Label functions matching an entry of this signature database, can be repeated
+    #[clap(long = "signatures")]
+    pub signatures: Vec<String>,
+
+    /// Write the signature database of the input modules into this file
+    #[clap(long = "build-signatures")]
+    pub build_signatures: Option<String>,
+
+    /// Source verification service queried for each module, `{hash}` is replaced by the
+    /// sha3-256 of the module; modules with verified source are not decompiled, requires
+    /// building with `--features remote`
+    #[clap(long = "registry")]
+    pub registry: Option<String>,
+
+    /// Print the metadata of each module as a header comment
+    #[clap(long = "metadata-header")]
+    pub metadata_header: bool,
+
+    /// `package-metadata.bcs` of the package the modules belong to, printed in the metadata
+    /// header (implies `--metadata-header`)
+    #[clap(long = "package-metadata")]
+    pub package_metadata: Option<String>,
+
+    /// Write Mermaid flowcharts of the control flow graph of each function into this
+    /// markdown file
+    #[clap(long = "mermaid-cfg")]
+    pub mermaid_cfg: Option<String>,
+
+    /// Write a Mermaid flowchart of the call graph into this markdown file
+    #[clap(long = "mermaid-call-graph")]
+    pub mermaid_call_graph: Option<String>,
+
+    /// Write a `Move.toml` making the decompiled modules a package into this file, with
+    /// framework dependencies pinned to the version found in `--package-metadata`
+    #[clap(long = "move-toml")]
+    pub move_toml: Option<String>,
+
+    /// Framework revision (git branch, tag or commit) used by `--move-toml` instead of the
+    /// detected one
+    #[clap(long = "framework-rev", requires = "move_toml")]
+    pub framework_rev: Option<String>,
+
+    /// Profile the run and write a flamegraph of where the time goes into this SVG file,
+    /// requires building with `--features flamegraph`
+    #[clap(long = "flamegraph")]
+    pub flamegraph: Option<String>,
+
+    /// Write the Move IR of the modules into this `.mvir` file, which assembles back to the
+    /// original bytecode more directly than the decompiled source
+    #[clap(long = "mvir")]
+    pub mvir: Option<String>,
+
+    /// Write a JSON index of the functions and structs of each module into this file, with
+    /// structural fingerprints to match functions across module upgrades
+    #[clap(long = "symbols")]
+    pub symbols: Option<String>,
+
+    /// Print to stderr how the functions changed since this JSON index, written by `--symbols`
+    /// for an earlier version of the modules; renamed functions are matched by fingerprint
+    #[clap(long = "symbols-diff")]
+    pub symbols_diff: Option<String>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Serve an HTTP API: `POST /decompile` with the module bytes as body (add `?script` for
+    /// a script) returns the source, warnings and symbol index as JSON, requires building
+    /// with `--features serve`
+    Serve {
+        #[clap(long = "address", default_value = "127.0.0.1:8080")]
+        address: SocketAddr,
+
+        /// Requests decompiled at the same time, further requests are rejected
+        #[clap(long = "max-concurrency", default_value = "4")]
+        max_concurrency: usize,
+
+        /// Seconds after which a request is answered with a timeout error
+        #[clap(long = "timeout", default_value = "30")]
+        timeout: u64,
+    },
 }
 
 enum CompiledBinary {
@@ -37,26 +258,48 @@ enum CompiledBinary {
 fn main() {
     let args = Args::parse();
 
+    if let Some(Command::Serve {
+        address,
+        max_concurrency,
+        timeout,
+    }) = args.command
+    {
+        serve::run(address, max_concurrency, Duration::from_secs(timeout))
+            .expect("Error: server failed");
+        return;
+    }
+
+    let flamegraph = args.flamegraph.as_ref().map(|path| {
+        Flamegraph::start(path).unwrap_or_else(|err| {
+            panic!("Error: unable to start profiling: {:#}", err);
+        })
+    });
+
     let binaries_store: Vec<_> = args
         .files
         .iter()
-        .map(|file| {
+        .flat_map(|file| {
             let bytecode_bytes = fs::read(file).unwrap_or_else(|err| {
                 panic!("Error: failed to read file {}: {}", file.to_string(), err);
             });
 
+            if let Some(format) = BundleFormat::detect(file, &bytecode_bytes) {
+                let modules = read_bundle(format, &bytecode_bytes).unwrap_or_else(|err| {
+                    panic!("Error: failed to read bundle {}: {:#}", file, err);
+                });
+                return modules.into_iter().map(CompiledBinary::Module).collect();
+            }
+
             if args.is_script {
-                CompiledBinary::Script(CompiledScript::deserialize(&bytecode_bytes).unwrap_or_else(
-                    |err| {
-                        panic!("Error: failed to deserialize script blob: {}", err);
-                    },
-                ))
+                vec![CompiledBinary::Script(
+                    CompiledScript::deserialize(&bytecode_bytes)
+                        .unwrap_or_else(|err| deserialize_error("script", &bytecode_bytes, err)),
+                )]
             } else {
-                CompiledBinary::Module(CompiledModule::deserialize(&bytecode_bytes).unwrap_or_else(
-                    |err| {
-                        panic!("Error: failed to deserialize module blob: {}", err);
-                    },
-                ))
+                vec![CompiledBinary::Module(
+                    CompiledModule::deserialize(&bytecode_bytes)
+                        .unwrap_or_else(|err| deserialize_error("module", &bytecode_bytes, err)),
+                )]
             }
         })
         .collect();
@@ -69,12 +312,233 @@ fn main() {
         })
         .collect();
 
+    if args.verify {
+        let errors = binaries
+            .iter()
+            .filter_map(|binary| verify_binary(binary).err())
+            .collect::<Vec<_>>();
+        for err in &errors {
+            if args.force {
+                eprintln!("warning: bytecode verification failed: {}", err);
+            } else {
+                eprintln!("error: bytecode verification failed: {}", err);
+            }
+        }
+        if !errors.is_empty() && !args.force {
+            panic!("Error: rejected by the bytecode verifier, use --force to decompile anyway");
+        }
+    }
+
+    let mut dependencies: Vec<_> = args
+        .dependencies
+        .iter()
+        .flat_map(|file| {
+            let bytes = fs::read(file).unwrap_or_else(|err| {
+                panic!("Error: failed to read file {}: {}", file, err);
+            });
+            match BundleFormat::detect(file, &bytes) {
+                Some(format) => read_bundle(format, &bytes).unwrap_or_else(|err| {
+                    panic!("Error: failed to read bundle {}: {:#}", file, err);
+                }),
+                None => vec![CompiledModule::deserialize(&bytes)
+                    .unwrap_or_else(|err| deserialize_error("module", &bytes, err))],
+            }
+        })
+        .collect();
+    if let Some(url) = &args.node {
+        remote::fetch_dependencies(url, &binaries, &mut dependencies).unwrap_or_else(|err| {
+            panic!(
+                "Error: unable to fetch dependencies from {}: {:#}",
+                url, err
+            );
+        });
+    }
+
+    let mut signatures = SignatureDb::new();
+    for path in &args.signatures {
+        let text = fs::read_to_string(path).unwrap_or_else(|err| {
+            panic!("Error: failed to read file {}: {}", path, err);
+        });
+        let db = SignatureDb::parse(&text).unwrap_or_else(|err| {
+            panic!("Error: failed to parse signatures {}: {}", path, err);
+        });
+        signatures.extend(db);
+    }
+
     let mut decompiler = Decompiler::new(
         binaries,
         OptimizerSettings {
             disable_optimize_variables_declaration: args.disable_variable_declaration_optimization,
+            annotate_gas_hints: args.gas_hints,
+            annotate_foreach_loops: args.foreach_hints,
+            annotate_loop_invariants: args.loop_invariant_hints,
+            annotate_call_ordering: args.ordering_hints,
+            eliminate_common_subexpressions: args.cse,
+            rewrite_early_returns: args.early_returns,
         },
-    );
+    )
+    .with_settings(DecompilerSettings {
+        entry_view_only: args.entry_view_only,
+        movefmt_compatible: args.movefmt,
+        stop_after: args.stop_after,
+        dump_after: args.dump_after.iter().copied().collect(),
+        metadata_header: args.metadata_header || args.package_metadata.is_some(),
+        emit_ir: args.emit_ir,
+        backend: args.backend,
+        pseudocode: args.pseudocode,
+        fold_accessors: args.fold_accessors,
+        bit_flags: args.bit_flags,
+        literal_style: args.literals,
+    })
+    .with_signatures(signatures)
+    .with_dependencies(dependencies);
+    if let Some(path) = &args.package_metadata {
+        let bytes = fs::read(path).unwrap_or_else(|err| {
+            panic!("Error: failed to read file {}: {}", path, err);
+        });
+        let metadata = PackageMetadata::from_bcs(&bytes).unwrap_or_else(|err| {
+            panic!("Error: failed to parse package metadata {}: {}", path, err);
+        });
+        decompiler = decompiler.with_package_metadata(metadata);
+    }
+    if let Some(url) = &args.registry {
+        decompiler = remote::with_registry(decompiler, url).unwrap_or_else(|err| {
+            panic!("Error: unable to use registry {}: {:#}", url, err);
+        });
+    }
+
+    if let Some(path) = &args.build_signatures {
+        fs::write(path, decompiler.collect_signatures().to_string()).unwrap_or_else(|err| {
+            panic!("Error: failed to write file {}: {}", path, err);
+        });
+    }
+
     let output = decompiler.decompile().expect("Error: unable to decompile");
-    println!("{}", output);
+    for dump in decompiler.dumps() {
+        eprintln!("{}", dump);
+    }
+    let color = match args.color.as_str() {
+        "always" => true,
+        "never" => false,
+        _ => std::io::stdout().is_terminal(),
+    };
+    if color {
+        println!("{}", highlight(&output));
+    } else {
+        println!("{}", output);
+    }
+
+    if let Some(export) = decompiler.ir_export() {
+        let json = serde_json::to_string_pretty(&export).expect("Error: unable to serialize");
+        match &args.emit_ir_file {
+            Some(path) => fs::write(path, json).unwrap_or_else(|err| {
+                panic!("Error: failed to write file {}: {}", path, err);
+            }),
+            None => eprintln!("{}", json),
+        }
+    }
+
+    if let Some(path) = &args.diagnostics {
+        let json = serde_json::to_string_pretty(decompiler.diagnostics())
+            .expect("Error: unable to serialize");
+        fs::write(path, json).unwrap_or_else(|err| {
+            panic!("Error: failed to write file {}: {}", path, err);
+        });
+    }
+
+    if let Some(path) = &args.xref {
+        fs::write(path, decompiler.xref().to_string()).unwrap_or_else(|err| {
+            panic!("Error: failed to write file {}: {}", path, err);
+        });
+    }
+
+    if let Some(path) = &args.move_toml {
+        fs::write(path, decompiler.move_toml(args.framework_rev.as_deref())).unwrap_or_else(
+            |err| {
+                panic!("Error: failed to write file {}: {}", path, err);
+            },
+        );
+    }
+
+    if let Some(path) = &args.mvir {
+        let ir = decompiler
+            .decompile_ir()
+            .expect("Error: unable to produce Move IR");
+        fs::write(path, ir).unwrap_or_else(|err| {
+            panic!("Error: failed to write file {}: {}", path, err);
+        });
+    }
+
+    if let Some(path) = &args.symbols {
+        let index = decompiler
+            .symbol_index()
+            .expect("Error: unable to build symbol index");
+        let json = serde_json::to_string_pretty(&index).expect("Error: unable to serialize");
+        fs::write(path, json).unwrap_or_else(|err| {
+            panic!("Error: failed to write file {}: {}", path, err);
+        });
+    }
+
+    if let Some(path) = &args.symbols_diff {
+        let text = fs::read_to_string(path).unwrap_or_else(|err| {
+            panic!("Error: failed to read file {}: {}", path, err);
+        });
+        let old = serde_json::from_str::<SymbolIndex>(&text).unwrap_or_else(|err| {
+            panic!("Error: failed to parse symbol index {}: {}", path, err);
+        });
+        let index = decompiler
+            .symbol_index()
+            .expect("Error: unable to build symbol index");
+        for change in index.diff(&old) {
+            eprintln!("{}", change);
+        }
+    }
+
+    if let Some(path) = &args.mermaid_call_graph {
+        let markdown = format!("```mermaid\n{}```\n", decompiler.call_graph());
+        fs::write(path, markdown).unwrap_or_else(|err| {
+            panic!("Error: failed to write file {}: {}", path, err);
+        });
+    }
+
+    if let Some(path) = &args.mermaid_cfg {
+        let markdown = decompiler
+            .cfg_graphs()
+            .expect("Error: unable to build control flow graphs")
+            .into_iter()
+            .map(|(function, chart)| format!("## {}\n\n```mermaid\n{}```\n", function, chart))
+            .collect::<Vec<_>>()
+            .join("\n");
+        fs::write(path, markdown).unwrap_or_else(|err| {
+            panic!("Error: failed to write file {}: {}", path, err);
+        });
+    }
+
+    if let Some(dir) = &args.emit_tests {
+        fs::create_dir_all(dir).unwrap_or_else(|err| {
+            panic!("Error: failed to create directory {}: {}", dir, err);
+        });
+        for (module_name, source) in decompiler
+            .decompile_test_skeletons()
+            .expect("Error: unable to generate test skeletons")
+        {
+            let path = Path::new(dir).join(format!("{}_tests.move", module_name));
+            fs::write(&path, source).unwrap_or_else(|err| {
+                panic!("Error: failed to write file {}: {}", path.display(), err);
+            });
+        }
+    }
+
+    if let Some(flamegraph) = flamegraph {
+        flamegraph.finish().unwrap_or_else(|err| {
+            panic!("Error: failed to write flamegraph: {:#}", err);
+        });
+    }
+}
+
+fn deserialize_error(kind: &str, bytes: &[u8], err: impl std::fmt::Display) -> ! {
+    match unsupported_version(bytes) {
+        Some(reason) => panic!("Error: failed to deserialize {} blob: {}", kind, reason),
+        None => panic!("Error: failed to deserialize {} blob: {}", kind, err),
+    }
 }
@@ -0,0 +1,44 @@
+// Copyright (c) Verichains, 2023
+
+/// Samples per second taken by `--flamegraph`
+#[cfg(all(feature = "flamegraph", unix))]
+const FREQUENCY: i32 = 997;
+
+/// Sampling CPU profile of the process, written as a flamegraph SVG by `finish`
+#[cfg(all(feature = "flamegraph", unix))]
+pub struct Flamegraph {
+    path: String,
+    guard: pprof::ProfilerGuard<'static>,
+}
+
+#[cfg(all(feature = "flamegraph", unix))]
+impl Flamegraph {
+    pub fn start(path: &str) -> anyhow::Result<Self> {
+        Ok(Self {
+            path: path.to_string(),
+            guard: pprof::ProfilerGuard::new(FREQUENCY)?,
+        })
+    }
+
+    pub fn finish(self) -> anyhow::Result<()> {
+        let report = self.guard.report().build()?;
+        report.flamegraph(std::fs::File::create(&self.path)?)?;
+        Ok(())
+    }
+}
+
+/// Stand-in when built without the `flamegraph` feature or on a platform pprof does not
+/// support, `start` always fails
+#[cfg(not(all(feature = "flamegraph", unix)))]
+pub struct Flamegraph;
+
+#[cfg(not(all(feature = "flamegraph", unix)))]
+impl Flamegraph {
+    pub fn start(_path: &str) -> anyhow::Result<Self> {
+        anyhow::bail!("built without profiling support, rebuild with `--features flamegraph`")
+    }
+
+    pub fn finish(self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
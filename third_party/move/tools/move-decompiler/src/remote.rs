@@ -0,0 +1,51 @@
+// Copyright (c) Verichains, 2023
+
+use move_binary_format::{binary_views::BinaryIndexedView, file_format::CompiledModule};
+use move_decompiler::decompiler::Decompiler;
+
+/// Fetch the modules called by `binaries` that are not in `dependencies` from the node at
+/// `url`, modules that can not be fetched are guessed from the call sites as without a node
+#[cfg(feature = "remote")]
+pub fn fetch_dependencies(
+    url: &str,
+    binaries: &[BinaryIndexedView],
+    dependencies: &mut Vec<CompiledModule>,
+) -> anyhow::Result<()> {
+    use move_decompiler::decompiler::{missing_dependencies, NodeClient};
+
+    let node = NodeClient::new(url.to_string());
+    for id in missing_dependencies(binaries, dependencies) {
+        match node.fetch_module(&id) {
+            Ok(module) => dependencies.push(module),
+            Err(err) => eprintln!("warning: failed to fetch module {}: {:#}", id, err),
+        }
+    }
+    Ok(())
+}
+
+/// Query the source verification service at `url` for the modules of `decompiler`
+#[cfg(feature = "remote")]
+pub fn with_registry<'a>(decompiler: Decompiler<'a>, url: &str) -> anyhow::Result<Decompiler<'a>> {
+    use move_decompiler::decompiler::HttpSourceRegistry;
+
+    Ok(decompiler.with_registry(HttpSourceRegistry::new(url.to_string())))
+}
+
+/// Stand-in when built without the `remote` feature, always fails
+#[cfg(not(feature = "remote"))]
+pub fn fetch_dependencies(
+    _url: &str,
+    _binaries: &[BinaryIndexedView],
+    _dependencies: &mut Vec<CompiledModule>,
+) -> anyhow::Result<()> {
+    anyhow::bail!("built without the HTTP client, rebuild with `--features remote`")
+}
+
+/// Stand-in when built without the `remote` feature, always fails
+#[cfg(not(feature = "remote"))]
+pub fn with_registry<'a>(
+    _decompiler: Decompiler<'a>,
+    _url: &str,
+) -> anyhow::Result<Decompiler<'a>> {
+    anyhow::bail!("built without the HTTP client, rebuild with `--features remote`")
+}
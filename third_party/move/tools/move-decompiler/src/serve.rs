@@ -0,0 +1,231 @@
+// Copyright (c) Verichains, 2023
+
+use std::{convert::Infallible, net::SocketAddr, sync::Arc, time::Duration};
+
+use hyper::{
+    body::HttpBody,
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, Server, StatusCode,
+};
+use move_binary_format::{
+    binary_views::BinaryIndexedView,
+    file_format::{CompiledModule, CompiledScript},
+};
+use move_decompiler::decompiler::{Decompiler, OptimizerSettings, SymbolIndex};
+use serde::Serialize;
+use tokio::sync::Semaphore;
+
+use crate::CompiledBinary;
+
+/// Request bodies are limited to 1 MiB, enough for any module a transaction can publish
+const MAX_BODY_SIZE: usize = 1 << 20;
+
+#[derive(Serialize)]
+struct DecompileResponse {
+    source: String,
+    warnings: Vec<String>,
+    symbols: SymbolIndex,
+}
+
+#[derive(Serialize)]
+struct ErrorResponse {
+    error: String,
+}
+
+/// Serve `POST /decompile` until the process is killed. The request body is a serialized
+/// module, or a script with the `?script` query. At most `max_concurrency` requests are
+/// decompiled at once, others are rejected with 503; requests taking longer than `timeout`
+/// get a 504.
+pub fn run(address: SocketAddr, max_concurrency: usize, timeout: Duration) -> anyhow::Result<()> {
+    let runtime = tokio::runtime::Runtime::new()?;
+    runtime.block_on(async move {
+        let limit = Arc::new(Semaphore::new(max_concurrency));
+        let make_service = make_service_fn(move |_| {
+            let limit = limit.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request| {
+                    handle(request, limit.clone(), timeout)
+                }))
+            }
+        });
+        eprintln!("listening on http://{}", address);
+        Server::try_bind(&address)?.serve(make_service).await?;
+        Ok(())
+    })
+}
+
+async fn handle(
+    request: Request<Body>,
+    limit: Arc<Semaphore>,
+    timeout: Duration,
+) -> Result<Response<Body>, Infallible> {
+    if request.method() != Method::POST || request.uri().path() != "/decompile" {
+        return Ok(error(StatusCode::NOT_FOUND, "not found".to_string()));
+    }
+    let is_script = request.uri().query().map_or(false, |query| {
+        query
+            .split('&')
+            .any(|param| param == "script" || param == "script=true")
+    });
+
+    // the permit moves into the decompilation task so that timed out requests still count
+    // against the limit until they finish
+    let Ok(permit) = limit.try_acquire_owned() else {
+        return Ok(error(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "too many concurrent requests".to_string(),
+        ));
+    };
+
+    // reject bodies announcing a larger size before reading them, chunked bodies are checked
+    // while reading
+    if request.body().size_hint().lower() > MAX_BODY_SIZE as u64 {
+        return Ok(too_large());
+    }
+    let bytes = match read_body(request.into_body()).await {
+        Ok(Some(bytes)) => bytes,
+        Ok(None) => return Ok(too_large()),
+        Err(err) => return Ok(error(StatusCode::BAD_REQUEST, err.to_string())),
+    };
+
+    let task = tokio::task::spawn_blocking(move || {
+        let _permit = permit;
+        decompile(&bytes, is_script)
+    });
+    Ok(match tokio::time::timeout(timeout, task).await {
+        Ok(Ok(Ok(response))) => json(StatusCode::OK, &response),
+        Ok(Ok(Err(err))) => error(StatusCode::UNPROCESSABLE_ENTITY, format!("{:#}", err)),
+        Ok(Err(_)) => error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "decompiler panicked".to_string(),
+        ),
+        Err(_) => error(
+            StatusCode::GATEWAY_TIMEOUT,
+            format!("decompilation took longer than {}s", timeout.as_secs()),
+        ),
+    })
+}
+
+/// Read the whole body, or `None` as soon as it exceeds `MAX_BODY_SIZE`
+async fn read_body(mut body: Body) -> Result<Option<Vec<u8>>, hyper::Error> {
+    let mut bytes = Vec::new();
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk?;
+        if bytes.len() + chunk.len() > MAX_BODY_SIZE {
+            return Ok(None);
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    Ok(Some(bytes))
+}
+
+fn decompile(bytes: &[u8], is_script: bool) -> anyhow::Result<DecompileResponse> {
+    let binary = if is_script {
+        CompiledBinary::Script(CompiledScript::deserialize(bytes)?)
+    } else {
+        CompiledBinary::Module(CompiledModule::deserialize(bytes)?)
+    };
+    let view = match &binary {
+        CompiledBinary::Script(script) => BinaryIndexedView::Script(script),
+        CompiledBinary::Module(module) => BinaryIndexedView::Module(module),
+    };
+
+    let mut decompiler = Decompiler::new(vec![view], OptimizerSettings::default());
+    let source = decompiler.decompile()?;
+    let warnings = decompiler.warnings();
+    let symbols = decompiler.symbol_index()?;
+    Ok(DecompileResponse {
+        source,
+        warnings,
+        symbols,
+    })
+}
+
+fn json<T: Serialize>(status: StatusCode, value: &T) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header("content-type", "application/json")
+        .body(Body::from(serde_json::to_string(value).unwrap()))
+        .unwrap()
+}
+
+fn error(status: StatusCode, error: String) -> Response<Body> {
+    json(status, &ErrorResponse { error })
+}
+
+fn too_large() -> Response<Body> {
+    error(
+        StatusCode::PAYLOAD_TOO_LARGE,
+        format!("body larger than {} bytes", MAX_BODY_SIZE),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use move_binary_format::file_format::{basic_test_module, empty_script};
+
+    fn read(body: Body) -> Result<Option<Vec<u8>>, hyper::Error> {
+        tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(read_body(body))
+    }
+
+    // a chunked body, without a size known in advance
+    fn chunked(chunks: Vec<Vec<u8>>) -> Body {
+        let (mut sender, body) = Body::channel();
+        std::thread::spawn(move || {
+            tokio::runtime::Runtime::new()
+                .unwrap()
+                .block_on(async move {
+                    for chunk in chunks {
+                        if sender.send_data(chunk.into()).await.is_err() {
+                            break;
+                        }
+                    }
+                })
+        });
+        body
+    }
+
+    #[test]
+    fn decompile_module() {
+        let mut bytes = vec![];
+        basic_test_module().serialize(&mut bytes).unwrap();
+
+        let response = decompile(&bytes, false).unwrap();
+        assert!(response.source.contains("fun foo("), "{}", response.source);
+    }
+
+    #[test]
+    fn decompile_script() {
+        let mut bytes = vec![];
+        empty_script().serialize(&mut bytes).unwrap();
+
+        assert!(decompile(&bytes, true).is_ok());
+        // a script is not a valid module
+        assert!(decompile(&bytes, false).is_err());
+    }
+
+    #[test]
+    fn decompile_garbage() {
+        assert!(decompile(&[0xde, 0xad, 0xbe, 0xef], false).is_err());
+    }
+
+    #[test]
+    fn read_body_within_limit() {
+        let chunks = vec![vec![1; MAX_BODY_SIZE / 2], vec![2; MAX_BODY_SIZE / 2]];
+        let bytes = read(chunked(chunks)).unwrap().unwrap();
+        assert_eq!(bytes.len(), MAX_BODY_SIZE);
+    }
+
+    #[test]
+    fn read_body_over_limit() {
+        let chunks = vec![vec![1; MAX_BODY_SIZE / 2], vec![2; MAX_BODY_SIZE / 2 + 1]];
+        assert!(read(chunked(chunks)).unwrap().is_none());
+
+        assert!(read(Body::from(vec![0; MAX_BODY_SIZE + 1]))
+            .unwrap()
+            .is_none());
+    }
+}
@@ -0,0 +1,153 @@
+mod utils;
+
+#[cfg(test)]
+mod test {
+    use super::utils;
+    use move_decompiler::decompiler::DecompilerSettings;
+
+    const SOURCE: &str = r#"
+module 0x12::counters {
+    struct Inner has drop {
+        x: u64,
+    }
+
+    struct Counter has drop {
+        value: u64,
+        inner: Inner,
+    }
+
+    public fun value(c: &Counter): u64 {
+        c.value
+    }
+
+    public fun inner_x(c: &Counter): u64 {
+        c.inner.x
+    }
+
+    public fun value_ref(c: &Counter): &u64 {
+        &c.value
+    }
+
+    public fun value_mut(c: &mut Counter): &mut u64 {
+        &mut c.value
+    }
+
+    public fun set(c: &mut Counter, v: u64) {
+        c.value = v;
+    }
+
+    public fun bumped(c: &Counter): u64 {
+        c.value + 1
+    }
+
+    public fun set_from(v: u64, c: &mut Counter) {
+        c.value = v;
+    }
+
+    public fun total(c: &Counter): u64 {
+        value(c) * 3
+    }
+
+    public fun reset(c: &mut Counter) {
+        set(c, 0);
+    }
+}
+"#;
+
+    fn decompile(pseudocode: bool) -> String {
+        utils::decompile_source(
+            SOURCE,
+            Default::default(),
+            DecompilerSettings {
+                fold_accessors: true,
+                pseudocode,
+                ..Default::default()
+            },
+        )
+    }
+
+    // comment right before the header of the function `name`
+    fn annotation<'a>(output: &'a str, name: &str) -> Option<&'a str> {
+        let header = format!("fun {}(", name);
+        let lines = output.lines().collect::<Vec<_>>();
+        let index = lines
+            .iter()
+            .position(|line| line.contains(&header))
+            .unwrap_or_else(|| panic!("{} not found in {}", name, output));
+        lines[..index]
+            .last()
+            .map(|line| line.trim())
+            .filter(|line| line.starts_with("// "))
+    }
+
+    #[test]
+    fn getters_and_setters_are_detected() {
+        let output = decompile(false);
+
+        assert_eq!(
+            annotation(&output, "value"),
+            Some("// getter of arg0.value"),
+            "{}",
+            output
+        );
+        assert_eq!(
+            annotation(&output, "inner_x"),
+            Some("// getter of arg0.inner.x"),
+            "{}",
+            output
+        );
+        assert_eq!(
+            annotation(&output, "value_ref"),
+            Some("// getter of &arg0.value"),
+            "{}",
+            output
+        );
+        assert_eq!(
+            annotation(&output, "value_mut"),
+            Some("// getter of &mut arg0.value"),
+            "{}",
+            output
+        );
+        assert_eq!(
+            annotation(&output, "set"),
+            Some("// setter of arg0.value = arg1"),
+            "{}",
+            output
+        );
+    }
+
+    #[test]
+    fn near_accessors_are_not_detected() {
+        let output = decompile(false);
+
+        // an extra operation on the field
+        assert_eq!(annotation(&output, "bumped"), None, "{}", output);
+        // the struct reference is not the first parameter
+        assert_eq!(annotation(&output, "set_from"), None, "{}", output);
+        // calling an accessor is not an accessor
+        assert_eq!(annotation(&output, "total"), None, "{}", output);
+    }
+
+    #[test]
+    fn calls_are_folded_in_pseudocode() {
+        let output = decompile(true);
+
+        assert!(output.contains("arg0.value * 3"), "{}", output);
+        assert!(output.contains("arg0.value = 0"), "{}", output);
+        assert!(!output.contains("value(arg0)"), "{}", output);
+        assert!(!output.contains("set(arg0, 0)"), "{}", output);
+
+        // calls are kept as they are in valid Move
+        let output = decompile(false);
+        assert!(output.contains("value(arg0) * 3"), "{}", output);
+        assert!(output.contains("set(arg0, 0)"), "{}", output);
+    }
+
+    #[test]
+    fn nothing_detected_by_default() {
+        let output = utils::decompile_source(SOURCE, Default::default(), Default::default());
+
+        assert!(!output.contains("// getter of"), "{}", output);
+        assert!(!output.contains("// setter of"), "{}", output);
+    }
+}
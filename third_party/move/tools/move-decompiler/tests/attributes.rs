@@ -0,0 +1,125 @@
+mod utils;
+
+#[cfg(test)]
+mod test {
+    use super::utils::{
+        self, Attribute, RuntimeMetadata, EVENT, RESOURCE_GROUP, RESOURCE_GROUP_MEMBER,
+        VIEW_FUNCTION,
+    };
+    use std::collections::BTreeMap;
+
+    const SOURCE: &str = r#"
+module 0x12::vault {
+    struct Deposited has drop, store {
+        amount: u64,
+    }
+
+    struct Config has key {
+        limit: u64,
+    }
+
+    struct Member has key {
+        value: u64,
+    }
+
+    public fun balance(): u64 {
+        0
+    }
+
+    public fun limit(): u64 {
+        10
+    }
+}
+"#;
+
+    // the group of a member is stored with a full length address
+    const GROUP: &str =
+        "0x0000000000000000000000000000000000000000000000000000000000000012::vault::Config";
+
+    fn attribute(kind: u8, args: &[&str]) -> Attribute {
+        Attribute {
+            kind,
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+        }
+    }
+
+    fn decompile(metadata: Option<RuntimeMetadata>) -> String {
+        utils::decompile_modules(
+            SOURCE,
+            |modules| {
+                if let Some(metadata) = &metadata {
+                    utils::attach_metadata(modules, "vault", metadata)
+                }
+            },
+            |decompiler| decompiler,
+        )
+        .unwrap()
+    }
+
+    // trimmed line before the first line containing `needle`
+    fn line_before<'a>(output: &'a str, needle: &str) -> &'a str {
+        let lines = output.lines().collect::<Vec<_>>();
+        let index = lines
+            .iter()
+            .position(|line| line.contains(needle))
+            .unwrap_or_else(|| panic!("{} not found in {}", needle, output));
+        lines[index - 1].trim()
+    }
+
+    #[test]
+    fn view_and_event_attributes() {
+        let output = decompile(Some(RuntimeMetadata {
+            struct_attributes: BTreeMap::from([(
+                "Deposited".to_string(),
+                vec![attribute(EVENT, &[])],
+            )]),
+            fun_attributes: BTreeMap::from([(
+                "balance".to_string(),
+                vec![attribute(VIEW_FUNCTION, &[])],
+            )]),
+            ..Default::default()
+        }));
+
+        assert_eq!(line_before(&output, "struct Deposited"), "#[event]");
+        assert_eq!(line_before(&output, "fun balance("), "#[view]");
+        // only the attributed ones
+        assert_ne!(line_before(&output, "struct Config"), "#[event]");
+        assert_ne!(line_before(&output, "fun limit("), "#[view]");
+    }
+
+    #[test]
+    fn resource_group_attributes() {
+        let output = decompile(Some(RuntimeMetadata {
+            struct_attributes: BTreeMap::from([
+                (
+                    "Config".to_string(),
+                    vec![attribute(RESOURCE_GROUP, &["global"])],
+                ),
+                (
+                    "Member".to_string(),
+                    vec![attribute(RESOURCE_GROUP_MEMBER, &[GROUP])],
+                ),
+                // kinds unknown to the decompiler are left out
+                ("Deposited".to_string(), vec![attribute(99, &["x"])]),
+            ]),
+            ..Default::default()
+        }));
+
+        assert_eq!(
+            line_before(&output, "struct Config"),
+            "#[resource_group(scope = global)]"
+        );
+        assert_eq!(
+            line_before(&output, "struct Member"),
+            "#[resource_group_member(group = 0x12::vault::Config)]"
+        );
+        assert_eq!(output.matches("#[").count(), 2, "{}", output);
+    }
+
+    #[test]
+    fn no_attributes_without_metadata() {
+        let output = decompile(None);
+
+        assert!(!output.contains("#["), "{}", output);
+    }
+}
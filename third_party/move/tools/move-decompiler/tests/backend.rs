@@ -0,0 +1,28 @@
+mod utils;
+
+#[cfg(test)]
+mod test {
+    use super::utils;
+    use move_decompiler::decompiler::{Backend, DecompilerSettings};
+
+    #[test]
+    fn stackless_dumps_bytecode() {
+        let output = utils::decompile_source(
+            r#"
+module 0x12::backend {
+    public fun add(a: u64, b: u64): u64 {
+        a + b
+    }
+}
+"#,
+            Default::default(),
+            DecompilerSettings {
+                backend: Backend::Stackless,
+                ..Default::default()
+            },
+        );
+        assert!(output.contains("// parameters are $t0..$t1"), "{}", output);
+        assert!(output.contains("return $t"), "{}", output);
+        assert!(!output.contains("arg0 + arg1"), "{}", output);
+    }
+}
@@ -0,0 +1,81 @@
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+
+    use move_binary_format::{access::ModuleAccess, CompiledModule};
+    use move_decompiler::decompiler::{read_bundle, BundleFormat};
+
+    const MODULES: &[&str] = &["string", "option", "vector", "ascii"];
+
+    fn module_bytes() -> Vec<Vec<u8>> {
+        MODULES
+            .iter()
+            .map(|name| {
+                std::fs::read(format!(
+                    "{}/tests/bytecode/dependencies/MoveStdlib/{}.mv",
+                    env!("CARGO_MANIFEST_DIR"),
+                    name
+                ))
+                .unwrap()
+            })
+            .collect()
+    }
+
+    fn assert_dependencies_first(modules: &[CompiledModule]) {
+        assert_eq!(modules.len(), MODULES.len());
+        let bundled = modules
+            .iter()
+            .map(|module| module.self_id())
+            .collect::<HashSet<_>>();
+        let mut seen = HashSet::new();
+        for module in modules {
+            for dep in module.immediate_dependencies() {
+                assert!(
+                    !bundled.contains(&dep) || seen.contains(&dep),
+                    "{} comes before its dependency {}",
+                    module.self_id(),
+                    dep
+                );
+            }
+            seen.insert(module.self_id());
+        }
+    }
+
+    #[test]
+    fn bcs_bundle() {
+        let bytes = bcs::to_bytes(&module_bytes()).unwrap();
+        let format = BundleFormat::detect("package.bcs", &bytes).unwrap();
+        assert_eq!(format, BundleFormat::Bcs);
+        assert_dependencies_first(&read_bundle(format, &bytes).unwrap());
+    }
+
+    #[test]
+    fn cbor_bundle() {
+        let blobs = module_bytes();
+        let mut bytes = vec![0x80 | blobs.len() as u8];
+        for blob in &blobs {
+            bytes.push(0x59);
+            bytes.extend((blob.len() as u16).to_be_bytes());
+            bytes.extend(blob);
+        }
+        let format = BundleFormat::detect("package.cbor", &bytes).unwrap();
+        assert_eq!(format, BundleFormat::Cbor);
+        assert_dependencies_first(&read_bundle(format, &bytes).unwrap());
+    }
+
+    #[test]
+    fn cbor_length_overflow() {
+        // a byte string claiming u64::MAX bytes
+        let mut bytes = vec![0x81, 0x5b];
+        bytes.extend(u64::MAX.to_be_bytes());
+        bytes.extend([0; 16]);
+        assert!(read_bundle(BundleFormat::Cbor, &bytes).is_err());
+    }
+
+    #[test]
+    fn modules_are_not_bundles() {
+        for blob in module_bytes() {
+            assert_eq!(BundleFormat::detect("module.bcs", &blob), None);
+        }
+    }
+}
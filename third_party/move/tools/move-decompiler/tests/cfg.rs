@@ -0,0 +1,219 @@
+#[cfg(test)]
+mod test {
+    use move_decompiler::decompiler::cfg::{
+        algo::{loop_reconstruction::loop_reconstruction, topo::topo_sort},
+        builder::CfgBuilder,
+        datastructs::{BlockContentTrait, Terminator},
+        stackless::{build_blocks, normalize_branch_conditions},
+    };
+    use move_stackless_bytecode::stackless_bytecode::{
+        AssignKind, AttrId, Bytecode, Label, Operation,
+    };
+
+    #[derive(Clone, Debug, Default)]
+    struct Content;
+
+    impl BlockContentTrait for Content {}
+
+    #[test]
+    fn while_loop() {
+        let mut cfg = CfgBuilder::new();
+        cfg.add_block(Content, Terminator::Branch { target: 1 });
+        cfg.add_block(
+            Content,
+            Terminator::IfElse {
+                if_block: 2,
+                else_block: 3,
+            },
+        );
+        cfg.add_block(Content, Terminator::Branch { target: 1 });
+        cfg.add_block(Content, Terminator::Ret);
+        let mut blocks = cfg.build().unwrap();
+
+        loop_reconstruction(&mut blocks).unwrap();
+
+        assert_eq!(blocks.len(), 4);
+        assert!(matches!(
+            blocks[1].terminator(),
+            Terminator::While {
+                inner_block: 2,
+                outer_block: 3
+            }
+        ));
+        assert!(matches!(
+            blocks[2].terminator(),
+            Terminator::Continue { target: 1 }
+        ));
+        assert_eq!(blocks[1].unconditional_loop_entry(), None);
+    }
+
+    #[test]
+    fn unconditional_loop() {
+        let mut cfg = CfgBuilder::new();
+        cfg.add_block(Content, Terminator::Branch { target: 1 });
+        cfg.add_block(Content, Terminator::Branch { target: 2 });
+        cfg.add_block(
+            Content,
+            Terminator::IfElse {
+                if_block: 1,
+                else_block: 3,
+            },
+        );
+        cfg.add_block(Content, Terminator::Ret);
+        let mut blocks = cfg.build().unwrap();
+
+        loop_reconstruction(&mut blocks).unwrap();
+
+        assert_eq!(blocks[1].unconditional_loop_entry(), Some(3));
+        // the conditional jumps out of the loop body go through dummy continue/break blocks
+        assert_eq!(blocks.len(), 6);
+        assert!(matches!(
+            blocks[2].terminator(),
+            Terminator::IfElse {
+                if_block: 4,
+                else_block: 5
+            }
+        ));
+        assert!(matches!(
+            blocks[4].terminator(),
+            Terminator::Continue { target: 1 }
+        ));
+        assert!(matches!(
+            blocks[5].terminator(),
+            Terminator::Break { target: 3 }
+        ));
+    }
+
+    #[test]
+    fn topo_sort_reports_constraint_cycle() {
+        let mut cfg = CfgBuilder::new();
+        cfg.add_block(
+            Content,
+            Terminator::IfElse {
+                if_block: 1,
+                else_block: 2,
+            },
+        );
+        cfg.add_block_with(|b| b.terminator(Terminator::Ret).topo_before([2]));
+        cfg.add_block_with(|b| b.terminator(Terminator::Ret).topo_before([1]));
+        let blocks = cfg.build().unwrap();
+
+        let err = topo_sort(blocks).unwrap_err().to_string();
+        assert!(err.contains("2 -> 1 -> 2"), "{}", err);
+        assert!(err.contains("block 1 has topo_before 2"), "{}", err);
+        assert!(err.contains("block 2 has topo_before 1"), "{}", err);
+    }
+
+    #[test]
+    fn builder_rejects_missing_blocks() {
+        let mut cfg = CfgBuilder::<Content>::new();
+        cfg.add_block(Content, Terminator::Branch { target: 1 });
+        assert!(cfg.build().is_err());
+    }
+
+    fn call(dst: usize, op: Operation, srcs: Vec<usize>) -> Bytecode {
+        Bytecode::Call(AttrId::new(0), vec![dst], op, srcs, None)
+    }
+
+    /// `code` followed by a branch on `cond` to two returns
+    fn branch_on(mut code: Vec<Bytecode>, cond: usize) -> Vec<Bytecode> {
+        let attr = AttrId::new(0);
+        code.extend([
+            Bytecode::Branch(attr, Label::new(0), Label::new(1), cond),
+            Bytecode::Label(attr, Label::new(0)),
+            Bytecode::Ret(attr, vec![]),
+            Bytecode::Label(attr, Label::new(1)),
+            Bytecode::Ret(attr, vec![]),
+        ]);
+        code
+    }
+
+    /// bytecode of the blocks of `code`, with the branch conditions normalized or not
+    fn lowered(code: &[Bytecode], normalize: bool) -> Vec<Bytecode> {
+        let mut blocks = build_blocks(code).unwrap();
+        if normalize {
+            normalize_branch_conditions(&mut blocks);
+        }
+        blocks
+            .iter()
+            .flat_map(|block| block.content().code.iter())
+            .map(|x| x.bytecode.clone())
+            .collect()
+    }
+
+    fn has_not(code: &[Bytecode]) -> bool {
+        code.iter()
+            .any(|x| matches!(x, Bytecode::Call(_, _, Operation::Not, _, _)))
+    }
+
+    #[test]
+    fn negated_comparison_is_inverted() {
+        // !(t0 == t1)
+        let code = branch_on(
+            vec![
+                call(2, Operation::Eq, vec![0, 1]),
+                call(3, Operation::Not, vec![2]),
+            ],
+            3,
+        );
+
+        let normalized = lowered(&code, true);
+        assert!(!has_not(&normalized), "{:?}", normalized);
+        assert!(
+            normalized.contains(&call(3, Operation::Neq, vec![0, 1])),
+            "{:?}",
+            normalized
+        );
+    }
+
+    #[test]
+    fn double_negation_is_removed() {
+        // !!t0
+        let code = branch_on(
+            vec![
+                call(1, Operation::Not, vec![0]),
+                call(2, Operation::Not, vec![1]),
+            ],
+            2,
+        );
+
+        let normalized = lowered(&code, true);
+        assert!(!has_not(&normalized), "{:?}", normalized);
+        assert!(
+            normalized
+                .iter()
+                .any(|x| matches!(x, Bytecode::Branch(_, _, _, 0))),
+            "{:?}",
+            normalized
+        );
+    }
+
+    #[test]
+    fn branch_conditions_kept_when_not_single_use() {
+        let attr = AttrId::new(0);
+
+        // the comparison is also copied to another temporary
+        let reused = branch_on(
+            vec![
+                call(2, Operation::Eq, vec![0, 1]),
+                Bytecode::Assign(attr, 4, 2, AssignKind::Copy),
+                call(3, Operation::Not, vec![2]),
+            ],
+            3,
+        );
+        assert_eq!(lowered(&reused, true), lowered(&reused, false));
+
+        // the negated value is reassigned before the branch
+        let reassigned = branch_on(
+            vec![
+                call(1, Operation::Not, vec![0]),
+                Bytecode::Assign(attr, 0, 5, AssignKind::Copy),
+                call(2, Operation::Not, vec![1]),
+            ],
+            2,
+        );
+        let normalized = lowered(&reassigned, true);
+        assert_eq!(normalized, lowered(&reassigned, false));
+        assert!(has_not(&normalized), "{:?}", normalized);
+    }
+}
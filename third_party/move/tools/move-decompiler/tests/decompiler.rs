@@ -57,6 +57,7 @@ mod test {
                     OptimizerSettings {
                         // this settings may cause the output to be different
                         disable_optimize_variables_declaration: true,
+                        ..Default::default()
                     },
                 );
                 output = decompiler.decompile().expect("Unable to decompile");
@@ -82,6 +83,7 @@ mod test {
                 binaries,
                 OptimizerSettings {
                     disable_optimize_variables_declaration: true,
+                    ..Default::default()
                 },
             );
 
@@ -0,0 +1,63 @@
+mod utils;
+
+#[cfg(test)]
+mod test {
+    use super::utils;
+    use move_binary_format::{access::ModuleAccess, binary_views::BinaryIndexedView};
+    use move_compiler::Flags;
+    use move_decompiler::decompiler::{missing_dependencies, Decompiler};
+
+    const SOURCE: &str = r#"
+module 0x12::lib {
+    struct Coin has drop {
+        value: u64,
+    }
+
+    public fun mint(value: u64): Coin {
+        Coin { value }
+    }
+
+    public fun value(coin: &Coin): u64 {
+        coin.value
+    }
+}
+
+module 0x12::user {
+    use 0x12::lib;
+
+    public fun round_trip(v: u64): u64 {
+        let coin = lib::mint(v);
+        lib::value(&coin)
+    }
+}
+"#;
+
+    #[test]
+    fn dependencies_are_loaded_not_decompiled() {
+        utils::tmp_project(vec![("tmp.move", SOURCE)], |files| {
+            let (_, modules) = utils::run_compiler(files, Flags::empty(), false);
+            let (lib, user): (Vec<_>, Vec<_>) = modules
+                .into_iter()
+                .partition(|module| module.self_id().name().as_str() == "lib");
+            let binaries = user
+                .iter()
+                .map(BinaryIndexedView::Module)
+                .collect::<Vec<_>>();
+
+            let missing = missing_dependencies(&binaries, &[]);
+            assert_eq!(missing, vec![lib[0].self_id()]);
+            assert!(missing_dependencies(&binaries, &lib).is_empty());
+
+            let output = Decompiler::new(binaries, Default::default())
+                .with_dependencies(lib.clone())
+                .decompile()
+                .unwrap();
+
+            assert!(output.contains("module 0x12::user {"), "{}", output);
+            assert!(!output.contains("module 0x12::lib {"), "{}", output);
+            // calls into the dependency are kept as such
+            assert!(output.contains("lib::mint(arg0)"), "{}", output);
+            assert!(output.contains("lib::value(&"), "{}", output);
+        });
+    }
+}
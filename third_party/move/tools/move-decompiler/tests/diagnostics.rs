@@ -0,0 +1,97 @@
+mod utils;
+
+#[cfg(test)]
+mod test {
+    use super::utils;
+    use move_compiler::Flags;
+    use move_core_types::metadata::Metadata;
+    use move_decompiler::decompiler::{Decompiler, Diagnostic, DiagnosticKind, Severity};
+
+    const SOURCE: &str = r#"
+module 0x12::diag {
+    public fun add(a: u64, b: u64): u64 {
+        a + b
+    }
+}
+"#;
+
+    // diagnostics of SOURCE, with `metadata` attached to the module
+    fn diagnose(metadata: Option<Metadata>) -> (String, Vec<Diagnostic>) {
+        let mut metadata = Some(metadata);
+        let mut result = None;
+        utils::tmp_project(vec![("tmp.move", SOURCE)], |files| {
+            let (scripts, mut modules) = utils::run_compiler(files, Flags::empty(), false);
+            modules[0].metadata.extend(metadata.take().unwrap());
+            let binaries = utils::into_binary_indexed_view(&scripts, &modules);
+            let mut decompiler = Decompiler::new(binaries, Default::default());
+            let output = decompiler.decompile().unwrap();
+            result = Some((output, decompiler.diagnostics().to_vec()));
+        });
+        result.unwrap()
+    }
+
+    #[test]
+    fn clean_module() {
+        let (_, diagnostics) = diagnose(None);
+
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+    }
+
+    #[test]
+    fn unreadable_metadata() {
+        let (output, diagnostics) = diagnose(Some(Metadata {
+            key: b"aptos::metadata_v1".to_vec(),
+            value: vec![0xff; 3],
+        }));
+
+        // reported, but the module is still decompiled
+        assert!(
+            output.contains("public fun add(arg0: u64, arg1: u64) : u64 {"),
+            "{}",
+            output
+        );
+        assert_eq!(diagnostics.len(), 1, "{:?}", diagnostics);
+        let diagnostic = &diagnostics[0];
+        assert_eq!(diagnostic.kind, DiagnosticKind::UnreadableMetadata);
+        assert_eq!(diagnostic.severity, Severity::Warning);
+        assert_eq!(diagnostic.module, "0x12::diag");
+        assert_eq!(diagnostic.function, None);
+        assert!(
+            diagnostic
+                .to_string()
+                .starts_with("0x12::diag: unreadable aptos::metadata_v1: "),
+            "{}",
+            diagnostic
+        );
+
+        let json = serde_json::to_value(diagnostic).unwrap();
+        assert_eq!(json["severity"], "warning");
+        assert_eq!(json["kind"], "unreadable_metadata");
+        assert!(json["function"].is_null());
+    }
+
+    #[test]
+    fn severity_follows_kind() {
+        let diagnostic = |kind| {
+            Diagnostic::new(
+                kind,
+                "0x12::diag".to_string(),
+                Some("0x12::diag::add".to_string()),
+                "message".to_string(),
+            )
+        };
+
+        assert_eq!(
+            diagnostic(DiagnosticKind::DecompilationFailed).severity,
+            Severity::Error
+        );
+        assert_eq!(
+            diagnostic(DiagnosticKind::Heuristic).severity,
+            Severity::Warning
+        );
+        assert_eq!(
+            diagnostic(DiagnosticKind::StackInconsistency).to_string(),
+            "0x12::diag::add: message"
+        );
+    }
+}
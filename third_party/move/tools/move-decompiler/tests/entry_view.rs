@@ -0,0 +1,78 @@
+mod utils;
+
+#[cfg(test)]
+mod test {
+    use super::utils;
+    use move_decompiler::decompiler::DecompilerSettings;
+
+    const SOURCE: &str = r#"
+module 0x12::entries {
+    entry fun run(x: u64) {
+        let y = helper(x);
+        assert!(y > 1, 1);
+    }
+
+    fun helper(x: u64): u64 {
+        nested(x) + 1
+    }
+
+    fun nested(x: u64): u64 {
+        x * 2
+    }
+
+    public fun unused(x: u64): u64 {
+        x + 3
+    }
+
+    fun also_unused(): u64 {
+        unused(4)
+    }
+}
+"#;
+
+    const ELIDED: &str = "abort 0 // elided: not reachable from entry or view functions";
+
+    // whether the body of the function `name` is the elided stub
+    fn is_elided(output: &str, name: &str) -> bool {
+        let header = format!("fun {}(", name);
+        let mut lines = output.lines().skip_while(|line| !line.contains(&header));
+        assert!(lines.next().is_some(), "{} not found in {}", name, output);
+        lines.next().unwrap().trim() == ELIDED
+    }
+
+    #[test]
+    fn unreachable_functions_are_elided() {
+        let output = utils::decompile_source(
+            SOURCE,
+            Default::default(),
+            DecompilerSettings {
+                entry_view_only: true,
+                ..Default::default()
+            },
+        );
+
+        // the entry function and the helpers it calls, directly or not
+        assert!(!is_elided(&output, "run"), "{}", output);
+        assert!(!is_elided(&output, "helper"), "{}", output);
+        assert!(!is_elided(&output, "nested"), "{}", output);
+
+        // public but not called from an entry function, nor is its only caller
+        assert!(is_elided(&output, "unused"), "{}", output);
+        assert!(is_elided(&output, "also_unused"), "{}", output);
+
+        // stubs keep their signature
+        assert!(
+            output.contains("public fun unused(arg0: u64) : u64 {"),
+            "{}",
+            output
+        );
+    }
+
+    #[test]
+    fn nothing_elided_by_default() {
+        let output = utils::decompile_source(SOURCE, Default::default(), Default::default());
+
+        assert!(!output.contains(ELIDED), "{}", output);
+        assert!(!is_elided(&output, "unused"), "{}", output);
+    }
+}
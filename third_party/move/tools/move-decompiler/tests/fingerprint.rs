@@ -0,0 +1,145 @@
+mod utils;
+
+#[cfg(test)]
+mod test {
+    use super::utils;
+    use move_decompiler::decompiler::SymbolIndex;
+    use std::collections::{BTreeSet, HashMap};
+
+    fn symbol_index(source: &str) -> SymbolIndex {
+        let mut result = None;
+        utils::with_decompiler(
+            source,
+            Default::default(),
+            Default::default(),
+            |decompiler| {
+                result = Some(decompiler.symbol_index().unwrap());
+            },
+        );
+        result.unwrap()
+    }
+
+    // fingerprints of the functions of `source`, by name
+    fn fingerprints(source: &str) -> HashMap<String, String> {
+        symbol_index(source)
+            .modules
+            .iter()
+            .flat_map(|m| m.functions.iter())
+            .map(|function| (function.name.clone(), function.fingerprint.clone().unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn stable_across_renames() {
+        let v1 = fingerprints(
+            r#"
+module 0x12::token {
+    fun double(x: u64): u64 {
+        x * 2
+    }
+
+    public fun scaled(x: u64): u64 {
+        double(x) + 7
+    }
+
+    public fun clamped(x: u64): u64 {
+        if (x > 3) x else 3
+    }
+}
+"#,
+        );
+        let v2 = fingerprints(
+            r#"
+module 0x12::token {
+    public fun clamped(x: u64): u64 {
+        if (x > 3) x else 3
+    }
+
+    public fun scaled(x: u64): u64 {
+        twice(x) + 7
+    }
+
+    fun twice(x: u64): u64 {
+        x * 2
+    }
+}
+"#,
+        );
+
+        let double = &v1["0x12::token::double"];
+        assert_eq!(double.len(), 16);
+        assert!(double.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_eq!(double, &v2["0x12::token::twice"]);
+        assert_eq!(v1["0x12::token::scaled"], v2["0x12::token::scaled"]);
+        assert_eq!(v1["0x12::token::clamped"], v2["0x12::token::clamped"]);
+
+        assert_ne!(v1["0x12::token::scaled"], v1["0x12::token::double"]);
+        assert_ne!(v1["0x12::token::scaled"], v1["0x12::token::clamped"]);
+        assert_ne!(v1["0x12::token::double"], v1["0x12::token::clamped"]);
+    }
+
+    #[test]
+    fn diff_across_versions() {
+        let v1 = symbol_index(
+            r#"
+module 0x12::token {
+    fun double(x: u64): u64 {
+        x * 2
+    }
+
+    public fun scaled(x: u64): u64 {
+        double(x) + 7
+    }
+
+    public fun clamped(x: u64): u64 {
+        if (x > 3) x else 3
+    }
+
+    public fun gone(x: u64): u64 {
+        x + 100
+    }
+}
+"#,
+        );
+        let v2 = symbol_index(
+            r#"
+module 0x12::token {
+    public fun clamped(x: u64): u64 {
+        if (x > 4) x else 4
+    }
+
+    public fun scaled(x: u64): u64 {
+        twice(x) + 7
+    }
+
+    fun twice(x: u64): u64 {
+        x * 2
+    }
+
+    public fun fresh(x: u64): u64 {
+        x - 1
+    }
+}
+"#,
+        );
+
+        // the old index is read back from the JSON written by `--symbols`
+        let v1 = serde_json::from_str::<SymbolIndex>(&serde_json::to_string(&v1).unwrap()).unwrap();
+        let changes = v2
+            .diff(&v1)
+            .iter()
+            .map(|change| change.to_string())
+            .collect::<BTreeSet<_>>();
+        assert_eq!(
+            changes,
+            BTreeSet::from([
+                "added 0x12::token::fresh".to_string(),
+                "modified 0x12::token::clamped".to_string(),
+                "removed 0x12::token::gone".to_string(),
+                "renamed 0x12::token::double -> 0x12::token::twice".to_string(),
+            ])
+        );
+
+        assert!(v2.diff(&v2).is_empty());
+    }
+}
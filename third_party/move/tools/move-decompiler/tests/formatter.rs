@@ -0,0 +1,52 @@
+mod utils;
+
+#[cfg(test)]
+mod test {
+    use super::utils;
+    use move_decompiler::decompiler::DecompilerSettings;
+
+    const SOURCE: &str = r#"
+module 0x12::fmt {
+    struct Pair has drop {
+        first: u64,
+        second: u64,
+    }
+
+    public fun make(x: u64): Pair {
+        Pair { first: x, second: x + 1 }
+    }
+
+    public fun sum(p: Pair): u64 {
+        let Pair { first, second } = p;
+        first + second
+    }
+}
+"#;
+
+    #[test]
+    fn movefmt_conventions() {
+        let unformatted = utils::decompile_source(SOURCE, Default::default(), Default::default());
+        // the default output does not follow the conventions checked below
+        assert!(unformatted.contains(") : "), "{}", unformatted);
+
+        let output = utils::decompile_source(
+            SOURCE,
+            Default::default(),
+            DecompilerSettings {
+                movefmt_compatible: true,
+                ..Default::default()
+            },
+        );
+        assert!(output.contains("fun make(arg0: u64): Pair {"), "{}", output);
+        assert!(output.contains("Pair {"), "{}", output);
+        assert!(!output.contains("Pair{"), "{}", output);
+        assert!(!output.contains(" : "), "{}", output);
+        assert!(!output.contains("\n\n\n"), "{}", output);
+        assert!(
+            output.lines().all(|line| line.trim_end() == line),
+            "{}",
+            output
+        );
+        assert!(output.ends_with("}\n"), "{}", output);
+    }
+}
@@ -0,0 +1,113 @@
+mod utils;
+
+#[cfg(test)]
+mod test {
+    use super::utils;
+    use move_decompiler::decompiler::highlight;
+
+    const RESET: &str = "\x1b[0m";
+
+    fn painted(color: &str, text: &str) -> String {
+        format!("{}{}{}", color, text, RESET)
+    }
+
+    // the text without escape codes
+    fn strip(text: &str) -> String {
+        let mut result = String::new();
+        let mut rest = text;
+        while let Some(start) = rest.find('\x1b') {
+            result.push_str(&rest[..start]);
+            let end = rest[start..].find('m').unwrap();
+            rest = &rest[start + end + 1..];
+        }
+        result.push_str(rest);
+        result
+    }
+
+    #[test]
+    fn tokens_are_colored() {
+        let output = highlight("public fun f(arg0: u64): Coin { let v0 = 42; b\"hi\" }");
+
+        assert!(
+            output.contains(&painted("\x1b[1;35m", "public")),
+            "{:?}",
+            output
+        );
+        assert!(
+            output.contains(&painted("\x1b[1;35m", "let")),
+            "{:?}",
+            output
+        );
+        assert!(output.contains(&painted("\x1b[34m", "u64")), "{:?}", output);
+        assert!(
+            output.contains(&painted("\x1b[34m", "Coin")),
+            "{:?}",
+            output
+        );
+        assert!(output.contains(&painted("\x1b[36m", "42")), "{:?}", output);
+        assert!(
+            output.contains(&painted("\x1b[32m", "b\"hi\"")),
+            "{:?}",
+            output
+        );
+        // identifiers are left alone
+        assert!(output.contains(" f(arg0: "), "{:?}", output);
+        assert!(output.contains(" v0 = "), "{:?}", output);
+    }
+
+    #[test]
+    fn low_confidence_comments_stand_out() {
+        let output = highlight("let v0 = 1; // plain\n// possible: let v1: u64;\n");
+
+        assert!(
+            output.contains(&painted("\x1b[2m", "// plain")),
+            "{:?}",
+            output
+        );
+        assert!(
+            output.contains(&painted("\x1b[1;33m", "// possible: let v1: u64;")),
+            "{:?}",
+            output
+        );
+    }
+
+    #[test]
+    fn block_comments_span_lines() {
+        let output = highlight("/* fun\nlet */ fun");
+
+        assert!(
+            output.contains(&painted("\x1b[2m", "/* fun")),
+            "{:?}",
+            output
+        );
+        assert!(
+            output.contains(&painted("\x1b[2m", "let */")),
+            "{:?}",
+            output
+        );
+        assert!(
+            output.ends_with(&painted("\x1b[1;35m", "fun")),
+            "{:?}",
+            output
+        );
+    }
+
+    #[test]
+    fn decompiled_text_is_unchanged() {
+        let source = utils::decompile_source(
+            r#"
+module 0x12::colors {
+    public fun pick(c: bool): vector<u8> {
+        if (c) b"yes" else x"00ff"
+    }
+}
+"#,
+            Default::default(),
+            Default::default(),
+        );
+
+        let output = highlight(&source);
+        assert_ne!(output, source);
+        assert_eq!(strip(&output), source);
+    }
+}
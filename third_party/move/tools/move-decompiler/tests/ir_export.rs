@@ -0,0 +1,102 @@
+mod utils;
+
+#[cfg(test)]
+mod test {
+    use super::utils;
+    use move_decompiler::decompiler::{DecompilerSettings, Stage, IR_FORMAT_VERSION};
+    use serde_json::Value;
+
+    const SOURCE: &str = r#"
+module 0x12::ir {
+    public fun pick(c: bool): u64 {
+        if (c) 1 else 2
+    }
+}
+"#;
+
+    // JSON export after `stage`, and the decompiled source
+    fn export(stage: Option<Stage>) -> (Option<Value>, String) {
+        let mut result = None;
+        utils::with_decompiler(
+            SOURCE,
+            Default::default(),
+            DecompilerSettings {
+                emit_ir: stage,
+                ..Default::default()
+            },
+            |decompiler| {
+                let output = decompiler.decompile().expect("Unable to decompile");
+                let export = decompiler
+                    .ir_export()
+                    .map(|export| serde_json::to_value(export).unwrap());
+                result = Some((export, output));
+            },
+        );
+        result.unwrap()
+    }
+
+    // the exported function `pick`
+    fn pick(export: &Value) -> &Value {
+        let functions = export["functions"].as_array().unwrap();
+        assert_eq!(functions.len(), 1, "{}", export);
+        assert_eq!(functions[0]["function"], "0x12::ir::pick");
+        &functions[0]
+    }
+
+    #[test]
+    fn blocks_export() {
+        let (json, output) = export(Some(Stage::Blocks));
+        let json = json.unwrap();
+
+        assert_eq!(json["version"], IR_FORMAT_VERSION);
+        assert_eq!(json["stage"], "blocks");
+
+        let function = pick(&json);
+        assert_eq!(function["kind"], "blocks");
+        let blocks = function["blocks"].as_array().unwrap();
+        assert!(blocks.len() > 1, "{}", json);
+        let branches = blocks
+            .iter()
+            .filter(|block| block["terminator"] == "if")
+            .collect::<Vec<_>>();
+        assert_eq!(branches.len(), 1, "{}", json);
+        assert_eq!(branches[0]["targets"].as_array().unwrap().len(), 2);
+        assert!(blocks.iter().any(|block| block["offset"] == 0), "{}", json);
+
+        // targets are indices of exported blocks
+        let indices = blocks
+            .iter()
+            .map(|block| block["index"].clone())
+            .collect::<Vec<_>>();
+        for block in blocks {
+            for target in block["targets"].as_array().unwrap() {
+                assert!(indices.contains(target), "{}", json);
+            }
+        }
+
+        // exporting does not change the output
+        assert_eq!(output, export(None).1);
+    }
+
+    #[test]
+    fn stackless_and_source_exports() {
+        let json = export(Some(Stage::Stackless)).0.unwrap();
+        let function = pick(&json);
+        assert_eq!(function["kind"], "instructions");
+        assert!(!function["instructions"].as_array().unwrap().is_empty());
+
+        let json = export(Some(Stage::Source)).0.unwrap();
+        let function = pick(&json);
+        assert_eq!(function["kind"], "text");
+        assert!(
+            function["text"].as_str().unwrap().contains("if"),
+            "{}",
+            json
+        );
+    }
+
+    #[test]
+    fn no_export_by_default() {
+        assert!(export(None).0.is_none());
+    }
+}
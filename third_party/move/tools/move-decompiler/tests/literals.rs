@@ -0,0 +1,190 @@
+mod utils;
+
+#[cfg(test)]
+mod test {
+    use super::utils;
+    use move_decompiler::decompiler::{DecompilerSettings, LiteralStyle};
+
+    fn decompile(source: &str) -> String {
+        utils::decompile_source(source, Default::default(), Default::default())
+    }
+
+    #[test]
+    fn text_bytes() {
+        let output = decompile(
+            r#"
+module 0x12::text {
+    use std::string::{Self, String};
+    use aptos_framework::event;
+
+    struct Message has drop, store {
+        text: vector<u8>,
+        newline: bool,
+    }
+
+    public fun greeting(): String {
+        string::utf8(b"hello\n\tworld")
+    }
+
+    public fun bytes(): vector<u8> {
+        x"0a00ff"
+    }
+
+    public fun message(v: vector<u8>) {
+        event::emit(Message { text: b"line\n", newline: v == x"0a" });
+    }
+}
+"#,
+        );
+        assert!(output.contains(r#"b"hello\n\tworld""#), "{}", output);
+        assert!(output.contains(r#"x"0a00ff""#), "{}", output);
+        assert!(output.contains(r#"b"line\n""#), "{}", output);
+        // the comparison is not text even inside an emitted event
+        assert!(output.contains(r#"arg0 == x"0a""#), "{}", output);
+    }
+
+    #[test]
+    fn let_annotations() {
+        let output = decompile(
+            r#"
+module 0x12::lets {
+    use std::vector;
+
+    public fun addresses(): u64 {
+        let v = vector[];
+        vector::push_back(&mut v, @0x1);
+        vector::length(&v)
+    }
+
+    public fun nested(): u64 {
+        let v = vector[];
+        vector::push_back(&mut v, b"a");
+        vector::length(&v)
+    }
+
+    public fun small_integers(): u64 {
+        let v = vector<u16>[1, 2];
+        vector::length(&v)
+    }
+
+    public fun integers(): u64 {
+        let v = vector[1, 2];
+        vector::length(&v)
+    }
+}
+"#,
+        );
+        assert!(
+            output.contains(": vector<address> = vector[];"),
+            "{}",
+            output
+        );
+        assert!(
+            output.contains(" = 0x1::vector::empty<vector<u8>>();"),
+            "{}",
+            output
+        );
+        assert!(
+            output.contains(": vector<u16> = vector[1, 2];"),
+            "{}",
+            output
+        );
+        // `u64` is the default of integer literals
+        assert!(output.contains(" = vector[1, 2];"), "{}", output);
+        assert!(!output.contains(": vector<u64>"), "{}", output);
+    }
+
+    #[test]
+    fn bit_flags() {
+        let source = r#"
+module 0x12::flags {
+    public fun grant(perms: u64): u64 {
+        perms | 4
+    }
+
+    public fun can_read(perms: u64): bool {
+        (perms & 1) != 0
+    }
+
+    public fun can_write(perms: u64): bool {
+        (perms & 2) != 0
+    }
+
+    public fun shift(x: u64): u64 {
+        x + 8
+    }
+}
+"#;
+        let output = utils::decompile_source(
+            source,
+            Default::default(),
+            DecompilerSettings {
+                bit_flags: true,
+                ..Default::default()
+            },
+        );
+        assert!(output.contains("const FLAG_0: u64 = 1 << 0;"), "{}", output);
+        assert!(output.contains("const FLAG_1: u64 = 1 << 1;"), "{}", output);
+        assert!(output.contains("const FLAG_2: u64 = 1 << 2;"), "{}", output);
+        assert!(output.contains("arg0 | FLAG_2"), "{}", output);
+        assert!(output.contains("arg0 & FLAG_0"), "{}", output);
+        // 8 is added, not combined as a flag
+        assert!(!output.contains("FLAG_3"), "{}", output);
+        assert!(output.contains("arg0 + 8"), "{}", output);
+
+        let output = decompile(source);
+        assert!(!output.contains("FLAG_"), "{}", output);
+    }
+
+    #[test]
+    fn literal_styles() {
+        let source = r#"
+module 0x12::numbers {
+    public fun mask(x: u64): u64 {
+        x & 0xff
+    }
+
+    public fun magic(x: u64): u64 {
+        x ^ 0xaaaaaaaaaaaaaaaa
+    }
+
+    public fun small(x: u64): u64 {
+        x + 42
+    }
+
+    public fun round(x: u64): u64 {
+        x * 1000000
+    }
+
+    public fun plain(x: u64): u64 {
+        x + 12345
+    }
+}
+"#;
+        let with_style = |literal_style| {
+            utils::decompile_source(
+                source,
+                Default::default(),
+                DecompilerSettings {
+                    literal_style,
+                    ..Default::default()
+                },
+            )
+        };
+
+        let output = with_style(LiteralStyle::Auto);
+        assert!(output.contains("arg0 & 0xff"), "{}", output);
+        assert!(output.contains("arg0 ^ 0xaaaaaaaaaaaaaaaa"), "{}", output);
+        assert!(output.contains("arg0 + 42"), "{}", output);
+        assert!(output.contains("arg0 * 1000000"), "{}", output);
+        assert!(output.contains("arg0 + 12345"), "{}", output);
+
+        let output = with_style(LiteralStyle::Hex);
+        assert!(output.contains("arg0 + 0x2a"), "{}", output);
+        assert!(output.contains("arg0 + 0x3039"), "{}", output);
+
+        let output = with_style(LiteralStyle::Decimal);
+        assert!(output.contains("arg0 & 255"), "{}", output);
+        assert!(output.contains("arg0 + 42"), "{}", output);
+    }
+}
@@ -0,0 +1,54 @@
+mod utils;
+
+#[cfg(test)]
+mod test {
+    use super::utils;
+    use move_binary_format::access::ModuleAccess;
+    use move_compiler::Flags;
+    use move_decompiler::decompiler::Decompiler;
+
+    #[test]
+    fn framework_and_unresolved_dependencies() {
+        let source = r#"
+module 0x34::other {
+    public fun value(): u64 {
+        1
+    }
+}
+
+module 0x12::app {
+    public fun owner(account: &signer): address {
+        std::signer::address_of(account)
+    }
+
+    public fun value(): u64 {
+        0x34::other::value()
+    }
+}
+"#;
+        utils::tmp_project(vec![("tmp.move", source)], |files| {
+            let (scripts, modules) = utils::run_compiler(files, Flags::empty(), false);
+            // only `app` is decompiled, `other` is a dependency from elsewhere
+            let modules = modules
+                .into_iter()
+                .filter(|m| m.name().as_str() == "app")
+                .collect::<Vec<_>>();
+            let binaries = utils::into_binary_indexed_view(&scripts, &modules);
+            let decompiler = Decompiler::new(binaries, Default::default());
+            assert_eq!(
+                decompiler.move_toml(Some("main")),
+                r#"[package]
+name = "Decompiled"
+version = "1.0.0"
+
+[addresses]
+decompiled = "0x12"
+
+[dependencies]
+AptosFramework = { git = "https://github.com/aptos-labs/aptos-core.git", subdir = "aptos-move/framework/aptos-framework", rev = "main" }
+# unresolved: 0x34::other
+"#
+            );
+        });
+    }
+}
@@ -0,0 +1,72 @@
+mod utils;
+
+#[cfg(test)]
+mod test {
+    use super::utils;
+
+    const SOURCE: &str = r#"
+module 0x12::graphs {
+    fun helper(x: u64): u64 {
+        x + 1
+    }
+
+    public fun branch(c: bool, x: u64): u64 {
+        if (c) helper(x) else x
+    }
+
+    public fun looped(n: u64): u64 {
+        let i = 0;
+        while (i < n) {
+            i = helper(i);
+        };
+        i
+    }
+}
+"#;
+
+    #[test]
+    fn cfg_flowcharts() {
+        utils::with_decompiler(
+            SOURCE,
+            Default::default(),
+            Default::default(),
+            |decompiler| {
+                let graphs = decompiler.cfg_graphs().unwrap();
+                let chart = |name: &str| {
+                    graphs
+                        .iter()
+                        .find(|(function, _)| function == name)
+                        .map(|(_, chart)| chart.clone())
+                        .unwrap_or_else(|| panic!("no flowchart for {} in {:?}", name, graphs))
+                };
+
+                let branch = chart("0x12::graphs::branch");
+                assert!(branch.starts_with("flowchart TD\n"), "{}", branch);
+                assert!(branch.contains("    B0[\"B0: "), "{}", branch);
+                assert!(branch.contains(" -->|true| "), "{}", branch);
+                assert!(branch.contains(" -->|false| "), "{}", branch);
+
+                let looped = chart("0x12::graphs::looped");
+                assert!(looped.contains(" -->|loop| "), "{}", looped);
+                assert!(looped.contains(" -->|exit| "), "{}", looped);
+            },
+        );
+    }
+
+    #[test]
+    fn call_graph() {
+        utils::with_decompiler(
+            SOURCE,
+            Default::default(),
+            Default::default(),
+            |decompiler| {
+                decompiler.decompile().expect("Unable to decompile");
+                let graph = decompiler.call_graph();
+                assert_eq!(
+                    graph,
+                    "flowchart LR\n    F0[\"0x12::graphs::branch\"]\n    F1[\"0x12::graphs::helper\"]\n    F2[\"0x12::graphs::looped\"]\n    F0 --> F1\n    F2 --> F1\n"
+                );
+            },
+        );
+    }
+}
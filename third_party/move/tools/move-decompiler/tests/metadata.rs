@@ -0,0 +1,143 @@
+mod utils;
+
+#[cfg(test)]
+mod test {
+    use super::utils::{self, Attribute, RuntimeMetadata, EVENT, VIEW_FUNCTION};
+    use move_core_types::errmap::ErrorDescription;
+    use move_decompiler::decompiler::{DecompilerSettings, PackageMetadata};
+    use std::collections::BTreeMap;
+
+    const SOURCE: &str = r#"
+module 0x12::vault {
+    struct Deposited has drop, store {
+        amount: u64,
+    }
+
+    entry fun deposit(amount: u64) {
+        assert!(amount > 0, 1);
+    }
+
+    public fun balance(): u64 {
+        0
+    }
+}
+"#;
+
+    fn metadata() -> RuntimeMetadata {
+        RuntimeMetadata {
+            error_map: BTreeMap::from([(
+                1,
+                ErrorDescription {
+                    code_name: "EZERO".to_string(),
+                    code_description: "nothing to deposit".to_string(),
+                },
+            )]),
+            struct_attributes: BTreeMap::from([(
+                "Deposited".to_string(),
+                vec![Attribute {
+                    kind: EVENT,
+                    args: vec![],
+                }],
+            )]),
+            fun_attributes: BTreeMap::from([(
+                "balance".to_string(),
+                vec![Attribute {
+                    kind: VIEW_FUNCTION,
+                    args: vec![],
+                }],
+            )]),
+        }
+    }
+
+    fn package() -> PackageMetadata {
+        serde_json::from_value(serde_json::json!({
+            "name": "Vault",
+            "upgrade_policy": 1,
+            "upgrade_number": 3,
+            "source_digest": "ABCDEF",
+            "manifest": [],
+            "modules": [{ "name": "vault", "source": [], "source_map": [], "extension": [] }],
+            "deps": [{ "account": "0x1", "package_name": "AptosFramework" }],
+            "extension": [],
+        }))
+        .unwrap()
+    }
+
+    fn header_settings() -> DecompilerSettings {
+        DecompilerSettings {
+            metadata_header: true,
+            ..Default::default()
+        }
+    }
+
+    // lines before the module declaration
+    fn header(output: &str) -> Vec<&str> {
+        output
+            .lines()
+            .take_while(|line| !line.starts_with("module "))
+            .collect()
+    }
+
+    #[test]
+    fn runtime_metadata_header() {
+        let output = utils::decompile_modules(
+            SOURCE,
+            |modules| utils::attach_metadata(modules, "vault", &metadata()),
+            |decompiler| decompiler.with_settings(header_settings()),
+        )
+        .unwrap();
+
+        assert_eq!(
+            header(&output),
+            vec![
+                "// metadata: aptos::metadata_v1",
+                "//   error codes: 1",
+                "// entry functions: deposit",
+                "// view functions: balance",
+                "// events: Deposited",
+            ],
+            "{}",
+            output
+        );
+    }
+
+    #[test]
+    fn package_metadata_header() {
+        let output = utils::decompile_modules(
+            SOURCE,
+            |_| {},
+            |decompiler| {
+                decompiler
+                    .with_settings(header_settings())
+                    .with_package_metadata(package())
+            },
+        )
+        .unwrap();
+
+        // without runtime metadata only what the bytecode tells is listed
+        assert_eq!(
+            header(&output),
+            vec![
+                "// entry functions: deposit",
+                "// package: Vault",
+                "//   upgrade policy: compatible, upgrade number: 3",
+                "//   source digest: ABCDEF",
+                "//   dependency: 0x1::AptosFramework",
+            ],
+            "{}",
+            output
+        );
+    }
+
+    #[test]
+    fn no_header_by_default() {
+        let output = utils::decompile_modules(
+            SOURCE,
+            |modules| utils::attach_metadata(modules, "vault", &metadata()),
+            |decompiler| decompiler.with_package_metadata(package()),
+        )
+        .unwrap();
+
+        assert!(header(&output).is_empty(), "{}", output);
+    }
+}
@@ -0,0 +1,55 @@
+mod utils;
+
+#[cfg(test)]
+mod test {
+    use super::utils;
+    use move_binary_format::access::ModuleAccess;
+    use move_ir_compiler::Compiler;
+
+    #[test]
+    fn module_ir_round_trips() {
+        utils::with_decompiler(
+            r#"
+module 0x12::ir {
+    struct Pair has copy, drop {
+        a: u64,
+        b: u64,
+    }
+
+    public fun square_sum(x: u64, y: u64): u64 {
+        let s = x * x + y * y;
+        if (s > x) s - x else x
+    }
+
+    public fun pair(x: u64): Pair {
+        let p = Pair { a: x, b: x + 1 };
+        let q = p;
+        q.a = p.b;
+        q
+    }
+
+    public fun count(n: u64): u64 {
+        let i = 0;
+        while (i < n) {
+            i = i + 1;
+        };
+        i
+    }
+}
+"#,
+            Default::default(),
+            Default::default(),
+            |decompiler| {
+                let ir = decompiler.decompile_ir().expect("Unable to decompile");
+                assert!(ir.contains("copy(t"), "{}", ir);
+                let module = Compiler::new(vec![])
+                    .into_compiled_module(&ir)
+                    .unwrap_or_else(|e| panic!("{}\n{:?}", ir, e));
+                move_bytecode_verifier::verify_module(&module)
+                    .unwrap_or_else(|e| panic!("{}\n{:?}", ir, e));
+                assert_eq!(module.function_defs().len(), 3);
+                assert_eq!(module.struct_defs().len(), 1);
+            },
+        );
+    }
+}
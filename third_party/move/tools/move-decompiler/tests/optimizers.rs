@@ -0,0 +1,505 @@
+mod utils;
+
+#[cfg(test)]
+mod test {
+    use super::utils;
+    use move_decompiler::decompiler::OptimizerSettings;
+
+    fn decompile(source: &str, settings: OptimizerSettings) -> String {
+        utils::decompile_source(source, settings, Default::default())
+    }
+
+    // source of the function `name` in the decompiled output
+    fn function<'a>(output: &'a str, name: &str) -> &'a str {
+        let start = output
+            .find(&format!("fun {}(", name))
+            .unwrap_or_else(|| panic!("no function {} in\n{}", name, output));
+        let end = output[start..]
+            .find("\n    }")
+            .map_or(output.len(), |end| start + end);
+        &output[start..end]
+    }
+
+    // statements only copying a variable into another, like `let v1 = v0;`
+    fn variable_copies(source: &str) -> Vec<&str> {
+        let is_variable = |s: &str| {
+            s.strip_prefix('v').map_or(false, |n| {
+                !n.is_empty() && n.chars().all(|c| c.is_ascii_digit())
+            })
+        };
+        source
+            .lines()
+            .map(str::trim)
+            .filter(|line| {
+                let line = line.strip_prefix("let ").unwrap_or(line);
+                match line
+                    .strip_suffix(';')
+                    .and_then(|line| line.split_once(" = "))
+                {
+                    Some((target, value)) => is_variable(target) && is_variable(value),
+                    None => false,
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn loop_invariants() {
+        let output = decompile(
+            r#"
+module 0x12::invariants {
+    struct Config has key {
+        limit: u64,
+    }
+
+    public fun invariant(addr: address, n: u64): u64 acquires Config {
+        let i = 0;
+        let sum = 0;
+        while (i < n) {
+            sum = sum + borrow_global<Config>(addr).limit;
+            i = i + 1;
+        };
+        sum
+    }
+
+    public fun with_call(addr: address, n: u64): u64 acquires Config {
+        let i = 0;
+        let sum = 0;
+        while (i < n) {
+            sum = sum + borrow_global<Config>(addr).limit;
+            reset(addr);
+            i = i + 1;
+        };
+        sum
+    }
+
+    public fun with_mutable_borrow(addr: address, n: u64): u64 acquires Config {
+        let i = 0;
+        let sum = 0;
+        while (i < n) {
+            sum = sum + borrow_global<Config>(addr).limit;
+            borrow_global_mut<Config>(addr).limit = sum;
+            i = i + 1;
+        };
+        sum
+    }
+
+    fun reset(addr: address) acquires Config {
+        borrow_global_mut<Config>(addr).limit = 0;
+    }
+}
+"#,
+            OptimizerSettings {
+                annotate_loop_invariants: true,
+                ..Default::default()
+            },
+        );
+        assert!(
+            function(&output, "invariant")
+                .contains("/* loop-invariant: borrow_global<Config>(arg0) */"),
+            "{}",
+            output
+        );
+        // `reset` may write the resource on every iteration
+        assert!(
+            !function(&output, "with_call").contains("loop-invariant"),
+            "{}",
+            output
+        );
+        assert!(
+            !function(&output, "with_mutable_borrow").contains("loop-invariant"),
+            "{}",
+            output
+        );
+    }
+
+    #[test]
+    fn join_variables() {
+        let output = decompile(
+            r#"
+module 0x12::joins {
+    public fun join(c: bool, x: u64): u64 {
+        let r;
+        if (c) {
+            let t = x * 2;
+            r = t;
+        } else {
+            let t = x + 3;
+            r = t;
+        };
+        r
+    }
+}
+"#,
+            Default::default(),
+        );
+        let join = function(&output, "join");
+        assert!(variable_copies(join).is_empty(), "{}", output);
+        assert!(join.contains("arg1 * 2"), "{}", output);
+        assert!(join.contains("arg1 + 3"), "{}", output);
+    }
+
+    #[test]
+    fn branch_tails() {
+        let output = decompile(
+            r#"
+module 0x12::tails {
+    fun touch(x: u64): u64 {
+        x
+    }
+
+    public fun tails(c: bool, x: u64): u64 {
+        let a;
+        if (c) {
+            a = x + 1;
+            touch(a);
+        } else {
+            a = x + 2;
+            touch(a);
+        };
+        a
+    }
+}
+"#,
+            Default::default(),
+        );
+        let tails = function(&output, "tails");
+        assert_eq!(tails.matches("touch(").count(), 1, "{}", output);
+        // the hoisted call follows the conditional
+        assert!(
+            tails.find("touch(").unwrap() > tails.find("} else {").unwrap(),
+            "{}",
+            output
+        );
+    }
+
+    #[test]
+    fn tuple_bindings() {
+        let output = decompile(
+            r#"
+module 0x12::tuples {
+    struct Token has drop {
+        value: u64,
+    }
+
+    fun make(x: u64): (Token, u64) {
+        (Token { value: x }, x + 1)
+    }
+
+    fun read(token: &Token): u64 {
+        token.value
+    }
+
+    fun consume(token: Token, x: u64): u64 {
+        let Token { value } = token;
+        value + x
+    }
+
+    public fun bind(x: u64): u64 {
+        let (token, y) = make(x);
+        let r = read(&token);
+        consume(token, y) + r
+    }
+}
+"#,
+            Default::default(),
+        );
+        let bind = function(&output, "bind");
+        assert!(bind.contains("= make(arg0);"), "{}", output);
+        assert!(variable_copies(bind).is_empty(), "{}", output);
+    }
+
+    #[test]
+    fn struct_bindings() {
+        let output = decompile(
+            r#"
+module 0x12::structs {
+    struct Token has drop {
+        value: u64,
+    }
+
+    struct Pair has drop {
+        token: Token,
+        amount: u64,
+    }
+
+    fun read(token: &Token): u64 {
+        token.value
+    }
+
+    fun consume(token: Token, x: u64): u64 {
+        let Token { value } = token;
+        value + x
+    }
+
+    public fun bind(pair: Pair): u64 {
+        let Pair { token, amount } = pair;
+        let r = read(&token);
+        consume(token, amount) + r
+    }
+}
+"#,
+            Default::default(),
+        );
+        let bind = function(&output, "bind");
+        assert!(bind.contains("let Pair {"), "{}", output);
+        assert!(variable_copies(bind).is_empty(), "{}", output);
+    }
+
+    #[test]
+    fn early_returns() {
+        let source = r#"
+module 0x12::guards {
+    fun touch(x: u64): u64 {
+        x
+    }
+
+    public fun guard(c: bool, x: u64): u64 {
+        if (c) {
+            x = x + 1;
+            touch(x);
+            touch(x + 2);
+        } else {
+            return 1
+        };
+        x
+    }
+}
+"#;
+        let output = decompile(
+            source,
+            OptimizerSettings {
+                rewrite_early_returns: true,
+                ..Default::default()
+            },
+        );
+        let guard = function(&output, "guard");
+        assert!(!guard.contains("} else {"), "{}", output);
+        // the guard clause precedes the statements of the other branch
+        assert!(
+            guard.find("return 1").unwrap() < guard.find("touch(").unwrap(),
+            "{}",
+            output
+        );
+        assert_eq!(guard.matches("touch(").count(), 2, "{}", output);
+
+        let output = decompile(source, Default::default());
+        assert!(
+            function(&output, "guard").contains("} else {"),
+            "{}",
+            output
+        );
+    }
+
+    #[test]
+    fn common_subexpressions() {
+        let output = decompile(
+            r#"
+module 0x12::subexpressions {
+    use std::vector;
+
+    public fun hoisted(v: vector<u64>): u64 {
+        assert!(vector::length(&v) > 0, 1);
+        vector::length(&v) - 1
+    }
+
+    public fun assigned(v: vector<u64>, w: vector<u64>): u64 {
+        assert!(vector::length(&v) > 0, 1);
+        v = w;
+        vector::length(&v)
+    }
+
+    public fun mutated(v: vector<u64>): u64 {
+        assert!(vector::length(&v) > 0, 1);
+        vector::push_back(&mut v, 1);
+        vector::length(&v)
+    }
+}
+"#,
+            OptimizerSettings {
+                eliminate_common_subexpressions: true,
+                ..Default::default()
+            },
+        );
+        let hoisted = function(&output, "hoisted");
+        assert_eq!(hoisted.matches("length").count(), 1, "{}", output);
+        assert_eq!(hoisted.matches("arg0_len").count(), 3, "{}", output);
+        // the variable changes between the two calls
+        let assigned = function(&output, "assigned");
+        assert_eq!(assigned.matches("length").count(), 2, "{}", output);
+        assert!(!assigned.contains("_len"), "{}", output);
+        // `push_back` modifies the vector between the two calls
+        let mutated = function(&output, "mutated");
+        assert_eq!(mutated.matches("length").count(), 2, "{}", output);
+        assert!(!mutated.contains("_len"), "{}", output);
+    }
+
+    #[test]
+    fn foreach_loops() {
+        let output = decompile(
+            r#"
+module 0x12::loops {
+    use std::vector;
+
+    public fun sum(v: &vector<u64>): u64 {
+        let i = 0;
+        let total = 0;
+        let len = vector::length(v);
+        while (i < len) {
+            let x = vector::borrow(v, i);
+            total = total + *x;
+            i = i + 1;
+        };
+        total
+    }
+
+    public fun strided(v: &vector<u64>): u64 {
+        let i = 0;
+        let total = 0;
+        while (i < vector::length(v)) {
+            total = total + *vector::borrow(v, i);
+            i = i + 2;
+        };
+        total
+    }
+}
+"#,
+            OptimizerSettings {
+                annotate_foreach_loops: true,
+                ..Default::default()
+            },
+        );
+        let sum = function(&output, "sum");
+        assert!(sum.contains("/* for each "), "{}", output);
+        assert!(sum.contains(" in arg0 (index "), "{}", output);
+        // not a foreach loop, every other element is skipped
+        assert!(
+            !function(&output, "strided").contains("for each"),
+            "{}",
+            output
+        );
+    }
+
+    #[test]
+    fn gas_hints() {
+        let output = decompile(
+            r#"
+module 0x12::gas {
+    use std::vector;
+
+    struct Config has key {
+        limit: u64,
+    }
+
+    public fun bump(addr: address) acquires Config {
+        let config = borrow_global_mut<Config>(addr);
+        config.limit = config.limit + 1;
+    }
+
+    public fun publish(account: &signer) {
+        move_to(account, Config { limit: 1 });
+    }
+
+    public fun total(v: &vector<u64>): u64 {
+        let i = 0;
+        let t = 0;
+        while (i < vector::length(v)) {
+            t = t + *vector::borrow(v, i);
+            i = i + 1;
+        };
+        t
+    }
+
+    public fun pure(x: u64): u64 {
+        let y = x * 2;
+        y + 1
+    }
+}
+"#,
+            OptimizerSettings {
+                annotate_gas_hints: true,
+                ..Default::default()
+            },
+        );
+        assert!(
+            function(&output, "bump")
+                .contains("/* gas: global storage access (borrow_global_mut) */"),
+            "{}",
+            output
+        );
+        assert!(
+            function(&output, "publish").contains("/* gas: global storage access (move_to) */"),
+            "{}",
+            output
+        );
+        assert!(
+            function(&output, "total")
+                .contains("/* gas: loop bounded by a vector length, cost grows with its size */"),
+            "{}",
+            output
+        );
+        assert!(!function(&output, "pure").contains("gas:"), "{}", output);
+    }
+
+    #[test]
+    fn call_ordering() {
+        let output = decompile(
+            r#"
+module 0x12::other {
+    public fun ping(): u64 {
+        1
+    }
+}
+
+module 0x12::ordering {
+    use 0x12::other;
+
+    struct Config has key {
+        limit: u64,
+    }
+
+    public fun write_after_call(addr: address) acquires Config {
+        let v = borrow_global<Config>(addr).limit;
+        other::ping();
+        borrow_global_mut<Config>(addr).limit = v + 1;
+    }
+
+    public fun borrow_after_call(addr: address): u64 acquires Config {
+        let v = borrow_global<Config>(addr).limit;
+        other::ping();
+        let config = borrow_global_mut<Config>(addr);
+        config.limit + v
+    }
+
+    public fun write_through_variable(addr: address) acquires Config {
+        let v = borrow_global<Config>(addr).limit;
+        let config = borrow_global_mut<Config>(addr);
+        other::ping();
+        config.limit = v + 1;
+    }
+}
+"#,
+            OptimizerSettings {
+                annotate_call_ordering: true,
+                ..Default::default()
+            },
+        );
+        let comment = "/* ordering: 0x12::other::ping called between read and write of Config */";
+        assert!(
+            function(&output, "write_after_call").contains(comment),
+            "{}",
+            output
+        );
+        // the mutable borrow alone does not write the resource
+        assert!(
+            !function(&output, "borrow_after_call").contains("ordering:"),
+            "{}",
+            output
+        );
+        assert!(
+            function(&output, "write_through_variable").contains(comment),
+            "{}",
+            output
+        );
+    }
+}
@@ -0,0 +1,153 @@
+mod utils;
+
+#[cfg(test)]
+mod test {
+    use super::utils;
+    use anyhow::Result;
+    use move_compiler::Flags;
+    use move_decompiler::decompiler::{Decompiler, Pass, PassIr, Stage};
+    use move_model::model::FunctionEnv;
+    use std::{cell::RefCell, rc::Rc};
+
+    const SOURCE: &str = r#"
+module 0x12::passes {
+    public fun add(a: u64, b: u64): u64 {
+        a + b
+    }
+
+    public fun pick(c: bool): u64 {
+        if (c) 1 else 2
+    }
+}
+"#;
+
+    // records the functions it runs on, and the number of blocks at the blocks stages
+    struct Recorder {
+        stage: Stage,
+        seen: Rc<RefCell<Vec<String>>>,
+    }
+
+    impl Pass for Recorder {
+        fn name(&self) -> &str {
+            "recorder"
+        }
+
+        fn stage(&self) -> Stage {
+            self.stage
+        }
+
+        fn run(&self, function: &FunctionEnv<'_>, ir: &mut PassIr<'_, '_>) -> Result<()> {
+            let name = function.get_name_str();
+            self.seen.borrow_mut().push(match ir {
+                PassIr::Blocks(blocks) => format!("{} {}", name, blocks.len()),
+                _ => name,
+            });
+            Ok(())
+        }
+    }
+
+    // appends a comment to every function body
+    struct Marker;
+
+    impl Pass for Marker {
+        fn name(&self) -> &str {
+            "marker"
+        }
+
+        fn stage(&self) -> Stage {
+            Stage::Source
+        }
+
+        fn run(&self, _function: &FunctionEnv<'_>, ir: &mut PassIr<'_, '_>) -> Result<()> {
+            match ir {
+                PassIr::Source(unit) => unit.add_line("// marked".to_string()),
+                _ => anyhow::bail!("expected source"),
+            }
+            Ok(())
+        }
+    }
+
+    struct Failing;
+
+    impl Pass for Failing {
+        fn name(&self) -> &str {
+            "failing"
+        }
+
+        fn stage(&self) -> Stage {
+            Stage::Loops
+        }
+
+        fn run(&self, _function: &FunctionEnv<'_>, _ir: &mut PassIr<'_, '_>) -> Result<()> {
+            anyhow::bail!("nothing to do")
+        }
+    }
+
+    fn decompile(register: impl FnOnce(Decompiler) -> Decompiler) -> Result<String> {
+        let mut register = Some(register);
+        let mut result = None;
+        utils::tmp_project(vec![("tmp.move", SOURCE)], |files| {
+            let (scripts, modules) = utils::run_compiler(files, Flags::empty(), false);
+            let binaries = utils::into_binary_indexed_view(&scripts, &modules);
+            let decompiler = Decompiler::new(binaries, Default::default());
+            let mut decompiler = register.take().unwrap()(decompiler);
+            result = Some(decompiler.decompile());
+        });
+        result.unwrap()
+    }
+
+    #[test]
+    fn passes_run_at_their_stage() {
+        let stackless = Rc::new(RefCell::new(vec![]));
+        let blocks = Rc::new(RefCell::new(vec![]));
+        let output = decompile(|decompiler| {
+            decompiler
+                .with_pass(Recorder {
+                    stage: Stage::Stackless,
+                    seen: stackless.clone(),
+                })
+                .with_pass(Recorder {
+                    stage: Stage::Blocks,
+                    seen: blocks.clone(),
+                })
+        })
+        .unwrap();
+
+        let mut stackless = stackless.borrow().clone();
+        stackless.sort();
+        assert_eq!(stackless, vec!["add", "pick"]);
+
+        // the branch gives `pick` more blocks than the straight-line `add`
+        let mut blocks = blocks.borrow().clone();
+        blocks.sort();
+        let counts = blocks
+            .iter()
+            .map(|entry| entry.split_once(' ').unwrap().1.parse::<usize>().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(counts.len(), 2, "{:?}", blocks);
+        assert!(counts[0] < counts[1], "{:?}", blocks);
+
+        // recording passes leave the output alone
+        assert_eq!(output, decompile(|decompiler| decompiler).unwrap());
+    }
+
+    #[test]
+    fn source_pass_rewrites_output() {
+        let output = decompile(|decompiler| decompiler.with_pass(Marker)).unwrap();
+
+        assert_eq!(output.matches("// marked").count(), 2, "{}", output);
+    }
+
+    #[test]
+    fn failing_pass_aborts() {
+        let err = decompile(|decompiler| decompiler.with_pass(Failing)).unwrap_err();
+
+        let message = format!("{:#}", err);
+        assert!(
+            message.contains("pass failing failed on 0x12::passes::"),
+            "{}",
+            message
+        );
+        assert!(message.contains("nothing to do"), "{}", message);
+    }
+}
@@ -0,0 +1,43 @@
+mod utils;
+
+#[cfg(test)]
+mod test {
+    use super::utils;
+    use move_decompiler::decompiler::DecompilerSettings;
+
+    fn pseudocode(source: &str) -> String {
+        utils::decompile_source(
+            source,
+            Default::default(),
+            DecompilerSettings {
+                pseudocode: true,
+                ..Default::default()
+            },
+        )
+    }
+
+    #[test]
+    fn only_widening_casts_are_dropped() {
+        let output = pseudocode(
+            r#"
+module 0x12::casts {
+    public fun widen(x: u8): u64 {
+        (x as u64) + 1
+    }
+
+    public fun narrow(x: u64): u8 {
+        (x as u8)
+    }
+
+    public fun same(x: u128): u128 {
+        (x as u128)
+    }
+}
+"#,
+        );
+        assert!(!output.contains("arg0 as u64"), "{}", output);
+        assert!(output.contains("arg0 + 1"), "{}", output);
+        assert!(output.contains("arg0 as u8"), "{}", output);
+        assert!(output.contains("arg0 as u128"), "{}", output);
+    }
+}
@@ -325,26 +325,25 @@ module 0x1::delegation_pool {
     
     fun calculate_stake_pool_drift(arg0: &DelegationPool) : (bool, u64, u64, u64, u64) {
         let (v0, v1, v2, v3) = 0x1::stake::get_stake(get_pool_address(arg0));
-        let v4 = v3;
         assert!(v1 >= arg0.total_coins_inactive, 0x1::error::invalid_state(7));
-        let v5 = v1 > arg0.total_coins_inactive;
-        let v6 = v0 + v2;
-        if (v5) {
-            v4 = v1 - arg0.total_coins_inactive;
-        };
-        let v7 = 0x1::pool_u64_unbound::total_coins(&arg0.active_shares);
-        let v8 = if (v6 > v7) {
-            multiply_then_divide(v6 - v7, arg0.operator_commission_percentage, 10000)
+        let v4 = v1 > arg0.total_coins_inactive;
+        let v5 = v0 + v2;
+        if (v4) {
+            v3 = v1 - arg0.total_coins_inactive;
+        };
+        let v6 = 0x1::pool_u64_unbound::total_coins(&arg0.active_shares);
+        let v7 = if (v5 > v6) {
+            multiply_then_divide(v5 - v6, arg0.operator_commission_percentage, 10000)
         } else {
             0
         };
-        let v9 = 0x1::pool_u64_unbound::total_coins(pending_inactive_shares_pool(arg0));
-        let v10 = if (v4 > v9) {
-            multiply_then_divide(v4 - v9, arg0.operator_commission_percentage, 10000)
+        let v8 = 0x1::pool_u64_unbound::total_coins(pending_inactive_shares_pool(arg0));
+        let v9 = if (v3 > v8) {
+            multiply_then_divide(v3 - v8, arg0.operator_commission_percentage, 10000)
         } else {
             0
         };
-        (v5, v6, v4, v8, v10)
+        (v4, v5, v3, v7, v9)
     }
     
     fun calculate_total_voting_power(arg0: &DelegationPool, arg1: &DelegatedVotes) : u64 {
@@ -458,8 +457,7 @@ module 0x1::delegation_pool {
     
     fun execute_pending_withdrawal(arg0: &mut DelegationPool, arg1: address) acquires GovernanceRecords {
         let (v0, v1) = pending_withdrawal_exists(arg0, arg1);
-        let v2 = v1;
-        if (v0 && v2.index < arg0.observed_lockup_cycle.index) {
+        if (v0 && v1.index < arg0.observed_lockup_cycle.index) {
             withdraw_internal(arg0, arg1, 18446744073709551615);
         };
     }
@@ -508,17 +506,16 @@ module 0x1::delegation_pool {
         let v0 = borrow_global<DelegationPool>(arg0);
         let (v1, _, v3, _, v5) = calculate_stake_pool_drift(v0);
         let (v6, v7) = pending_withdrawal_exists(v0, arg1);
-        let v8 = v7;
         if (!v6) {
             (false, 0)
         } else {
-            let v11 = 0x1::table::borrow<ObservedLockupCycle, 0x1::pool_u64_unbound::Pool>(&v0.inactive_shares, v8);
-            let (v12, v13) = if (v8.index < v0.observed_lockup_cycle.index) {
-                (true, 0x1::pool_u64_unbound::balance(v11, arg1))
+            let v10 = 0x1::table::borrow<ObservedLockupCycle, 0x1::pool_u64_unbound::Pool>(&v0.inactive_shares, v7);
+            let (v11, v12) = if (v7.index < v0.observed_lockup_cycle.index) {
+                (true, 0x1::pool_u64_unbound::balance(v10, arg1))
             } else {
-                (v1, 0x1::pool_u64_unbound::shares_to_amount_with_total_coins(v11, 0x1::pool_u64_unbound::shares(v11, arg1), v3 - v5))
+                (v1, 0x1::pool_u64_unbound::shares_to_amount_with_total_coins(v10, 0x1::pool_u64_unbound::shares(v10, arg1), v3 - v5))
             };
-            (v12, v13)
+            (v11, v12)
         }
     }
     
@@ -541,39 +538,38 @@ module 0x1::delegation_pool {
         assert!(!owner_cap_exists(v0), 0x1::error::already_exists(2));
         assert!(arg1 <= 10000, 0x1::error::invalid_argument(5));
         let (v1, v2) = 0x1::account::create_resource_account(arg0, create_resource_account_seed(arg2));
-        let v3 = v1;
-        0x1::coin::register<0x1::aptos_coin::AptosCoin>(&v3);
-        let v4 = 0x1::signer::address_of(&v3);
-        0x1::stake::initialize_stake_owner(&v3, 0, v0, v0);
-        let v5 = 0x1::table::new<ObservedLockupCycle, 0x1::pool_u64_unbound::Pool>();
-        0x1::table::add<ObservedLockupCycle, 0x1::pool_u64_unbound::Pool>(&mut v5, olc_with_index(0), 0x1::pool_u64_unbound::create_with_scaling_factor(10000000000000000));
-        let v6 = 0x1::pool_u64_unbound::create_with_scaling_factor(10000000000000000);
-        let v7 = olc_with_index(0);
-        let v8 = 0x1::table::new<address, ObservedLockupCycle>();
-        let v9 = 0x1::account::new_event_handle<AddStakeEvent>(&v3);
-        let v10 = 0x1::account::new_event_handle<ReactivateStakeEvent>(&v3);
-        let v11 = 0x1::account::new_event_handle<UnlockStakeEvent>(&v3);
-        let v12 = 0x1::account::new_event_handle<WithdrawStakeEvent>(&v3);
-        let v13 = 0x1::account::new_event_handle<DistributeCommissionEvent>(&v3);
-        let v14 = DelegationPool{
-            active_shares                  : v6, 
-            observed_lockup_cycle          : v7, 
-            inactive_shares                : v5, 
-            pending_withdrawals            : v8, 
+        0x1::coin::register<0x1::aptos_coin::AptosCoin>(&v1);
+        let v3 = 0x1::signer::address_of(&v1);
+        0x1::stake::initialize_stake_owner(&v1, 0, v0, v0);
+        let v4 = 0x1::table::new<ObservedLockupCycle, 0x1::pool_u64_unbound::Pool>();
+        0x1::table::add<ObservedLockupCycle, 0x1::pool_u64_unbound::Pool>(&mut v4, olc_with_index(0), 0x1::pool_u64_unbound::create_with_scaling_factor(10000000000000000));
+        let v5 = 0x1::pool_u64_unbound::create_with_scaling_factor(10000000000000000);
+        let v6 = olc_with_index(0);
+        let v7 = 0x1::table::new<address, ObservedLockupCycle>();
+        let v8 = 0x1::account::new_event_handle<AddStakeEvent>(&v1);
+        let v9 = 0x1::account::new_event_handle<ReactivateStakeEvent>(&v1);
+        let v10 = 0x1::account::new_event_handle<UnlockStakeEvent>(&v1);
+        let v11 = 0x1::account::new_event_handle<WithdrawStakeEvent>(&v1);
+        let v12 = 0x1::account::new_event_handle<DistributeCommissionEvent>(&v1);
+        let v13 = DelegationPool{
+            active_shares                  : v5, 
+            observed_lockup_cycle          : v6, 
+            inactive_shares                : v4, 
+            pending_withdrawals            : v7, 
             stake_pool_signer_cap          : v2, 
             total_coins_inactive           : 0, 
             operator_commission_percentage : arg1, 
-            add_stake_events               : v9, 
-            reactivate_stake_events        : v10, 
-            unlock_stake_events            : v11, 
-            withdraw_stake_events          : v12, 
-            distribute_commission_events   : v13,
-        };
-        move_to<DelegationPool>(&v3, v14);
-        let v15 = DelegationPoolOwnership{pool_address: v4};
-        move_to<DelegationPoolOwnership>(arg0, v15);
+            add_stake_events               : v8, 
+            reactivate_stake_events        : v9, 
+            unlock_stake_events            : v10, 
+            withdraw_stake_events          : v11, 
+            distribute_commission_events   : v12,
+        };
+        move_to<DelegationPool>(&v1, v13);
+        let v14 = DelegationPoolOwnership{pool_address: v3};
+        move_to<DelegationPoolOwnership>(arg0, v14);
         if (0x1::features::partial_governance_voting_enabled() && 0x1::features::delegation_pool_partial_governance_voting_enabled()) {
-            enable_partial_governance_voting(v4);
+            enable_partial_governance_voting(v3);
         };
     }
     
@@ -820,43 +816,42 @@ module 0x1::delegation_pool {
         };
         let v0 = get_pool_address(arg0);
         let (v1, v2) = pending_withdrawal_exists(arg0, arg1);
-        let v3 = v2;
-        let v4 = if (v1) {
-            let v5 = v3.index < arg0.observed_lockup_cycle.index || can_withdraw_pending_inactive(v0);
-            v5
+        let v3 = if (v1) {
+            let v4 = v2.index < arg0.observed_lockup_cycle.index || can_withdraw_pending_inactive(v0);
+            v4
         } else {
             false
         };
-        if (!v4) {
+        if (!v3) {
             return
         };
-        if (v3.index == arg0.observed_lockup_cycle.index) {
+        if (v2.index == arg0.observed_lockup_cycle.index) {
             arg2 = coins_to_redeem_to_ensure_min_stake(pending_inactive_shares_pool(arg0), arg1, arg2);
         };
-        let v6 = redeem_inactive_shares(arg0, arg1, arg2, v3);
-        let v7 = retrieve_stake_pool_owner(arg0);
-        let v8 = &v7;
+        let v5 = redeem_inactive_shares(arg0, arg1, arg2, v2);
+        let v6 = retrieve_stake_pool_owner(arg0);
+        let v7 = &v6;
         if (can_withdraw_pending_inactive(v0)) {
-            let (_, _, _, v12) = 0x1::stake::get_stake(v0);
-            let v13 = v12;
-            if (v3.index == arg0.observed_lockup_cycle.index) {
-                v13 = v12 - v6;
+            let (_, _, _, v11) = 0x1::stake::get_stake(v0);
+            let v12 = v11;
+            if (v2.index == arg0.observed_lockup_cycle.index) {
+                v12 = v11 - v5;
             };
-            0x1::stake::reactivate_stake(v8, v13);
-            0x1::stake::withdraw(v8, v6);
-            0x1::stake::unlock(v8, v13);
+            0x1::stake::reactivate_stake(v7, v12);
+            0x1::stake::withdraw(v7, v5);
+            0x1::stake::unlock(v7, v12);
         } else {
-            0x1::stake::withdraw(v8, v6);
+            0x1::stake::withdraw(v7, v5);
         };
-        0x1::coin::transfer<0x1::aptos_coin::AptosCoin>(v8, arg1, v6);
-        let (_, v15, _, _) = 0x1::stake::get_stake(v0);
-        arg0.total_coins_inactive = v15;
-        let v18 = WithdrawStakeEvent{
+        0x1::coin::transfer<0x1::aptos_coin::AptosCoin>(v7, arg1, v5);
+        let (_, v14, _, _) = 0x1::stake::get_stake(v0);
+        arg0.total_coins_inactive = v14;
+        let v17 = WithdrawStakeEvent{
             pool_address      : v0, 
             delegator_address : arg1, 
-            amount_withdrawn  : v6,
+            amount_withdrawn  : v5,
         };
-        0x1::event::emit_event<WithdrawStakeEvent>(&mut arg0.withdraw_stake_events, v18);
+        0x1::event::emit_event<WithdrawStakeEvent>(&mut arg0.withdraw_stake_events, v17);
     }
     
     // decompiled from Move bytecode v6
@@ -42,31 +42,30 @@ module 0x1::genesis {
     
     fun initialize(arg0: vector<u8>, arg1: u8, arg2: u64, arg3: vector<u8>, arg4: vector<u8>, arg5: u64, arg6: u64, arg7: u64, arg8: u64, arg9: bool, arg10: u64, arg11: u64, arg12: u64) {
         let (v0, v1) = 0x1::account::create_framework_reserved_account(@0x1);
-        let v2 = v0;
-        0x1::account::initialize(&v2);
-        let v3 = &v2;
-        0x1::transaction_validation::initialize(v3, b"script_prologue", b"module_prologue", b"multi_agent_script_prologue", b"epilogue");
-        0x1::aptos_governance::store_signer_cap(&v2, @0x1, v1);
-        let v4 = vector[@0x2, @0x3, @0x4, @0x5, @0x6, @0x7, @0x8, @0x9, @0xa];
-        while (!0x1::vector::is_empty<address>(&v4)) {
-            let v5 = 0x1::vector::pop_back<address>(&mut v4);
-            let (_, v7) = 0x1::account::create_framework_reserved_account(v5);
-            0x1::aptos_governance::store_signer_cap(&v2, v5, v7);
+        0x1::account::initialize(&v0);
+        let v2 = &v0;
+        0x1::transaction_validation::initialize(v2, b"script_prologue", b"module_prologue", b"multi_agent_script_prologue", b"epilogue");
+        0x1::aptos_governance::store_signer_cap(&v0, @0x1, v1);
+        let v3 = vector[@0x2, @0x3, @0x4, @0x5, @0x6, @0x7, @0x8, @0x9, @0xa];
+        while (!0x1::vector::is_empty<address>(&v3)) {
+            let v4 = 0x1::vector::pop_back<address>(&mut v3);
+            let (_, v6) = 0x1::account::create_framework_reserved_account(v4);
+            0x1::aptos_governance::store_signer_cap(&v0, v4, v6);
         };
-        0x1::consensus_config::initialize(&v2, arg3);
-        0x1::execution_config::set(&v2, arg4);
-        0x1::version::initialize(&v2, arg2);
-        0x1::stake::initialize(&v2);
-        0x1::staking_config::initialize(&v2, arg6, arg7, arg8, arg9, arg10, arg11, arg12);
-        0x1::storage_gas::initialize(&v2);
-        0x1::gas_schedule::initialize(&v2, arg0);
-        0x1::aggregator_factory::initialize_aggregator_factory(&v2);
-        0x1::coin::initialize_supply_config(&v2);
-        0x1::chain_id::initialize(&v2, arg1);
-        0x1::reconfiguration::initialize(&v2);
-        0x1::block::initialize(&v2, arg5);
-        0x1::state_storage::initialize(&v2);
-        0x1::timestamp::set_time_has_started(&v2);
+        0x1::consensus_config::initialize(&v0, arg3);
+        0x1::execution_config::set(&v0, arg4);
+        0x1::version::initialize(&v0, arg2);
+        0x1::stake::initialize(&v0);
+        0x1::staking_config::initialize(&v0, arg6, arg7, arg8, arg9, arg10, arg11, arg12);
+        0x1::storage_gas::initialize(&v0);
+        0x1::gas_schedule::initialize(&v0, arg0);
+        0x1::aggregator_factory::initialize_aggregator_factory(&v0);
+        0x1::coin::initialize_supply_config(&v0);
+        0x1::chain_id::initialize(&v0, arg1);
+        0x1::reconfiguration::initialize(&v0);
+        0x1::block::initialize(&v0, arg5);
+        0x1::state_storage::initialize(&v0);
+        0x1::timestamp::set_time_has_started(&v0);
     }
     
     fun set_genesis_end(arg0: &signer) {
@@ -163,11 +163,10 @@ module 0x1::multisig_account {
         let v0 = 0x1::account::get_sequence_number(0x1::signer::address_of(arg0));
         let v1 = create_multisig_account_seed(0x1::bcs::to_bytes<u64>(&v0));
         let (v2, v3) = 0x1::account::create_resource_account(arg0, v1);
-        let v4 = v2;
-        if (!0x1::coin::is_account_registered<0x1::aptos_coin::AptosCoin>(0x1::signer::address_of(&v4))) {
-            0x1::coin::register<0x1::aptos_coin::AptosCoin>(&v4);
+        if (!0x1::coin::is_account_registered<0x1::aptos_coin::AptosCoin>(0x1::signer::address_of(&v2))) {
+            0x1::coin::register<0x1::aptos_coin::AptosCoin>(&v2);
         };
-        (v4, v3)
+        (v2, v3)
     }
     
     fun create_multisig_account_seed(arg0: vector<u8>) : vector<u8> {
@@ -258,10 +257,9 @@ module 0x1::multisig_account {
     
     public entry fun create_with_owners(arg0: &signer, arg1: vector<address>, arg2: u64, arg3: vector<0x1::string::String>, arg4: vector<vector<u8>>) acquires MultisigAccount {
         let (v0, v1) = create_multisig_account(arg0);
-        let v2 = v0;
         0x1::vector::push_back<address>(&mut arg1, 0x1::signer::address_of(arg0));
-        let v3 = 0x1::option::some<0x1::account::SignerCapability>(v1);
-        create_with_owners_internal(&v2, arg1, arg2, v3, arg3, arg4);
+        let v2 = 0x1::option::some<0x1::account::SignerCapability>(v1);
+        create_with_owners_internal(&v0, arg1, arg2, v2, arg3, arg4);
     }
     
     fun create_with_owners_internal(arg0: &signer, arg1: vector<address>, arg2: u64, arg3: 0x1::option::Option<0x1::account::SignerCapability>, arg4: vector<0x1::string::String>, arg5: vector<vector<u8>>) acquires MultisigAccount {
@@ -514,7 +512,7 @@ module 0x1::multisig_account {
         };
         if (0x1::vector::length<address>(&arg2) > 0) {
             let v5 = &mut v0.owners;
-            let v6 = vector[];
+            let v6: vector<address> = vector[];
             let v7 = &arg2;
             let v8 = 0;
             while (v8 < 0x1::vector::length<address>(v7)) {
@@ -567,7 +565,7 @@ module 0x1::multisig_account {
     }
     
     fun validate_owners(arg0: &vector<address>, arg1: address) {
-        let v0 = vector[];
+        let v0: vector<address> = vector[];
         let v1 = 0;
         while (v1 < 0x1::vector::length<address>(arg0)) {
             let v2 = *0x1::vector::borrow<address>(arg0, v1);
@@ -40,7 +40,7 @@ module 0x1337::property_map {
     public fun values(arg0: &PropertyMap) : vector<vector<u8>> {
         let v0 = 0x1::simple_map::values<0x1::string::String, PropertyValue>(&arg0.map);
         let v1 = &v0;
-        let v2 = vector[];
+        let v2 = 0x1::vector::empty<vector<u8>>();
         let v3 = 0;
         while (v3 < 0x1::vector::length<PropertyValue>(v1)) {
             0x1::vector::push_back<vector<u8>>(&mut v2, 0x1::vector::borrow<PropertyValue>(v1, v3).value);
@@ -10,17 +10,15 @@ module 0x1::resource_account {
     
     public entry fun create_resource_account_and_fund(arg0: &signer, arg1: vector<u8>, arg2: vector<u8>, arg3: u64) acquires Container {
         let (v0, v1) = 0x1::account::create_resource_account(arg0, arg1);
-        let v2 = v0;
-        0x1::coin::register<0x1::aptos_coin::AptosCoin>(&v2);
-        0x1::coin::transfer<0x1::aptos_coin::AptosCoin>(arg0, 0x1::signer::address_of(&v2), arg3);
-        rotate_account_authentication_key_and_store_capability(arg0, v2, v1, arg2);
+        0x1::coin::register<0x1::aptos_coin::AptosCoin>(&v0);
+        0x1::coin::transfer<0x1::aptos_coin::AptosCoin>(arg0, 0x1::signer::address_of(&v0), arg3);
+        rotate_account_authentication_key_and_store_capability(arg0, v0, v1, arg2);
     }
     
     public entry fun create_resource_account_and_publish_package(arg0: &signer, arg1: vector<u8>, arg2: vector<u8>, arg3: vector<vector<u8>>) acquires Container {
         let (v0, v1) = 0x1::account::create_resource_account(arg0, arg1);
-        let v2 = v0;
-        0x1::code::publish_package_txn(&v2, arg2, arg3);
-        rotate_account_authentication_key_and_store_capability(arg0, v2, v1, x"0000000000000000000000000000000000000000000000000000000000000000");
+        0x1::code::publish_package_txn(&v0, arg2, arg3);
+        rotate_account_authentication_key_and_store_capability(arg0, v0, v1, x"0000000000000000000000000000000000000000000000000000000000000000");
     }
     
     public fun retrieve_resource_account_cap(arg0: &signer, arg1: address) : 0x1::account::SignerCapability acquires Container {
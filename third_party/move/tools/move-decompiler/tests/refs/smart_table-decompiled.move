@@ -261,7 +261,7 @@ module 0x1::smart_table {
         let v3 = 0;
         let v4 = 0x1::vector::length<Entry<T0, T1>>(v2);
         while (v3 < v4) {
-            if (!(bucket_index(arg0.level, arg0.num_buckets, 0x1::vector::borrow<Entry<T0, T1>>(v2, v3).hash) != v0)) {
+            if (bucket_index(arg0.level, arg0.num_buckets, 0x1::vector::borrow<Entry<T0, T1>>(v2, v3).hash) == v0) {
                 break
             };
             v3 = v3 + 1;
@@ -148,9 +148,8 @@ module 0x1::staking_contract {
     fun create_stake_pool(arg0: &signer, arg1: address, arg2: address, arg3: vector<u8>) : (signer, 0x1::account::SignerCapability, 0x1::stake::OwnerCapability) {
         let v0 = create_resource_account_seed(0x1::signer::address_of(arg0), arg1, arg3);
         let (v1, v2) = 0x1::account::create_resource_account(arg0, v0);
-        let v3 = v1;
-        0x1::stake::initialize_stake_owner(&v3, 0, arg1, arg2);
-        (v3, v2, 0x1::stake::extract_owner_cap(&v3))
+        0x1::stake::initialize_stake_owner(&v1, 0, arg1, arg2);
+        (v1, v2, 0x1::stake::extract_owner_cap(&v1))
     }
     
     public entry fun create_staking_contract(arg0: &signer, arg1: address, arg2: address, arg3: u64, arg4: u64, arg5: vector<u8>) acquires Store {
@@ -172,29 +171,27 @@ module 0x1::staking_contract {
         let v6 = &mut v5.staking_contracts;
         assert!(!0x1::simple_map::contains_key<address, StakingContract>(v6, &arg1), 0x1::error::already_exists(6));
         let (v7, v8, v9) = create_stake_pool(arg0, arg1, arg2, arg5);
-        let v10 = v9;
-        let v11 = v7;
-        0x1::stake::add_stake_with_cap(&v10, arg3);
-        let v12 = 0x1::signer::address_of(&v11);
-        let v13 = 0x1::pool_u64::create(20);
-        let v14 = StakingContract{
+        0x1::stake::add_stake_with_cap(&v9, arg3);
+        let v10 = 0x1::signer::address_of(&v7);
+        let v11 = 0x1::pool_u64::create(20);
+        let v12 = StakingContract{
             principal             : v3, 
-            pool_address          : v12, 
-            owner_cap             : v10, 
+            pool_address          : v10, 
+            owner_cap             : v9, 
             commission_percentage : arg4, 
-            distribution_pool     : v13, 
+            distribution_pool     : v11, 
             signer_cap            : v8,
         };
-        0x1::simple_map::add<address, StakingContract>(v6, arg1, v14);
-        let v15 = CreateStakingContractEvent{
+        0x1::simple_map::add<address, StakingContract>(v6, arg1, v12);
+        let v13 = CreateStakingContractEvent{
             operator              : arg1, 
             voter                 : arg2, 
-            pool_address          : v12, 
+            pool_address          : v10, 
             principal             : v3, 
             commission_percentage : arg4,
         };
-        0x1::event::emit_event<CreateStakingContractEvent>(&mut v5.create_staking_contract_events, v15);
-        v12
+        0x1::event::emit_event<CreateStakingContractEvent>(&mut v5.create_staking_contract_events, v13);
+        v10
     }
     
     public entry fun distribute(arg0: address, arg1: address) acquires Store {
@@ -360,19 +357,18 @@ module 0x1::staking_contract {
         let v3 = !0x1::simple_map::contains_key<address, StakingContract>(v2, &arg2);
         assert!(v3, 0x1::error::invalid_state(5));
         let (_, v5) = 0x1::simple_map::remove<address, StakingContract>(v2, &arg1);
-        let v6 = v5;
-        distribute_internal(v0, arg1, &mut v6, &mut v1.distribute_events);
-        let v7 = &mut v1.request_commission_events;
-        request_commission_internal(arg1, &mut v6, &mut v1.add_distribution_events, v7);
-        0x1::stake::set_operator_with_cap(&v6.owner_cap, arg2);
-        v6.commission_percentage = arg3;
-        0x1::simple_map::add<address, StakingContract>(v2, arg2, v6);
-        let v8 = SwitchOperatorEvent{
+        distribute_internal(v0, arg1, &mut v5, &mut v1.distribute_events);
+        let v6 = &mut v1.request_commission_events;
+        request_commission_internal(arg1, &mut v5, &mut v1.add_distribution_events, v6);
+        0x1::stake::set_operator_with_cap(&v5.owner_cap, arg2);
+        v5.commission_percentage = arg3;
+        0x1::simple_map::add<address, StakingContract>(v2, arg2, v5);
+        let v7 = SwitchOperatorEvent{
             old_operator : arg1, 
             new_operator : arg2, 
-            pool_address : v6.pool_address,
+            pool_address : v5.pool_address,
         };
-        0x1::event::emit_event<SwitchOperatorEvent>(&mut v1.switch_operator_events, v8);
+        0x1::event::emit_event<SwitchOperatorEvent>(&mut v1.switch_operator_events, v7);
     }
     
     public entry fun switch_operator_with_same_commission(arg0: &signer, arg1: address, arg2: address) acquires Store {
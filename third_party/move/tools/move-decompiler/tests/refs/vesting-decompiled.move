@@ -251,78 +251,76 @@ module 0x1::vesting {
         while (v3 < 0x1::vector::length<address>(arg1)) {
             let v4 = *0x1::vector::borrow<address>(arg1, v3);
             let (_, v6) = 0x1::simple_map::remove<address, 0x1::coin::Coin<0x1::aptos_coin::AptosCoin>>(&mut arg2, &v4);
-            let v7 = v6;
-            let v8 = 0x1::coin::value<0x1::aptos_coin::AptosCoin>(&v7);
-            0x1::coin::merge<0x1::aptos_coin::AptosCoin>(&mut v0, v7);
-            0x1::pool_u64::buy_in(&mut v2, v4, v8);
-            v1 = v1 + v8;
+            let v7 = 0x1::coin::value<0x1::aptos_coin::AptosCoin>(&v6);
+            0x1::coin::merge<0x1::aptos_coin::AptosCoin>(&mut v0, v6);
+            0x1::pool_u64::buy_in(&mut v2, v4, v7);
+            v1 = v1 + v7;
             v3 = v3 + 1;
         };
         assert!(v1 > 0, 0x1::error::invalid_argument(12));
-        let v9 = 0x1::signer::address_of(arg0);
-        if (!exists<AdminStore>(v9)) {
-            let v10 = AdminStore{
+        let v8 = 0x1::signer::address_of(arg0);
+        if (!exists<AdminStore>(v8)) {
+            let v9 = AdminStore{
                 vesting_contracts : 0x1::vector::empty<address>(), 
                 nonce             : 0, 
                 create_events     : 0x1::account::new_event_handle<CreateVestingContractEvent>(arg0),
             };
-            move_to<AdminStore>(arg0, v10);
+            move_to<AdminStore>(arg0, v9);
         };
-        let (v11, v12) = create_vesting_contract_account(arg0, arg8);
-        let v13 = v11;
-        let v14 = 0x1::staking_contract::create_staking_contract_with_coins(&v13, arg5, arg6, v0, arg7, arg8);
-        let v15 = 0x1::signer::address_of(&v13);
-        let v16 = borrow_global_mut<AdminStore>(v9);
-        0x1::vector::push_back<address>(&mut v16.vesting_contracts, v15);
-        let v17 = CreateVestingContractEvent{
+        let (v10, v11) = create_vesting_contract_account(arg0, arg8);
+        let v12 = 0x1::staking_contract::create_staking_contract_with_coins(&v10, arg5, arg6, v0, arg7, arg8);
+        let v13 = 0x1::signer::address_of(&v10);
+        let v14 = borrow_global_mut<AdminStore>(v8);
+        0x1::vector::push_back<address>(&mut v14.vesting_contracts, v13);
+        let v15 = CreateVestingContractEvent{
             operator                 : arg5, 
             voter                    : arg6, 
             grant_amount             : v1, 
             withdrawal_address       : arg4, 
-            vesting_contract_address : v15, 
-            staking_pool_address     : v14, 
+            vesting_contract_address : v13, 
+            staking_pool_address     : v12, 
             commission_percentage    : arg7,
         };
-        0x1::event::emit_event<CreateVestingContractEvent>(&mut v16.create_events, v17);
-        let v18 = 0x1::simple_map::create<address, address>();
-        let v19 = StakingInfo{
-            pool_address          : v14, 
+        0x1::event::emit_event<CreateVestingContractEvent>(&mut v14.create_events, v15);
+        let v16 = 0x1::simple_map::create<address, address>();
+        let v17 = StakingInfo{
+            pool_address          : v12, 
             operator              : arg5, 
             voter                 : arg6, 
             commission_percentage : arg7,
         };
-        let v20 = 0x1::account::new_event_handle<UpdateOperatorEvent>(&v13);
-        let v21 = 0x1::account::new_event_handle<UpdateVoterEvent>(&v13);
-        let v22 = 0x1::account::new_event_handle<ResetLockupEvent>(&v13);
-        let v23 = 0x1::account::new_event_handle<SetBeneficiaryEvent>(&v13);
-        let v24 = 0x1::account::new_event_handle<UnlockRewardsEvent>(&v13);
-        let v25 = 0x1::account::new_event_handle<VestEvent>(&v13);
-        let v26 = 0x1::account::new_event_handle<DistributeEvent>(&v13);
-        let v27 = 0x1::account::new_event_handle<TerminateEvent>(&v13);
-        let v28 = 0x1::account::new_event_handle<AdminWithdrawEvent>(&v13);
-        let v29 = VestingContract{
+        let v18 = 0x1::account::new_event_handle<UpdateOperatorEvent>(&v10);
+        let v19 = 0x1::account::new_event_handle<UpdateVoterEvent>(&v10);
+        let v20 = 0x1::account::new_event_handle<ResetLockupEvent>(&v10);
+        let v21 = 0x1::account::new_event_handle<SetBeneficiaryEvent>(&v10);
+        let v22 = 0x1::account::new_event_handle<UnlockRewardsEvent>(&v10);
+        let v23 = 0x1::account::new_event_handle<VestEvent>(&v10);
+        let v24 = 0x1::account::new_event_handle<DistributeEvent>(&v10);
+        let v25 = 0x1::account::new_event_handle<TerminateEvent>(&v10);
+        let v26 = 0x1::account::new_event_handle<AdminWithdrawEvent>(&v10);
+        let v27 = VestingContract{
             state                  : 1, 
-            admin                  : v9, 
+            admin                  : v8, 
             grant_pool             : v2, 
-            beneficiaries          : v18, 
+            beneficiaries          : v16, 
             vesting_schedule       : arg3, 
             withdrawal_address     : arg4, 
-            staking                : v19, 
+            staking                : v17, 
             remaining_grant        : v1, 
-            signer_cap             : v12, 
-            update_operator_events : v20, 
-            update_voter_events    : v21, 
-            reset_lockup_events    : v22, 
-            set_beneficiary_events : v23, 
-            unlock_rewards_events  : v24, 
-            vest_events            : v25, 
-            distribute_events      : v26, 
-            terminate_events       : v27, 
-            admin_withdraw_events  : v28,
+            signer_cap             : v11, 
+            update_operator_events : v18, 
+            update_voter_events    : v19, 
+            reset_lockup_events    : v20, 
+            set_beneficiary_events : v21, 
+            unlock_rewards_events  : v22, 
+            vest_events            : v23, 
+            distribute_events      : v24, 
+            terminate_events       : v25, 
+            admin_withdraw_events  : v26,
         };
-        move_to<VestingContract>(&v13, v29);
+        move_to<VestingContract>(&v10, v27);
         0x1::simple_map::destroy_empty<address, 0x1::coin::Coin<0x1::aptos_coin::AptosCoin>>(arg2);
-        v15
+        v13
     }
     
     fun create_vesting_contract_account(arg0: &signer, arg1: vector<u8>) : (signer, 0x1::account::SignerCapability) acquires AdminStore {
@@ -334,9 +332,8 @@ module 0x1::vesting {
         0x1::vector::append<u8>(&mut v2, b"aptos_framework::vesting");
         0x1::vector::append<u8>(&mut v2, arg1);
         let (v3, v4) = 0x1::account::create_resource_account(arg0, v2);
-        let v5 = v3;
-        0x1::coin::register<0x1::aptos_coin::AptosCoin>(&v5);
-        (v5, v4)
+        0x1::coin::register<0x1::aptos_coin::AptosCoin>(&v3);
+        (v3, v4)
     }
     
     public fun create_vesting_schedule(arg0: vector<0x1::fixed_point32::FixedPoint32>, arg1: u64, arg2: u64) : VestingSchedule {
@@ -0,0 +1,79 @@
+mod utils;
+
+#[cfg(test)]
+mod test {
+    use super::utils;
+    use move_binary_format::access::ModuleAccess;
+    use move_compiler::Flags;
+    use move_decompiler::decompiler::{module_hash, Decompiler, SourceRegistry, VerifiedSource};
+    use std::collections::HashMap;
+
+    const SOURCE: &str = r#"
+module 0x12::known {
+    public fun one(): u64 {
+        1
+    }
+}
+
+module 0x12::unknown {
+    public fun double(x: u64): u64 {
+        x * 2
+    }
+}
+"#;
+
+    // registry answering from memory
+    struct MockRegistry {
+        sources: HashMap<String, String>,
+    }
+
+    impl SourceRegistry for MockRegistry {
+        fn lookup(&self, module_hash: &str) -> anyhow::Result<Option<VerifiedSource>> {
+            Ok(self.sources.get(module_hash).map(|source| VerifiedSource {
+                origin: format!("mock/{}", module_hash),
+                source: source.clone(),
+            }))
+        }
+    }
+
+    #[test]
+    fn verified_source_or_decompiled() {
+        utils::tmp_project(vec![("tmp.move", SOURCE)], |files| {
+            let (scripts, modules) = utils::run_compiler(files, Flags::empty(), false);
+            let known = modules
+                .iter()
+                .find(|module| module.self_id().name().as_str() == "known")
+                .unwrap();
+            let mut bytes = vec![];
+            known.serialize(&mut bytes).unwrap();
+            let hash = module_hash(&bytes);
+
+            let registry = MockRegistry {
+                sources: HashMap::from([(
+                    hash.clone(),
+                    "module 0x12::known { /* verified */ }".to_string(),
+                )]),
+            };
+            let binaries = utils::into_binary_indexed_view(&scripts, &modules);
+            let mut decompiler =
+                Decompiler::new(binaries, Default::default()).with_registry(registry);
+            let output = decompiler.decompile().unwrap();
+
+            assert!(
+                output.contains(&format!("// verified source from mock/{}", hash)),
+                "{}",
+                output
+            );
+            assert!(output.contains("/* verified */"), "{}", output);
+            assert!(!output.contains("public fun one()"), "{}", output);
+
+            // the module unknown to the registry is decompiled
+            assert!(output.contains("module 0x12::unknown {"), "{}", output);
+            assert!(
+                output.contains("public fun double(arg0: u64) : u64 {"),
+                "{}",
+                output
+            );
+        });
+    }
+}
@@ -0,0 +1,101 @@
+mod utils;
+
+#[cfg(test)]
+mod test {
+    use super::utils;
+    use move_decompiler::decompiler::SignatureDb;
+
+    const SOURCE: &str = r#"
+module 0x12::lib {
+    use std::vector;
+
+    public fun sum(v: &vector<u64>): u64 {
+        let total = 0;
+        let i = 0;
+        while (i < vector::length(v)) {
+            total = total + *vector::borrow(v, i);
+            i = i + 1;
+        };
+        total
+    }
+
+    public fun one(): u64 {
+        1
+    }
+}
+
+module 0x13::stats {
+    use std::vector;
+
+    struct Pair has drop {
+        a: u128,
+        b: vector<bool>,
+    }
+
+    public fun pair(): Pair {
+        Pair { a: 1, b: vector[true, false] }
+    }
+
+    public fun sum(v: &vector<u64>): u64 {
+        let total = 0;
+        let i = 0;
+        while (i < vector::length(v)) {
+            total = total + *vector::borrow(v, i);
+            i = i + 1;
+        };
+        total
+    }
+}
+"#;
+
+    fn collect(source: &str) -> SignatureDb {
+        let mut db = None;
+        utils::with_decompiler(
+            source,
+            Default::default(),
+            Default::default(),
+            |decompiler| {
+                db = Some(decompiler.collect_signatures());
+            },
+        );
+        db.unwrap()
+    }
+
+    // hash recorded for the function `name`
+    fn hash_of(db: &SignatureDb, name: &str) -> Option<String> {
+        db.to_string().lines().find_map(|line| {
+            let (hash, entry) = line.split_once(' ').unwrap();
+            (entry == name).then(|| hash.to_string())
+        })
+    }
+
+    #[test]
+    fn copies_match_across_modules() {
+        let db = collect(SOURCE);
+
+        // the pools of `stats` also hold the entries of `Pair` and `pair`, so `sum`
+        // references the vector functions and signatures at other indices
+        let original = hash_of(&db, "0x12::lib::sum").expect("sum should be recorded");
+        assert_eq!(hash_of(&db, "0x13::stats::sum"), Some(original));
+    }
+
+    #[test]
+    fn short_functions_are_skipped() {
+        let db = collect(SOURCE);
+
+        assert_eq!(hash_of(&db, "0x12::lib::one"), None);
+        assert!(hash_of(&db, "0x12::lib::sum").is_some());
+    }
+
+    #[test]
+    fn parse_round_trip() {
+        let db = collect(SOURCE);
+        let text = db.to_string();
+        assert!(!text.is_empty());
+
+        let parsed = SignatureDb::parse(&format!("# signatures\n\n{}", text)).unwrap();
+        assert_eq!(parsed.to_string(), text);
+
+        assert!(SignatureDb::parse("0123456789abcdef").is_err());
+    }
+}
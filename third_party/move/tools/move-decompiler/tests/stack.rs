@@ -0,0 +1,100 @@
+mod utils;
+
+#[cfg(test)]
+mod test {
+    use super::utils;
+    use move_binary_format::{
+        access::ModuleAccess,
+        file_format::{Bytecode, CompiledModule},
+    };
+    use move_compiler::Flags;
+    use move_decompiler::decompiler::{Decompiler, DiagnosticKind};
+
+    const SOURCE: &str = r#"
+module 0x12::stack {
+    public fun one(): u64 {
+        1
+    }
+
+    public fun pick(c: bool): u64 {
+        if (c) 1 else 2
+    }
+}
+"#;
+
+    // decompile `SOURCE` with the code of `function` replaced by `code`
+    fn decompile_with_code(function: &str, code: Vec<Bytecode>) -> (String, Vec<String>) {
+        let mut result = None;
+        utils::tmp_project(vec![("tmp.move", SOURCE)], |files| {
+            let (scripts, mut modules) = utils::run_compiler(files, Flags::empty(), false);
+            replace_code(&mut modules[0], function, code.clone());
+            let binaries = utils::into_binary_indexed_view(&scripts, &modules);
+            let mut decompiler = Decompiler::new(binaries, Default::default());
+            let output = decompiler.decompile().expect("Unable to decompile");
+            let stack_warnings = decompiler
+                .diagnostics()
+                .iter()
+                .filter(|d| d.kind == DiagnosticKind::StackInconsistency)
+                .map(|d| d.message.clone())
+                .collect();
+            result = Some((output, stack_warnings));
+        });
+        result.unwrap()
+    }
+
+    fn replace_code(module: &mut CompiledModule, function: &str, code: Vec<Bytecode>) {
+        let idx = module
+            .function_defs()
+            .iter()
+            .position(|def| {
+                module
+                    .identifier_at(module.function_handle_at(def.function).name)
+                    .as_str()
+                    == function
+            })
+            .unwrap();
+        module.function_defs[idx].code.as_mut().unwrap().code = code;
+    }
+
+    #[test]
+    fn stack_underflow() {
+        let (output, warnings) = decompile_with_code("one", vec![Bytecode::Ret]);
+        assert_eq!(warnings, vec!["stack underflow at offset 0".to_string()]);
+        assert!(
+            output.contains("// warning: stack underflow at offset 0"),
+            "{}",
+            output
+        );
+        assert!(output.contains("__havoc__"), "{}", output);
+        assert!(!output.contains("abort 0"), "{}", output);
+    }
+
+    #[test]
+    fn inconsistent_join() {
+        let (output, warnings) = decompile_with_code(
+            "pick",
+            vec![
+                Bytecode::MoveLoc(0),
+                Bytecode::BrFalse(4),
+                Bytecode::LdU64(1),
+                Bytecode::Branch(6),
+                Bytecode::LdU64(2),
+                Bytecode::LdU64(3),
+                Bytecode::Ret,
+            ],
+        );
+        assert_eq!(
+            warnings,
+            vec!["inconsistent stack depths at join: 1, 2 at offset 6".to_string()]
+        );
+        assert!(output.contains("__havoc__"), "{}", output);
+    }
+
+    #[test]
+    fn well_formed_code_has_no_warnings() {
+        let (output, warnings) =
+            decompile_with_code("one", vec![Bytecode::LdU64(1), Bytecode::Ret]);
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        assert!(!output.contains("__havoc__"), "{}", output);
+    }
+}
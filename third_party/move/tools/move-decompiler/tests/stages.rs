@@ -0,0 +1,104 @@
+mod utils;
+
+#[cfg(test)]
+mod test {
+    use super::utils;
+    use move_decompiler::decompiler::{DecompilerSettings, Stage, StageDump};
+    use std::collections::BTreeSet;
+
+    const SOURCE: &str = r#"
+module 0x12::stages {
+    public fun max(a: u64, b: u64): u64 {
+        if (a > b) a else b
+    }
+
+    public fun sum(n: u64): u64 {
+        let total = 0;
+        while (n > 0) {
+            total = total + n;
+            n = n - 1;
+        };
+        total
+    }
+}
+"#;
+
+    // output and dumps of SOURCE
+    fn decompile(settings: DecompilerSettings) -> (String, Vec<StageDump>) {
+        let mut result = None;
+        utils::with_decompiler(SOURCE, Default::default(), settings, |decompiler| {
+            let output = decompiler.decompile().expect("Unable to decompile");
+            result = Some((output, decompiler.dumps().to_vec()));
+        });
+        result.unwrap()
+    }
+
+    #[test]
+    fn stop_after_stage() {
+        let (output, dumps) = decompile(DecompilerSettings {
+            stop_after: Some(Stage::Blocks),
+            ..Default::default()
+        });
+
+        // bodies are stubs, the functions themselves are still declared
+        assert_eq!(
+            output.matches("abort 0 // stopped after blocks").count(),
+            2,
+            "{}",
+            output
+        );
+        assert!(
+            output.contains("public fun max(arg0: u64, arg1: u64) : u64 {"),
+            "{}",
+            output
+        );
+        assert!(!output.contains("while"), "{}", output);
+
+        // the stage stopped at is dumped, once per function
+        assert_eq!(dumps.len(), 2);
+        assert!(dumps.iter().all(|dump| dump.stage == Stage::Blocks));
+        let functions = dumps
+            .iter()
+            .map(|dump| dump.function.as_str())
+            .collect::<BTreeSet<_>>();
+        assert_eq!(
+            functions,
+            BTreeSet::from(["0x12::stages::max", "0x12::stages::sum"])
+        );
+    }
+
+    #[test]
+    fn dump_after_stages() {
+        let (output, dumps) = decompile(DecompilerSettings {
+            dump_after: BTreeSet::from([Stage::Stackless, Stage::Source]),
+            ..Default::default()
+        });
+
+        // dumping does not change the output
+        let (plain, no_dumps) = decompile(Default::default());
+        assert_eq!(output, plain);
+        assert!(no_dumps.is_empty());
+
+        // stages are dumped in pipeline order for each function
+        let sum = dumps
+            .iter()
+            .filter(|dump| dump.function == "0x12::stages::sum")
+            .collect::<Vec<_>>();
+        assert_eq!(
+            sum.iter().map(|dump| dump.stage).collect::<Vec<_>>(),
+            vec![Stage::Stackless, Stage::Source]
+        );
+        assert!(sum[1].ir.contains("while"), "{}", sum[1].ir);
+        assert!(sum[0]
+            .to_string()
+            .starts_with("// ===== 0x12::stages::sum after stackless ====="));
+    }
+
+    #[test]
+    fn stage_names() {
+        for stage in Stage::ALL {
+            assert_eq!(stage.name().parse::<Stage>().unwrap(), stage);
+        }
+        assert!("parse".parse::<Stage>().is_err());
+    }
+}
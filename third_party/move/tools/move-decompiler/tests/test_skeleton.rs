@@ -0,0 +1,38 @@
+mod utils;
+
+#[cfg(test)]
+mod test {
+    use super::utils;
+
+    const SOURCE: &str = r#"
+module 0x12::skeleton {
+    public fun register(account: &signer, amount: u64): u64 {
+        let _ = account;
+        amount
+    }
+}
+"#;
+
+    #[test]
+    fn signers_are_bound_to_address_values() {
+        utils::with_decompiler(
+            SOURCE,
+            Default::default(),
+            Default::default(),
+            |decompiler| {
+                let skeletons = decompiler
+                    .decompile_test_skeletons()
+                    .expect("Unable to decompile");
+                assert_eq!(skeletons.len(), 1);
+                let (name, source) = &skeletons[0];
+                assert_eq!(name, "skeleton");
+                assert!(source.contains("#[test(arg0 = @0x12)]"), "{}", source);
+                assert!(
+                    source.contains("fun test_register(arg0: signer)"),
+                    "{}",
+                    source
+                );
+            },
+        );
+    }
+}
@@ -4,10 +4,14 @@ use std::{
 };
 
 use move_binary_format::{
-    binary_views::BinaryIndexedView, file_format::CompiledScript, CompiledModule,
+    access::ModuleAccess, binary_views::BinaryIndexedView, file_format::CompiledScript,
+    CompiledModule,
 };
 use move_command_line_common::address::NumericalAddress;
 use move_compiler::{compiled_unit::CompiledUnit, shared::known_attributes::KnownAttribute, Flags};
+use move_core_types::{errmap::ErrorDescription, metadata::Metadata};
+use move_decompiler::decompiler::{Decompiler, DecompilerSettings, OptimizerSettings};
+use serde::Serialize;
 
 #[allow(dead_code)]
 fn default_testing_addresses() -> BTreeMap<String, NumericalAddress> {
@@ -162,6 +166,102 @@ pub(crate) fn tmp_project(tmp_files: Vec<(&str, &str)>, mut runner: impl FnMut(V
     std::fs::remove_dir_all(&project_root).unwrap();
 }
 
+#[allow(dead_code)]
+// Compile `source` in a temporary project and run `runner` on a decompiler of its binaries
+pub(crate) fn with_decompiler(
+    source: &str,
+    optimizer_settings: OptimizerSettings,
+    settings: DecompilerSettings,
+    mut runner: impl FnMut(&mut Decompiler),
+) {
+    let mut settings = Some((optimizer_settings, settings));
+    tmp_project(vec![("tmp.move", source)], |files| {
+        let (scripts, modules) = run_compiler(files, Flags::empty(), false);
+        let binaries = into_binary_indexed_view(&scripts, &modules);
+        let (optimizer_settings, settings) = settings.take().unwrap();
+        let mut decompiler = Decompiler::new(binaries, optimizer_settings).with_settings(settings);
+        runner(&mut decompiler);
+    });
+}
+
+#[allow(dead_code)]
+// Decompiled source of `source` with the given settings
+pub(crate) fn decompile_source(
+    source: &str,
+    optimizer_settings: OptimizerSettings,
+    settings: DecompilerSettings,
+) -> String {
+    let mut output = String::new();
+    with_decompiler(source, optimizer_settings, settings, |decompiler| {
+        output = decompiler.decompile().expect("Unable to decompile");
+    });
+    output
+}
+
+#[allow(dead_code)]
+// Decompiled source of `source`, after `edit` changed its modules and `build` configured
+// the decompiler
+pub(crate) fn decompile_modules(
+    source: &str,
+    edit: impl FnOnce(&mut Vec<CompiledModule>),
+    build: impl FnOnce(Decompiler) -> Decompiler,
+) -> anyhow::Result<String> {
+    let mut steps = Some((edit, build));
+    let mut result = None;
+    tmp_project(vec![("tmp.move", source)], |files| {
+        let (edit, build) = steps.take().unwrap();
+        let (scripts, mut modules) = run_compiler(files, Flags::empty(), false);
+        edit(&mut modules);
+        let binaries = into_binary_indexed_view(&scripts, &modules);
+        let mut decompiler = build(Decompiler::new(binaries, Default::default()));
+        result = Some(decompiler.decompile());
+    });
+    result.unwrap()
+}
+
+// kinds of `Attribute`, as numbered by the aptos framework
+#[allow(dead_code)]
+pub(crate) const VIEW_FUNCTION: u8 = 1;
+#[allow(dead_code)]
+pub(crate) const RESOURCE_GROUP: u8 = 2;
+#[allow(dead_code)]
+pub(crate) const RESOURCE_GROUP_MEMBER: u8 = 3;
+#[allow(dead_code)]
+pub(crate) const EVENT: u8 = 4;
+
+#[allow(dead_code)]
+// Layout of the aptos runtime metadata, only the aptos framework builder emits it
+#[derive(Default, Serialize)]
+pub(crate) struct RuntimeMetadata {
+    pub error_map: BTreeMap<u64, ErrorDescription>,
+    pub struct_attributes: BTreeMap<String, Vec<Attribute>>,
+    pub fun_attributes: BTreeMap<String, Vec<Attribute>>,
+}
+
+#[allow(dead_code)]
+#[derive(Serialize)]
+pub(crate) struct Attribute {
+    pub kind: u8,
+    pub args: Vec<String>,
+}
+
+#[allow(dead_code)]
+// Attach `metadata` to the module `name` of `modules`
+pub(crate) fn attach_metadata(
+    modules: &mut [CompiledModule],
+    name: &str,
+    metadata: &RuntimeMetadata,
+) {
+    let module = modules
+        .iter_mut()
+        .find(|module| module.self_id().name().as_str() == name)
+        .unwrap_or_else(|| panic!("module {} not found", name));
+    module.metadata.push(Metadata {
+        key: b"aptos::metadata_v1".to_vec(),
+        value: bcs::to_bytes(metadata).unwrap(),
+    });
+}
+
 #[allow(dead_code)]
 // Compare output and output2 which has variables may be renamed
 // all variables are in the form v\d+
@@ -220,7 +320,10 @@ pub(crate) fn assert_same_source(output: &String, output2: &String) {
 }
 
 #[allow(dead_code)]
-pub(crate) fn should_same_script_bytecode(src_scripts: &[CompiledScript], scripts: &[CompiledScript]) {
+pub(crate) fn should_same_script_bytecode(
+    src_scripts: &[CompiledScript],
+    scripts: &[CompiledScript],
+) {
     assert_eq!(src_scripts.len(), scripts.len());
 
     for (src_script, script) in src_scripts.iter().zip(scripts.iter()) {
@@ -234,7 +337,10 @@ pub(crate) fn should_same_script_bytecode(src_scripts: &[CompiledScript], script
 }
 
 #[allow(dead_code)]
-pub(crate) fn should_same_module_bytecode(src_modules: &[CompiledModule], modules: &[CompiledModule]) {
+pub(crate) fn should_same_module_bytecode(
+    src_modules: &[CompiledModule],
+    modules: &[CompiledModule],
+) {
     assert_eq!(src_modules.len(), modules.len());
 
     for (src_module, module) in src_modules.iter().zip(modules.iter()) {
@@ -0,0 +1,60 @@
+mod utils;
+
+#[cfg(test)]
+mod test {
+    use super::utils;
+    use move_binary_format::{
+        access::ModuleAccess, binary_views::BinaryIndexedView, file_format::Bytecode,
+    };
+    use move_compiler::Flags;
+    use move_core_types::vm_status::StatusCode;
+    use move_decompiler::decompiler::verify_binary;
+
+    const SOURCE: &str = r#"
+module 0x12::checked {
+    public fun one(): u64 {
+        1
+    }
+
+    public fun add(a: u64, b: u64): u64 {
+        a + b
+    }
+}
+"#;
+
+    #[test]
+    fn rejection_is_located() {
+        utils::tmp_project(vec![("tmp.move", SOURCE)], |files| {
+            let (_, mut modules) = utils::run_compiler(files, Flags::empty(), false);
+            assert!(verify_binary(&BinaryIndexedView::Module(&modules[0])).is_ok());
+
+            // pop from the empty stack at the start of `add`
+            let module = &mut modules[0];
+            let index = module
+                .function_defs()
+                .iter()
+                .position(|def| {
+                    module
+                        .identifier_at(module.function_handle_at(def.function).name)
+                        .as_str()
+                        == "add"
+                })
+                .unwrap();
+            module.function_defs[index].code.as_mut().unwrap().code =
+                vec![Bytecode::Pop, Bytecode::Ret];
+
+            let err = verify_binary(&BinaryIndexedView::Module(&modules[0])).unwrap_err();
+            assert_eq!(err.binary, "0x12::checked");
+            assert_eq!(err.function.as_deref(), Some("add"));
+            assert_eq!(err.offset, Some(0));
+            assert_eq!(err.status, StatusCode::NEGATIVE_STACK_SIZE_WITHIN_BLOCK);
+            assert!(
+                err.to_string().starts_with(
+                    "0x12::checked::add at offset 0: NEGATIVE_STACK_SIZE_WITHIN_BLOCK"
+                ),
+                "{}",
+                err
+            );
+        });
+    }
+}
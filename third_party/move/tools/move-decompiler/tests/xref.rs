@@ -0,0 +1,88 @@
+mod utils;
+
+#[cfg(test)]
+mod test {
+    use super::utils;
+    use move_decompiler::decompiler::{XrefKind, XrefReport};
+    use std::collections::BTreeSet;
+
+    // kinds of the references to `target` made from `function`
+    fn kinds(report: &XrefReport, target: &str, function: &str) -> BTreeSet<XrefKind> {
+        report
+            .get(target)
+            .unwrap_or_else(|| panic!("no references to {} in\n{}", target, report))
+            .iter()
+            .filter(|location| location.function == function)
+            .map(|location| location.kind)
+            .collect()
+    }
+
+    #[test]
+    fn references() {
+        utils::with_decompiler(
+            r#"
+module 0x12::refs {
+    struct Config has key {
+        limit: u64,
+    }
+
+    struct Pair has drop {
+        a: u64,
+        b: u64,
+    }
+
+    fun helper(x: u64): u64 {
+        x + 1
+    }
+
+    public fun read(addr: address): u64 acquires Config {
+        helper(borrow_global<Config>(addr).limit)
+    }
+
+    public fun write(addr: address) acquires Config {
+        borrow_global_mut<Config>(addr).limit = 0;
+    }
+
+    public fun publish(account: &signer) {
+        move_to(account, Config { limit: 1 });
+    }
+
+    public fun pair(x: u64): u64 {
+        let Pair { a, b } = Pair { a: x, b: x };
+        a + b
+    }
+}
+"#,
+            Default::default(),
+            Default::default(),
+            |decompiler| {
+                decompiler.decompile().expect("Unable to decompile");
+                let report = decompiler.xref();
+
+                let config = "0x12::refs::Config";
+                assert_eq!(
+                    kinds(report, "0x12::refs::helper", "0x12::refs::read"),
+                    BTreeSet::from([XrefKind::Call])
+                );
+                assert_eq!(
+                    kinds(report, config, "0x12::refs::read"),
+                    BTreeSet::from([XrefKind::Read])
+                );
+                assert_eq!(
+                    kinds(report, config, "0x12::refs::write"),
+                    BTreeSet::from([XrefKind::Write])
+                );
+                assert_eq!(
+                    kinds(report, config, "0x12::refs::publish"),
+                    BTreeSet::from([XrefKind::Pack, XrefKind::Write])
+                );
+                assert_eq!(
+                    kinds(report, "0x12::refs::Pair", "0x12::refs::pair"),
+                    BTreeSet::from([XrefKind::Pack, XrefKind::Unpack])
+                );
+                // functions which are never called have no references
+                assert!(report.get("0x12::refs::pair").is_none(), "{}", report);
+            },
+        );
+    }
+}
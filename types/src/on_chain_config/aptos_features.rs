@@ -41,6 +41,7 @@ pub enum FeatureFlag {
     SAFER_RESOURCE_GROUPS = 31,
     SAFER_METADATA = 32,
     SECP256K1_ECDSA_AUTHENTICATOR = 33,
+    AGGREGATOR_BOUND_CHECKS = 34,
 }
 
 /// Representation of features on chain as a bitset.
@@ -97,6 +98,10 @@ impl Features {
     pub fn is_aggregator_snapshots_enabled(&self) -> bool {
         self.is_enabled(FeatureFlag::AGGREGATOR_SNAPSHOTS)
     }
+
+    pub fn is_aggregator_bound_checks_enabled(&self) -> bool {
+        self.is_enabled(FeatureFlag::AGGREGATOR_BOUND_CHECKS)
+    }
 }
 
 // --------------------------------------------------------------------------------------------